@@ -0,0 +1,59 @@
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_db: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Check the dependencies of a package for security advisories
+
+Usage:
+    cargo audit [options]
+
+Options:
+    -h, --help              Print this message
+    --db PATH               Path to the advisory database to check against
+    --manifest-path PATH    Path to the manifest of the package
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The versions of the registry dependencies in Cargo.lock are checked against
+the advisory database, and each affected package is printed along with the
+advisory. The command fails if any package is affected.
+
+Without `--db`, the database is the directory configured in `audit.database`
+or the git repository configured in `audit.url`, which is fetched first. With
+`audit.on-build` set, `cargo build` warns about affected packages as well.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    let db = options.flag_db.map(|db| config.cwd().join(db));
+
+    let affected = try!(ops::audit(&root, db.as_ref(), config));
+    for &(ref id, ref advisory) in affected.iter() {
+        println!("{}: {}", advisory.id, advisory.title);
+        println!("    package: {}", id);
+        if let Some(ref url) = advisory.url {
+            println!("    url:     {}", url);
+        }
+    }
+    match affected.len() {
+        0 => Ok(None),
+        1 => Err(CliError::new("1 package is affected by an advisory", 1)),
+        n => {
+            let msg = format!("{} packages are affected by advisories", n);
+            Err(CliError::new(&msg, 1))
+        }
+    }
+}
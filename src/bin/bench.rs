@@ -14,7 +14,9 @@ struct Options {
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     arg_args: Vec<String>,
 }
 
@@ -34,7 +36,9 @@ Options:
     --no-default-features    Do not build the `default` feature
     --target TRIPLE          Build for the target triple
     --manifest-path PATH     Path to the manifest to build benchmarks for
-    -v, --verbose            Use verbose output
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
 
 All of the trailing arguments are passed to the benchmark binaries generated
 for filtering benchmarks and generally providing options configuring how they
@@ -50,7 +54,8 @@ Compilation can be customized with the `bench` profile in the manifest.
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
 
     let ops = ops::TestOptions {
         name: options.flag_bench.as_ref().map(|s| s.as_slice()),
@@ -66,6 +71,9 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             spec: options.flag_package.as_ref().map(|s| s.as_slice()),
             lib_only: false,
             exec_engine: None,
+            out_dir: None,
+            cap_lints: None,
+            hooks: true,
         },
     };
 
@@ -1,21 +1,30 @@
 use std::os;
 
-use cargo::ops::CompileOptions;
+use rustc_serialize::json;
+
+use cargo::ops::{CompileOptions, ArtifactSize};
 use cargo::ops;
 use cargo::util::important_paths::{find_root_manifest_for_cwd};
-use cargo::util::{CliResult, CliError, Config};
+use cargo::util::{CliResult, CliError, Config, human_size};
 
 #[derive(RustcDecodable)]
 struct Options {
     flag_package: Option<String>,
+    flag_workspace: bool,
     flag_jobs: Option<u32>,
     flag_features: Vec<String>,
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_release: bool,
-    flag_lib: bool
+    flag_lib: bool,
+    flag_out_dir: Option<String>,
+    flag_watch: bool,
+    flag_size_report: Option<String>,
+    flag_cap_lints: Option<String>,
 }
 
 pub const USAGE: &'static str = "
@@ -27,6 +36,7 @@ Usage:
 Options:
     -h, --help               Print this message
     -p SPEC, --package SPEC  Package to build
+    --workspace              Build all members of the workspace
     -j N, --jobs N           The number of jobs to run in parallel
     --lib                    Build only lib (if present in package)
     --release                Build artifacts in release mode, with optimizations
@@ -34,21 +44,55 @@ Options:
     --no-default-features    Do not build the `default` feature
     --target TRIPLE          Build for the target triple
     --manifest-path PATH     Path to the manifest to compile
-    -v, --verbose            Use verbose output
+    --out-dir PATH           Copy the final artifacts to this directory
+    --watch                  Build again whenever a source file changes
+    --size-report FMT        Print the sizes of the artifacts: human or json
+    --cap-lints LEVEL        Cap lints of dependencies outside the workspace
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package should be built. If it is not given, then the
 current package is built. For more information on SPEC and its format, see the
 `cargo help pkgid` command.
 
+With --workspace, every member of the workspace of the current package is
+built. Without it, --features only applies to the package being built, where
+`dep/feature` activates a feature of its dependency `dep`. With it, features
+are given to the members: `member/feature` activates a feature of the member
+named `member`, and a feature without a member is activated for the member
+which has it. Such a feature is an error if no member or more than one member
+has it.
+
 Compilation can be configured via the use of profiles which are configured in
 the manifest. The default profile for this command is `dev`, but passing
 the --release flag will use the `release` profile instead.
+
+With --out-dir, the binaries, dynamic libraries and static libraries of the
+package are copied to the given directory after a successful build, named
+without the hashes cargo adds to file names in the target directory.
+
+With --cap-lints, the lints of dependencies which aren't developed locally are
+capped at the given level, one of allow, warn, deny or forbid, or left alone
+with none. This overrides the `build.cap-lints` configuration, which defaults
+to allow.
+
+With --watch, the package is built again whenever a source file of it or of its
+path dependencies changes, until cargo is interrupted with Ctrl-C.
+
+With --size-report, the sizes of the binaries, dynamic libraries and static
+libraries of the package are printed after the build, broken down by section
+and by the crate their symbols come from, which is mostly useful for release
+builds. The `size` and `nm` tools of binutils are used for the analysis. The
+json format prints one JSON object, for tracking sizes over time, and prints
+status lines to stderr instead of stdout.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     debug!("executing; cmd=cargo-build; args={:?}", os::args());
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
 
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
@@ -58,6 +102,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         "compile"
     };
 
+    let out_dir = options.flag_out_dir.map(|dir| config.cwd().join(dir));
     let opts = CompileOptions {
         env: env,
         config: config,
@@ -69,9 +114,69 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         spec: options.flag_package.as_ref().map(|s| s.as_slice()),
         lib_only: options.flag_lib,
         exec_engine: None,
+        out_dir: out_dir.as_ref(),
+        cap_lints: options.flag_cap_lints.as_ref().map(|s| s.as_slice()),
+        hooks: true,
     };
 
-    ops::compile(&root, &opts).map(|_| None).map_err(|err| {
+    let size_format = options.flag_size_report.as_ref().map(|s| s.as_slice());
+    match size_format {
+        None | Some("human") | Some("json") => {}
+        Some(s) => {
+            let msg = format!("--size-report must be human or json, but \
+                               found `{}`", s);
+            return Err(CliError::new(&msg, 1))
+        }
+    }
+    config.shell().set_status_to_stderr(size_format == Some("json"));
+
+    let build = || {
+        let compilations = if options.flag_workspace {
+            ops::compile_workspace(&root, &opts)
+        } else {
+            ops::compile(&root, &opts).map(|c| vec![c])
+        };
+        let compilations = try!(compilations.map_err(|err| {
+            CliError::from_boxed(err, 101)
+        }));
+        let format = match size_format {
+            Some(format) => format,
+            None => return Ok(None),
+        };
+        let mut sizes = Vec::new();
+        for compilation in compilations.iter() {
+            sizes.extend(try!(ops::size_report(compilation, config)
+                                 .map_err(|err| {
+                CliError::from_boxed(err, 101)
+            })).into_iter());
+        }
+        if format == "json" {
+            #[derive(RustcEncodable)]
+            struct SizeReport<'a> { artifacts: &'a [ArtifactSize] }
+            println!("{}", json::encode(&SizeReport { artifacts: &sizes })
+                               .unwrap());
+        } else {
+            print_sizes(&sizes);
+        }
+        Ok(None)
+    };
+    if !options.flag_watch { return build() }
+    ops::watch(&root, config, build).map(|_| None).map_err(|err| {
         CliError::from_boxed(err, 101)
     })
 }
+
+fn print_sizes(sizes: &[ArtifactSize]) {
+    for artifact in sizes.iter() {
+        println!("{} ({})", artifact.path, human_size(artifact.file_size));
+        for &(title, entries) in [("sections", &artifact.sections),
+                                  ("crates", &artifact.crates)].iter() {
+            if entries.is_empty() { continue }
+            println!("  {}:", title);
+            for entry in entries.iter() {
+                println!("    {:<24} {:>10}", entry.name,
+                         human_size(entry.size));
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_max_age: Option<u32>,
+    flag_dry_run: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Remove unused entries from cargo's global caches
+
+Usage:
+    cargo cache [options]
+
+Options:
+    -h, --help              Print this message
+    --max-age DAYS          Remove entries unused for this many days
+    -n, --dry-run           Report what would be removed without removing it
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+Cargo keeps downloaded crates, registry indices and git repositories in
+$CARGO_HOME (~/.cargo by default) and records when each of them was last used
+by a build. This command deletes all entries which have not been used within
+the last --max-age days. If the flag is not given, the `cache.max-age`
+configuration key is used, falling back to 90 days.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let opts = ops::CacheOptions {
+        config: config,
+        max_age: options.flag_max_age,
+        dry_run: options.flag_dry_run,
+    };
+    ops::gc(&opts).map(|_| None).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    })
+}
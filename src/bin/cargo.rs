@@ -13,13 +13,20 @@ use std::old_io::process::{Command,InheritFd,ExitStatus,ExitSignal};
 use std::old_io;
 
 use cargo::{execute_main_without_stdin, handle_error, shell};
-use cargo::core::MultiShell;
-use cargo::util::{CliError, CliResult, lev_distance, Config};
+use cargo::core::Verbosity;
+use cargo::util::{CargoResult, CliError, CliResult, Config, human};
+use cargo::util::lev_distance;
+use cargo::util::config::ConfigValue;
+use cargo::util::important_paths::find_root_manifest_for_cwd;
 
 #[derive(RustcDecodable)]
 struct Flags {
     flag_list: bool,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+    flag_error_format: Option<String>,
+    flag_config: Vec<String>,
     arg_command: String,
     arg_args: Vec<String>,
 }
@@ -28,22 +35,30 @@ const USAGE: &'static str = "
 Rust's package manager
 
 Usage:
-    cargo <command> [<args>...]
+    cargo [options] <command> [<args>...]
     cargo [options]
 
 Options:
-    -h, --help       Display this message
-    -V, --version    Print version info and exit
-    --list           List installed commands
-    -v, --verbose    Use verbose output
+    -h, --help              Display this message
+    -V, --version           Print version info and exit
+    --list                  List installed commands, with -v also running
+                            external commands to show their descriptions
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+    --error-format FMT      Print errors as human-readable text or, for tools,
+                            as JSON objects: human, json
+    --config SETTING ...    Override a configuration value, given as
+                            KEY=VALUE with the value in TOML, like
+                            build.jobs=4
 
 Some common cargo commands are:
-    build       Compile the current project
+    build       Compile the current project (alias: b)
     clean       Remove the target directory
     doc         Build this project's and its dependencies' documentation
     new         Create a new cargo project
-    run         Build and execute src/main.rs
-    test        Run the tests
+    run         Build and execute src/main.rs (alias: r)
+    test        Run the tests (alias: t)
     bench       Run the benchmarks
     update      Update dependencies listed in Cargo.lock
 
@@ -55,31 +70,49 @@ fn main() {
     execute_main_without_stdin(execute, true, USAGE)
 }
 
-macro_rules! each_subcommand{ ($mac:ident) => ({
+macro_rules! each_subcommand{ ($mac:ident) => {
+    $mac!(audit);
     $mac!(bench);
     $mac!(build);
+    $mac!(cache);
     $mac!(clean);
+    $mac!(completions);
+    $mac!(config);
     $mac!(doc);
     $mac!(fetch);
+    $mac!(fix);
     $mac!(generate_lockfile);
     $mac!(git_checkout);
     $mac!(help);
+    $mac!(info);
+    $mac!(licenses);
     $mac!(locate_project);
     $mac!(login);
+    $mac!(logout);
     $mac!(new);
+    $mac!(outdated);
     $mac!(owner);
     $mac!(package);
     $mac!(pkgid);
     $mac!(publish);
     $mac!(read_manifest);
+    $mac!(report);
     $mac!(run);
+    $mac!(sbom);
+    $mac!(script);
     $mac!(search);
+    $mac!(set_version);
     $mac!(test);
+    $mac!(tree);
     $mac!(update);
+    $mac!(verify_cache);
     $mac!(verify_project);
     $mac!(version);
     $mac!(yank);
-}) }
+} }
+
+macro_rules! declare_mod{ ($name:ident) => (mod $name;) }
+each_subcommand!(declare_mod);
 
 /**
   The top-level `cargo` command handles configuration and project location
@@ -87,21 +120,41 @@ macro_rules! each_subcommand{ ($mac:ident) => ({
   on this top-level information.
 */
 fn execute(flags: Flags, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(flags.flag_verbose);
+    try!(config.set_cli_values(&flags.flag_config));
+    try!(config.configure_error_format(&flags.flag_error_format));
+    try!(config.configure_shell(flags.flag_verbose, flags.flag_quiet,
+                                &flags.flag_color));
 
     init_git_transports(config);
 
     if flags.flag_list {
         println!("Installed Commands:");
         for command in list_commands().into_iter() {
-            println!("    {}", command);
+            let description = match builtin_usage(&command) {
+                Some(usage) => description(usage).to_string(),
+                None => {
+                    // Describing external commands means running them, so
+                    // that's only done when asked for
+                    let path = find_command(&command).unwrap();
+                    let help = if flags.flag_verbose > 0 {
+                        external_description(&path)
+                    } else {
+                        None
+                    };
+                    match help {
+                        Some(help) => format!("{} ({})", help, path.display()),
+                        None => path.display().to_string(),
+                    }
+                }
+            };
+            println!("    {:<20} {}", command, description);
         };
         return Ok(None)
     }
 
     let (mut args, command) = match &flags.arg_command[] {
         "" | "help" if flags.arg_args.len() == 0 => {
-            config.shell().set_verbose(true);
+            config.shell().set_verbosity(Verbosity::Verbose);
             let args = &["foo".to_string(), "-h".to_string()];
             let r = cargo::call_main_without_stdin(execute, config, USAGE, args,
                                                    false);
@@ -111,80 +164,262 @@ fn execute(flags: Flags, config: &Config) -> CliResult<Option<()>> {
         "help" if flags.arg_args[0] == "-h" ||
                   flags.arg_args[0] == "--help" =>
             (flags.arg_args, "help"),
-        "help" => (vec!["-h".to_string()], &flags.arg_args[0][]),
+        "help" => {
+            let command = &flags.arg_args[0][];
+            match builtin_usage(command) {
+                Some(usage) => {
+                    try!(help::manual(command, usage));
+                    return Ok(None)
+                }
+                // Aliases and external commands are asked for their usage
+                None => (vec!["-h".to_string()], command),
+            }
+        }
         s => (flags.arg_args.clone(), s),
     };
-    args.insert(0, command.to_string());
+    let mut command = command.to_string();
+    args.insert(0, command.clone());
     args.insert(0, "foo".to_string());
 
-    macro_rules! cmd{ ($name:ident) => (
+    // Aliases are expanded until a built-in or external command is found,
+    // remembering the aliases seen so far to catch ones expanding to
+    // themselves.
+    let mut expanded = Vec::new();
+    loop {
+        let alias = try!(aliased_command(config, &command));
+
+        macro_rules! cmd{ ($name:ident) => (
+            if command == stringify!($name).replace("_", "-") {
+                if alias.is_some() {
+                    try!(config.shell().warn(format!("warning: the alias \
+                                                      `{}` is ignored, as it \
+                                                      is shadowed by a \
+                                                      built-in command",
+                                                     command)));
+                }
+                config.shell().set_verbosity(Verbosity::Verbose);
+                let r = cargo::call_main_without_stdin($name::execute, config,
+                                                       $name::USAGE,
+                                                       &args,
+                                                       false);
+                cargo::process_executed(r, &mut **config.shell());
+                return Ok(None)
+            }
+        ) }
+        each_subcommand!(cmd);
+
+        let alias = match alias {
+            Some(alias) => alias,
+            None => break,
+        };
+        if expanded.contains(&command) {
+            expanded.push(command);
+            return Err(CliError::new(&format!("alias `{}` is recursive: {}",
+                                              expanded[0],
+                                              expanded.connect(" -> ")), 101))
+        }
+        expanded.push(command);
+        let rest = args[2..].to_vec();
+        args.truncate(1);
+        args.extend(alias.into_iter());
+        args.extend(rest.into_iter());
+        command = args[1].clone();
+    }
+
+    let env = try!(subcommand_env(config, &flags.flag_color));
+    execute_subcommand(&command, &args, &env, config);
+    Ok(None)
+}
+
+/// Returns the environment variables describing this invocation of cargo to
+/// external subcommands, so they don't need to rediscover it.
+fn subcommand_env(config: &Config, color: &Option<String>)
+                  -> CargoResult<Vec<(String, String)>> {
+    let mut env = Vec::new();
+    if let Ok(exe) = env::current_exe() {
+        env.push(("CARGO".to_string(), exe.display().to_string()));
+    }
+    if let Ok(root) = find_root_manifest_for_cwd(None) {
+        env.push(("CARGO_MANIFEST_PATH".to_string(),
+                  root.display().to_string()));
+    }
+    let verbosity = match config.shell().get_verbosity() {
+        Verbosity::Quiet => "quiet",
+        Verbosity::Normal => "normal",
+        Verbosity::Verbose => "verbose",
+        Verbosity::VeryVerbose => "very-verbose",
+    };
+    env.push(("CARGO_TERM_VERBOSITY".to_string(), verbosity.to_string()));
+    let color = match *color {
+        Some(ref color) => color.clone(),
+        None => {
+            try!(config.get_string("term.color")).map(|(color, _)| color)
+                       .unwrap_or("auto".to_string())
+        }
+    };
+    env.push(("CARGO_TERM_COLOR".to_string(), color));
+    env.extend(config.cli_values_env().into_iter());
+    Ok(env)
+}
+
+/// Returns the usage string of the built-in command `command`.
+fn builtin_usage(command: &str) -> Option<&'static str> {
+    macro_rules! usage{ ($name:ident) => (
         if command == stringify!($name).replace("_", "-") {
-            mod $name;
-            config.shell().set_verbose(true);
-            let r = cargo::call_main_without_stdin($name::execute, config,
-                                                   $name::USAGE,
-                                                   &args,
-                                                   false);
-            cargo::process_executed(r, &mut **config.shell());
-            return Ok(None)
+            return Some($name::USAGE)
         }
     ) }
-    each_subcommand!(cmd);
+    each_subcommand!(usage);
+    None
+}
 
-    execute_subcommand(&command, &args, &mut config.shell());
-    Ok(None)
+/// Returns the one-line description at the start of the usage of a built-in
+/// command.
+fn description(usage: &str) -> &str {
+    let line = usage.lines().map(|l| l.trim()).find(|l| !l.is_empty());
+    match line {
+        Some(line) if !line.starts_with("Usage:") => line,
+        _ => "",
+    }
+}
+
+/// Returns the first line printed by `--help` of the external command at
+/// `path`, if it prints anything.
+fn external_description(path: &Path) -> Option<String> {
+    let output = match subcommand_process(path, &["--help".to_string()]) {
+        Ok(mut command) => command.output(),
+        Err(..) => return None,
+    };
+    let output = match output {
+        Ok(ref output) if output.status.success() => output.output.clone(),
+        _ => return None,
+    };
+    let output = String::from_utf8_lossy(&output).into_owned();
+    output.lines().map(|l| l.trim()).find(|l| !l.is_empty())
+          .map(|l| l.to_string())
 }
 
-fn find_closest(cmd: &str) -> Option<String> {
-    match list_commands().iter()
-                            // doing it this way (instead of just .min_by(|c|
-                            // c.lev_distance(cmd))) allows us to only make
-                            // suggestions that have an edit distance of
-                            // 3 or less
-                            .map(|c| (lev_distance(&c, cmd), c))
-                            .filter(|&(d, _): &(usize, &String)| d < 4)
-                            .min_by(|&(d, _)| d) {
-        Some((_, c)) => {
-            Some(c.to_string())
-        },
-        None => None
+/// Aliases which are always available, unless overridden by the `alias` table
+/// of the configuration.
+const BUILTIN_ALIASES: &'static [(&'static str, &'static str)] = &[
+    ("b", "build"),
+    ("r", "run"),
+    ("t", "test"),
+];
+
+/// Returns the command line `command` is an alias for, as defined by the
+/// `alias` table of the configuration or by the built-in aliases.
+fn aliased_command(config: &Config, command: &str)
+                   -> CargoResult<Option<Vec<String>>> {
+    let key = format!("alias.{}", command);
+    let args = match try!(config.get(&key)) {
+        Some(ConfigValue::String(s, _)) => {
+            s.split(' ').filter(|s| !s.is_empty())
+             .map(|s| s.to_string()).collect::<Vec<_>>()
+        }
+        Some(ConfigValue::List(list, _)) => {
+            list.into_iter().map(|(s, _)| s).collect()
+        }
+        Some(val) => return config.expected("string or list", &key, val),
+        None => {
+            return Ok(BUILTIN_ALIASES.iter().find(|&&(name, _)| {
+                name == command
+            }).map(|&(_, cmd)| vec![cmd.to_string()]))
+        }
+    };
+    if args.is_empty() {
+        return Err(human(format!("alias `{}` is empty", command)))
+    }
+    Ok(Some(args))
+}
+
+/// Returns the commands and aliases closest to `cmd`, which are suggested in
+/// place of a command that doesn't exist.
+///
+/// Only candidates within an edit distance of 3 are considered, and all of
+/// those which are equally close are returned, built-in commands first, then
+/// aliases and then external commands.
+fn find_closest(config: &Config, cmd: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    for command in list_commands().into_iter() {
+        let rank = if builtin_usage(&command).is_some() {0} else {2};
+        candidates.push((rank, command));
     }
+    for &(alias, _) in BUILTIN_ALIASES.iter() {
+        candidates.push((1, alias.to_string()));
+    }
+    // A broken configuration is reported when the alias is actually used
+    if let Ok(Some((aliases, _))) = config.get_table("alias") {
+        candidates.extend(aliases.into_iter().map(|(alias, _)| (1, alias)));
+    }
+
+    let mut candidates = candidates.into_iter().map(|(rank, c)| {
+        (lev_distance(&c, cmd), rank, c)
+    }).filter(|&(d, _, _)| d < 4).collect::<Vec<_>>();
+    candidates.sort();
+    let best = match candidates.first() {
+        Some(&(d, _, _)) => d,
+        None => return Vec::new(),
+    };
+    let mut closest = Vec::new();
+    for (d, _, c) in candidates.into_iter() {
+        if d == best && !closest.contains(&c) {
+            closest.push(c);
+        }
+    }
+    closest
 }
 
-fn execute_subcommand(cmd: &str, args: &[String], shell: &mut MultiShell) {
+fn execute_subcommand(cmd: &str, args: &[String], env: &[(String, String)],
+                      config: &Config) {
     let command = match find_command(cmd) {
         Some(command) => command,
         None => {
-            let msg = match find_closest(cmd) {
-                Some(closest) => format!("No such subcommand\n\n\t\
-                                          Did you mean `{}`?\n", closest),
-                None => "No such subcommand".to_string()
+            let closest = find_closest(config, cmd).iter().map(|c| {
+                format!("`{}`", c)
+            }).collect::<Vec<_>>();
+            let msg = match closest.len() {
+                0 => "No such subcommand".to_string(),
+                1 => format!("No such subcommand\n\n\t\
+                              Did you mean {}?\n", closest[0]),
+                _ => format!("No such subcommand\n\n\t\
+                              Did you mean one of {}?\n",
+                             closest.connect(", ")),
             };
-            return handle_error(CliError::new(&msg, 127), shell)
+            return handle_error(CliError::new(&msg, 127), &mut config.shell())
+        }
+    };
+    let mut command = match subcommand_process(&command, args) {
+        Ok(command) => command,
+        Err(e) => {
+            return handle_error(CliError::from_boxed(e, 101),
+                                &mut config.shell())
         }
     };
-    let status = Command::new(command)
-                         .args(args)
-                         .stdin(InheritFd(0))
-                         .stdout(InheritFd(1))
-                         .stderr(InheritFd(2))
-                         .status();
+    for &(ref key, ref val) in env.iter() {
+        command.env(key, val);
+    }
+    let status = command.stdin(InheritFd(0))
+                        .stdout(InheritFd(1))
+                        .stderr(InheritFd(2))
+                        .status();
 
     match status {
         Ok(ExitStatus(0)) => (),
         Ok(ExitStatus(i)) => {
-            handle_error(CliError::new("", i as i32), shell)
+            handle_error(CliError::new("", i as i32), &mut config.shell())
         }
         Ok(ExitSignal(i)) => {
             let msg = format!("subcommand failed with signal: {}", i);
-            handle_error(CliError::new(&msg, i as i32), shell)
+            handle_error(CliError::new(&msg, i as i32), &mut config.shell())
         }
         Err(old_io::IoError{kind, ..}) if kind == old_io::FileNotFound =>
-            handle_error(CliError::new("No such subcommand", 127), shell),
+            handle_error(CliError::new("No such subcommand", 127),
+                         &mut config.shell()),
         Err(err) => handle_error(
             CliError::new(
                 &format!("Subcommand failed to run: {}", err), 127),
-            shell)
+            &mut config.shell())
     }
 }
 
@@ -203,12 +438,16 @@ fn list_commands() -> BTreeSet<String> {
                 Some(filename) => filename,
                 _ => continue
             };
-            if filename.starts_with(command_prefix) &&
-                    filename.ends_with(env::consts::EXE_SUFFIX) &&
-                    is_executable(entry) {
+            if !filename.starts_with(command_prefix) || !is_executable(entry) {
+                continue
+            }
+            let suffix = COMMAND_SUFFIXES.iter().find(|s| {
+                filename.ends_with(**s)
+            });
+            if let Some(suffix) = suffix {
                 let command = &filename[
                     command_prefix.len()..
-                    filename.len() - env::consts::EXE_SUFFIX.len()];
+                    filename.len() - suffix.len()];
                 commands.insert(String::from_str(command));
             }
         }
@@ -221,8 +460,21 @@ fn list_commands() -> BTreeSet<String> {
     commands
 }
 
+/// Suffixes of the files which are run as external subcommands, in order of
+/// preference. Besides executables these include the common script wrappers
+/// on Windows.
+#[cfg(windows)]
+const COMMAND_SUFFIXES: &'static [&'static str] = &[
+    env::consts::EXE_SUFFIX, ".bat", ".cmd", ".ps1",
+];
+#[cfg(not(windows))]
+const COMMAND_SUFFIXES: &'static [&'static str] = &[env::consts::EXE_SUFFIX];
+
 fn is_executable(path: &Path) -> bool {
     match fs::stat(path) {
+        // Windows has no notion of executable permissions
+        Ok(old_io::FileStat{ kind: old_io::FileType::RegularFile, .. })
+            if cfg!(windows) => true,
         Ok(old_io::FileStat{ kind: old_io::FileType::RegularFile, perm, ..}) =>
             perm.contains(old_io::OTHER_EXECUTE),
         _ => false
@@ -231,12 +483,70 @@ fn is_executable(path: &Path) -> bool {
 
 /// Get `Command` to run given command.
 fn find_command(cmd: &str) -> Option<Path> {
-    let command_exe = format!("cargo-{}{}", cmd, env::consts::EXE_SUFFIX);
     let dirs = list_command_directory();
-    let mut command_paths = dirs.iter().map(|dir| dir.join(&command_exe));
+    let mut command_paths = dirs.iter().flat_map(|dir| {
+        COMMAND_SUFFIXES.iter().map(move |suffix| {
+            dir.join(format!("cargo-{}{}", cmd, suffix))
+        })
+    });
     command_paths.find(|path| path.exists())
 }
 
+/// Returns the process running the external subcommand at `path` with `args`.
+/// Scripts on Windows are run through their interpreter, as they can't be run
+/// directly.
+fn subcommand_process(path: &Path, args: &[String]) -> CargoResult<Command> {
+    let script = if cfg!(windows) {path.extension_str()} else {None};
+    match script {
+        Some("bat") | Some("cmd") => {
+            let mut command = Command::new("cmd");
+            command.arg("/D").arg("/C").arg(path);
+            for arg in args.iter() {
+                command.arg(try!(escape_for_cmd(arg)));
+            }
+            Ok(command)
+        }
+        Some("ps1") => {
+            // Scripts are only run as far as the execution policy allows
+            let mut command = Command::new("powershell");
+            command.arg("-NoProfile").arg("-File").arg(path).args(args);
+            Ok(command)
+        }
+        _ => {
+            let mut command = Command::new(path);
+            command.args(args);
+            Ok(command)
+        }
+    }
+}
+
+/// Escapes `arg` for the command line of `cmd /C`, which would otherwise run
+/// anything following a `&` or `|` in it as another command.
+///
+/// Arguments containing whitespace are quoted when the command line is built,
+/// and nothing within quotes is special to `cmd`, so only other arguments have
+/// their special characters escaped with `^`. Quotes would end the quoting
+/// early, and variables are expanded even within quotes, so arguments
+/// containing them are refused.
+fn escape_for_cmd(arg: &str) -> CargoResult<String> {
+    if arg.chars().any(|c| c == '"' || c == '%' || c == '!' || c == '\n') {
+        return Err(human(format!("the argument `{}` can't be passed to a \
+                                  batch file safely", arg)))
+    }
+    if arg.chars().any(|c| c.is_whitespace()) {
+        return Ok(arg.to_string())
+    }
+    let mut ret = String::new();
+    for c in arg.chars() {
+        match c {
+            '&' | '|' | '<' | '>' | '^' | '(' | ')' => ret.push('^'),
+            _ => {}
+        }
+        ret.push(c);
+    }
+    Ok(ret)
+}
+
 /// List candidate locations where subcommands might be installed.
 fn list_command_directory() -> Vec<Path> {
     let mut dirs = vec![];
@@ -252,11 +562,12 @@ fn list_command_directory() -> Vec<Path> {
 }
 
 fn init_git_transports(config: &Config) {
-    // Only use a custom transport if a proxy is configured, right now libgit2
-    // doesn't support proxies and we have to use a custom transport in this
-    // case. The custom transport, however, is not as well battle-tested.
-    match cargo::ops::http_proxy(config) {
-        Ok(Some(..)) => {}
+    // Only use a custom transport if a proxy, TLS settings or timeouts are
+    // configured, right now libgit2 doesn't support these and we have to use
+    // a custom transport in this case. The custom transport, however, is not
+    // as well battle-tested.
+    match cargo::ops::needs_custom_http(config) {
+        Ok(true) => {}
         _ => return
     }
 
@@ -9,7 +9,12 @@ struct Options {
     flag_package: Option<String>,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+    flag_dry_run: bool,
+    flag_release: bool,
+    flag_doc: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -21,18 +26,27 @@ Usage:
 Options:
     -h, --help               Print this message
     -p SPEC, --package SPEC  Package to clean artifacts for
-    --manifest-path PATH     Path to the manifest to the package to clean
+    --manifest-path PATH     Path to the manifest of the package to clean
     --target TRIPLE          Target triple to clean output for (default all)
-    -v, --verbose            Use verbose output
+    --release                Only clean artifacts of the release profile
+    --doc                    Only clean generated documentation
+    -n, --dry-run            Report what would be removed without removing it
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
 
 If the --package argument is given, then SPEC is a package id specification
 which indicates which package's artifacts should be cleaned out. If it is not
 given, then all packages' artifacts are removed. For more information on SPEC
 and its format, see the `cargo help pkgid` command.
+
+With --dry-run nothing is deleted; instead the number of files and their total
+size is printed. Pass --verbose to also list each path that would be removed.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     debug!("executing; cmd=cargo-clean; args={:?}", os::args());
 
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
@@ -40,6 +54,9 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         config: config,
         spec: options.flag_package.as_ref().map(|s| s.as_slice()),
         target: options.flag_target.as_ref().map(|s| s.as_slice()),
+        dry_run: options.flag_dry_run,
+        release: options.flag_release,
+        doc: options.flag_doc,
     };
     ops::clean(&root, &opts).map(|_| None).map_err(|err| {
       CliError::from_boxed(err, 101)
@@ -0,0 +1,345 @@
+use std::collections::BTreeSet;
+
+use cargo::core::SourceId;
+use cargo::ops;
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+use cargo::util::usage::{Opt, Usage};
+use cargo::util::{CargoResult, CliResult, CliError, Config, human};
+
+#[derive(RustcDecodable)]
+struct Options {
+    arg_shell: Option<String>,
+    flag_targets: Option<String>,
+    flag_manifest_path: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Generate a shell completion script for cargo
+
+Usage:
+    cargo completions [options] <shell>
+    cargo completions [options] --targets KIND
+    cargo completions -h | --help
+
+Options:
+    -h, --help              Print this message
+    --targets KIND          Print the names of the targets of a kind: bin,
+                            example, test or bench
+    --manifest-path PATH    Path to the manifest to list the targets of
+
+The supported shells are bash, zsh and fish. The script completes the commands
+of cargo and their options, and is meant to be loaded by the shell, e.g. for
+bash:
+
+    $ source <(cargo completions bash)
+
+The --targets option is used by the scripts to complete the names of targets.
+";
+
+/// A built-in command along with the usage string declaring its options.
+struct Command {
+    name: String,
+    usage: Usage,
+}
+
+/// How the value of an option is completed.
+enum Value {
+    /// The option doesn't take a value
+    Flag,
+    /// The value is a path
+    File,
+    /// The value is the name of a target of the given kind
+    Target(&'static str),
+    /// The value can't be completed
+    Any,
+}
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    if let Some(kind) = options.flag_targets {
+        let names = try!(target_names(&kind, options.flag_manifest_path,
+                                      config));
+        for name in names.iter() {
+            println!("{}", name);
+        }
+        return Ok(None)
+    }
+
+    let cargo = Usage::parse(::USAGE);
+    let commands = commands();
+    let script = match options.arg_shell.as_ref().map(|s| &s[]) {
+        Some("bash") => bash(&cargo, &commands),
+        Some("zsh") => zsh(&cargo, &commands),
+        Some("fish") => fish(&cargo, &commands),
+        Some(shell) => {
+            let msg = format!("unsupported shell `{}`, expected bash, zsh or \
+                               fish", shell);
+            return Err(CliError::new(&msg, 1))
+        }
+        None => return Err(CliError::new("no shell given", 1)),
+    };
+    print!("{}", script);
+    Ok(None)
+}
+
+fn commands() -> Vec<Command> {
+    let mut commands = Vec::new();
+    macro_rules! add_cmd{ ($name:ident) => ({
+        commands.push(Command {
+            name: stringify!($name).replace("_", "-"),
+            usage: Usage::parse(::$name::USAGE),
+        });
+    }) }
+    each_subcommand!(add_cmd);
+    commands
+}
+
+/// Returns the names of the targets of `kind` in the current package.
+fn target_names(kind: &str, manifest_path: Option<String>, config: &Config)
+                -> CargoResult<BTreeSet<String>> {
+    match kind {
+        "bin" | "example" | "test" | "bench" => {}
+        _ => {
+            return Err(human(format!("--targets must be one of bin, example, \
+                                      test or bench, but found `{}`", kind)))
+        }
+    }
+    let root = try!(find_root_manifest_for_cwd(manifest_path));
+    let source_id = try!(SourceId::for_path(&root.dir_path()));
+    let (pkg, _) = try!(ops::read_package(&root, &source_id, config));
+
+    // Targets are listed once per profile, so pick a profile for each kind
+    Ok(pkg.targets().iter().filter(|t| {
+        let profile = t.profile();
+        match kind {
+            "bin" => {
+                t.is_bin() && profile.env() == "compile" &&
+                    !profile.is_custom_build()
+            }
+            "example" => t.is_example(),
+            "test" => t.is_bin() && profile.env() == "test",
+            _ => t.is_bin() && profile.env() == "bench",
+        }
+    }).map(|t| t.name().to_string()).collect())
+}
+
+fn value(opt: &Opt) -> Value {
+    if opt.value.is_none() { return Value::Flag }
+    match opt.long.as_ref().map(|s| &s[]) {
+        Some("--manifest-path") => Value::File,
+        Some("--bin") => Value::Target("bin"),
+        Some("--example") => Value::Target("example"),
+        Some("--test") => Value::Target("test"),
+        Some("--bench") => Value::Target("bench"),
+        _ => Value::Any,
+    }
+}
+
+fn names(opt: &Opt) -> Vec<&str> {
+    opt.short.iter().chain(opt.long.iter()).map(|s| &s[]).collect()
+}
+
+fn bash(cargo: &Usage, commands: &[Command]) -> String {
+    let top = cargo.options.iter().flat_map(|o| names(o).into_iter())
+                   .collect::<Vec<_>>().connect(" ");
+    let mut s = String::new();
+    s.push_str("_cargo() {\n\
+                \x20   local cur prev cmd\n\
+                \x20   COMPREPLY=()\n\
+                \x20   cur=\"${COMP_WORDS[COMP_CWORD]}\"\n\
+                \x20   prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n\
+                \x20   cmd=\"${COMP_WORDS[1]}\"\n\
+                \n\
+                \x20   if [[ ${COMP_CWORD} -eq 1 ]]; then\n\
+                \x20       local commands=$(cargo --list 2>/dev/null | \
+                                            tail -n +2 | \
+                                            awk '{print $1}')\n");
+    s.push_str(&format!("        COMPREPLY=( $(compgen -W \"${{commands}} {}\" \
+                         -- \"${{cur}}\") )\n", top));
+    s.push_str("        return 0\n\
+                \x20   fi\n\
+                \n\
+                \x20   case \"${cmd}\" in\n");
+
+    for cmd in commands.iter() {
+        s.push_str(&format!("        {})\n", cmd.name));
+        let mut values = String::new();
+        let mut others = Vec::new();
+        for opt in cmd.usage.options.iter() {
+            let pattern = names(opt).connect("|");
+            let reply = match value(opt) {
+                Value::Flag => continue,
+                Value::File => "COMPREPLY=( $(compgen -f -- \"${cur}\") )"
+                                   .to_string(),
+                Value::Target(kind) => {
+                    format!("COMPREPLY=( $(compgen -W \"$(cargo completions \
+                             --targets {} 2>/dev/null)\" -- \"${{cur}}\") )",
+                            kind)
+                }
+                Value::Any => { others.push(pattern); continue }
+            };
+            values.push_str(&format!("                    {})\n\
+                                      \x20                       {}\n\
+                                      \x20                       return 0;;\n",
+                                     pattern, reply));
+        }
+        if !others.is_empty() {
+            values.push_str(&format!("                    {})\n\
+                                      \x20                       return 0;;\n",
+                                     others.connect("|")));
+        }
+        if !values.is_empty() {
+            s.push_str("            case \"${prev}\" in\n");
+            s.push_str(&values);
+            s.push_str("            esac\n");
+        }
+        let opts = cmd.usage.options.iter().flat_map(|o| names(o).into_iter())
+                      .collect::<Vec<_>>().connect(" ");
+        s.push_str(&format!("            COMPREPLY=( $(compgen -W \"{}\" -- \
+                             \"${{cur}}\") )\n\
+                             \x20           return 0;;\n", opts));
+    }
+
+    s.push_str("    esac\n\
+                }\n\
+                complete -F _cargo cargo\n");
+    s
+}
+
+fn zsh(cargo: &Usage, commands: &[Command]) -> String {
+    fn escape(s: &str) -> String {
+        s.replace("'", "'\\''").replace("[", "\\[").replace("]", "\\]")
+         .replace(":", "\\:")
+    }
+    fn spec(opt: &Opt) -> String {
+        let names = names(opt);
+        let mut spec = if opt.repeatable {
+            "'*'".to_string()
+        } else if names.len() > 1 {
+            format!("'({})'", names.connect(" "))
+        } else {
+            String::new()
+        };
+        if names.len() > 1 {
+            spec.push_str(&format!("{{{}}}", names.connect(",")));
+        } else {
+            spec.push_str(names[0]);
+        }
+        spec.push_str(&format!("'[{}]", escape(&opt.help)));
+        let action = match value(opt) {
+            Value::Flag => return spec + "'",
+            Value::File => "_files".to_string(),
+            Value::Target(kind) => format!("_cargo_targets {}", kind),
+            Value::Any => " ".to_string(),
+        };
+        let name = opt.value.as_ref().unwrap().to_lowercase();
+        format!("{}:{}:{}'", spec, name, action)
+    }
+
+    let mut s = String::new();
+    s.push_str("#compdef cargo\n\
+                \n\
+                _cargo_targets() {\n\
+                \x20   compadd -- ${(f)\"$(cargo completions --targets $1 \
+                                         2>/dev/null)\"}\n\
+                }\n\
+                \n\
+                _cargo() {\n\
+                \x20   local curcontext=\"$curcontext\" state line\n\
+                \x20   typeset -A opt_args\n\
+                \n\
+                \x20   _arguments -C \\\n");
+    for opt in cargo.options.iter() {
+        s.push_str(&format!("        {} \\\n", spec(opt)));
+    }
+    s.push_str("        '1: :->command' \\\n\
+                \x20       '*:: :->args'\n\
+                \n\
+                \x20   case $state in\n\
+                \x20       command)\n\
+                \x20           local -a commands builtins external\n\
+                \x20           commands=(\n");
+    for cmd in commands.iter() {
+        let about = cmd.usage.about.as_ref().map(|s| &s[]).unwrap_or("");
+        s.push_str(&format!("                '{}:{}'\n", cmd.name,
+                            about.replace("'", "'\\''").replace(":", "\\:")));
+    }
+    let builtins = commands.iter().map(|c| &c.name[]).collect::<Vec<_>>();
+    s.push_str(&format!("            )\n\
+                         \x20           builtins=({})\n",
+                        builtins.connect(" ")));
+    s.push_str("            external=(${(f)\"$(cargo --list 2>/dev/null | \
+                                          tail -n +2 | \
+                                          awk '{print $1}')\"})\n\
+                \x20           external=(${external:|builtins})\n\
+                \x20           _describe -t commands 'command' commands\n\
+                \x20           _describe -t external 'external command' \
+                                         external\n\
+                \x20           ;;\n\
+                \x20       args)\n\
+                \x20           case $line[1] in\n");
+    for cmd in commands.iter() {
+        s.push_str(&format!("                {})\n", cmd.name));
+        if cmd.usage.options.is_empty() {
+            s.push_str("                    ;;\n");
+            continue
+        }
+        s.push_str("                    _arguments");
+        for opt in cmd.usage.options.iter() {
+            s.push_str(&format!(" \\\n                        {}", spec(opt)));
+        }
+        s.push_str("\n                    ;;\n");
+    }
+    s.push_str("            esac\n\
+                \x20           ;;\n\
+                \x20   esac\n\
+                }\n\
+                \n\
+                _cargo \"$@\"\n");
+    s
+}
+
+fn fish(cargo: &Usage, commands: &[Command]) -> String {
+    fn escape(s: &str) -> String {
+        s.replace("\\", "\\\\").replace("'", "\\'")
+    }
+    fn complete(condition: &str, opt: &Opt) -> String {
+        let mut line = format!("complete -c cargo -n '{}'", condition);
+        if let Some(ref short) = opt.short {
+            line.push_str(&format!(" -s {}", &short[1..]));
+        }
+        if let Some(ref long) = opt.long {
+            line.push_str(&format!(" -l {}", &long[2..]));
+        }
+        match value(opt) {
+            Value::Flag => {}
+            Value::File => line.push_str(" -r"),
+            Value::Target(kind) => {
+                line.push_str(&format!(" -x -a '(cargo completions --targets \
+                                        {} 2>/dev/null)'", kind));
+            }
+            Value::Any => line.push_str(" -x"),
+        }
+        line.push_str(&format!(" -d '{}'\n", escape(&opt.help)));
+        line
+    }
+
+    let mut s = String::new();
+    s.push_str("complete -c cargo -n '__fish_use_subcommand' -f -a \
+                '(cargo --list 2>/dev/null | tail -n +2 | \
+                awk \"{print \\$1}\")'\n");
+    for opt in cargo.options.iter() {
+        s.push_str(&complete("__fish_use_subcommand", opt));
+    }
+    for cmd in commands.iter() {
+        let about = cmd.usage.about.as_ref().map(|s| &s[]).unwrap_or("");
+        s.push_str(&format!("complete -c cargo -n '__fish_use_subcommand' -f \
+                             -a {} -d '{}'\n", cmd.name, escape(about)));
+    }
+    for cmd in commands.iter() {
+        let condition = format!("__fish_seen_subcommand_from {}", cmd.name);
+        for opt in cmd.usage.options.iter() {
+            s.push_str(&complete(&condition, opt));
+        }
+    }
+    s
+}
@@ -0,0 +1,86 @@
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::config::{self, ConfigValue};
+
+#[derive(RustcDecodable)]
+struct Options {
+    cmd_get: bool,
+    arg_key: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Inspect the configuration of cargo
+
+Usage:
+    cargo config get [options] <key>
+    cargo config list [options]
+    cargo config -h | --help
+
+Options:
+    -h, --help              Print this message
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The `list` command prints every configuration value after merging all
+configuration files, environment variables and --config arguments, along with
+where each value was defined. The `get` command prints a single value, like
+`build.jobs`, or all values within a table, like `alias`.
+
+Keys which are only set with environment variables are printed by `get`, but
+not by `list`. The values of credentials, like `registry.token`, are redacted.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+
+    if !options.cmd_get {
+        for (key, val) in try!(config.list()).into_iter() {
+            print_value(&key, val);
+        }
+        return Ok(None)
+    }
+
+    let key = options.arg_key.unwrap();
+    match try!(config.get(&key)) {
+        Some(ConfigValue::Table(..)) => {
+            let prefix = format!("{}.", key);
+            for (key, val) in try!(config.list()).into_iter() {
+                if key.starts_with(&prefix) {
+                    print_value(&key, val);
+                }
+            }
+        }
+        Some(val) => print_value(&key, val),
+        None => {
+            let msg = format!("configuration key `{}` is not set", key);
+            return Err(CliError::new(&msg, 1))
+        }
+    }
+    Ok(None)
+}
+
+/// Prints `val` as TOML, followed by where it was defined. Credentials are
+/// redacted.
+fn print_value(key: &str, val: ConfigValue) {
+    if config::is_secret(key) {
+        println!("{} = \"<redacted>\"  # {}", key,
+                 val.definition_path().display());
+        return
+    }
+    let mut paths = Vec::new();
+    match val {
+        // The elements of a list may come from several files
+        ConfigValue::List(ref list, _) if !list.is_empty() => {
+            for &(_, ref path) in list.iter() {
+                let path = path.display().to_string();
+                if !paths.contains(&path) { paths.push(path) }
+            }
+        }
+        _ => paths.push(val.definition_path().display().to_string()),
+    }
+    println!("{} = {}  # {}", key, val.into_toml(), paths.connect(", "));
+}
@@ -10,7 +10,9 @@ struct Options {
     flag_no_default_features: bool,
     flag_no_deps: bool,
     flag_open: bool,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_package: Option<String>,
 }
 
@@ -29,7 +31,9 @@ Options:
     --features FEATURES      Space-separated list of features to also build
     --no-default-features    Do not build the `default` feature
     --manifest-path PATH     Path to the manifest to document
-    -v, --verbose            Use verbose output
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
 
 By default the documentation for the local package and all dependencies is
 built. The output is all placed in `target/doc` in rustdoc's usual format.
@@ -41,7 +45,8 @@ the `cargo help pkgid` command.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
 
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
@@ -59,6 +64,9 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             spec: options.flag_package.as_ref().map(|s| s.as_slice()),
             lib_only: false,
             exec_engine: None,
+            out_dir: None,
+            cap_lints: None,
+            hooks: true,
         },
     };
 
@@ -5,7 +5,9 @@ use cargo::util::important_paths::find_root_manifest_for_cwd;
 #[derive(RustcDecodable)]
 struct Options {
     flag_manifest_path: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
 }
 
 pub const USAGE: &'static str = "
@@ -17,7 +19,9 @@ Usage:
 Options:
     -h, --help              Print this message
     --manifest-path PATH    Path to the manifest to fetch dependencies for
-    -v, --verbose           Use verbose output
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
 If a lockfile is available, this command will ensure that all of the git
 dependencies and/or registries dependencies are downloaded and locally
@@ -30,7 +34,8 @@ all updated.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
     try!(ops::fetch(&root, config).map_err(|e| {
         CliError::from_boxed(e, 101)
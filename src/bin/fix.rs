@@ -0,0 +1,83 @@
+use std::os;
+
+use cargo::ops;
+use cargo::util::important_paths::{find_root_manifest_for_cwd};
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_jobs: Option<u32>,
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+    flag_tests: bool,
+    flag_allow_dirty: bool,
+    flag_allow_no_vcs: bool,
+}
+
+pub const USAGE: &'static str = "
+Automatically fix compiler warnings and errors in a local package
+
+Usage:
+    cargo fix [options]
+
+Options:
+    -h, --help               Print this message
+    -j N, --jobs N           The number of jobs to run in parallel
+    --tests                  Also fix the tests of the package
+    --features FEATURES      Space-separated list of features to also build
+    --no-default-features    Do not build the `default` feature
+    --target TRIPLE          Build for the target triple
+    --manifest-path PATH     Path to the manifest of the package to fix
+    --allow-dirty            Fix files even if they have uncommitted changes
+    --allow-no-vcs           Fix the package even if it isn't under git
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
+
+The package is compiled, and the fixes the compiler suggests for its warnings
+and errors which it knows to be correct are applied to its source files, even
+if the compilation failed. The package is then compiled again: if it compiled
+before the fixes but doesn't anymore, the original sources are restored.
+
+As the source files are rewritten, the package needs to be in a git repository
+without uncommitted changes, unless --allow-dirty or --allow-no-vcs is passed.
+Without git, the original of each fixed file is kept next to it, with an
+`.orig` extension.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    debug!("executing; cmd=cargo-fix; args={:?}", os::args());
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let opts = ops::FixOptions {
+        compile_opts: ops::CompileOptions {
+            env: if options.flag_tests {"test"} else {"compile"},
+            config: config,
+            jobs: options.flag_jobs,
+            target: options.flag_target.as_ref().map(|t| t.as_slice()),
+            dev_deps: options.flag_tests,
+            features: &options.flag_features,
+            no_default_features: options.flag_no_default_features,
+            spec: None,
+            lib_only: false,
+            exec_engine: None,
+            out_dir: None,
+            cap_lints: None,
+            hooks: true,
+        },
+        allow_dirty: options.flag_allow_dirty,
+        allow_no_vcs: options.flag_allow_no_vcs,
+    };
+
+    ops::fix(&root, &opts).map(|_| None).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    })
+}
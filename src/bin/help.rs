@@ -1,4 +1,9 @@
-use cargo::util::{CliResult, CliError, Config};
+use std::old_io::process::{Command, InheritFd};
+use std::old_io::{stdio, File, TempDir};
+
+use cargo;
+use cargo::util::usage::Usage;
+use cargo::util::{CargoResult, CliResult, CliError, Config};
 
 #[derive(RustcDecodable)]
 struct Options;
@@ -12,6 +17,9 @@ Usage:
 
 Options:
     -h, --help          Print this message
+
+The manual page of built-in commands is shown with `man` when printing to a
+terminal, and as plain text otherwise.
 ";
 
 pub fn execute(_: Options, _: &Config) -> CliResult<Option<()>> {
@@ -20,3 +28,31 @@ pub fn execute(_: Options, _: &Config) -> CliResult<Option<()>> {
     // cargo command.
     Err(CliError::new("Help command should not be executed directly.", 101))
 }
+
+/// Shows the manual page of the built-in command `command`, generated from
+/// its usage string.
+pub fn manual(command: &str, usage: &str) -> CargoResult<()> {
+    let usage = Usage::parse(usage);
+    let name = format!("cargo-{}", command);
+
+    if stdio::stdout_raw().isatty() {
+        let dir = try!(TempDir::new("cargo-help"));
+        let page = dir.path().join(format!("{}.1", name));
+        let contents = usage.man_page(&name, &cargo::version());
+        try!(File::create(&page).write_str(&contents));
+        // A path is recognized as a page to format by both the GNU and BSD
+        // versions of `man`. If it can't be run, fall back to plain text.
+        let status = Command::new("man").arg(&page)
+                                        .stdin(InheritFd(0))
+                                        .stdout(InheritFd(1))
+                                        .stderr(InheritFd(2))
+                                        .status();
+        match status {
+            Ok(ref status) if status.success() => return Ok(()),
+            _ => {}
+        }
+    }
+
+    print!("{}", usage.text(&name));
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use cargo::ops::{self, CrateInfo};
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    arg_crate: String,
+    flag_host: Option<String>,
+    flag_offline: bool,
+    flag_message_format: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Show information about a crate in the registry
+
+Usage:
+    cargo info [options] <crate>
+
+Options:
+    -h, --help              Print this message
+    --host HOST             Host of the registry to look the crate up in
+    --offline               Don't update the index or download the crate
+    --message-format FMT    Output format: plain or json [default: plain]
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The versions, features and dependencies of the crate are read from the index
+of the registry, and its description, license and links from the manifest of
+its newest version, which is downloaded if it hasn't been before. With
+`--offline` the local copy of the index is used, and the description, license
+and links are only shown if the crate was downloaded before.
+
+With `--message-format json` the information is printed as a JSON object.
+";
+
+pub fn execute(options: Options,
+               config: &Config) -> CliResult<Option<CrateInfo>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let json = match options.flag_message_format.as_ref().map(|s| &s[]) {
+        None | Some("plain") => false,
+        Some("json") => true,
+        Some(s) => {
+            let msg = format!("--message-format must be plain or json, but \
+                               found `{}`", s);
+            return Err(CliError::new(&msg, 1))
+        }
+    };
+
+    let opts = ops::InfoOptions {
+        config: config,
+        index: options.flag_host.clone(),
+        offline: options.flag_offline,
+    };
+    let info = try!(ops::info(&options.arg_crate, &opts));
+    if json { return Ok(Some(info)) }
+
+    println!("{} {}", info.name, info.version);
+    if let Some(ref description) = info.description {
+        println!("{}", description.trim());
+    }
+    let fields = [("license", &info.license),
+                  ("homepage", &info.homepage),
+                  ("repository", &info.repository),
+                  ("documentation", &info.documentation)];
+    for &(name, value) in fields.iter() {
+        if let Some(ref value) = *value {
+            println!("{}: {}", name, value);
+        }
+    }
+    println!("versions: {}", info.versions.connect(", "));
+
+    if !info.features.is_empty() {
+        println!("features:");
+        for (name, features) in info.features.iter() {
+            println!("    {} = [{}]", name, features.connect(", "));
+        }
+    }
+    for &(kind, header) in [("normal", "dependencies"),
+                            ("build", "build-dependencies"),
+                            ("dev", "dev-dependencies")].iter() {
+        let mut deps = info.dependencies.iter().filter(|d| d.kind == kind)
+                           .peekable();
+        if deps.peek().is_none() { continue }
+        println!("{}:", header);
+        for dep in deps {
+            let optional = if dep.optional {" (optional)"} else {""};
+            println!("    {} = \"{}\"{}", dep.name, dep.req, optional);
+        }
+    }
+    Ok(None)
+}
@@ -0,0 +1,51 @@
+use cargo::ops;
+use cargo::util::{CliResult, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+List the licenses of a package and its dependencies
+
+Usage:
+    cargo licenses [options]
+
+Options:
+    -h, --help              Print this message
+    --manifest-path PATH    Path to the manifest of the package
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+Every package in the dependency graph is listed under the license expression
+in the `license` key of its manifest. Packages which only specify a
+`license-file` are listed under `license-file`, and a warning is printed for
+each package without either.
+
+Dependencies are taken from the lockfile, which is generated if there is none.
+The licenses of registry packages are read from the `license` key of their
+entries in the index of the registry, so nothing is downloaded. Registry
+packages whose entries have no license are reported as having no license
+metadata.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let licenses = try!(ops::licenses(&root, config));
+    for (license, ids) in licenses.iter() {
+        println!("{} ({})", license, ids.len());
+        for id in ids.iter() {
+            println!("    {} {}", id.name(), id.version());
+        }
+    }
+    Ok(None)
+}
@@ -1,18 +1,29 @@
 use cargo::util::{CliResult, CliError, human, ChainError, Config};
 use cargo::util::important_paths::{find_root_manifest_for_cwd};
+use cargo::util::workspace;
 
 #[derive(RustcDecodable)]
 struct LocateProjectFlags {
     flag_manifest_path: Option<String>,
+    flag_workspace: bool,
+    flag_message_format: Option<String>,
 }
 
 pub const USAGE: &'static str = "
+Print the location of the manifest of the current project
+
 Usage:
     cargo locate-project [options]
 
 Options:
-    --manifest-path PATH    Path to the manifest to build benchmarks for
+    --manifest-path PATH    Path to the manifest to locate
+    --workspace             Locate the manifest of the workspace root
+    --message-format FMT    Output format: json or plain [default: json]
     -h, --help              Print this message
+
+The location is printed as a JSON object with the path of the manifest in its
+`root` key, or as just the path with `--message-format plain`. If the package
+isn't a member of a workspace, `--workspace` locates its own manifest.
 ";
 
 #[derive(RustcEncodable)]
@@ -22,7 +33,12 @@ struct ProjectLocation {
 
 pub fn execute(flags: LocateProjectFlags,
                _: &Config) -> CliResult<Option<ProjectLocation>> {
-    let root = try!(find_root_manifest_for_cwd(flags.flag_manifest_path));
+    let mut root = try!(find_root_manifest_for_cwd(flags.flag_manifest_path));
+    if flags.flag_workspace {
+        if let Some(dir) = try!(workspace::find_root(&root.dir_path())) {
+            root = dir.join("Cargo.toml");
+        }
+    }
 
     let string = try!(root.as_str()
                       .chain_error(|| human("Your project path contains \
@@ -30,5 +46,18 @@ pub fn execute(flags: LocateProjectFlags,
                                              Unicode"))
                       .map_err(|e| CliError::from_boxed(e, 1)));
 
-    Ok(Some(ProjectLocation { root: string.to_string() }))
+    match flags.flag_message_format.as_ref().map(|s| &s[]) {
+        None | Some("json") => {
+            Ok(Some(ProjectLocation { root: string.to_string() }))
+        }
+        Some("plain") => {
+            println!("{}", string);
+            Ok(None)
+        }
+        Some(s) => {
+            let msg = format!("--message-format must be json or plain, but \
+                               found `{}`", s);
+            Err(CliError::new(&msg, 1))
+        }
+    }
 }
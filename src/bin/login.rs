@@ -1,4 +1,5 @@
 use std::old_io;
+use std::old_io::stdio;
 
 use cargo::ops;
 use cargo::core::{SourceId, Source};
@@ -9,7 +10,10 @@ use cargo::util::{CliResult, CliError, Config};
 struct Options {
     flag_host: Option<String>,
     arg_token: Option<String>,
-    flag_verbose: bool,
+    flag_stdin: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
 }
 
 pub const USAGE: &'static str = "
@@ -21,14 +25,37 @@ Usage:
 Options:
     -h, --help              Print this message
     --host HOST             Host to set the token for
-    -v, --verbose           Use verbose output
+    --stdin                 Read the token from stdin without prompting
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
+The token is also read from stdin if it isn't a terminal, for example when
+piping the token into cargo in scripts. Registry commands can alternatively
+take their token from the CARGO_REGISTRY_TOKEN environment variable, in which
+case no login is needed at all.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let piped = !stdio::stdin_raw().isatty();
     let token = match options.arg_token.clone() {
+        Some(..) if options.flag_stdin => {
+            return Err(CliError::new("cannot specify both a token and --stdin",
+                                     1))
+        }
         Some(token) => token,
+        None if options.flag_stdin || piped => {
+            let token = try!(old_io::stdin().read_to_string().map_err(|e| {
+                CliError::new(&format!("failed to read the token from stdin: \
+                                        {}", e), 101)
+            }));
+            if token.trim().is_empty() {
+                return Err(CliError::new("no token was provided on stdin", 101))
+            }
+            token
+        }
         None => {
             let err = (|| {
                 let src = try!(SourceId::for_central(config));
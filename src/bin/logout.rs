@@ -0,0 +1,36 @@
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Remove the api token of the registry from local storage
+
+Usage:
+    cargo logout [options]
+
+Options:
+    -h, --help              Print this message
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The token is revoked on the registry and then removed from wherever `cargo
+login` saved it, which is either the credentials file or the configured
+credential provider. Tokens set in a project's configuration, the environment
+or with --config are left alone, and are reported if there are any.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    try!(ops::registry_logout(config).map_err(|e| {
+        CliError::from_boxed(e, 101)
+    }));
+    Ok(None)
+}
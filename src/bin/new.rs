@@ -5,8 +5,12 @@ use cargo::util::{CliResult, CliError, Config};
 
 #[derive(RustcDecodable)]
 struct Options {
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_bin: bool,
+    flag_lib: bool,
+    flag_name: Option<String>,
     arg_path: String,
     flag_vcs: Option<ops::VersionControl>,
 }
@@ -21,22 +25,37 @@ Usage:
 Options:
     -h, --help          Print this message
     --vcs <vcs>         Initialize a new repository for the given version
-                        control system (git or hg) or do not initialize any version
-                        control at all (none) overriding a global configuration.
-    --bin               Use a binary instead of a library template
-    -v, --verbose       Use verbose output
+                        control system (git or hg) or do not initialize any
+                        version control at all (none) overriding a global
+                        configuration. Defaults to git, or to none when
+                        <path> is already inside of a repository.
+    --bin               Use a binary template
+    --lib               Use a library template (the default)
+    --name NAME         Set the resulting package name, defaulting to the
+                        name of the destination directory
+    -v, --verbose ...   Use verbose output (-vv for very verbose output)
+    -q, --quiet         Print no status lines, only warnings and errors
+    --color WHEN        Coloring: auto, always, never
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     debug!("executing; cmd=cargo-new; args={:?}", os::args());
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
 
-    let Options { flag_bin, arg_path, flag_vcs, .. } = options;
+    let Options {
+        flag_bin, flag_lib, arg_path, flag_name, flag_vcs, ..
+    } = options;
+
+    if flag_bin && flag_lib {
+        return Err(CliError::new("can't specify both --lib and --bin", 101))
+    }
 
     let opts = ops::NewOptions {
         version_control: flag_vcs,
         path: &arg_path,
         bin: flag_bin,
+        name: flag_name.as_ref().map(|s| s.as_slice()),
     };
 
     ops::new(opts, config).map(|_| None).map_err(|err| {
@@ -0,0 +1,94 @@
+use std::cmp;
+
+use cargo::ops::{self, OutdatedDependency};
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_manifest_path: Option<String>,
+    flag_workspace: bool,
+    flag_message_format: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Show dependencies which have newer versions in the registry
+
+Usage:
+    cargo outdated [options]
+
+Options:
+    -h, --help              Print this message
+    --manifest-path PATH    Path to the manifest of the package
+    --workspace             Check all members of the workspace
+    --message-format FMT    Output format: plain or json [default: plain]
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The index of the registry is updated, and each registry dependency whose
+locked version in Cargo.lock isn't the newest one is listed along with the
+newest version matching its version requirement, which `cargo update` would
+upgrade it to, and the newest version overall, which requires editing the
+manifest.
+
+With `--message-format json` the dependencies are printed as a JSON array of
+objects with the keys `package`, `name`, `kind`, `locked`, `compatible` and
+`latest`, and status lines are printed to stderr instead of stdout.
+
+Prereleases are only reported as the newest version if the locked version is
+a prerelease as well.
+";
+
+pub fn execute(options: Options,
+               config: &Config) -> CliResult<Option<Vec<OutdatedDependency>>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let json = match options.flag_message_format.as_ref().map(|s| &s[]) {
+        None | Some("plain") => false,
+        Some("json") => true,
+        Some(s) => {
+            let msg = format!("--message-format must be plain or json, but \
+                               found `{}`", s);
+            return Err(CliError::new(&msg, 1))
+        }
+    };
+    config.shell().set_status_to_stderr(json);
+
+    let opts = ops::OutdatedOptions {
+        config: config,
+        workspace: options.flag_workspace,
+    };
+    let deps = try!(ops::outdated(&root, &opts));
+    if json { return Ok(Some(deps)) }
+
+    if deps.is_empty() {
+        println!("All dependencies are up to date");
+        return Ok(None)
+    }
+
+    let mut rows = vec![["Package", "Name", "Kind", "Locked", "Compatible",
+                         "Latest"].iter().map(|s| s.to_string())
+                                         .collect::<Vec<_>>()];
+    for dep in deps.into_iter() {
+        rows.push(vec![dep.package, dep.name, dep.kind, dep.locked,
+                       dep.compatible, dep.latest]);
+    }
+    // The package column is only interesting with several packages
+    let skip = if options.flag_workspace {0} else {1};
+    let widths = (0..rows[0].len()).map(|i| {
+        rows.iter().fold(0, |w, row| cmp::max(w, row[i].len()))
+    }).collect::<Vec<_>>();
+    for row in rows.iter() {
+        let line = row.iter().zip(widths.iter()).skip(skip).map(|(s, &w)| {
+            format!("{:1$}", s, w)
+        }).collect::<Vec<_>>().connect("  ");
+        println!("{}", line.trim_right());
+    }
+    Ok(None)
+}
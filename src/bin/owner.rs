@@ -4,11 +4,14 @@ use cargo::util::{CliResult, CliError, Config};
 #[derive(RustcDecodable)]
 struct Options {
     arg_crate: Option<String>,
+    flag_manifest_path: Option<String>,
     flag_token: Option<String>,
     flag_add: Option<Vec<String>>,
     flag_remove: Option<Vec<String>>,
     flag_index: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_list: bool,
 }
 
@@ -25,7 +28,11 @@ Options:
     -l, --list              List owners of a crate
     --index INDEX           Registry index to modify owners for
     --token TOKEN           API token to use when authenticating
-    -v, --verbose           Use verbose output
+    --manifest-path PATH    Path to the manifest of the crate, if <crate> is not
+                            given
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
 This command will modify the owners for a package on the specified registry (or
 default). Note that owners of a package can upload new versions, yank old
@@ -33,9 +40,11 @@ versions, and also modify the set of owners, so take caution!
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let opts = ops::OwnersOptions {
         krate: options.arg_crate,
+        manifest_path: options.flag_manifest_path,
         token: options.flag_token,
         index: options.flag_index,
         to_add: options.flag_add,
@@ -4,11 +4,15 @@ use cargo::util::important_paths::find_root_manifest_for_cwd;
 
 #[derive(RustcDecodable)]
 struct Options {
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_manifest_path: Option<String>,
     flag_no_verify: bool,
     flag_no_metadata: bool,
     flag_list: bool,
+    flag_allow_dirty: bool,
+    flag_workspace: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -22,18 +26,42 @@ Options:
     -l, --list              Print files included in a package without making one
     --no-verify             Don't verify the contents by building them
     --no-metadata           Ignore warnings about a lack of human-usable metadata
-    --manifest-path PATH    Path to the manifest to compile
-    -v, --verbose           Use verbose output
+    --allow-dirty           Allow packaging with uncommitted changes
+    --workspace             Package all members of the workspace
+    --manifest-path PATH    Path to the manifest to package
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
+The --list flag prints the exact set of files which would be archived, after
+applying the `include` and `exclude` manifest keys as well as any VCS ignore
+rules. Nothing is built and no tarball is created.
+
+When the package lives in a git repository, packaging fails if any of its files
+have uncommitted changes unless --allow-dirty is passed.
+
+With --workspace, every member of the workspace without `publish = false` is
+packaged after the members it depends on, and verified against their freshly
+packaged sources, so they don't need to be in the registry yet.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
-    ops::package(&root, config,
-                 !options.flag_no_verify,
-                 options.flag_list,
-                 !options.flag_no_metadata).map(|_| None).map_err(|err| {
+    let opts = ops::PackageOpts {
+        config: config,
+        verify: !options.flag_no_verify,
+        list: options.flag_list,
+        check_metadata: !options.flag_no_metadata,
+        allow_dirty: options.flag_allow_dirty,
+    };
+    let res = if options.flag_workspace {
+        ops::package_workspace(&root, &opts).map(|_| ())
+    } else {
+        ops::package(&root, &opts).map(|_| ())
+    };
+    res.map(|_| None).map_err(|err| {
         CliError::from_boxed(err, 101)
     })
 }
@@ -4,7 +4,9 @@ use cargo::util::important_paths::{find_root_manifest_for_cwd};
 
 #[derive(RustcDecodable)]
 struct Options {
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_manifest_path: Option<String>,
     arg_spec: Option<String>,
 }
@@ -17,16 +19,19 @@ Usage:
 
 Options:
     -h, --help              Print this message
-    --manifest-path PATH    Path to the manifest to the package to clean
-    -v, --verbose           Use verbose output
+    --manifest-path PATH    Path to the manifest of the package
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
 Given a <spec> argument, print out the fully qualified package id specifier.
 This command will generate an error if <spec> is ambiguous as to which package
 it refers to in the dependency graph. If no <spec> is given, then the pkgid for
 the local package is printed.
 
-This command requires that a lockfile is available and dependencies have been
-fetched.
+The <spec> is looked up among the members of the workspace of the package,
+and among the dependencies recorded in the lockfile. If there is no lockfile,
+the dependencies are resolved without writing one.
 
 Example Package IDs
 
@@ -43,7 +48,8 @@ Example Package IDs
 
 pub fn execute(options: Options,
                config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path.clone()));
 
     let spec = options.arg_spec.as_ref().map(|s| s.as_slice());
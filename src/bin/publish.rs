@@ -7,8 +7,12 @@ struct Options {
     flag_host: Option<String>,
     flag_token: Option<String>,
     flag_manifest_path: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_no_verify: bool,
+    flag_allow_dirty: bool,
+    flag_workspace: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -22,23 +26,45 @@ Options:
     --host HOST             Host to upload the package to
     --token TOKEN           Token to use when uploading
     --no-verify             Don't verify package tarball before publish
-    --manifest-path PATH    Path to the manifest to compile
-    -v, --verbose           Use verbose output
+    --allow-dirty           Allow publishing with uncommitted changes
+    --workspace             Publish all members of the workspace
+    --manifest-path PATH    Path to the manifest to publish
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
+With `--workspace`, every member of the workspace is published after the
+members it depends on. Members whose version is already in the registry are
+skipped, so an interrupted release can be resumed by running it again.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let Options {
         flag_token: token,
         flag_host: host,
         flag_manifest_path,
         flag_no_verify: no_verify,
+        flag_allow_dirty: allow_dirty,
+        flag_workspace: workspace,
         ..
     } = options;
 
     let root = try!(find_root_manifest_for_cwd(flag_manifest_path.clone()));
-    ops::publish(&root, config, token, host, !no_verify).map(|_| None).map_err(|err| {
+    let opts = ops::PublishOpts {
+        config: config,
+        token: token,
+        index: host,
+        verify: !no_verify,
+        allow_dirty: allow_dirty,
+    };
+    let res = if workspace {
+        ops::publish_workspace(&root, &opts)
+    } else {
+        ops::publish(&root, &opts)
+    };
+    res.map(|_| None).map_err(|err| {
         CliError::from_boxed(err, 101)
     })
 }
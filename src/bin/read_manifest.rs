@@ -1,24 +1,32 @@
 use cargo::core::{Package, Source};
 use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
 use cargo::sources::{PathSource};
 
 #[derive(RustcDecodable)]
 struct Options {
-    flag_manifest_path: String,
+    flag_manifest_path: Option<String>,
 }
 
 pub const USAGE: &'static str = "
+Print a JSON representation of a Cargo.toml manifest
+
 Usage:
-    cargo read-manifest [options] --manifest-path=PATH
+    cargo read-manifest [options]
     cargo read-manifest -h | --help
 
 Options:
     -h, --help              Print this message
+    --manifest-path PATH    Path to the manifest to read
     -v, --verbose           Use verbose output
+
+The package is printed as JSON, in the format described in the documentation
+of the manifest.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<Package>> {
-    let path = Path::new(&options.flag_manifest_path);
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    let path = root.dir_path();
     let mut source = try!(PathSource::for_path(&path, config).map_err(|e| {
         CliError::new(e.description(), 1)
     }));
@@ -0,0 +1,44 @@
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::{find_root_manifest_for_cwd};
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_package: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Report problems found by the last build of a local package
+
+Usage:
+    cargo report future-incompat [options]
+    cargo report -h | --help
+
+Options:
+    -h, --help               Print this message
+    -p NAME, --package NAME  Only report the problems of this package
+    --manifest-path PATH     Path to the manifest of the package
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
+
+The `future-incompat` report lists the warnings of the compiler about code
+which future versions of Rust will reject, by package. The warnings of every
+package of a build are collected, including those of dependencies and of
+packages which weren't rebuilt, and the report is saved in the target directory
+once the build finished.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let package = options.flag_package.as_ref().map(|s| s.as_slice());
+    ops::future_incompat_report(&root, package, config).map(|_| None)
+       .map_err(|err| CliError::from_boxed(err, 101))
+}
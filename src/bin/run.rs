@@ -14,7 +14,9 @@ struct Options {
     flag_no_default_features: bool,
     flag_target: Option<String>,
     flag_manifest_path: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_release: bool,
     arg_args: Vec<String>,
 }
@@ -35,7 +37,9 @@ Options:
     --no-default-features   Do not build the `default` feature
     --target TRIPLE         Build for the target triple
     --manifest-path PATH    Path to the manifest to execute
-    -v, --verbose           Use verbose output
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
 If neither `--bin` or `--example` are given, then if the project only has one
 bin target it will be run. Otherwise `--bin` specifies the bin target to run,
@@ -46,7 +50,8 @@ All of the trailing arguments are passed as to the binary to run.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
     let env = match (options.flag_release, options.flag_example.is_some()) {
@@ -66,6 +71,9 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         spec: None,
         lib_only: false,
         exec_engine: None,
+        out_dir: None,
+        cap_lints: None,
+        hooks: true,
     };
 
     let (target_kind, name) = match (options.flag_bin, options.flag_example) {
@@ -0,0 +1,61 @@
+use rustc_serialize::json::Json;
+
+use cargo::ops::{self, SbomFormat};
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_format: String,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Generate a software bill of materials for a package
+
+Usage:
+    cargo sbom [options]
+
+Options:
+    -h, --help              Print this message
+    --format FMT            Format: spdx or cyclonedx [default: spdx]
+    --manifest-path PATH    Path to the manifest of the package
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The bill of materials lists every package in the dependency graph of the
+package with its version, declared license, source and, for packages from a
+registry, the SHA-256 checksum of the downloaded `.crate` file. It is printed
+as JSON, in the SPDX 2.2 format or the CycloneDX 1.4 format.
+
+Only the packages a build of the package with its default features uses are
+listed, so its dev-dependencies are left out.
+
+Dependencies are taken from the lockfile, which is generated if there is none,
+and packages which haven't been downloaded yet are downloaded to compute their
+checksums.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<Json>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    config.shell().set_status_to_stderr(true);
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let format = match &options.flag_format[] {
+        "spdx" => SbomFormat::Spdx,
+        "cyclonedx" => SbomFormat::CycloneDx,
+        s => {
+            let msg = format!("--format must be spdx or cyclonedx, but \
+                               found `{}`", s);
+            return Err(CliError::new(&msg, 1))
+        }
+    };
+
+    let sbom = try!(ops::sbom(&root, format, config));
+    Ok(Some(sbom))
+}
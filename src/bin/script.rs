@@ -0,0 +1,74 @@
+use std::old_io::process::ExitStatus;
+
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    arg_script: String,
+    arg_args: Vec<String>,
+    flag_jobs: Option<u32>,
+    flag_release: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Build and run a single-file Rust program
+
+Usage:
+    cargo script [options] <script> [--] [<args>...]
+
+Options:
+    -h, --help              Print this message
+    -j N, --jobs N          The number of jobs to run in parallel
+    --release               Build the script in release mode, with optimizations
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The script is built as the binary of a package which cargo generates for it,
+and run with the trailing arguments. The dependencies of the script, and any
+other sections of its manifest, can be given in a code block marked as `cargo`
+in a doc comment at the top of the script:
+
+    //! ```cargo
+    //! [dependencies]
+    //! time = \"0.1\"
+    //! ```
+    extern crate time;
+    fn main() { println!(\"{:?}\", time::now()); }
+
+The package is kept in $CARGO_HOME/script, in a directory named after a hash of
+the contents of the script, so running a script again which didn't change
+doesn't build it again. Paths of path dependencies are relative to the script.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+
+    let compile_opts = ops::CompileOptions {
+        jobs: options.flag_jobs,
+        .. ops::CompileOptions::new(if options.flag_release {
+            "release"
+        } else {
+            "compile"
+        }, config)
+    };
+
+    let err = try!(ops::script(&Path::new(&options.arg_script), &compile_opts,
+                               &options.arg_args).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    }));
+    match err {
+        None => Ok(None),
+        Some(err) => {
+            Err(match err.exit {
+                Some(ExitStatus(i)) => CliError::from_boxed(box err, i as i32),
+                _ => CliError::from_boxed(box err, 101),
+            })
+        }
+    }
+}
@@ -4,7 +4,11 @@ use cargo::util::{CliResult, CliError, Config};
 #[derive(RustcDecodable)]
 struct Options {
     flag_host: Option<String>,
-    flag_verbose: bool,
+    flag_registry: Option<String>,
+    flag_limit: u32,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     arg_query: String
 }
 
@@ -17,18 +21,43 @@ Usage:
 Options:
     -h, --help              Print this message
     --host HOST             Host of a registry to search in
-    -v, --verbose           Use verbose output
+    --registry NAME         Name of a configured registry to search in
+    --limit N               Number of results to show [default: 10]
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+Each matching crate is listed with its newest version and its description. A
+crate whose name is exactly the query is listed first and highlighted. At most
+100 results can be shown.
+
+The registry searched is the one given with `--host`, the one configured as
+`registries.<name>.index` for `--registry <name>`, or the default one.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let Options {
         flag_host: host,
+        flag_registry: registry,
+        flag_limit: limit,
         arg_query: query,
         ..
     } = options;
 
-    ops::search(&query, config, host)
+    let host = match (host, registry) {
+        (Some(..), Some(..)) => {
+            return Err(CliError::new("only one of --host and --registry may \
+                                      be given", 1))
+        }
+        (Some(host), None) => Some(host),
+        (None, Some(name)) => try!(ops::named_registry_index(config, &name)
+                                      .map(Some)),
+        (None, None) => None,
+    };
+
+    ops::search(&query, config, host, limit)
         .map(|_| None)
         .map_err(|err| CliError::from_boxed(err, 101))
 }
@@ -0,0 +1,59 @@
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    arg_version: Option<String>,
+    flag_bump: Option<String>,
+    flag_package: Option<String>,
+    flag_workspace: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Change the version of packages in the local workspace
+
+Usage:
+    cargo set-version [options] <version>
+    cargo set-version [options] --bump LEVEL
+
+Options:
+    -h, --help               Print this message
+    --bump LEVEL             Increment the version: major, minor or patch
+    -p NAME, --package NAME  Member of the workspace to change
+    --workspace              Change all members of the workspace
+    --manifest-path PATH     Path to the manifest of the package to change
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
+
+The `version` field of the manifest is changed in place. With --bump, the
+given component of the current version is incremented, the lower ones are reset
+to 0 and any pre-release is dropped; with --workspace each member is bumped
+from its own version.
+
+A version inherited from `[workspace.package]` is changed in the root manifest,
+which changes all members inheriting it. Members depending on a changed package
+by path have the version requirement of that dependency updated to the new
+version, and the lock files of the workspace are updated to match.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+    let opts = ops::SetVersionOptions {
+        config: config,
+        version: options.arg_version.as_ref().map(|s| s.as_slice()),
+        bump: options.flag_bump.as_ref().map(|s| s.as_slice()),
+        spec: options.flag_package.as_ref().map(|s| s.as_slice()),
+        workspace: options.flag_workspace,
+    };
+    ops::set_version(&root, &opts).map(|_| None).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    })
+}
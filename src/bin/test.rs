@@ -15,7 +15,10 @@ struct Options {
     flag_no_run: bool,
     flag_package: Option<String>,
     flag_target: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+    flag_watch: bool,
 }
 
 pub const USAGE: &'static str = "
@@ -34,7 +37,10 @@ Options:
     --no-default-features    Do not build the `default` feature
     --target TRIPLE          Build for the target triple
     --manifest-path PATH     Path to the manifest to build tests for
-    -v, --verbose            Use verbose output
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
+    --watch                  Run the tests again whenever a source file changes
 
 All of the trailing arguments are passed to the test binaries generated for
 filtering tests and generally providing options configuring how they run. For
@@ -48,11 +54,16 @@ current package is tested. For more information on SPEC and its format, see the
 `cargo help pkgid` command.
 
 Compilation can be configured via the `test` profile in the manifest.
+
+With --watch, the tests are built and run again whenever a source file of the
+package or of its path dependencies changes, until cargo is interrupted with
+Ctrl-C.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
 
     let ops = ops::TestOptions {
         name: options.flag_test.as_ref().map(|s| s.as_slice()),
@@ -68,20 +79,29 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
             spec: options.flag_package.as_ref().map(|s| s.as_slice()),
             lib_only: false,
             exec_engine: None,
+            out_dir: None,
+            cap_lints: None,
+            hooks: true,
         },
     };
 
-    let err = try!(ops::run_tests(&root, &ops,
-                                  &options.arg_args).map_err(|err| {
-        CliError::from_boxed(err, 101)
-    }));
-    match err {
-        None => Ok(None),
-        Some(err) => {
-            Err(match err.exit {
-                Some(ExitStatus(i)) => CliError::new("", i as i32),
-                _ => CliError::from_boxed(box Human(err), 101)
-            })
+    let test = || {
+        let err = try!(ops::run_tests(&root, &ops,
+                                      &options.arg_args).map_err(|err| {
+            CliError::from_boxed(err, 101)
+        }));
+        match err {
+            None => Ok(None),
+            Some(err) => {
+                Err(match err.exit {
+                    Some(ExitStatus(i)) => CliError::new("", i as i32),
+                    _ => CliError::from_boxed(box Human(err), 101)
+                })
+            }
         }
-    }
+    };
+    if !options.flag_watch { return test() }
+    ops::watch(&root, config, test).map(|_| None).map_err(|err| {
+        CliError::from_boxed(err, 101)
+    })
 }
@@ -0,0 +1,70 @@
+use cargo::ops::{self, TreeEdges};
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_edges: String,
+    flag_features: Vec<String>,
+    flag_no_default_features: bool,
+    flag_target: Option<String>,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Display the dependency graph of a package as a tree
+
+Usage:
+    cargo tree [options]
+
+Options:
+    -h, --help              Print this message
+    -e, --edges KIND        Edges to show: normal or features [default: normal]
+    --features FEATURES     Space-separated list of features to activate
+    --no-default-features   Do not activate the `default` feature
+    --target TRIPLE         Resolve the dependencies for the target triple
+    --manifest-path PATH    Path to the manifest of the package
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The dependencies are resolved like for a build with the same features and
+target, including the dev-dependencies of the package. Packages which already
+appeared in the tree are marked with `(*)` rather than shown again.
+
+With `--edges features`, the tree shows which features were activated and why:
+a dependency points to the features of the package it activates, like
+`bar feature \"default\"`, and a feature points to its package and to the
+features it activates in turn. The path from the root to a feature is what
+caused it to be activated. Optional dependencies are shown under the feature
+which activated them.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
+
+    let edges = match &options.flag_edges[] {
+        "normal" => TreeEdges::Normal,
+        "features" => TreeEdges::Features,
+        s => {
+            let msg = format!("--edges must be normal or features, but \
+                               found `{}`", s);
+            return Err(CliError::new(&msg, 1))
+        }
+    };
+
+    let tree = try!(ops::tree(&root, &ops::TreeOptions {
+        config: config,
+        edges: edges,
+        features: &options.flag_features,
+        no_default_features: options.flag_no_default_features,
+        target: options.flag_target.as_ref().map(|t| t.as_slice()),
+    }));
+    print!("{}", tree);
+    Ok(None)
+}
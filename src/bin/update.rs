@@ -9,8 +9,11 @@ struct Options {
     flag_package: Option<String>,
     flag_aggressive: bool,
     flag_precise: Option<String>,
+    flag_explain: bool,
     flag_manifest_path: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
 }
 
 pub const USAGE: &'static str = "
@@ -24,8 +27,11 @@ Options:
     -p SPEC, --package SPEC  Package to update
     --aggressive             Force updating all dependencies of <name> as well
     --precise PRECISE        Update a single dependency to exactly PRECISE
-    --manifest-path PATH     Path to the manifest to compile
-    -v, --verbose            Use verbose output
+    --explain                Print why each version was selected or rejected
+    --manifest-path PATH     Path to the manifest to update
+    -v, --verbose ...        Use verbose output (-vv for very verbose output)
+    -q, --quiet              Print no status lines, only warnings and errors
+    --color WHEN             Coloring: auto, always, never
 
 This command requires that a `Cargo.lock` already exists as generated by
 `cargo build` or related commands.
@@ -45,12 +51,19 @@ be updated to.
 If SPEC is not given, then all dependencies will be re-resolved and
 updated.
 
+With --explain, the decisions of the resolver are printed as it goes: which
+requirement of which package each dependency is resolved for, which versions
+are rejected because they don't match it or conflict with versions selected
+before, which version is selected, and when a selection had to be undone as
+it led to a conflict further down.
+
 For more information about package id specifications, see `cargo help pkgid`.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
     debug!("executing; cmd=cargo-update; args={:?}", os::args());
-    config.shell().set_verbose(options.flag_verbose);
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
     let root = try!(find_root_manifest_for_cwd(options.flag_manifest_path));
 
     let spec = options.flag_package.as_ref();
@@ -59,6 +72,7 @@ pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
         aggressive: options.flag_aggressive,
         precise: options.flag_precise.as_ref().map(|s| s.as_slice()),
         to_update: spec.map(|s| s.as_slice()),
+        explain: options.flag_explain,
         config: config,
     };
 
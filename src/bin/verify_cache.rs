@@ -0,0 +1,46 @@
+use cargo::ops;
+use cargo::util::{CliResult, CliError, Config};
+
+#[derive(RustcDecodable)]
+struct Options {
+    flag_fix: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
+}
+
+pub const USAGE: &'static str = "
+Check cargo's global caches for corrupted entries
+
+Usage:
+    cargo verify-cache [options]
+
+Options:
+    -h, --help              Print this message
+    --fix                   Remove corrupted entries so they are fetched again
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+Downloaded `.crate` files in $CARGO_HOME are checked against the checksums in
+the index of their registry, the sources unpacked from them against their
+contents, and git checkouts against the commit they checked out. A warning is
+printed for each corrupted entry, and the command fails if there are any.
+
+With `--fix` corrupted entries are removed instead, and the next build which
+needs them downloads or checks them out again.
+";
+
+pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    let corrupted = try!(ops::verify_cache(config, options.flag_fix));
+    if corrupted.is_empty() || options.flag_fix {
+        return Ok(None)
+    }
+    let msg = format!("found {} corrupted {} in the cache, run the command \
+                       again with --fix to remove {}", corrupted.len(),
+                      if corrupted.len() == 1 {"entry"} else {"entries"},
+                      if corrupted.len() == 1 {"it"} else {"them"});
+    Err(CliError::new(&msg, 1))
+}
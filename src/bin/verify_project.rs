@@ -1,52 +1,56 @@
-extern crate toml;
-
-use std::collections::HashMap;
-use std::old_io::File;
 use std::os;
 
-use cargo::util::{CliResult, Config};
-
-pub type Error = HashMap<String, String>;
+use cargo::ops::{self, ProjectReport};
+use cargo::util::{CliResult, CliError, Config};
+use cargo::util::important_paths::find_root_manifest_for_cwd;
 
 #[derive(RustcDecodable)]
 struct Flags {
-    flag_manifest_path: String,
-    flag_verbose: bool,
+    flag_manifest_path: Option<String>,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
 }
 
 pub const USAGE: &'static str = "
+Check the correctness of the manifest of the current project
+
 Usage:
-    cargo verify-project [options] --manifest-path PATH
+    cargo verify-project [options]
     cargo verify-project -h | --help
 
 Options:
     -h, --help              Print this message
     --manifest-path PATH    Path to the manifest to verify
-    -v, --verbose           Use verbose output
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
+
+The manifest is fully loaded, and so are the manifests of the members of the
+workspace it is the root of. The result is printed as JSON, with `success`
+set to `true`, or the first error as `invalid`, and lists of `errors` and
+`warnings`, each with the `manifest` they were found in and a `message`.
+Errors include invalid version requirements, targets whose source file is
+missing and binaries sharing a name; warnings include unused manifest keys
+and packages inside of a workspace which aren't one of its members. The exit
+status is 1 if there were any errors.
 ";
 
-pub fn execute(args: Flags, config: &Config) -> CliResult<Option<Error>> {
-    config.shell().set_verbose(args.flag_verbose);
-
-    let file = Path::new(args.flag_manifest_path);
-    let contents = match File::open(&file).read_to_string() {
-        Ok(s) => s,
-        Err(e) => return fail("invalid", format!("error reading file: {}",
-                                                 e).as_slice())
+pub fn execute(args: Flags, config: &Config)
+               -> CliResult<Option<ProjectReport>> {
+    try!(config.configure_shell(args.flag_verbose, args.flag_quiet,
+                                &args.flag_color));
+
+    let report = match find_root_manifest_for_cwd(args.flag_manifest_path) {
+        Ok(file) => {
+            try!(ops::verify_project(&file, config).map_err(|e| {
+                CliError::from_boxed(e, 101)
+            }))
+        }
+        Err(e) => ProjectReport::invalid(e.description()),
     };
-    match toml::Parser::new(contents.as_slice()).parse() {
-        None => return fail("invalid", "invalid-format"),
-        Some(..) => {}
-    };
-
-    let mut h = HashMap::new();
-    h.insert("success".to_string(), "true".to_string());
-    Ok(Some(h))
-}
-
-fn fail(reason: &str, value: &str) -> CliResult<Option<Error>>{
-    let mut h = HashMap::new();
-    h.insert(reason.to_string(), value.to_string());
-    os::set_exit_status(1);
-    Ok(Some(h))
+    if !report.success {
+        os::set_exit_status(1);
+    }
+    Ok(Some(report))
 }
@@ -4,21 +4,31 @@ use cargo;
 use cargo::util::{CliResult, Config};
 
 #[derive(RustcDecodable)]
-struct Options;
+struct Options {
+    flag_verbose: bool,
+}
 
 pub const USAGE: &'static str = "
+Show version information
+
 Usage:
     cargo version [options]
 
 Options:
     -h, --help              Print this message
-    -v, --verbose           Use verbose output
+    -v, --verbose           Also print the details of the build
+
+Bug reports should include the output of `cargo version --verbose`.
 ";
 
-pub fn execute(_: Options, _: &Config) -> CliResult<Option<()>> {
+pub fn execute(options: Options, _: &Config) -> CliResult<Option<()>> {
     debug!("executing; cmd=cargo-version; args={:?}", os::args());
 
-    println!("{}", cargo::version());
+    if options.flag_verbose {
+        println!("{}", cargo::version_verbose());
+    } else {
+        println!("{}", cargo::version());
+    }
 
     Ok(None)
 }
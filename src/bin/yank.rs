@@ -4,11 +4,15 @@ use cargo::util::{CliResult, CliError, Config};
 #[derive(RustcDecodable)]
 struct Options {
     arg_crate: Option<String>,
+    flag_manifest_path: Option<String>,
     flag_token: Option<String>,
     flag_vers: Option<String>,
     flag_index: Option<String>,
-    flag_verbose: bool,
+    flag_verbose: u32,
+    flag_quiet: bool,
+    flag_color: Option<String>,
     flag_undo: bool,
+    flag_list: bool,
 }
 
 pub static USAGE: &'static str = "
@@ -21,9 +25,15 @@ Options:
     -h, --help              Print this message
     --vers VERSION          The version to yank or un-yank
     --undo                  Undo a yank, putting a version back into the index
+    -l, --list              List the published versions and whether they
+                            are yanked
     --index INDEX           Registry index to yank from
     --token TOKEN           API token to use when authenticating
-    -v, --verbose           Use verbose output
+    --manifest-path PATH    Path to the manifest of the crate, if <crate> is not
+                            given
+    -v, --verbose ...       Use verbose output (-vv for very verbose output)
+    -q, --quiet             Print no status lines, only warnings and errors
+    --color WHEN            Coloring: auto, always, never
 
 The yank command removes a previously pushed crate's version from the server's
 index. This command does not delete any data, and the crate will still be
@@ -35,13 +45,17 @@ crates to be locked to any yanked version.
 ";
 
 pub fn execute(options: Options, config: &Config) -> CliResult<Option<()>> {
-    config.shell().set_verbose(options.flag_verbose);
-    try!(ops::yank(config,
-                   options.arg_crate,
-                   options.flag_vers,
-                   options.flag_token,
-                   options.flag_index,
-                   options.flag_undo).map_err(|e| {
+    try!(config.configure_shell(options.flag_verbose, options.flag_quiet,
+                                &options.flag_color));
+    try!(ops::yank(config, &ops::YankOptions {
+        krate: options.arg_crate,
+        manifest_path: options.flag_manifest_path,
+        version: options.flag_vers,
+        token: options.flag_token,
+        index: options.flag_index,
+        undo: options.flag_undo,
+        list: options.flag_list,
+    }).map_err(|e| {
         CliError::from_boxed(e, 101)
     }));
     Ok(None)
@@ -1,7 +1,7 @@
 use semver::VersionReq;
 
 use core::{SourceId, Summary, PackageId};
-use util::CargoResult;
+use util::{CargoResult, ChainError, human};
 
 /// Informations about a dependency requested by a Cargo manifest.
 #[derive(PartialEq,Clone,Debug)]
@@ -20,6 +20,10 @@ pub struct Dependency {
     // This dependency should be used only for this platform.
     // `None` means *all platforms*.
     only_for_platform: Option<String>,
+
+    // The kind of artifact of the package this dependency is on, like
+    // `bin`, which is built for the host and handed to the build script.
+    artifact: Option<String>,
 }
 
 #[derive(PartialEq, Clone, Debug, Copy)]
@@ -35,7 +39,10 @@ impl Dependency {
                  version: Option<&str>,
                  source_id: &SourceId) -> CargoResult<Dependency> {
         let version_req = match version {
-            Some(v) => try!(VersionReq::parse(v)),
+            Some(v) => try!(VersionReq::parse(v).chain_error(|| {
+                human(format!("the version requirement `{}` of dependency \
+                               `{}` is invalid", v, name))
+            })),
             None => VersionReq::any()
         };
 
@@ -59,6 +66,7 @@ impl Dependency {
             default_features: true,
             specified_req: None,
             only_for_platform: None,
+            artifact: None,
         }
     }
 
@@ -76,6 +84,11 @@ impl Dependency {
         self.only_for_platform.as_ref().map(|s| s.as_slice())
     }
 
+    /// The kind of artifact, like `bin`, requested from the package.
+    pub fn artifact(&self) -> Option<&str> {
+        self.artifact.as_ref().map(|s| s.as_slice())
+    }
+
     pub fn set_kind(mut self, kind: Kind) -> Dependency {
         self.kind = kind;
         self
@@ -117,6 +130,12 @@ impl Dependency {
         self
     }
 
+    /// Sets the kind of artifact requested from the package.
+    pub fn set_artifact(mut self, artifact: Option<String>) -> Dependency {
+        self.artifact = artifact;
+        self
+    }
+
     /// Lock this dependency to depending on the specified package id
     pub fn lock_to(self, id: &PackageId) -> Dependency {
         assert_eq!(self.source_id, *id.source_id());
@@ -163,17 +182,35 @@ impl Dependency {
     }
 }
 
+/// The serialization of a dependency, as part of
+/// `core::manifest::SerializedPackage`.
 #[derive(PartialEq,Clone,RustcEncodable)]
 pub struct SerializedDependency {
     name: String,
-    req: String
+    req: String,
+    source: SourceId,
+    kind: &'static str,
+    target: Option<String>,
+    optional: bool,
+    uses_default_features: bool,
+    features: Vec<String>,
 }
 
 impl SerializedDependency {
     pub fn from_dependency(dep: &Dependency) -> SerializedDependency {
         SerializedDependency {
             name: dep.name().to_string(),
-            req: dep.version_req().to_string()
+            req: dep.version_req().to_string(),
+            source: dep.source_id().clone(),
+            kind: match dep.kind() {
+                Kind::Normal => "normal",
+                Kind::Development => "dev",
+                Kind::Build => "build",
+            },
+            target: dep.only_for_platform().map(|s| s.to_string()),
+            optional: dep.is_optional(),
+            uses_default_features: dep.uses_default_features(),
+            features: dep.features().to_vec(),
         }
     }
 }
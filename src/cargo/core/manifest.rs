@@ -1,9 +1,9 @@
+use std::collections::{BTreeMap, HashMap};
 use std::hash;
 
 use semver::Version;
-use rustc_serialize::{Encoder,Encodable};
 
-use core::{Dependency, PackageId, Summary};
+use core::{Dependency, PackageId, SourceId, Summary};
 use core::package_id::Metadata;
 use core::dependency::SerializedDependency;
 use util::{CargoResult, human};
@@ -20,6 +20,10 @@ pub struct Manifest {
     exclude: Vec<String>,
     include: Vec<String>,
     metadata: ManifestMetadata,
+    default_target: Option<String>,
+    forced_target: Option<String>,
+    publish: bool,
+    hooks: HashMap<String, Vec<String>>,
 }
 
 /// General metadata about a package which is just blindly uploaded to the
@@ -35,6 +39,7 @@ pub struct ManifestMetadata {
     pub authors: Vec<String>,
     pub keywords: Vec<String>,
     pub license: Option<String>,
+    pub edition: Option<String>,
     pub license_file: Option<String>,
     pub description: Option<String>,    // not markdown
     pub readme: Option<String>,         // file, not contents
@@ -43,28 +48,66 @@ pub struct ManifestMetadata {
     pub documentation: Option<String>,  // url
 }
 
-#[derive(PartialEq,Clone,RustcEncodable)]
-pub struct SerializedManifest {
+/// Version of the format of `SerializedPackage`. Fields may be added to the
+/// format without changing it, but it is incremented whenever a field is
+/// removed or changes its meaning.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The serialization of a package for external tools, as printed by `cargo
+/// read-manifest`.
+///
+/// Unlike the internal representation of manifests this format is stable, and
+/// it is documented in `src/doc/manifest.md`.
+#[derive(RustcEncodable)]
+pub struct SerializedPackage {
+    format_version: u32,
     name: String,
     version: String,
+    id: PackageId,
+    source: SourceId,
+    manifest_path: String,
     dependencies: Vec<SerializedDependency>,
-    targets: Vec<Target>,
-    target_dir: String,
-    doc_dir: String,
+    targets: Vec<SerializedTarget>,
+    features: BTreeMap<String, Vec<String>>,
+    authors: Vec<String>,
+    description: Option<String>,
+    license: Option<String>,
+    license_file: Option<String>,
+    homepage: Option<String>,
+    repository: Option<String>,
+    documentation: Option<String>,
+    readme: Option<String>,
+    keywords: Vec<String>,
 }
 
-impl Encodable for Manifest {
-    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        SerializedManifest {
-            name: self.summary.name().to_string(),
-            version: self.summary.version().to_string(),
-            dependencies: self.summary.dependencies().iter().map(|d| {
+impl SerializedPackage {
+    pub fn new(manifest: &Manifest, manifest_path: &Path) -> SerializedPackage {
+        let summary = manifest.summary();
+        let metadata = manifest.metadata();
+        SerializedPackage {
+            format_version: FORMAT_VERSION,
+            name: summary.name().to_string(),
+            version: summary.version().to_string(),
+            id: summary.package_id().clone(),
+            source: summary.source_id().clone(),
+            manifest_path: manifest_path.display().to_string(),
+            dependencies: summary.dependencies().iter().map(|d| {
                 SerializedDependency::from_dependency(d)
             }).collect(),
-            targets: self.targets.clone(),
-            target_dir: self.target_dir.display().to_string(),
-            doc_dir: self.doc_dir.display().to_string(),
-        }.encode(s)
+            targets: SerializedTarget::from_targets(manifest.targets()),
+            features: summary.features().iter().map(|(k, v)| {
+                (k.clone(), v.clone())
+            }).collect(),
+            authors: metadata.authors.clone(),
+            description: metadata.description.clone(),
+            license: metadata.license.clone(),
+            license_file: metadata.license_file.clone(),
+            homepage: metadata.homepage.clone(),
+            repository: metadata.repository.clone(),
+            documentation: metadata.documentation.clone(),
+            readme: metadata.readme.clone(),
+            keywords: metadata.keywords.clone(),
+        }
     }
 }
 
@@ -313,32 +356,54 @@ pub struct Target {
     metadata: Option<Metadata>,
 }
 
-#[derive(RustcEncodable)]
+/// The serialization of a target, as part of `SerializedPackage`.
+#[derive(PartialEq, Clone, RustcEncodable)]
 pub struct SerializedTarget {
     kind: Vec<&'static str>,
     name: String,
     src_path: String,
-    profile: Profile,
-    metadata: Option<Metadata>
 }
 
-impl Encodable for Target {
-    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        let kind = match self.kind {
-            TargetKind::Lib(ref kinds) => {
-                kinds.iter().map(|k| k.crate_type()).collect()
+impl SerializedTarget {
+    /// Serializes the targets of a manifest.
+    ///
+    /// A manifest has a target for each profile a target is built with, and
+    /// tests and benchmarks are binaries built with the test profiles, so
+    /// the targets are deduplicated and tests told apart from the unit tests
+    /// of binaries here.
+    pub fn from_targets(targets: &[Target]) -> Vec<SerializedTarget> {
+        let mut ret: Vec<SerializedTarget> = Vec::new();
+        for target in targets.iter() {
+            let kind = match target.kind {
+                _ if target.profile.is_custom_build() => vec!["custom-build"],
+                TargetKind::Lib(ref kinds) => {
+                    kinds.iter().map(|k| k.crate_type()).collect()
+                }
+                TargetKind::Example => vec!["example"],
+                TargetKind::Bin => {
+                    let is_bin = targets.iter().any(|t| {
+                        t.is_bin() && t.name == target.name &&
+                            !t.profile.is_test() && t.profile.env() != "bench"
+                    });
+                    if is_bin {
+                        vec!["bin"]
+                    } else if target.profile.env() == "bench" {
+                        vec!["bench"]
+                    } else {
+                        vec!["test"]
+                    }
+                }
+            };
+            let serialized = SerializedTarget {
+                kind: kind,
+                name: target.name.clone(),
+                src_path: target.src_path.display().to_string(),
+            };
+            if !ret.contains(&serialized) {
+                ret.push(serialized);
             }
-            TargetKind::Bin => vec!("bin"),
-            TargetKind::Example => vec!["example"],
-        };
-
-        SerializedTarget {
-            kind: kind,
-            name: self.name.clone(),
-            src_path: self.src_path.display().to_string(),
-            profile: self.profile.clone(),
-            metadata: self.metadata.clone()
-        }.encode(s)
+        }
+        ret
     }
 }
 
@@ -359,6 +424,10 @@ impl Manifest {
             include: include,
             links: links,
             metadata: metadata,
+            default_target: None,
+            forced_target: None,
+            publish: true,
+            hooks: HashMap::new(),
         }
     }
 
@@ -377,6 +446,27 @@ impl Manifest {
     pub fn links(&self) -> Option<&str> {
         self.links.as_ref().map(|s| s.as_slice())
     }
+    /// The platform the package is built for if `--target` isn't given
+    pub fn default_target(&self) -> Option<&str> {
+        self.default_target.as_ref().map(|s| s.as_slice())
+    }
+    /// The platform the package is always built for, even with `--target`
+    pub fn forced_target(&self) -> Option<&str> {
+        self.forced_target.as_ref().map(|s| s.as_slice())
+    }
+    /// The platform the package is built for when `requested` is given with
+    /// `--target`, if it isn't built for the host.
+    pub fn build_target<'a>(&'a self, requested: Option<&'a str>)
+                            -> Option<&'a str> {
+        self.forced_target().or(requested).or(self.default_target())
+    }
+    /// Whether the package may be published to a registry
+    pub fn publish(&self) -> bool { self.publish }
+    /// The command of the hook `name` of the `[hooks]` section, like
+    /// `pre-build`
+    pub fn hook(&self, name: &str) -> Option<&[String]> {
+        self.hooks.get(name).map(|v| v.as_slice())
+    }
 
     pub fn add_warning(&mut self, s: String) {
         self.warnings.push(s)
@@ -389,6 +479,22 @@ impl Manifest {
     pub fn set_target_dir(&mut self, target_dir: Path) {
         self.target_dir = target_dir;
     }
+
+    pub fn set_default_target(&mut self, target: Option<String>) {
+        self.default_target = target;
+    }
+
+    pub fn set_forced_target(&mut self, target: Option<String>) {
+        self.forced_target = target;
+    }
+
+    pub fn set_publish(&mut self, publish: bool) {
+        self.publish = publish;
+    }
+
+    pub fn set_hooks(&mut self, hooks: HashMap<String, Vec<String>>) {
+        self.hooks = hooks;
+    }
 }
 
 impl Target {
@@ -11,7 +11,7 @@ use core::{
     Target,
     Summary,
 };
-use core::dependency::SerializedDependency;
+use core::manifest::SerializedPackage;
 use util::{CargoResult, graph};
 use rustc_serialize::{Encoder,Encodable};
 use core::source::{SourceId, Source};
@@ -30,30 +30,9 @@ pub struct Package {
     source_id: SourceId,
 }
 
-#[derive(RustcEncodable)]
-struct SerializedPackage {
-    name: String,
-    version: String,
-    dependencies: Vec<SerializedDependency>,
-    targets: Vec<Target>,
-    manifest_path: String,
-}
-
 impl Encodable for Package {
     fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
-        let manifest = self.manifest();
-        let summary = manifest.summary();
-        let package_id = summary.package_id();
-
-        SerializedPackage {
-            name: package_id.name().to_string(),
-            version: package_id.version().to_string(),
-            dependencies: summary.dependencies().iter().map(|d| {
-                SerializedDependency::from_dependency(d)
-            }).collect(),
-            targets: manifest.targets().to_vec(),
-            manifest_path: self.manifest_path.display().to_string()
-        }.encode(s)
+        SerializedPackage::new(self.manifest(), &self.manifest_path).encode(s)
     }
 }
 
@@ -79,6 +79,8 @@ impl<'a, 'b> PackageRegistry<'a, 'b> {
         }
     }
 
+    pub fn config(&self) -> &'a Config<'b> { self.config }
+
     pub fn get(&mut self, package_ids: &[PackageId]) -> CargoResult<Vec<Package>> {
         trace!("getting packages; sources={}", self.sources.len());
 
@@ -222,51 +224,45 @@ impl<'a, 'b> PackageRegistry<'a, 'b> {
             None => summary,
         };
         summary.map_dependencies(|dep| {
-            match pair {
-                // If we've got a known set of overrides for this summary, then
-                // one of a few cases can arise:
-                //
-                // 1. We have a lock entry for this dependency from the same
-                //    source as its listed as coming from. In this case we make
-                //    sure to lock to precisely the given package id.
-                //
-                // 2. We have a lock entry for this dependency, but it's from a
-                //    different source than what's listed, or the version
-                //    requirement has changed. In this case we must discard the
-                //    locked version because the dependency needs to be
-                //    re-resolved.
-                //
-                // 3. We don't have a lock entry for this dependency, in which
-                //    case it was likely an optional dependency which wasn't
-                //    included previously so we just pass it through anyway.
-                Some(&(_, ref deps)) => {
-                    match deps.iter().find(|d| d.name() == dep.name()) {
-                        Some(lock) => {
-                            if dep.matches_id(lock) {
-                                dep.lock_to(lock)
-                            } else {
-                                dep
-                            }
-                        }
-                        None => dep,
-                    }
-                }
-
-                // If this summary did not have a locked version, then we query
-                // all known locked packages to see if they match this
-                // dependency. If anything does then we lock it to that and move
-                // on.
-                None => {
-                    let v = self.locked.get(dep.source_id()).and_then(|map| {
-                        map.get(dep.name())
-                    }).and_then(|vec| {
-                        vec.iter().find(|&&(ref id, _)| dep.matches_id(id))
-                    });
-                    match v {
-                        Some(&(ref id, _)) => dep.lock_to(id),
-                        None => dep
-                    }
-                }
+            // If we've got a known set of overrides for this summary, then
+            // one of a few cases can arise:
+            //
+            // 1. We have a lock entry for this dependency from the same
+            //    source as its listed as coming from. In this case we make
+            //    sure to lock to precisely the given package id.
+            //
+            // 2. We have a lock entry for this dependency, but it's from a
+            //    different source than what's listed, or the version
+            //    requirement has changed. In this case the locked version
+            //    must be discarded because the dependency needs to be
+            //    re-resolved.
+            //
+            // 3. We don't have a lock entry for this dependency, in which
+            //    case it was likely an optional dependency which wasn't
+            //    included previously.
+            //
+            // In the last two cases, and if this summary did not have a
+            // locked version at all, we query all known locked packages to see
+            // if they match this dependency. If anything does then we lock it
+            // to that and move on, so editing one requirement doesn't shift
+            // the versions of packages which still satisfy it.
+            let lock = pair.and_then(|&(_, ref deps)| {
+                deps.iter().find(|d| d.name() == dep.name())
+            }).and_then(|lock| {
+                if dep.matches_id(lock) {Some(lock)} else {None}
+            });
+            match lock {
+                Some(lock) => return dep.lock_to(lock),
+                None => {}
+            }
+            let v = self.locked.get(dep.source_id()).and_then(|map| {
+                map.get(dep.name())
+            }).and_then(|vec| {
+                vec.iter().find(|&&(ref id, _)| dep.matches_id(id))
+            });
+            match v {
+                Some(&(ref id, _)) => dep.lock_to(id),
+                None => dep
             }
         })
     }
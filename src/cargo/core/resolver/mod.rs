@@ -3,12 +3,14 @@ use std::collections::HashSet;
 use std::collections::hash_map::HashMap;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::fmt;
+use std::iter;
 use std::rc::Rc;
 use semver;
 
 use core::{PackageId, Registry, SourceId, Summary, Dependency};
 use core::PackageIdSpec;
-use util::{CargoResult, Graph, human, ChainError, CargoError};
+use util::{CargoResult, Graph, human, coded_human, ChainError, CargoError};
+use util::lev_distance;
 use util::profile;
 use util::graph::{Nodes, Edges};
 
@@ -50,6 +52,57 @@ impl Resolve {
         self.metadata = other.metadata.clone();
     }
 
+    /// Adds all packages and dependency edges of `other` to this resolve,
+    /// with the root package of `other` merged into this one's root.
+    ///
+    /// The result is not a valid resolution on its own, as it may contain
+    /// several versions of a package, but it can guide the resolution of the
+    /// union of two lockfiles.
+    pub fn merge(&mut self, other: &Resolve) {
+        self.add_all(other, true)
+    }
+
+    /// Adds all packages and dependency edges of `other`, including its root
+    /// package, to this resolve.
+    ///
+    /// This is how the resolves of the members of a workspace are combined
+    /// into the single lockfile of the workspace, whose root is the root
+    /// package of the workspace.
+    pub fn extend(&mut self, other: &Resolve) {
+        self.add_all(other, false)
+    }
+
+    fn add_all(&mut self, other: &Resolve, merge_roots: bool) {
+        let map = |id: &PackageId| merge_roots && id == other.root();
+        for node in other.iter() {
+            let from = if map(node) {&self.root} else {node};
+            if !self.graph.get_nodes().contains_key(from) {
+                self.graph.add(from.clone(), &[]);
+            }
+            for dep in other.deps(node).into_iter().flat_map(|i| i) {
+                self.graph.link(from.clone(), dep.clone());
+            }
+        }
+        for (id, features) in other.features.iter() {
+            let id = if map(id) {&self.root} else {id};
+            match self.features.entry(id.clone()) {
+                Occupied(e) => e.into_mut(),
+                Vacant(e) => e.insert(HashSet::new()),
+            }.extend(features.iter().cloned());
+        }
+        if let Some(ref theirs) = other.metadata {
+            let mut mine = self.metadata.take().unwrap_or_else(|| {
+                theirs.clone()
+            });
+            for (k, v) in theirs.iter() {
+                if !mine.contains_key(k) {
+                    mine.insert(k.clone(), v.clone());
+                }
+            }
+            self.metadata = Some(mine);
+        }
+    }
+
     pub fn iter(&self) -> Nodes<PackageId> {
         self.graph.iter()
     }
@@ -125,21 +178,51 @@ struct Context {
     activations: HashMap<(String, SourceId), Vec<Rc<Summary>>>,
     resolve: Resolve,
     visited: Rc<RefCell<HashSet<PackageId>>>,
+    trace: Option<Rc<RefCell<Vec<String>>>>,
+}
+
+impl Context {
+    /// Records the line returned by `line` in the trace, if there is one,
+    /// indented by how deep in the dependency graph the resolver currently
+    /// is.
+    fn trace<F: FnOnce() -> String>(&self, line: F) {
+        if let Some(ref trace) = self.trace {
+            let depth = self.visited.borrow().len();
+            let indent = iter::repeat("  ").take(depth).collect::<String>();
+            trace.borrow_mut().push(format!("{}{}", indent, line()));
+        }
+    }
 }
 
 /// Builds the list of all packages required to build the first argument.
 pub fn resolve(summary: &Summary, method: Method,
                registry: &mut Registry) -> CargoResult<Resolve> {
+    resolve_with_trace(summary, method, registry, None)
+}
+
+/// Like `resolve`, but also records why each candidate version of each
+/// dependency was selected or rejected in `trace`, if given, including when
+/// the resolver had to backtrack.
+pub fn resolve_with_trace(summary: &Summary, method: Method,
+                          registry: &mut Registry,
+                          trace: Option<&mut Vec<String>>)
+                          -> CargoResult<Resolve> {
     trace!("resolve; summary={}", summary.package_id());
     let summary = Rc::new(summary.clone());
 
+    let lines = trace.as_ref().map(|_| Rc::new(RefCell::new(Vec::new())));
     let cx = Box::new(Context {
         resolve: Resolve::new(summary.package_id().clone()),
         activations: HashMap::new(),
         visited: Rc::new(RefCell::new(HashSet::new())),
+        trace: lines.clone(),
     });
     let _p = profile::start(format!("resolving: {:?}", summary));
-    match try!(activate(cx, registry, &summary, method)) {
+    let res = try!(activate(cx, registry, &summary, method));
+    if let (Some(trace), Some(lines)) = (trace, lines) {
+        trace.extend(lines.borrow_mut().drain());
+    }
+    match res {
         Ok(cx) => {
             debug!("resolved: {:?}", cx.resolve);
             Ok(cx.resolve)
@@ -157,8 +240,10 @@ fn activate(mut cx: Box<Context>,
     // packages we're visiting and bail if we hit a dupe.
     let id = parent.package_id();
     if !cx.visited.borrow_mut().insert(id.clone()) {
-        return Err(human(format!("cyclic package dependency: package `{}` \
-                                  depends on itself", id)))
+        return Err(coded_human("resolve-cycle",
+                               format!("cyclic package dependency: package \
+                                        `{}` depends on itself", id),
+                               Vec::new()))
     }
 
     // If we're already activated, then that was easy!
@@ -257,6 +342,8 @@ fn activate_deps<'a>(cx: Box<Context>,
          candidates.len());
     trace!("{}[{}]>{} {} prev activations", parent.name(), cur,
          dep.name(), prev_active.len());
+    try!(trace_candidates(&cx, registry, parent, dep, features, candidates,
+                          prev_active));
 
     // Filter the set of candidates based on the previously activated
     // versions for this dependency. We can actually use a version if it
@@ -296,14 +383,23 @@ fn activate_deps<'a>(cx: Box<Context>,
         if !dep.is_transitive() {
             my_cx.visited.borrow_mut().clear();
         }
+        cx.trace(|| format!("trying {}", candidate.package_id()));
         let my_cx = match try!(activate(my_cx, registry, candidate, method)) {
             Ok(cx) => cx,
-            Err(e) => { last_err = Some(e); continue }
+            Err(e) => {
+                cx.trace(|| backtrack_line(candidate, &*e));
+                last_err = Some(e);
+                continue
+            }
         };
+        cx.trace(|| format!("selected {}", candidate.package_id()));
         match try!(activate_deps(my_cx, registry, parent, platform, deps,
                                  cur + 1)) {
             Ok(cx) => return Ok(Ok(cx)),
-            Err(e) => { last_err = Some(e); }
+            Err(e) => {
+                cx.trace(|| backtrack_line(candidate, &*e));
+                last_err = Some(e);
+            }
         }
     }
     trace!("{}[{}]>{} -- {:?}", parent.name(), cur, dep.name(),
@@ -315,6 +411,67 @@ fn activate_deps<'a>(cx: Box<Context>,
                         &candidates[]))
 }
 
+/// Records in the trace of `cx` which requirement `dep` of `parent` is
+/// resolved for next, and which of the versions of `dep` are rejected by it or
+/// by the versions of `dep` which are already selected.
+fn trace_candidates(cx: &Context,
+                    registry: &mut Registry,
+                    parent: &Summary,
+                    dep: &Dependency,
+                    features: &[String],
+                    candidates: &[Rc<Summary>],
+                    prev_active: &[Rc<Summary>]) -> CargoResult<()> {
+    if cx.trace.is_none() { return Ok(()) }
+
+    cx.trace(|| {
+        let mut line = format!("{} requires `{} {}`", parent.package_id(),
+                               dep.name(), dep.version_req());
+        if dep.is_optional() {
+            line.push_str(" (optional, enabled by its features)");
+        }
+        if !features.is_empty() {
+            line.push_str(&format!(" with features `{}`",
+                                   features.connect(", "))[]);
+        }
+        line
+    });
+
+    let all_req = semver::VersionReq::parse("*").unwrap();
+    let all = try!(registry.query(&dep.clone().set_version_req(all_req)));
+    let mut unmatched = all.iter().filter(|s| !dep.matches(*s))
+                           .map(|s| s.version().clone()).collect::<Vec<_>>();
+    unmatched.sort_by(|a, b| b.cmp(a));
+    if !unmatched.is_empty() {
+        cx.trace(|| {
+            format!("  rejected {} {}: not matched by `{}`", dep.name(),
+                    unmatched.iter().map(|v| v.to_string())
+                             .collect::<Vec<_>>().connect(", "),
+                    dep.version_req())
+        });
+    }
+
+    for candidate in candidates.iter() {
+        if prev_active.iter().any(|a| a == candidate) { continue }
+        let conflict = prev_active.iter().find(|a| {
+            compatible(a.version(), candidate.version())
+        });
+        if let Some(active) = conflict {
+            cx.trace(|| {
+                format!("  rejected {}: {} is already selected, and only one \
+                         semver compatible version may be",
+                        candidate.package_id(), active.package_id())
+            });
+        }
+    }
+    Ok(())
+}
+
+fn backtrack_line(candidate: &Summary, err: &CargoError) -> String {
+    let err = err.to_string();
+    format!("backtracking from {}: {}", candidate.package_id(),
+            err.lines().next().unwrap_or(""))
+}
+
 fn activation_error(cx: &Context,
                     registry: &mut Registry,
                     err: Option<Box<CargoError>>,
@@ -358,7 +515,7 @@ fn activation_error(cx: &Context,
                                         .collect::<Vec<_>>()
                                         .connect(", "))[]);
 
-        return Err(human(msg))
+        return Err(coded_human("resolve-conflict", msg, Vec::new()))
     }
     // Once we're all the way down here, we're definitely lost in the
     // weeds! We didn't actually use any candidates above, so we need to
@@ -402,7 +559,7 @@ fn activation_error(cx: &Context,
                       a path dependency's locked version");
 
     }
-    Err(human(msg))
+    Err(coded_human("resolve-not-found", msg, Vec::new()))
 }
 
 // Returns if `a` and `b` are compatible in the semver sense. This is a
@@ -469,8 +626,9 @@ fn resolve_features<'a>(cx: &mut Context, parent: &'a Summary,
     // features are bugs in that the package does not actually have those
     // features.
     if feature_deps.len() > 0 {
-        let unknown = feature_deps.keys().map(|s| s.as_slice())
-                                  .collect::<Vec<&str>>();
+        let mut unknown = feature_deps.keys().map(|s| s.as_slice())
+                                      .collect::<Vec<&str>>();
+        unknown.sort();
         if unknown.len() > 0 {
             let features = unknown.connect(", ");
             return Err(human(format!("Package `{}` does not have these features: \
@@ -490,6 +648,34 @@ fn resolve_features<'a>(cx: &mut Context, parent: &'a Summary,
     Ok(ret)
 }
 
+/// The error for the features `unknown` of the package of `s` it doesn't
+/// have, suggesting the features it has which are the closest to them.
+fn unknown_features(s: &Summary, unknown: &[&str]) -> Box<CargoError> {
+    let optional = s.dependencies().iter().filter(|d| {
+        d.is_optional() && s.has_implicit_feature(d.name())
+    }).map(|d| d.name());
+    let candidates = s.features().keys().map(|f| f.as_slice())
+                      .chain(optional).collect::<Vec<_>>();
+    let mut msg = format!("Package `{}` does not have these features: `{}`",
+                          s.package_id(), unknown.connect(", "));
+    for feature in unknown.iter() {
+        let closest = candidates.iter().map(|c| {
+            (lev_distance(*c, *feature), *c)
+        }).filter(|&(d, _)| d < 4 && d < feature.len()).min();
+        match closest {
+            Some((_, c)) if unknown.len() == 1 => {
+                msg.push_str(&format!("\nDid you mean `{}`?", c));
+            }
+            Some((_, c)) => {
+                msg.push_str(&format!("\nDid you mean `{}` instead of `{}`?",
+                                      c, feature));
+            }
+            None => {}
+        }
+    }
+    human(msg)
+}
+
 // Returns a pair of (feature dependencies, all used features)
 //
 // The feature dependencies map is a mapping of package name to list of features
@@ -502,21 +688,36 @@ fn resolve_features<'a>(cx: &mut Context, parent: &'a Summary,
 fn build_features(s: &Summary, method: Method)
                   -> CargoResult<(HashMap<String, Vec<String>>, HashSet<String>)> {
     let mut deps = HashMap::new();
+    let mut weak = HashMap::new();
     let mut used = HashSet::new();
     let mut visited = HashSet::new();
     match method {
         Method::Everything => {
             for key in s.features().keys() {
-                try!(add_feature(s, key, &mut deps, &mut used, &mut visited));
+                try!(add_feature(s, key, &mut deps, &mut weak, &mut used,
+                                 &mut visited));
             }
             for dep in s.dependencies().iter().filter(|d| d.is_optional()) {
-                try!(add_feature(s, dep.name(), &mut deps, &mut used,
+                let feat = if s.has_implicit_feature(dep.name()) {
+                    dep.name().to_string()
+                } else {
+                    format!("dep:{}", dep.name())
+                };
+                try!(add_feature(s, &feat, &mut deps, &mut weak, &mut used,
                                  &mut visited));
             }
         }
         Method::Required(_, requested_features, _, _) =>  {
             for feat in requested_features.iter() {
-                try!(add_feature(s, feat, &mut deps, &mut used, &mut visited));
+                if feat.starts_with("dep:") {
+                    return Err(human(format!("Package `{}` was requested with \
+                                              `{}`, but `dep:` can only be \
+                                              used in the `[features]` \
+                                              section of a manifest",
+                                             s.package_id(), feat)))
+                }
+                try!(add_feature(s, feat, &mut deps, &mut weak, &mut used,
+                                 &mut visited));
             }
         }
     }
@@ -524,28 +725,73 @@ fn build_features(s: &Summary, method: Method)
         Method::Everything | Method::Required(_, _, true, _) => {
             if s.features().get("default").is_some() &&
                !visited.contains("default") {
-                try!(add_feature(s, "default", &mut deps, &mut used,
-                                 &mut visited));
+                try!(add_feature(s, "default", &mut deps, &mut weak,
+                                 &mut used, &mut visited));
             }
         }
         _ => {}
     }
+
+    // Weak features of dependencies are only enabled if the dependency is
+    // activated anyway, which non-optional ones always are
+    for (package, features) in weak.into_iter() {
+        let optional = s.dependencies().iter().any(|d| {
+            d.name() == package && d.is_optional()
+        });
+        if optional && !deps.contains_key(&package) { continue }
+        match deps.entry(package) {
+            Occupied(e) => e.into_mut(),
+            Vacant(e) => e.insert(Vec::new()),
+        }.extend(features.into_iter());
+    }
+
+    // Features which are neither features of the package nor optional
+    // dependencies, like misspelled ones, would otherwise be passed to rustc
+    // and never be used
+    let mut unknown = used.iter().filter(|f| {
+        !s.features().contains_key(*f) && !s.dependencies().iter().any(|d| {
+            d.name() == f.as_slice() && d.is_optional()
+        })
+    }).map(|f| f.as_slice()).collect::<Vec<_>>();
+    if unknown.len() > 0 {
+        unknown.sort();
+        return Err(unknown_features(s, &unknown))
+    }
     return Ok((deps, used));
 
     fn add_feature(s: &Summary, feat: &str,
                    deps: &mut HashMap<String, Vec<String>>,
+                   weak: &mut HashMap<String, Vec<String>>,
                    used: &mut HashSet<String>,
                    visited: &mut HashSet<String>) -> CargoResult<()> {
         if feat.is_empty() { return Ok(()) }
 
+        // A `dep:foo` feature only activates the optional dependency `foo`,
+        // without a `foo` feature
+        if feat.starts_with("dep:") {
+            match deps.entry(feat[4..].to_string()) {
+                Occupied(..) => {} // already activated
+                Vacant(e) => { e.insert(Vec::new()); }
+            }
+            return Ok(())
+        }
+
         // If this feature is of the form `foo/bar`, then we just lookup package
         // `foo` and enable its feature `bar`. Otherwise this feature is of the
         // form `foo` and we need to recurse to enable the feature `foo` for our
         // own package, which may end up enabling more features or just enabling
-        // a dependency.
+        // a dependency. A `foo?/bar` feature enables the feature `bar` of
+        // `foo` only if `foo` is activated by something else.
         let mut parts = feat.splitn(1, '/');
         let feat_or_package = parts.next().unwrap();
         match parts.next() {
+            Some(feat) if feat_or_package.ends_with("?") => {
+                let package = &feat_or_package[..feat_or_package.len() - 1];
+                match weak.entry(package.to_string()) {
+                    Occupied(e) => e.into_mut(),
+                    Vacant(e) => e.insert(Vec::new()),
+                }.push(feat.to_string());
+            }
             Some(feat) => {
                 let package = feat_or_package;
                 match deps.entry(package.to_string()) {
@@ -564,10 +810,18 @@ fn build_features(s: &Summary, method: Method)
                 match s.features().get(feat) {
                     Some(recursive) => {
                         for f in recursive.iter() {
-                            try!(add_feature(s, f, deps, used,
+                            try!(add_feature(s, f, deps, weak, used,
                                              visited));
                         }
                     }
+                    None if !s.has_implicit_feature(feat) => {
+                        return Err(human(format!("Package `{}` does not have \
+                                                  the feature `{}`; it has an \
+                                                  optional dependency of that \
+                                                  name, but it's activated \
+                                                  with `dep:{}` instead",
+                                                 s.package_id(), feat, feat)))
+                    }
                     None => {
                         match deps.entry(feat.to_string()) {
                             Occupied(..) => {} // already activated
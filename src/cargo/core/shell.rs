@@ -1,8 +1,10 @@
 use std::fmt;
-use std::old_io::{IoResult, stderr};
+use std::old_io::IoResult;
+use std::sync::{Arc, Mutex};
 
 use term::Attr;
 use term::color::{Color, BLACK, RED, GREEN, YELLOW};
+use term::terminfo::TermInfo;
 use term::{Terminal, TerminfoTerminal, color};
 
 use self::AdequateTerminal::{NoColor, Colored};
@@ -14,6 +16,15 @@ pub struct ShellConfig {
     pub tty: bool
 }
 
+/// Whether output is colored.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum ColorConfig {
+    /// Only when writing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
 enum AdequateTerminal {
     NoColor(Box<Writer + Send>),
     Colored(Box<Terminal<UghWhyIsThisNecessary> + Send>)
@@ -22,21 +33,69 @@ enum AdequateTerminal {
 pub struct Shell {
     terminal: AdequateTerminal,
     config: ShellConfig,
+    color_config: ColorConfig,
+}
+
+/// How much output is printed to the shell.
+#[derive(Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Verbosity {
+    /// Only warnings and errors (`--quiet`)
+    Quiet,
+    /// Status lines such as `Compiling` in addition
+    Normal,
+    /// The commands being run in addition (`--verbose`)
+    Verbose,
+    /// The output of build scripts and the reasons packages are rebuilt in
+    /// addition (`-vv`)
+    VeryVerbose,
+}
+
+/// How errors aborting cargo are printed.
+#[derive(Copy, PartialEq, Eq, Debug)]
+pub enum ErrorFormat {
+    /// As text along with their causes
+    Human,
+    /// As a JSON object on a single line (`--error-format json`)
+    Json,
 }
 
 pub struct MultiShell {
     out: Shell,
     err: Shell,
-    verbose: bool
+    verbosity: Verbosity,
+    error_format: ErrorFormat,
+    status_to_stderr: bool,
 }
 
+// Shared so that the stream can still be written to without colors if a
+// terminal can't be set up around it.
+#[derive(Clone)]
 struct UghWhyIsThisNecessary {
-    inner: Box<Writer + Send>,
+    inner: Arc<Mutex<Box<Writer + Send>>>,
 }
 
 impl MultiShell {
-    pub fn new(out: Shell, err: Shell, verbose: bool) -> MultiShell {
-        MultiShell { out: out, err: err, verbose: verbose }
+    pub fn new(out: Shell, err: Shell, verbosity: Verbosity) -> MultiShell {
+        MultiShell {
+            out: out,
+            err: err,
+            verbosity: verbosity,
+            error_format: ErrorFormat::Human,
+            status_to_stderr: false,
+        }
+    }
+
+    /// Creates a shell printing to arbitrary writers rather than the standard
+    /// streams, for using cargo as a library. Nothing written is colored.
+    pub fn from_writers(out: Box<Writer + Send>, err: Box<Writer + Send>,
+                        verbosity: Verbosity) -> MultiShell {
+        let config = ShellConfig {
+            color: false,
+            verbose: verbosity >= Verbosity::Verbose,
+            tty: false,
+        };
+        MultiShell::new(Shell::create(out, config), Shell::create(err, config),
+                        verbosity)
     }
 
     pub fn out(&mut self) -> &mut Shell {
@@ -54,20 +113,32 @@ impl MultiShell {
     pub fn status<T, U>(&mut self, status: T, message: U) -> IoResult<()>
         where T: fmt::Display, U: fmt::Display
     {
-        self.out().say_status(status, message, GREEN)
+        if self.verbosity == Verbosity::Quiet { return Ok(()) }
+        if self.status_to_stderr {
+            self.err().say_status(status, message, GREEN)
+        } else {
+            self.out().say_status(status, message, GREEN)
+        }
     }
 
     pub fn verbose<F>(&mut self, mut callback: F) -> IoResult<()>
         where F: FnMut(&mut MultiShell) -> IoResult<()>
     {
-        if self.verbose { return callback(self) }
+        if self.verbosity >= Verbosity::Verbose { return callback(self) }
+        Ok(())
+    }
+
+    pub fn very_verbose<F>(&mut self, mut callback: F) -> IoResult<()>
+        where F: FnMut(&mut MultiShell) -> IoResult<()>
+    {
+        if self.verbosity == Verbosity::VeryVerbose { return callback(self) }
         Ok(())
     }
 
     pub fn concise<F>(&mut self, mut callback: F) -> IoResult<()>
         where F: FnMut(&mut MultiShell) -> IoResult<()>
     {
-        if !self.verbose { return callback(self) }
+        if self.verbosity < Verbosity::Verbose { return callback(self) }
         Ok(())
     }
 
@@ -79,31 +150,71 @@ impl MultiShell {
         self.err().say(message, YELLOW)
     }
 
-    pub fn set_verbose(&mut self, verbose: bool) {
-        self.verbose = verbose;
+    pub fn set_verbosity(&mut self, verbosity: Verbosity) {
+        self.verbosity = verbosity;
+    }
+
+    pub fn get_verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    pub fn set_color_config(&mut self, color_config: ColorConfig) {
+        self.out.set_color_config(color_config);
+        self.err.set_color_config(color_config);
     }
 
-    pub fn get_verbose(&self) -> bool {
-        self.verbose
+    pub fn set_error_format(&mut self, error_format: ErrorFormat) {
+        self.error_format = error_format;
+    }
+
+    pub fn get_error_format(&self) -> ErrorFormat {
+        self.error_format
+    }
+
+    /// Prints status lines to stderr rather than stdout, for commands which
+    /// print machine-readable output to stdout.
+    pub fn set_status_to_stderr(&mut self, status_to_stderr: bool) {
+        self.status_to_stderr = status_to_stderr;
     }
 }
 
 impl Shell {
     pub fn create(out: Box<Writer + Send>, config: ShellConfig) -> Shell {
-        let out = UghWhyIsThisNecessary { inner: out };
-        if config.tty && config.color {
-            let term = TerminfoTerminal::new(out);
-            term.map(|t| Shell {
-                terminal: Colored(Box::new(t)),
-                config: config
-            }).unwrap_or_else(|| {
-                Shell { terminal: NoColor(Box::new(stderr())), config: config }
-            })
+        let out = UghWhyIsThisNecessary { inner: Arc::new(Mutex::new(out)) };
+        // Whether colors are actually used is only decided when writing, as
+        // the color configuration may be changed after the shell is created.
+        let terminal = if config.color && TermInfo::from_env().is_ok() {
+            match TerminfoTerminal::new(out.clone()) {
+                Some(t) => Colored(Box::new(t)),
+                None => NoColor(Box::new(out)),
+            }
         } else {
-            Shell { terminal: NoColor(out.inner), config: config }
+            NoColor(Box::new(out))
+        };
+        Shell {
+            terminal: terminal,
+            config: config,
+            color_config: ColorConfig::Auto,
+        }
+    }
+
+    pub fn set_color_config(&mut self, color_config: ColorConfig) {
+        self.color_config = color_config;
+    }
+
+    fn colored(&self) -> bool {
+        match self.color_config {
+            ColorConfig::Auto => self.config.tty,
+            ColorConfig::Always => true,
+            ColorConfig::Never => false,
         }
     }
 
+    /// Whether this shell writes to a terminal.
+    pub fn is_tty(&self) -> bool {
+        self.config.tty
+    }
+
     pub fn verbose<F>(&mut self, mut callback: F) -> IoResult<()>
         where F: FnMut(&mut Shell) -> IoResult<()>
     {
@@ -141,7 +252,21 @@ impl Shell {
         Ok(())
     }
 
+    /// Clears the screen and moves the cursor to its top left corner, which
+    /// is only done when colors are used on a terminal.
+    pub fn clear_screen(&mut self) -> IoResult<()> {
+        if !self.config.tty || !self.colored() { return Ok(()) }
+        match self.terminal {
+            Colored(ref mut c) => {
+                try!(c.write_all(b"\x1b[2J\x1b[H"));
+                c.flush()
+            }
+            NoColor(_) => Ok(())
+        }
+    }
+
     fn fg(&mut self, color: color::Color) -> IoResult<bool> {
+        if !self.colored() { return Ok(false) }
         match self.terminal {
             Colored(ref mut c) => c.fg(color),
             NoColor(_) => Ok(false)
@@ -149,6 +274,7 @@ impl Shell {
     }
 
     fn attr(&mut self, attr: Attr) -> IoResult<bool> {
+        if !self.colored() { return Ok(false) }
         match self.terminal {
             Colored(ref mut c) => c.attr(attr),
             NoColor(_) => Ok(false)
@@ -156,6 +282,7 @@ impl Shell {
     }
 
     fn supports_attr(&self, attr: Attr) -> bool {
+        if !self.colored() { return false }
         match self.terminal {
             Colored(ref c) => c.supports_attr(attr),
             NoColor(_) => false
@@ -163,6 +290,7 @@ impl Shell {
     }
 
     fn reset(&mut self) -> IoResult<()> {
+        if !self.colored() { return Ok(()) }
         match self.terminal {
             Colored(ref mut c) => c.reset().map(|_| ()),
             NoColor(_) => Ok(())
@@ -188,6 +316,10 @@ impl Writer for Shell {
 
 impl Writer for UghWhyIsThisNecessary {
     fn write_all(&mut self, bytes: &[u8]) -> IoResult<()> {
-        self.inner.write_all(bytes)
+        self.inner.lock().unwrap().write_all(bytes)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.lock().unwrap().flush()
     }
 }
@@ -41,6 +41,17 @@ pub trait Source: Registry {
     /// The `pkg` argument is the package which this fingerprint should only be
     /// interested in for when this source may contain multiple packages.
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String>;
+
+    /// Lists the files of `pkg` whose modification times make up its
+    /// fingerprint, for sources whose files are edited in place. The listing
+    /// may be kept at `cache` to be reused while it's still valid.
+    ///
+    /// The packages of other sources never change once they are downloaded,
+    /// so by default there is no such listing.
+    fn cached_files(&self, _pkg: &Package,
+                    _cache: &Path) -> CargoResult<Option<Vec<Path>>> {
+        Ok(None)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -22,7 +22,8 @@ impl Summary {
                dependencies: Vec<Dependency>,
                features: HashMap<String, Vec<String>>) -> CargoResult<Summary> {
         for dep in dependencies.iter() {
-            if features.get(dep.name()).is_some() {
+            if features.get(dep.name()).is_some() &&
+               !uses_dep_syntax(&features, dep.name()) {
                 return Err(human(format!("Features and dependencies cannot have \
                                           the same name: `{}`", dep.name())))
             }
@@ -34,11 +35,38 @@ impl Summary {
         }
         for (feature, list) in features.iter() {
             for dep in list.iter() {
+                if dep.starts_with("dep:") {
+                    let name = &dep[4..];
+                    let optional = dependencies.iter().any(|d| {
+                        d.name() == name && d.is_optional()
+                    });
+                    if optional && !name.contains("/") { continue }
+                    return Err(human(format!("Feature `{}` includes `{}`, but \
+                                              `{}` is not an optional \
+                                              dependency", feature, dep,
+                                             name)))
+                }
                 let mut parts = dep.splitn(1, '/');
                 let dep = parts.next().unwrap();
                 let is_reexport = parts.next().is_some();
+                // `foo?/bar` only enables `bar` if `foo` is activated anyway
+                let dep = if is_reexport && dep.ends_with("?") {
+                    &dep[..dep.len() - 1]
+                } else {
+                    dep
+                };
                 if !is_reexport && features.get(dep).is_some() { continue }
                 match dependencies.iter().find(|d| d.name() == dep) {
+                    Some(d) if d.is_optional() && !is_reexport &&
+                               uses_dep_syntax(&features, dep) => {
+                        return Err(human(format!("Feature `{}` includes `{}`, \
+                                                  but `{}` has no implicit \
+                                                  feature as it is activated \
+                                                  with `dep:{}` elsewhere; use \
+                                                  `dep:{}` to activate it \
+                                                  here as well", feature, dep,
+                                                 dep, dep, dep)))
+                    }
                     Some(d) => {
                         if d.is_optional() || is_reexport { continue }
                         return Err(human(format!("Feature `{}` depends on `{}` \
@@ -75,6 +103,13 @@ impl Summary {
     pub fn dependencies(&self) -> &[Dependency] { &self.dependencies }
     pub fn features(&self) -> &HashMap<String, Vec<String>> { &self.features }
 
+    /// Whether the optional dependency `dep` can be activated as a feature of
+    /// the same name, which is the case unless a feature activates it with
+    /// `dep:`.
+    pub fn has_implicit_feature(&self, dep: &str) -> bool {
+        !uses_dep_syntax(&self.features, dep)
+    }
+
     pub fn override_id(mut self, id: PackageId) -> Summary {
         self.package_id = id;
         self
@@ -88,6 +123,12 @@ impl Summary {
     }
 }
 
+fn uses_dep_syntax(features: &HashMap<String, Vec<String>>, dep: &str) -> bool {
+    features.values().any(|list| {
+        list.iter().any(|f| f.starts_with("dep:") && &f[4..] == dep)
+    })
+}
+
 impl PartialEq for Summary {
     fn eq(&self, other: &Summary) -> bool {
         self.package_id == other.package_id
@@ -1,3 +1,28 @@
+//! Cargo, Rust's package manager, as a library.
+//!
+//! Besides the `cargo` binary, the operations of cargo can be driven by other
+//! programs through the functions of the `ops` module. The status lines of an
+//! operation and the output of the compiler go through the `MultiShell` of the
+//! `Config` passed to it, so a program can capture the output of a build by
+//! creating a shell with its own writers:
+//!
+//! ```ignore
+//! use std::old_io::ChanWriter;
+//! use std::sync::mpsc::channel;
+//! use cargo::core::{MultiShell, Verbosity};
+//! use cargo::ops::{self, CompileOptions};
+//! use cargo::util::Config;
+//!
+//! let (tx, rx) = channel();
+//! let out = Box::new(ChanWriter::new(tx.clone()));
+//! let err = Box::new(ChanWriter::new(tx));
+//! let mut shell = MultiShell::from_writers(out, err, Verbosity::Normal);
+//! let config = try!(Config::with_cwd(&mut shell, project_dir.clone()));
+//! let options = CompileOptions::new("compile", &config);
+//! try!(ops::compile(&project_dir.join("Cargo.toml"), &options));
+//! // `rx` has received everything printed by the build
+//! ```
+
 #![deny(unused)]
 #![feature(collections, hash, io, libc, os, path, std_misc, unicode, env, core)]
 #![cfg_attr(test, deny(warnings))]
@@ -30,7 +55,7 @@ use rustc_serialize::{Decodable, Encodable};
 use rustc_serialize::json::{self, Json};
 use docopt::Docopt;
 
-use core::{Shell, MultiShell, ShellConfig};
+use core::{Shell, MultiShell, ShellConfig, Verbosity, ErrorFormat};
 use term::color::{BLACK, RED};
 
 pub use util::{CargoError, CliError, CliResult, human, Config, ChainError};
@@ -92,18 +117,45 @@ fn process<V, F>(mut callback: F)
     where F: FnMut(&[String], &Config) -> CliResult<Option<V>>,
           V: Encodable
 {
-    let mut shell = shell(true);
+    let mut shell = shell(Verbosity::Verbose);
     process_executed((|| {
-        let config = try!(Config::new(&mut shell));
         let args: Vec<_> = try!(env::args().map(|s| {
             s.into_string().map_err(|s| {
                 human(format!("invalid unicode in argument: {:?}", s))
             })
         }).collect());
+        match error_format_from_args(&args) {
+            Some(format) => shell.set_error_format(format),
+            None => {}
+        }
+        let config = try!(Config::new(&mut shell));
         callback(&args, &config)
     })(), &mut shell)
 }
 
+/// Picks `--error-format` out of the raw arguments, so that errors in loading
+/// the configuration or parsing the arguments themselves are already printed
+/// in that format. Invalid values are left to `configure_error_format` to
+/// complain about.
+fn error_format_from_args(args: &[String]) -> Option<ErrorFormat> {
+    let mut args = args.iter().skip(1).take_while(|a| a.as_slice() != "--");
+    while let Some(arg) = args.next() {
+        let value = if arg.as_slice() == "--error-format" {
+            args.next().map(|s| s.as_slice())
+        } else if arg.starts_with("--error-format=") {
+            Some(&arg["--error-format=".len()..])
+        } else {
+            continue
+        };
+        return match value {
+            Some("human") => Some(ErrorFormat::Human),
+            Some("json") => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+    None
+}
+
 pub fn process_executed<T>(result: CliResult<Option<T>>, shell: &mut MultiShell)
     where T: Encodable
 {
@@ -117,7 +169,8 @@ pub fn process_executed<T>(result: CliResult<Option<T>>, shell: &mut MultiShell)
     }
 }
 
-pub fn shell(verbose: bool) -> MultiShell {
+pub fn shell(verbosity: Verbosity) -> MultiShell {
+    let verbose = verbosity >= Verbosity::Verbose;
     let tty = stderr_raw().isatty();
     let stderr = Box::new(stderr()) as Box<Writer + Send>;
 
@@ -130,7 +183,7 @@ pub fn shell(verbose: bool) -> MultiShell {
     let config = ShellConfig { color: true, verbose: verbose, tty: tty };
     let out = Shell::create(stdout, config);
 
-    MultiShell::new(out, err, verbose)
+    MultiShell::new(out, err, verbosity)
 }
 
 
@@ -149,8 +202,13 @@ pub fn handle_error(err: CliError, shell: &mut MultiShell) {
     let CliError { error, exit_code, unknown } = err;
     let fatal = exit_code != 0; // exit_code == 0 is non-fatal error
 
+    if fatal && shell.get_error_format() == ErrorFormat::Json {
+        let _ = shell.err().say(json_error(&*error), BLACK);
+        std::env::set_exit_status(exit_code);
+        return
+    }
 
-    let hide = unknown && !shell.get_verbose();
+    let hide = unknown && shell.get_verbosity() < Verbosity::Verbose;
     if hide {
         let _ = shell.err().say("An unknown error occurred", RED);
     } else {
@@ -164,8 +222,46 @@ pub fn handle_error(err: CliError, shell: &mut MultiShell) {
     std::env::set_exit_status(exit_code);
 }
 
+/// An error as printed by `--error-format json`.
+#[derive(RustcEncodable)]
+struct JsonError {
+    reason: &'static str,
+    /// The code of the innermost cause which has one, as that's the most
+    /// specific about what went wrong
+    code: Option<&'static str>,
+    message: String,
+    causes: Vec<String>,
+    spans: Vec<util::ErrorSpan>,
+}
+
+fn json_error(error: &CargoError) -> String {
+    let mut json = JsonError {
+        reason: "cargo-error",
+        code: error.code(),
+        message: error.to_string(),
+        causes: Vec::new(),
+        spans: error.spans(),
+    };
+    let mut cargo_err = error;
+    let mut err;
+    loop {
+        cargo_err = match cargo_err.cargo_cause() {
+            Some(cause) => cause,
+            None => { err = cargo_err.cause(); break }
+        };
+        json.code = cargo_err.code().or(json.code);
+        json.spans.extend(cargo_err.spans().into_iter());
+        json.causes.push(cargo_err.to_string());
+    }
+    while let Some(cause) = err {
+        json.causes.push(cause.to_string());
+        err = cause.cause();
+    }
+    json::encode(&json).unwrap()
+}
+
 fn handle_cause(mut cargo_err: &CargoError, shell: &mut MultiShell) -> bool {
-    let verbose = shell.get_verbose();
+    let verbose = shell.get_verbosity() >= Verbosity::Verbose;
     let mut err;
     loop {
         cargo_err = match cargo_err.cargo_cause() {
@@ -199,6 +295,36 @@ pub fn version() -> String {
     })
 }
 
+/// Returns the version of cargo along with the details of its build, as
+/// embedded by the makefile, including the configure options it was built
+/// with. Builds which don't go through the makefile don't know about their
+/// commit or options, so those details are `unknown`.
+pub fn version_verbose() -> String {
+    let mut ret = version();
+    ret.push_str(&format!("\nrelease: {}", match option_env!("CFG_RELEASE") {
+        Some(s) => s.to_string(),
+        None => format!("{}.{}.{}{}",
+                        env!("CARGO_PKG_VERSION_MAJOR"),
+                        env!("CARGO_PKG_VERSION_MINOR"),
+                        env!("CARGO_PKG_VERSION_PATCH"),
+                        option_env!("CARGO_PKG_VERSION_PRE").unwrap_or("")),
+    }));
+    ret.push_str(&format!("\ncommit-hash: {}",
+                          option_env!("CFG_COMMIT_HASH").unwrap_or("unknown")));
+    ret.push_str(&format!("\ncommit-date: {}",
+                          option_env!("CFG_VER_DATE").unwrap_or("unknown")));
+    ret.push_str(&format!("\nbuild-date: {}",
+                          option_env!("CFG_BUILD_DATE").unwrap_or("unknown")));
+    ret.push_str(&format!("\nrelease-channel: {}",
+                          option_env!("CFG_RELEASE_CHANNEL").unwrap_or("dev")));
+    ret.push_str(&format!("\nfeatures: {}", match option_env!("CFG_FEATURES") {
+        Some("") => "none",
+        Some(s) => s,
+        None => "unknown",
+    }));
+    ret
+}
+
 fn flags_from_args<'a, T>(usage: &str, args: &[String],
                           options_first: bool) -> CliResult<T>
     where T: Decodable
@@ -0,0 +1,238 @@
+//! Checking locked dependencies against a database of security advisories.
+//!
+//! The advisory database is a directory, or a git repository which is fetched
+//! into `$CARGO_HOME/advisory-db`, containing one TOML file per advisory:
+//!
+//! ```toml
+//! [advisory]
+//! id = "EXAMPLE-2015-0001"
+//! package = "bar"
+//! title = "Buffer overflow when parsing headers"
+//! url = "https://example.com/advisories/EXAMPLE-2015-0001"
+//! patched_versions = [">= 0.3.2"]
+//! unaffected_versions = ["< 0.2.0"]
+//! ```
+//!
+//! A version of the package is affected by the advisory unless it matches one
+//! of the patched or unaffected version requirements. Only packages from a
+//! registry are checked, as advisories refer to published versions.
+
+use std::old_io::fs::{self, PathExtensions};
+use std::old_io::{self, File};
+
+use git2;
+use rustc_serialize::Decodable;
+use semver::VersionReq;
+use toml;
+
+use core::{PackageId, Resolve};
+use core::registry::PackageRegistry;
+use ops;
+use sources::git;
+use util::{paths, CargoResult, ChainError, Config, FileLock, human};
+use util::toml as cargo_toml;
+
+/// How often (in milliseconds) builds fetch the advisory database.
+const FETCH_INTERVAL: u64 = 24 * 60 * 60 * 1000;
+
+/// A security advisory for a package.
+#[derive(Clone)]
+pub struct Advisory {
+    pub id: String,
+    pub package: String,
+    pub title: String,
+    pub url: Option<String>,
+    patched: Vec<VersionReq>,
+    unaffected: Vec<VersionReq>,
+}
+
+#[derive(RustcDecodable)]
+struct AdvisoryFile {
+    advisory: TomlAdvisory,
+}
+
+#[derive(RustcDecodable)]
+struct TomlAdvisory {
+    id: String,
+    package: String,
+    title: String,
+    url: Option<String>,
+    patched_versions: Option<Vec<String>>,
+    unaffected_versions: Option<Vec<String>>,
+}
+
+impl Advisory {
+    /// Returns whether the version of `id` is affected by this advisory.
+    pub fn affects(&self, id: &PackageId) -> bool {
+        id.name() == self.package &&
+            !self.patched.iter().chain(self.unaffected.iter())
+                 .any(|req| req.matches(id.version()))
+    }
+}
+
+/// Checks the dependencies of the package of the manifest at `manifest_path`
+/// against the advisory database, returning each affected package along with
+/// the advisory affecting it. Like a build, this resolves the dependencies
+/// and writes the lockfile if there is none.
+///
+/// The database in `db` is used if given, and the one configured in
+/// `audit.database` or `audit.url` otherwise.
+pub fn audit(manifest_path: &Path, db: Option<&Path>, config: &Config)
+             -> CargoResult<Vec<(PackageId, Advisory)>> {
+    let db = match db {
+        Some(db) => db.clone(),
+        None => match try!(database_path(config, false)) {
+            Some(db) => db,
+            None => return Err(human("no advisory database is configured, \
+                                      pass one with `--db` or set \
+                                      `audit.database` or `audit.url`")),
+        },
+    };
+    let advisories = try!(load_advisories(&db));
+
+    let mut registry = PackageRegistry::new(config);
+    let (_, resolve) = try!(ops::resolve_manifest(&mut registry,
+                                                  manifest_path, true));
+    Ok(affected(&resolve, advisories))
+}
+
+/// Warns about each package of `resolve` affected by an advisory, if
+/// `audit.on-build` is set.
+pub fn audit_on_build(resolve: &Resolve, config: &Config) -> CargoResult<()> {
+    let enabled = match try!(config.get("audit.on-build")) {
+        Some(val) => try!(val.boolean()).0,
+        None => false,
+    };
+    if !enabled { return Ok(()) }
+    let db = match try!(database_path(config, true)) {
+        Some(db) => db,
+        None => return Err(human("`audit.on-build` is set, but no advisory \
+                                  database is configured in `audit.database` \
+                                  or `audit.url`")),
+    };
+    let advisories = try!(load_advisories(&db));
+    for (id, advisory) in affected(resolve, advisories).into_iter() {
+        try!(config.shell().warn(format!("warning: {} is affected by {}: {}",
+                                         id, advisory.id, advisory.title)));
+    }
+    Ok(())
+}
+
+fn affected(resolve: &Resolve, advisories: Vec<Advisory>)
+            -> Vec<(PackageId, Advisory)> {
+    let mut ids = resolve.iter().filter(|id| {
+        id.source_id().is_registry()
+    }).cloned().collect::<Vec<_>>();
+    ids.sort();
+
+    let mut ret = Vec::new();
+    for advisory in advisories.into_iter() {
+        for id in ids.iter().filter(|id| advisory.affects(id)) {
+            ret.push((id.clone(), advisory.clone()));
+        }
+    }
+    ret.sort_by(|a, b| (&a.0, &a.1.id).cmp(&(&b.0, &b.1.id)));
+    ret
+}
+
+/// Returns the directory of the configured advisory database, fetching it
+/// first if it's a git repository. With `throttle`, a database fetched less
+/// than a day ago isn't fetched again.
+fn database_path(config: &Config, throttle: bool) -> CargoResult<Option<Path>> {
+    if let Some((db, path)) = try!(config.get_string("audit.database")) {
+        // The path is relative to the directory containing `.cargo/config`
+        return Ok(Some(path.dir_path().dir_path().join(db)))
+    }
+    match try!(config.get_string("audit.url")) {
+        Some((url, _)) => {
+            Ok(Some(try!(fetch_database(&url, throttle, config))))
+        }
+        None => Ok(None),
+    }
+}
+
+fn fetch_database(url: &str, throttle: bool,
+                  config: &Config) -> CargoResult<Path> {
+    let dst = config.home().join("advisory-db");
+    // Like git dependencies, the database is shared by all projects
+    let lock_path = config.home().join("advisory-db.lock");
+    let _lock = try!(FileLock::acquire(&lock_path,
+        &format!("advisory database `{}`", url), config));
+
+    let fetched = dst.join(".git").join("cargo-fetched");
+    if throttle {
+        if let Ok(stat) = fs::stat(&fetched) {
+            if paths::now_ms().saturating_sub(stat.modified) < FETCH_INTERVAL {
+                return Ok(dst)
+            }
+        }
+    }
+
+    try!(config.shell().status("Updating",
+                               format!("advisory database `{}`", url)));
+    let repo = match git2::Repository::open(&dst) {
+        Ok(repo) => repo,
+        Err(..) => {
+            try!(fs::mkdir_recursive(&dst, old_io::USER_DIR));
+            try!(git2::Repository::init(&dst))
+        }
+    };
+    // The database is checked out at whichever branch the remote's HEAD
+    // points to
+    let refspec = "+HEAD:refs/remotes/origin/HEAD";
+    try!(git::fetch(&repo, url, refspec, config).chain_error(|| {
+        human(format!("failed to fetch the advisory database from `{}`", url))
+    }));
+    let oid = try!(repo.refname_to_id("refs/remotes/origin/HEAD"));
+    let object = try!(repo.find_object(oid, None));
+    try!(repo.reset(&object, git2::ResetType::Hard, None, None, None));
+    try!(File::create(&fetched));
+    let now = paths::now_ms();
+    try!(fs::change_file_times(&fetched, now, now));
+    Ok(dst)
+}
+
+/// Loads every advisory in the database at `db`.
+pub fn load_advisories(db: &Path) -> CargoResult<Vec<Advisory>> {
+    if !db.is_dir() {
+        return Err(human(format!("the advisory database `{}` does not exist",
+                                 db.display())))
+    }
+    let mut ret = Vec::new();
+    for path in try!(fs::walk_dir(db)) {
+        if path.extension_str() != Some("toml") { continue }
+        // Skip the metadata of a git checkout
+        if path.str_components().any(|c| c == Some(".git")) { continue }
+        ret.push(try!(load_advisory(&path).chain_error(|| {
+            human(format!("failed to load the advisory `{}`", path.display()))
+        })));
+    }
+    Ok(ret)
+}
+
+fn load_advisory(path: &Path) -> CargoResult<Advisory> {
+    let contents = try!(File::open(path).read_to_string());
+    let table = try!(cargo_toml::parse(&contents, path));
+    let mut d = toml::Decoder::new(toml::Value::Table(table));
+    let file: AdvisoryFile = try!(Decodable::decode(&mut d).map_err(|e| {
+        human(e.to_string())
+    }));
+    let AdvisoryFile { advisory } = file;
+
+    fn reqs(reqs: Option<Vec<String>>) -> CargoResult<Vec<VersionReq>> {
+        reqs.unwrap_or(Vec::new()).iter().map(|req| {
+            VersionReq::parse(req).chain_error(|| {
+                human(format!("invalid version requirement `{}`", req))
+            })
+        }).collect()
+    }
+
+    Ok(Advisory {
+        patched: try!(reqs(advisory.patched_versions)),
+        unaffected: try!(reqs(advisory.unaffected_versions)),
+        id: advisory.id,
+        package: advisory.package,
+        title: advisory.title,
+        url: advisory.url,
+    })
+}
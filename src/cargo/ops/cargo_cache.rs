@@ -0,0 +1,266 @@
+use std::old_io::fs::{self, PathExtensions};
+use std::old_io::{File, TempDir};
+
+use flate2::reader::GzDecoder;
+use git2;
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json::Json;
+use semver::Version;
+use tar::Archive;
+
+use sources::registry::index_file;
+use util::{CargoResult, ChainError, Config, Sha256, human, paths};
+
+pub struct CacheOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    /// Entries not used within this many days are removed. If `None` the
+    /// `cache.max-age` configuration key is consulted.
+    pub max_age: Option<u32>,
+    pub dry_run: bool,
+}
+
+/// Number of days an unused cache entry is kept around by default.
+const DEFAULT_MAX_AGE: u32 = 90;
+
+/// Removes entries from the global caches in `$CARGO_HOME` which have not
+/// been used recently.
+///
+/// This covers downloaded `.crate` files, their unpacked sources, registry
+/// indices, git databases and git checkouts. Last-use times are recorded by
+/// the sources themselves through `paths::mark_used`.
+pub fn gc(opts: &CacheOptions) -> CargoResult<()> {
+    let config = opts.config;
+    let max_age = match opts.max_age {
+        Some(days) => days,
+        None => match try!(config.get_i64("cache.max-age")) {
+            Some((days, _)) if days >= 0 => days as u32,
+            Some((_, path)) => {
+                return Err(human(format!("`cache.max-age` in {} must not \
+                                          be negative", path.display())))
+            }
+            None => DEFAULT_MAX_AGE,
+        },
+    };
+    let cutoff = paths::now_ms().saturating_sub(max_age as u64 * 86400000);
+
+    // Collect every entry of each cache along with its time of last use
+    let mut entries = Vec::new();
+    for dir in try!(children(&config.registry_cache_path())).iter() {
+        entries.extend(try!(children(dir)).into_iter());
+    }
+    for dir in try!(children(&config.registry_source_path())).iter() {
+        entries.extend(try!(children(dir)).into_iter());
+    }
+    entries.extend(try!(children(&config.registry_index_path())).into_iter());
+    entries.extend(try!(children(&config.git_db_path())).into_iter());
+    let checkouts = try!(children(&config.git_checkout_path()));
+    for dir in checkouts.iter() {
+        entries.extend(try!(children(dir)).into_iter());
+    }
+
+    let mut removed = 0;
+    let mut bytes = 0;
+    for entry in entries.iter() {
+        let used = try!(paths::last_used(config.home(), entry).chain_error(|| {
+            human(format!("failed to read `{}`", entry.display()))
+        }));
+        if used >= cutoff { continue }
+
+        let display = entry.path_relative_from(config.home())
+                           .unwrap_or(entry.clone());
+        try!(config.shell().verbose(|s| {
+            s.status("Removing", display.display())
+        }));
+        let (_, size) = try!(paths::disk_usage(entry).chain_error(|| {
+            human(format!("failed to read `{}`", entry.display()))
+        }));
+        removed += 1;
+        bytes += size;
+        if opts.dry_run { continue }
+
+        try!(rm_rf(entry));
+        if let Some(marker) = paths::last_use_marker(config.home(), entry) {
+            let _ = fs::unlink(&marker);
+        }
+    }
+
+    // Clean up the per-repository directories of checkouts that are now empty
+    if !opts.dry_run {
+        for dir in checkouts.iter() {
+            if try!(children(dir)).is_empty() {
+                try!(rm_rf(dir));
+            }
+        }
+    }
+
+    let verb = if opts.dry_run {"would be removed"} else {"removed"};
+    try!(config.shell().status("Summary",
+        format!("{} unused {} {}, {} total", removed,
+                if removed == 1 {"entry"} else {"entries"}, verb,
+                paths::human_size(bytes))));
+    Ok(())
+}
+
+/// Checks the global caches in `$CARGO_HOME` for corruption, returning the
+/// corrupted entries, and removing them if `fix` is set so they are fetched
+/// again by the next build.
+///
+/// Downloaded `.crate` files are checked against the checksums in the index
+/// of their registry, unpacked sources against the `.crate` file they were
+/// unpacked from, and git checkouts against the commit they checked out.
+pub fn verify_cache(config: &Config, fix: bool) -> CargoResult<Vec<Path>> {
+    let mut corrupted = Vec::new();
+
+    for dir in try!(children(&config.registry_cache_path())).iter() {
+        let index = config.registry_index_path().join(dir.filename().unwrap());
+        let src = config.registry_source_path().join(dir.filename().unwrap());
+        for file in try!(children(dir)).iter() {
+            if file.extension_str() != Some("crate") { continue }
+            let stem = file.filestem_str().unwrap_or("");
+            let unpacked = src.join(stem);
+            try!(config.shell().verbose(|s| {
+                s.status("Verifying", display(config, file).display())
+            }));
+            if !try!(verify_crate_file(file, &index)) {
+                corrupted.push(file.clone());
+                if unpacked.exists() { corrupted.push(unpacked) }
+                continue
+            }
+            if !unpacked.join(".cargo-ok").exists() { continue }
+            try!(config.shell().verbose(|s| {
+                s.status("Verifying", display(config, &unpacked).display())
+            }));
+            if !try!(verify_unpacked(file, &unpacked)) {
+                corrupted.push(unpacked);
+            }
+        }
+    }
+
+    for dir in try!(children(&config.git_checkout_path())).iter() {
+        for checkout in try!(children(dir)).iter() {
+            try!(config.shell().verbose(|s| {
+                s.status("Verifying", display(config, checkout).display())
+            }));
+            if !verify_checkout(checkout) {
+                corrupted.push(checkout.clone());
+            }
+        }
+    }
+
+    for path in corrupted.iter() {
+        try!(config.shell().warn(format!("warning: `{}` is corrupted",
+                                         path.display())));
+        if fix && path.exists() {
+            try!(config.shell().status("Removing",
+                                       display(config, path).display()));
+            try!(rm_rf(path));
+        }
+    }
+    Ok(corrupted)
+}
+
+fn display(config: &Config, path: &Path) -> Path {
+    path.path_relative_from(config.home()).unwrap_or(path.clone())
+}
+
+/// Checks the `.crate` file `file` against the checksum in the registry index
+/// checked out at `index`. Files for which the index has no checksum are
+/// assumed to be fine.
+fn verify_crate_file(file: &Path, index: &Path) -> CargoResult<bool> {
+    // The file is named `<name>-<version>.crate`, where both the name and
+    // the version may contain dashes
+    let stem = file.filestem_str().unwrap_or("");
+    let split = stem.char_indices().filter(|&(_, c)| c == '-').find(|&(i, _)| {
+        Version::parse(&stem[i + 1..]).is_ok()
+    });
+    let (name, version) = match split {
+        Some((i, _)) => (&stem[..i], &stem[i + 1..]),
+        None => return Ok(true),
+    };
+
+    let lines = match File::open(&index_file(index, name)).read_to_string() {
+        Ok(lines) => lines,
+        Err(..) => return Ok(true),
+    };
+    let expected = lines.lines().filter_map(|line| {
+        Json::from_str(line).ok()
+    }).find(|pkg| {
+        pkg.find("vers").and_then(|v| v.as_string()) == Some(version)
+    }).and_then(|pkg| {
+        pkg.find("cksum").and_then(|c| c.as_string()).map(|c| c.to_string())
+    });
+    let expected = match expected {
+        Some(cksum) => cksum,
+        None => return Ok(true),
+    };
+
+    let contents = try!(File::open(file).read_to_end().chain_error(|| {
+        human(format!("failed to read `{}`", file.display()))
+    }));
+    let mut state = Sha256::new();
+    state.update(&contents);
+    Ok(state.finish().to_hex() == expected)
+}
+
+/// Checks that every file of the `.crate` file `file` is present with the
+/// same contents in the directory `unpacked` it was unpacked to.
+fn verify_unpacked(file: &Path, unpacked: &Path) -> CargoResult<bool> {
+    let tmp = try!(TempDir::new("cargo-verify"));
+    let f = try!(File::open(file));
+    let gz = try!(GzDecoder::new(f));
+    let mut tar = Archive::new(gz);
+    if tar.unpack(tmp.path()).is_err() { return Ok(false) }
+
+    let root = tmp.path().join(unpacked.filename().unwrap());
+    for path in try!(fs::walk_dir(&root)) {
+        if !path.is_file() { continue }
+        let relative = path.path_relative_from(&root).unwrap();
+        let expected = try!(File::open(&path).read_to_end());
+        match File::open(&unpacked.join(relative)).read_to_end() {
+            Ok(ref actual) if *actual == expected => {}
+            _ => return Ok(false),
+        }
+    }
+    Ok(true)
+}
+
+/// Checks that no file tracked in the git checkout `checkout` was modified or
+/// removed.
+fn verify_checkout(checkout: &Path) -> bool {
+    let repo = match git2::Repository::open(checkout) {
+        Ok(repo) => repo,
+        Err(..) => return false,
+    };
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(false).include_ignored(false);
+    let statuses = match repo.statuses(Some(&mut opts)) {
+        Ok(statuses) => statuses,
+        Err(..) => return false,
+    };
+    let changed = statuses.iter().any(|entry| {
+        entry.status().intersects(git2::STATUS_WT_MODIFIED |
+                                  git2::STATUS_WT_DELETED |
+                                  git2::STATUS_WT_TYPECHANGE)
+    });
+    !changed
+}
+
+fn children(dir: &Path) -> CargoResult<Vec<Path>> {
+    if !dir.is_dir() { return Ok(Vec::new()) }
+    let ret = try!(fs::readdir(dir).chain_error(|| {
+        human(format!("failed to read directory `{}`", dir.display()))
+    }));
+    Ok(ret)
+}
+
+fn rm_rf(path: &Path) -> CargoResult<()> {
+    let long = paths::long_path(path);
+    let res = if long.is_dir() {
+        fs::rmdir_recursive(&long)
+    } else {
+        fs::unlink(&long)
+    };
+    res.chain_error(|| {
+        human(format!("failed to remove `{}`", path.display()))
+    })
+}
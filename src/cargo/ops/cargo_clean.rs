@@ -4,13 +4,19 @@ use std::old_io::fs::{self, PathExtensions};
 use core::PackageSet;
 use core::source::{Source, SourceMap};
 use sources::PathSource;
-use util::{CargoResult, human, ChainError, Config};
+use util::{CargoResult, human, ChainError, Config, disk_usage, human_size};
 use ops::{self, Layout, Context};
 
 pub struct CleanOptions<'a, 'b: 'a> {
     pub spec: Option<&'a str>,
     pub target: Option<&'a str>,
     pub config: &'a Config<'b>,
+    /// Only report what would be removed, don't touch the filesystem.
+    pub dry_run: bool,
+    /// Restrict cleaning to the output of the release profile.
+    pub release: bool,
+    /// Restrict cleaning to generated documentation.
+    pub doc: bool,
 }
 
 /// Cleans the project from build artifacts.
@@ -21,17 +27,35 @@ pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
     let root = try!(src.root_package());
     let manifest = root.manifest();
 
+    if opts.doc && (opts.release || opts.spec.is_some()) {
+        return Err(human("--doc cannot be combined with --release or \
+                          --package"))
+    }
+
     // If we have a spec, then we need to delete some package,s otherwise, just
-    // remove the whole target directory and be done with it!
+    // remove the whole target directory (or the requested profile inside of
+    // it) and be done with it!
     let spec = match opts.spec {
         Some(spec) => spec,
-        None => return rm_rf(manifest.target_dir()),
+        None => {
+            let mut dir = manifest.target_dir().clone();
+            if opts.doc {
+                dir.push("doc");
+            } else {
+                match manifest.build_target(opts.target) {
+                    Some(s) => dir.push(s),
+                    None => {}
+                }
+                if opts.release {
+                    dir.push("release");
+                }
+            }
+            return remove(opts, &[dir])
+        }
     };
 
     // Load the lockfile (if one's available), and resolve spec to a pkgid
-    let lockfile = root.root().join("Cargo.lock");
-    let source_id = root.package_id().source_id();
-    let resolve = match try!(ops::load_lockfile(&lockfile, source_id)) {
+    let resolve = match try!(ops::load_pkg_lockfile(&root)) {
         Some(resolve) => resolve,
         None => return Err(human("A Cargo.lock must exist before cleaning"))
     };
@@ -53,16 +77,48 @@ pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
                                None, &pkg, Default::default()));
 
     // And finally, clean everything out!
+    let mut paths = Vec::new();
     for target in pkg.targets().iter() {
-        let layout = Layout::new(&root, opts.target, target.profile().dest());
-        try!(rm_rf(&layout.native(&pkg)));
-        try!(rm_rf(&layout.fingerprint(&pkg)));
+        let dest = target.profile().dest();
+        if opts.release && dest != Some("release") { continue }
+        let layout = Layout::new(&root, manifest.build_target(opts.target),
+                                 dest);
+        paths.push(layout.native(&pkg));
+        paths.push(layout.fingerprint(&pkg));
         for filename in try!(cx.target_filenames(target)).iter() {
-            try!(rm_rf(&layout.dest().join(filename)));
-            try!(rm_rf(&layout.deps().join(filename)));
+            paths.push(layout.dest().join(filename));
+            paths.push(layout.deps().join(filename));
         }
     }
+    paths.sort_by(|a, b| a.as_vec().cmp(b.as_vec()));
+    paths.dedup();
 
+    remove(opts, &paths)
+}
+
+fn remove(opts: &CleanOptions, paths: &[Path]) -> CargoResult<()> {
+    if !opts.dry_run {
+        for path in paths.iter() {
+            try!(rm_rf(path));
+        }
+        return Ok(())
+    }
+
+    let mut files = 0;
+    let mut bytes = 0;
+    for path in paths.iter().filter(|p| p.exists()) {
+        try!(opts.config.shell().verbose(|s| {
+            s.status("Removing", path.display())
+        }));
+        let (f, b) = try!(disk_usage(path).chain_error(|| {
+            human(format!("failed to read `{}`", path.display()))
+        }));
+        files += f;
+        bytes += b;
+    }
+    try!(opts.config.shell().status("Summary",
+        format!("{} {} would be removed, {} total", files,
+                if files == 1 {"file"} else {"files"}, human_size(bytes))));
     Ok(())
 }
 
@@ -24,17 +24,21 @@
 
 use std::collections::HashMap;
 use std::default::Default;
+use std::env;
 use std::num::ToPrimitive;
+use std::old_io::{self, fs};
 use std::os;
 use std::sync::Arc;
 
 use core::registry::PackageRegistry;
 use core::{Source, SourceId, PackageSet, Package, Target, PackageId};
+use core::PackageIdSpec;
 use core::resolver::Method;
 use ops::{self, BuildOutput, ExecEngine};
 use sources::{PathSource};
-use util::config::Config;
-use util::{CargoResult, internal, human, ChainError, profile};
+use util::config::{Config, ConfigValue};
+use util::{CargoResult, internal, human, ChainError, profile, process, paths};
+use util::workspace;
 
 /// Contains informations about how a package should be compiled.
 pub struct CompileOptions<'a, 'b: 'a> {
@@ -51,8 +55,42 @@ pub struct CompileOptions<'a, 'b: 'a> {
     pub spec: Option<&'a str>,
     pub lib_only: bool,
     pub exec_engine: Option<Arc<Box<ExecEngine>>>,
+    /// Directory to copy the final artifacts of the package to.
+    pub out_dir: Option<&'a Path>,
+    /// Level to cap the lints of dependencies outside of the workspace at,
+    /// overriding `build.cap-lints`.
+    pub cap_lints: Option<&'a str>,
+    /// Whether the pre-build and post-build hooks of the package are run.
+    pub hooks: bool,
 }
 
+impl<'a, 'b> CompileOptions<'a, 'b> {
+    /// Returns the options for compiling the package with the profile `env`,
+    /// like `compile` or `test`, as if no flags were passed to `cargo build`.
+    pub fn new(env: &'a str, config: &'a Config<'b>) -> CompileOptions<'a, 'b> {
+        CompileOptions {
+            env: env,
+            config: config,
+            jobs: None,
+            target: None,
+            dev_deps: false,
+            features: &[],
+            no_default_features: false,
+            spec: None,
+            lib_only: false,
+            exec_engine: None,
+            out_dir: None,
+            cap_lints: None,
+            hooks: true,
+        }
+    }
+}
+
+/// Compiles the package of the manifest at `manifest_path`.
+///
+/// Status lines and the output of the compiler are printed through the shell
+/// of `options.config`, so a shell created with `MultiShell::from_writers`
+/// captures all of the output of a build.
 pub fn compile(manifest_path: &Path,
                options: &CompileOptions)
                -> CargoResult<ops::Compilation> {
@@ -72,13 +110,119 @@ pub fn compile(manifest_path: &Path,
     compile_pkg(&package, options)
 }
 
+/// Compiles every member of the workspace the package of the manifest at
+/// `manifest_path` belongs to, one after the other, starting with the root.
+///
+/// The features requested are split up among the members: `member/feature`
+/// activates `feature` of the member named `member`, and a feature which isn't
+/// qualified with a member is activated for the one member which has it. It
+/// is an error if no member or several members have it.
+pub fn compile_workspace(manifest_path: &Path, options: &CompileOptions)
+                         -> CargoResult<Vec<ops::Compilation>> {
+    if options.spec.is_some() {
+        return Err(human("`-p` can't be used when compiling a workspace"))
+    }
+    let root = try!(workspace::root_manifest(manifest_path));
+    let members = try!(workspace::packages(&root, options.config));
+    let features = try!(member_features(&members, options.features));
+
+    let mut ret = Vec::new();
+    for (member, features) in members.iter().zip(features.iter()) {
+        for key in member.manifest().warnings().iter() {
+            try!(options.config.shell().warn(key))
+        }
+        ret.push(try!(compile_pkg(member, &CompileOptions {
+            features: features,
+            exec_engine: options.exec_engine.clone(),
+            .. *options
+        })));
+    }
+    Ok(ret)
+}
+
+/// Splits up `features` among the workspace `members`, returning the features
+/// of each member.
+fn member_features(members: &[Package],
+                   features: &[String]) -> CargoResult<Vec<Vec<String>>> {
+    let mut ret = members.iter().map(|_| Vec::new()).collect::<Vec<_>>();
+    let features = features.iter().flat_map(|s| {
+        s.split(' ')
+    }).filter(|s| !s.is_empty());
+    for feature in features {
+        let mut parts = feature.splitn(1, '/');
+        let first = parts.next().unwrap();
+        if let Some(rest) = parts.next() {
+            let i = try!(members.iter().position(|m| m.name() == first)
+                                .chain_error(|| {
+                human(format!("`{}` is not a member of the workspace, but \
+                               features of the form `member/feature` must \
+                               name a member when compiling a workspace",
+                              first))
+            }));
+            ret[i].push(rest.to_string());
+            continue
+        }
+
+        let having = members.iter().enumerate().filter(|&(_, m)| {
+            let summary = m.summary();
+            summary.features().contains_key(feature) ||
+                summary.dependencies().iter().any(|d| {
+                    d.is_optional() && d.name() == feature &&
+                        summary.has_implicit_feature(feature)
+                })
+        }).map(|(i, _)| i).collect::<Vec<_>>();
+        match having.len() {
+            0 => {
+                return Err(human(format!("none of the members of the \
+                                          workspace have the feature `{}`",
+                                         feature)))
+            }
+            1 => ret[having[0]].push(feature.to_string()),
+            _ => {
+                let names = having.iter().map(|&i| {
+                    format!("`{}/{}`", members[i].name(), feature)
+                }).collect::<Vec<_>>();
+                return Err(human(format!("the feature `{}` is ambiguous, as \
+                                          several members of the workspace \
+                                          have it; qualify it with the \
+                                          member to activate it for, like \
+                                          {}", feature, names.connect(" or "))))
+            }
+        }
+    }
+    Ok(ret)
+}
+
 pub fn compile_pkg(package: &Package, options: &CompileOptions)
                    -> CargoResult<ops::Compilation> {
+    compile_pkg_with_overrides(package, options, Vec::new())
+}
+
+/// Compiles `package` with the packages of the sources `overrides` replacing
+/// those of the same name throughout the dependency graph, like the `paths`
+/// configuration. Unlike `paths`, the overrides are in place before anything
+/// is resolved, so the packages they replace don't need to exist elsewhere.
+pub fn compile_pkg_with_overrides(package: &Package, options: &CompileOptions,
+                                  overrides: Vec<SourceId>)
+                                  -> CargoResult<ops::Compilation> {
     let CompileOptions { env, config, jobs, target, spec,
                          dev_deps, features, no_default_features,
-                         lib_only, ref exec_engine } = *options;
-
-    let target = target.map(|s| s.to_string());
+                         lib_only, ref exec_engine, out_dir,
+                         cap_lints, hooks } = *options;
+
+    // A package may declare the platform it's built for, which `--target`
+    // only overrides if the package doesn't force it
+    match (package.manifest().forced_target(), target) {
+        (Some(forced), Some(requested)) if forced != requested => {
+            try!(config.shell().warn(format!("warning: `{}` is always built \
+                                              for `{}`, ignoring `--target \
+                                              {}`", package.name(), forced,
+                                             requested)));
+        }
+        _ => {}
+    }
+    let target = package.manifest().build_target(target)
+                        .map(|s| s.to_string());
     let features = features.iter().flat_map(|s| {
         s.split(' ')
     }).map(|s| s.to_string()).collect::<Vec<String>>();
@@ -96,10 +240,12 @@ pub fn compile_pkg(package: &Package, options: &CompileOptions)
     let (packages, resolve_with_overrides, sources) = {
         let rustc_host = config.rustc_host().to_string();
         let mut registry = PackageRegistry::new(config);
+        try!(registry.add_overrides(overrides));
 
         // First, resolve the package's *listed* dependencies, as well as
         // downloading and updating all remotes and such.
         let resolve = try!(ops::resolve_pkg(&mut registry, package));
+        try!(ops::audit_on_build(&resolve, config));
 
         // Second, resolve with precisely what we're doing. Filter out
         // transitive dependencies if necessary, specify features, handle
@@ -145,9 +291,14 @@ pub fn compile_pkg(package: &Package, options: &CompileOptions)
         return Err(human("There is no lib to build, remove `--lib` flag".to_string()));
     }
 
+    if hooks {
+        try!(run_hook(config, "pre-build", to_build, env));
+    }
+
     let ret = {
         let _p = profile::start("compiling");
-        let lib_overrides = try!(scrape_build_config(config, jobs, target));
+        let lib_overrides = try!(scrape_build_config(config, jobs, target,
+                                                     cap_lints));
 
         try!(ops::compile_targets(&env, &targets, to_build,
                                   &PackageSet::new(&packages),
@@ -155,9 +306,108 @@ pub fn compile_pkg(package: &Package, options: &CompileOptions)
                                   config, lib_overrides, exec_engine.clone()))
     };
 
+    if let Some(out_dir) = out_dir {
+        try!(copy_artifacts(&ret, to_build, &targets, out_dir));
+    }
+
+    if hooks {
+        try!(run_hook(config, "post-build", to_build, env));
+    }
+
     return Ok(ret);
 }
 
+/// Copies the binaries, dynamic libraries and static libraries built for `pkg`
+/// to `out_dir`, without the hashes in their file names. The copies are hard
+/// links where possible, as artifacts are replaced rather than modified by the
+/// next build.
+fn copy_artifacts(compilation: &ops::Compilation, pkg: &Package,
+                  targets: &[&Target], out_dir: &Path) -> CargoResult<()> {
+    let long_out_dir = paths::long_path(out_dir);
+    try!(fs::mkdir_recursive(&long_out_dir, old_io::USER_RWX).chain_error(|| {
+        human(format!("failed to create the output directory `{}`",
+                      out_dir.display()))
+    }));
+    let libs = compilation.libraries.get(pkg.package_id());
+    let libs = libs.map(|libs| &libs[]).unwrap_or(&[]);
+    for src in compilation.binaries.iter().chain(libs.iter()) {
+        let filename = src.filename_str().unwrap();
+        if filename.ends_with(".rlib") { continue }
+        let stem_of = targets.iter().find(|t| {
+            t.is_lib() && filename.contains(&t.file_stem())
+        });
+        let name = match stem_of {
+            Some(t) => filename.replace(&t.file_stem(), t.name()),
+            None => filename.to_string(),
+        };
+        let dst = out_dir.join(name);
+        try!(paths::link_or_copy(src, &dst).chain_error(|| {
+            human(format!("failed to copy `{}` to `{}`", src.display(),
+                          dst.display()))
+        }));
+    }
+    Ok(())
+}
+
+/// Runs the command configured as `hooks.<name>`, or else the one of the
+/// `[hooks]` section of the manifest, if any, in the root of `package`. The
+/// output of the command is printed through the shell, and the
+/// build is aborted if it fails.
+fn run_hook(config: &Config, name: &str, package: &Package, env: &str)
+            -> CargoResult<()> {
+    let key = format!("hooks.{}", name);
+    let args = match try!(config_command(config, &key)) {
+        Some(args) => args,
+        None => match package.manifest().hook(name) {
+            Some(args) => args.to_vec(),
+            None => return Ok(()),
+        },
+    };
+    if args.is_empty() {
+        return Err(human(format!("the {} hook is empty", name)))
+    }
+
+    try!(config.shell().status("Running", format!("{} hook `{}`", name,
+                                                  args.connect(" "))));
+    let cmd = try!(process(&args[0])).args(&args[1..])
+                                     .cwd(package.root())
+                                     .env("CARGO_MANIFEST_DIR",
+                                          Some(package.root()))
+                                     .env("PROFILE", Some(env));
+    let result = cmd.exec_with_output();
+    {
+        // The output is shown whether or not the command succeeded
+        let output = match result {
+            Ok(ref output) => Some(output),
+            Err(ref e) => e.output.as_ref(),
+        };
+        if let Some(output) = output {
+            let mut shell = config.shell();
+            try!(shell.out().write_all(&output.output));
+            try!(shell.err().write_all(&output.error));
+        }
+    }
+    try!(result.chain_error(|| human(format!("the {} hook failed", name))));
+    Ok(())
+}
+
+/// Reads a command from the configuration key `key`, given either as a string
+/// of space-separated arguments or as a list of arguments.
+fn config_command(config: &Config, key: &str)
+                  -> CargoResult<Option<Vec<String>>> {
+    match try!(config.get(key)) {
+        Some(ConfigValue::String(s, _)) => {
+            Ok(Some(s.split(' ').filter(|s| !s.is_empty())
+                     .map(|s| s.to_string()).collect()))
+        }
+        Some(ConfigValue::List(list, _)) => {
+            Ok(Some(list.into_iter().map(|(s, _)| s).collect()))
+        }
+        Some(val) => config.expected("string or list", key, val),
+        None => Ok(None),
+    }
+}
+
 fn source_ids_from_config(config: &Config, cur_path: Path)
                           -> CargoResult<Vec<SourceId>> {
 
@@ -183,9 +433,21 @@ fn source_ids_from_config(config: &Config, cur_path: Path)
     }).map(|p| SourceId::for_path(&p)).collect()
 }
 
+/// Parses a level to cap lints at, where `none` leaves lints alone. Returns
+/// `None` if the level is invalid.
+fn lint_cap(level: &str) -> Option<Option<String>> {
+    match level {
+        "none" => Some(None),
+        "allow" | "warn" | "deny" | "forbid" => Some(Some(level.to_string())),
+        _ => None,
+    }
+}
+
 fn scrape_build_config(config: &Config,
                        jobs: Option<u32>,
-                       target: Option<String>) -> CargoResult<ops::BuildConfig> {
+                       target: Option<String>,
+                       cap_lints: Option<&str>)
+                       -> CargoResult<ops::BuildConfig> {
     let cfg_jobs = match try!(config.get_i64("build.jobs")) {
         Some((n, p)) => {
             match n.to_u32() {
@@ -203,9 +465,64 @@ fn scrape_build_config(config: &Config,
         None => None,
     };
     let jobs = jobs.or(cfg_jobs).unwrap_or(os::num_cpus() as u32);
+    let cap_lints = match cap_lints {
+        Some(level) => match lint_cap(level) {
+            Some(cap) => cap,
+            None => {
+                return Err(human(format!("--cap-lints must be one of allow, \
+                                          warn, deny, forbid or none, but \
+                                          found `{}`", level)))
+            }
+        },
+        None => match try!(config.get_string("build.cap-lints")) {
+            Some((level, path)) => match lint_cap(&level) {
+                Some(cap) => cap,
+                None => {
+                    return Err(human(format!("build.cap-lints must be one of \
+                                              allow, warn, deny, forbid or \
+                                              none, but found `{}` in {}",
+                                             level, path.display())))
+                }
+            },
+            None => Some("allow".to_string()),
+        },
+    };
+    let script_allowlist = match try!(config.get("build.script-allowlist")) {
+        Some(ConfigValue::List(list, path)) => {
+            Some(try!(list.into_iter().map(|(s, _)| {
+                PackageIdSpec::parse(&s).chain_error(|| {
+                    human(format!("invalid package id spec `{}` in \
+                                   `build.script-allowlist` in {}", s,
+                                  path.display()))
+                })
+            }).collect::<CargoResult<Vec<_>>>()))
+        }
+        Some(val) => return config.expected("list", "build.script-allowlist",
+                                            val),
+        None => None,
+    };
+    let script_sandbox = match try!(config.get("build.script-sandbox")) {
+        Some(ConfigValue::Boolean(b, _)) => b,
+        Some(val) => return config.expected("boolean", "build.script-sandbox",
+                                            val),
+        None => false,
+    };
+    if script_sandbox {
+        try!(check_sandbox());
+    }
+    let jobserver = match try!(config.get("build.jobserver")) {
+        Some(ConfigValue::Boolean(b, _)) => b,
+        Some(val) => return config.expected("boolean", "build.jobserver", val),
+        None => false,
+    };
     let mut base = ops::BuildConfig {
         jobs: jobs,
         requested_target: target.clone(),
+        cap_lints: cap_lints,
+        env: try!(scrape_env_config(config)),
+        script_allowlist: script_allowlist,
+        script_sandbox: script_sandbox,
+        jobserver: jobserver,
         ..Default::default()
     };
     base.host = try!(scrape_target_config(config, config.rustc_host()));
@@ -216,15 +533,95 @@ fn scrape_build_config(config: &Config,
     Ok(base)
 }
 
+/// Makes sure build scripts can be sandboxed, which is done with bubblewrap
+/// on Linux and not supported elsewhere.
+fn check_sandbox() -> CargoResult<()> {
+    if !cfg!(target_os = "linux") {
+        return Err(human("`build.script-sandbox` is only supported on Linux"))
+    }
+    try!(try!(process("bwrap")).arg("--version").exec_with_output()
+                                .chain_error(|| {
+        human("`build.script-sandbox` requires bubblewrap, but `bwrap` could \
+               not be run")
+    }));
+    Ok(())
+}
+
+/// Reads the environment variables of the `[env]` table, which are either
+/// strings or tables like `{ value = "..", force = true, relative = true }`.
+///
+/// Variables which are already set in the environment cargo was run in are
+/// left alone unless `force` is set, and `relative` values are paths relative
+/// to the directory containing the `.cargo` directory they were defined in.
+fn scrape_env_config(config: &Config) -> CargoResult<Vec<(String, String)>> {
+    let table = match try!(config.get_table("env")) {
+        Some((table, _)) => table,
+        None => return Ok(Vec::new()),
+    };
+    let mut ret = Vec::new();
+    for (name, value) in table.into_iter() {
+        let key = format!("env.{}", name);
+        let (value, force, relative, path) = match value {
+            ConfigValue::String(s, path) => (s, false, false, path),
+            ConfigValue::Table(mut table, path) => {
+                let value = match table.remove("value") {
+                    Some(ConfigValue::String(s, _)) => s,
+                    Some(val) => return config.expected("string",
+                                                        &format!("{}.value",
+                                                                 key), val),
+                    None => {
+                        return Err(human(format!("`{}` in {} must have a \
+                                                  `value`", key,
+                                                 path.display())))
+                    }
+                };
+                let mut flag = |name: &str| -> CargoResult<bool> {
+                    match table.remove(name) {
+                        Some(ConfigValue::Boolean(b, _)) => Ok(b),
+                        Some(val) => config.expected("boolean",
+                                                     &format!("{}.{}", key,
+                                                              name), val),
+                        None => Ok(false),
+                    }
+                };
+                let force = try!(flag("force"));
+                let relative = try!(flag("relative"));
+                (value, force, relative, path)
+            }
+            val => return config.expected("string or table", &key, val),
+        };
+        if !force && env::var(&name).is_some() { continue }
+        let value = if relative {
+            let dir = path.dir_path().dir_path().join(&value);
+            try!(dir.as_str().map(|s| s.to_string()).chain_error(|| {
+                human(format!("`{}` is not valid unicode", dir.display()))
+            }))
+        } else {
+            value
+        };
+        ret.push((name, value));
+    }
+    // Sorted, as this is part of the fingerprint of every target
+    ret.sort();
+    Ok(ret)
+}
+
 fn scrape_target_config(config: &Config, triple: &str)
                         -> CargoResult<ops::TargetConfig> {
     let key = format!("target.{}", triple);
     let ar = try!(config.get_string(&format!("{}.ar", key)[]));
     let linker = try!(config.get_string(&format!("{}.linker", key)[]));
 
+    let runner_key = format!("{}.runner", key);
+    let runner = try!(config_command(config, &runner_key));
+    if runner.as_ref().map(|r| r.is_empty()) == Some(true) {
+        return Err(human(format!("`{}` must not be empty", runner_key)))
+    }
+
     let mut ret = ops::TargetConfig {
         ar: ar.map(|p| p.0),
         linker: linker.map(|p| p.0),
+        runner: runner,
         overrides: HashMap::new(),
     };
     let table = match try!(config.get_table(&key[])) {
@@ -232,7 +629,9 @@ fn scrape_target_config(config: &Config, triple: &str)
         None => return Ok(ret),
     };
     for (lib_name, _) in table.into_iter() {
-        if lib_name == "ar" || lib_name == "linker" { continue }
+        if lib_name == "ar" || lib_name == "linker" || lib_name == "runner" {
+            continue
+        }
 
         let mut output = BuildOutput {
             library_paths: Vec::new(),
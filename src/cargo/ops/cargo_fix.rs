@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::old_io::fs::{self, PathExtensions};
+use std::old_io::File;
+use std::old_io::process::ProcessOutput;
+use std::sync::{Arc, Mutex};
+
+use git2;
+use rustc_serialize::json;
+
+use core::Package;
+use core::source::Source;
+use ops::{self, CommandPrototype, CommandType, ExecEngine, Layout};
+use ops::ProcessEngine;
+use sources::PathSource;
+use util::{CargoResult, ProcessError, human, ChainError};
+
+pub struct FixOptions<'a, 'b: 'a> {
+    pub compile_opts: ops::CompileOptions<'a, 'b>,
+    /// Fix files even if they contain changes not yet committed into git
+    pub allow_dirty: bool,
+    /// Fix packages which aren't in a git repository at all
+    pub allow_no_vcs: bool,
+}
+
+/// A diagnostic emitted by rustc with `--error-format json`.
+#[derive(RustcDecodable, Clone)]
+struct Diagnostic {
+    spans: Vec<DiagnosticSpan>,
+    children: Vec<Diagnostic>,
+    rendered: Option<String>,
+}
+
+#[derive(RustcDecodable, Clone)]
+struct DiagnosticSpan {
+    file_name: String,
+    byte_start: usize,
+    byte_end: usize,
+    suggested_replacement: Option<String>,
+    suggestion_applicability: Option<String>,
+}
+
+/// Runs processes like the default engine, except that rustc reports the
+/// diagnostics of the package being fixed as JSON, which are collected here
+/// and printed as they would be normally.
+struct FixEngine {
+    root: Path,
+    diagnostics: Arc<Mutex<Vec<Diagnostic>>>,
+}
+
+impl FixEngine {
+    fn is_local(&self, command: &CommandPrototype) -> bool {
+        let is_rustc = match *command.get_type() {
+            CommandType::Rustc => true,
+            _ => false,
+        };
+        is_rustc && command.get_env("CARGO_MANIFEST_DIR").map_or(false, |dir| {
+            dir.as_bytes() == self.root.as_vec()
+        })
+    }
+
+    /// Collects the JSON diagnostics from the output of rustc, replacing them
+    /// with their human readable rendering.
+    fn collect(&self, output: &mut ProcessOutput) {
+        let mut rendered = Vec::new();
+        {
+            let mut diagnostics = self.diagnostics.lock().unwrap();
+            let error = String::from_utf8_lossy(&output.error).into_owned();
+            for line in error.lines() {
+                match json::decode::<Diagnostic>(line) {
+                    Ok(diagnostic) => {
+                        if let Some(ref s) = diagnostic.rendered {
+                            rendered.push_all(s.as_bytes());
+                        }
+                        diagnostics.push(diagnostic);
+                    }
+                    Err(..) => {
+                        rendered.push_all(line.as_bytes());
+                        rendered.push(b'\n');
+                    }
+                }
+            }
+        }
+        output.error = rendered;
+    }
+}
+
+impl ExecEngine for FixEngine {
+    fn exec(&self, command: CommandPrototype) -> Result<(), ProcessError> {
+        ProcessEngine.exec(command)
+    }
+
+    fn exec_with_output(&self, command: CommandPrototype)
+                        -> Result<ProcessOutput, ProcessError> {
+        if !self.is_local(&command) {
+            return ProcessEngine.exec_with_output(command)
+        }
+        let command = command.arg("--error-format").arg("json");
+        match ProcessEngine.exec_with_output(command) {
+            Ok(mut output) => { self.collect(&mut output); Ok(output) }
+            Err(mut e) => {
+                if let Some(ref mut output) = e.output {
+                    self.collect(output);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Compiles the package, applies the fixes rustc suggests as machine
+/// applicable to its sources, and compiles it again to make sure it still
+/// compiles. If it compiled before but doesn't anymore, the original sources
+/// are restored.
+pub fn fix(manifest_path: &Path, opts: &FixOptions) -> CargoResult<()> {
+    let config = opts.compile_opts.config;
+    let mut src = try!(PathSource::for_path(&manifest_path.dir_path(),
+                                            config));
+    try!(src.update());
+    let pkg = try!(src.root_package());
+
+    // Fixes rewrite source files, so make sure they can be recovered
+    let repo = git2::Repository::discover(&pkg.root()).ok().and_then(|repo| {
+        if ops::is_tracked(&pkg, &repo) {Some(repo)} else {None}
+    });
+    match repo {
+        Some(ref repo) if !opts.allow_dirty => {
+            try!(ops::check_not_dirty(&pkg, repo));
+        }
+        Some(..) => {}
+        None if opts.allow_no_vcs => {}
+        None => {
+            return Err(human("no VCS found for this package and `cargo fix` \
+                              can potentially perform destructive changes; \
+                              to proceed despite this, pass the \
+                              `--allow-no-vcs` flag"))
+        }
+    }
+
+    // Units which are fresh aren't compiled, so they wouldn't report their
+    // diagnostics
+    try!(remove_fingerprints(&pkg, &opts.compile_opts));
+
+    let diagnostics = Arc::new(Mutex::new(Vec::new()));
+    let engine = FixEngine {
+        root: pkg.root(),
+        diagnostics: diagnostics.clone(),
+    };
+    // Errors may have fixes too, so a failed compilation is only reported
+    // once there's nothing to fix
+    let compiled = ops::compile(manifest_path, &ops::CompileOptions {
+        exec_engine: Some(Arc::new(Box::new(engine) as Box<ExecEngine>)),
+        .. opts.compile_opts
+    });
+
+    let diagnostics = diagnostics.lock().unwrap().clone();
+    let fixes = suggested_fixes(&diagnostics, config.cwd());
+    if fixes.is_empty() { return compiled.map(|_| ()) }
+
+    let mut originals = Vec::new();
+    for (file, replacements) in fixes.into_iter() {
+        let original = try!(File::open(&file).read_to_end().chain_error(|| {
+            human(format!("failed to read `{}`", file.display()))
+        }));
+        let fixed = apply(&original, &replacements);
+        if fixed == original { continue }
+
+        // Without version control, keep a copy of the original file around
+        if repo.is_none() {
+            try!(File::create(&file.with_extension("rs.orig"))
+                      .write_all(&original));
+        }
+        try!(File::create(&file).write_all(&fixed).chain_error(|| {
+            human(format!("failed to write `{}`", file.display()))
+        }));
+        let shown = file.path_relative_from(&pkg.root())
+                        .unwrap_or(file.clone());
+        try!(config.shell().status("Fixing",
+            format!("{} ({} {})", shown.display(), replacements.len(),
+                    if replacements.len() == 1 {"fix"} else {"fixes"})));
+        originals.push((file, original));
+    }
+
+    if originals.is_empty() { return compiled.map(|_| ()) }
+
+    // Make sure the fixes didn't break the package. A package which didn't
+    // compile in the first place keeps its fixes, as they may still have
+    // fixed some of its errors.
+    let e = match ops::compile(manifest_path, &opts.compile_opts) {
+        Ok(..) => return Ok(()),
+        Err(e) => e,
+    };
+    if compiled.is_err() {
+        return Err(e).chain_error(|| {
+            human("the package still fails to compile after applying fixes")
+        })
+    }
+    for &(ref file, ref original) in originals.iter() {
+        try!(File::create(file).write_all(original));
+    }
+    Err(e).chain_error(|| {
+        human("the package failed to compile after applying fixes, the \
+               original sources were restored")
+    })
+}
+
+/// Collects the replacements rustc suggests as machine applicable, by file.
+fn suggested_fixes(diagnostics: &[Diagnostic], cwd: &Path)
+                   -> Vec<(Path, Vec<(usize, usize, String)>)> {
+    let mut ret = HashMap::new();
+    let mut diagnostics = diagnostics.iter().collect::<Vec<_>>();
+    while let Some(diagnostic) = diagnostics.pop() {
+        diagnostics.extend(diagnostic.children.iter());
+        for span in diagnostic.spans.iter() {
+            let replacement = match span.suggested_replacement {
+                Some(ref s) => s,
+                None => continue,
+            };
+            let applicable = span.suggestion_applicability.as_ref().map(|s| {
+                s.as_slice() == "MachineApplicable"
+            }).unwrap_or(false);
+            if !applicable { continue }
+            let file = cwd.join(&span.file_name);
+            let fixes = match ret.entry(file) {
+                Occupied(e) => e.into_mut(),
+                Vacant(e) => e.insert(Vec::new()),
+            };
+            fixes.push((span.byte_start, span.byte_end, replacement.clone()));
+        }
+    }
+
+    // A unit compiled for several targets reports the same fixes once for
+    // each of them, and fixes overlapping one another can't both be applied
+    let mut ret = ret.into_iter().map(|(file, mut fixes)| {
+        fixes.sort();
+        fixes.dedup();
+        let mut end = 0;
+        let fixes = fixes.into_iter().filter(|&(start, stop, _)| {
+            let keep = start >= end;
+            if keep { end = stop; }
+            keep
+        }).collect::<Vec<_>>();
+        (file, fixes)
+    }).collect::<Vec<_>>();
+    ret.sort_by(|a, b| a.0.as_vec().cmp(b.0.as_vec()));
+    ret
+}
+
+/// Applies the sorted, non-overlapping `replacements` to `contents`.
+fn apply(contents: &[u8], replacements: &[(usize, usize, String)]) -> Vec<u8> {
+    let mut ret = Vec::new();
+    let mut last = 0;
+    for &(start, end, ref replacement) in replacements.iter() {
+        if end > contents.len() { break }
+        ret.push_all(&contents[last..start]);
+        ret.push_all(replacement.as_bytes());
+        last = end;
+    }
+    ret.push_all(&contents[last..]);
+    ret
+}
+
+/// Removes the fingerprints of the targets of `pkg`, so they are compiled
+/// again.
+fn remove_fingerprints(pkg: &Package,
+                       opts: &ops::CompileOptions) -> CargoResult<()> {
+    let triple = pkg.manifest().build_target(opts.target);
+    for target in pkg.targets().iter() {
+        let layout = Layout::new(pkg, triple, target.profile().dest());
+        let dir = layout.fingerprint(pkg);
+        if dir.exists() {
+            try!(fs::rmdir_recursive(&dir).chain_error(|| {
+                human(format!("failed to remove `{}`", dir.display()))
+            }));
+        }
+    }
+    Ok(())
+}
@@ -1,9 +1,10 @@
 use std::collections::HashSet;
 
+use term::color::BLACK;
+
 use core::PackageId;
 use core::registry::PackageRegistry;
 use core::{Source, Resolve};
-use core::resolver::Method;
 use ops;
 use sources::{PathSource};
 use util::config::{Config};
@@ -14,6 +15,8 @@ pub struct UpdateOptions<'a, 'b: 'a> {
     pub to_update: Option<&'a str>,
     pub precise: Option<&'a str>,
     pub aggressive: bool,
+    /// Print why each version was selected or rejected while resolving
+    pub explain: bool,
 }
 
 pub fn generate_lockfile(manifest_path: &Path, config: &Config)
@@ -22,10 +25,14 @@ pub fn generate_lockfile(manifest_path: &Path, config: &Config)
                                                config));
     try!(source.update());
     let package = try!(source.root_package());
+    let previous = try!(ops::load_pkg_lockfile_guide(&package));
     let mut registry = PackageRegistry::new(config);
-    let resolve = try!(ops::resolve_with_previous(&mut registry, &package,
-                                                  Method::Everything,
-                                                  None, None));
+    let (_, resolve) = try!(ops::resolve_for_lockfile(&mut registry,
+                                                      &package, None, None,
+                                                      None));
+    if let Some(ref previous) = previous {
+        try!(ops::print_lockfile_changes(config, previous, &resolve));
+    }
     try!(ops::write_pkg_lockfile(&package, &resolve));
     Ok(())
 }
@@ -37,7 +44,7 @@ pub fn update_lockfile(manifest_path: &Path,
     try!(source.update());
     let package = try!(source.root_package());
 
-    let previous_resolve = match try!(ops::load_pkg_lockfile(&package)) {
+    let previous_resolve = match try!(ops::load_pkg_lockfile_guide(&package)) {
         Some(resolve) => resolve,
         None => return Err(human("A Cargo.lock must exist before it is updated"))
     };
@@ -79,11 +86,20 @@ pub fn update_lockfile(manifest_path: &Path,
         None => to_avoid.extend(previous_resolve.iter()),
     }
 
-    let resolve = try!(ops::resolve_with_previous(&mut registry,
-                                                  &package,
-                                                  Method::Everything,
-                                                  Some(&previous_resolve),
-                                                  Some(&to_avoid)));
+    let mut trace = Vec::new();
+    let resolve = ops::resolve_for_lockfile(&mut registry,
+                                            &package,
+                                            Some(&previous_resolve),
+                                            Some(&to_avoid),
+                                            if opts.explain {Some(&mut trace)}
+                                            else {None});
+    // The trace is most useful when resolution fails, so it's printed first
+    for line in trace.iter() {
+        try!(opts.config.shell().say(line, BLACK));
+    }
+    let (_, resolve) = try!(resolve);
+    try!(ops::print_lockfile_changes(opts.config, &previous_resolve,
+                                     &resolve));
     try!(ops::write_pkg_lockfile(&package, &resolve));
     return Ok(());
 
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::old_io::fs::PathExtensions;
+
+use core::{Dependency, Registry, Source, SourceId};
+use core::dependency::Kind;
+use sources::RegistrySource;
+use util::{CargoResult, ChainError, Config, ToUrl, human};
+
+pub struct InfoOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    /// The index of the registry, instead of the configured one
+    pub index: Option<String>,
+    /// Use the local copy of the index and the downloaded `.crate` files only
+    pub offline: bool,
+}
+
+/// The information about a crate in a registry.
+#[derive(RustcEncodable)]
+pub struct CrateInfo {
+    pub name: String,
+    /// The newest version which isn't yanked
+    pub version: String,
+    /// All versions which aren't yanked, newest first
+    pub versions: Vec<String>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+    pub repository: Option<String>,
+    pub documentation: Option<String>,
+    pub features: BTreeMap<String, Vec<String>>,
+    pub dependencies: Vec<CrateDependency>,
+}
+
+#[derive(RustcEncodable)]
+pub struct CrateDependency {
+    pub name: String,
+    pub req: String,
+    /// `normal`, `dev` or `build`
+    pub kind: String,
+    pub optional: bool,
+}
+
+/// Looks up the crate `name` in the registry.
+///
+/// The versions, features and dependencies are read from the index. The
+/// description, license and links are only in the manifest of the crate, so
+/// the newest version is downloaded to read them, unless working offline
+/// without it having been downloaded before, in which case they're left out.
+pub fn info(name: &str, opts: &InfoOptions) -> CargoResult<CrateInfo> {
+    let config = opts.config;
+    let sid = match opts.index {
+        Some(ref index) => {
+            SourceId::for_registry(&try!(index.to_url().map_err(human)))
+        }
+        None => try!(SourceId::for_central(config)),
+    };
+    let mut src = RegistrySource::new(&sid, config);
+    if !opts.offline {
+        try!(src.update().chain_error(|| {
+            human(format!("failed to update registry {}", sid.url()))
+        }));
+    }
+
+    let dep = try!(Dependency::parse(name, None, &sid));
+    let mut summaries = try!(src.query(&dep));
+    summaries.sort_by(|a, b| b.version().cmp(a.version()));
+    let summary = match summaries.first() {
+        Some(summary) => summary.clone(),
+        None => return Err(human(format!("crate `{}` could not be found in \
+                                          registry `{}`", name, sid.url()))),
+    };
+    let id = summary.package_id();
+
+    let mut ret = CrateInfo {
+        name: id.name().to_string(),
+        version: id.version().to_string(),
+        versions: summaries.iter().map(|s| s.version().to_string()).collect(),
+        description: None,
+        license: None,
+        homepage: None,
+        repository: None,
+        documentation: None,
+        features: summary.features().iter().map(|(k, v)| {
+            (k.clone(), v.clone())
+        }).collect(),
+        dependencies: summary.dependencies().iter().map(|dep| {
+            CrateDependency {
+                name: dep.name().to_string(),
+                req: dep.version_req().to_string(),
+                kind: match dep.kind() {
+                    Kind::Normal => "normal",
+                    Kind::Development => "dev",
+                    Kind::Build => "build",
+                }.to_string(),
+                optional: dep.is_optional(),
+            }
+        }).collect(),
+    };
+
+    if opts.offline && !src.crate_file(id).exists() {
+        return Ok(ret)
+    }
+    try!(src.download(&[id.clone()]));
+    if let Some(pkg) = try!(src.get(&[id.clone()])).into_iter().next() {
+        let metadata = pkg.manifest().metadata();
+        ret.description = metadata.description.clone();
+        ret.license = metadata.license.clone();
+        ret.homepage = metadata.homepage.clone();
+        ret.repository = metadata.repository.clone();
+        ret.documentation = metadata.documentation.clone();
+    }
+    Ok(ret)
+}
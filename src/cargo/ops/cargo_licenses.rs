@@ -0,0 +1,70 @@
+use std::collections::{BTreeMap, HashMap};
+use std::collections::btree_map::Entry::{Occupied, Vacant};
+
+use core::PackageId;
+use core::registry::PackageRegistry;
+use ops;
+use sources::RegistrySource;
+use util::{CargoResult, ChainError, Config, human};
+
+/// Groups the packages in the dependency graph of the package of the manifest
+/// at `manifest_path` by the license expression they declare.
+///
+/// Packages which only point to a license file are grouped under
+/// `license-file`, and packages without any license metadata are left out
+/// with a warning. The licenses of registry packages are read from the index
+/// of their registry, which resolution has already updated, so nothing is
+/// downloaded. Those of other packages are read from their manifests, which
+/// are available locally after resolution.
+pub fn licenses(manifest_path: &Path, config: &Config)
+                -> CargoResult<BTreeMap<String, Vec<PackageId>>> {
+    let mut registry = PackageRegistry::new(config);
+    let (_, resolve) = try!(ops::resolve_manifest(&mut registry,
+                                                  manifest_path, true));
+    let local = resolve.iter().filter(|id| {
+        !id.source_id().is_registry()
+    }).cloned().collect::<Vec<_>>();
+    let packages = try!(registry.get(&local).chain_error(|| {
+        human("Unable to get packages from source")
+    }));
+
+    let mut licenses = Vec::new();
+    for pkg in packages.iter() {
+        let metadata = pkg.manifest().metadata();
+        let license = match (&metadata.license, &metadata.license_file) {
+            (&Some(ref license), _) => Some(license.clone()),
+            (&None, &Some(..)) => Some("license-file".to_string()),
+            (&None, &None) => None,
+        };
+        licenses.push((pkg.package_id().clone(), license));
+    }
+    let mut sources = HashMap::new();
+    for id in resolve.iter().filter(|id| id.source_id().is_registry()) {
+        let source_id = id.source_id();
+        if !sources.contains_key(source_id) {
+            sources.insert(source_id.clone(),
+                           RegistrySource::new(source_id, config));
+        }
+        let source = sources.get_mut(source_id).unwrap();
+        licenses.push((id.clone(), try!(source.license(id))));
+    }
+    licenses.sort();
+
+    let mut ret = BTreeMap::new();
+    for (id, license) in licenses.into_iter() {
+        let license = match license {
+            Some(license) => license,
+            None => {
+                try!(config.shell().warn(format!("warning: package `{}` \
+                                                  has no license metadata",
+                                                 id)));
+                continue
+            }
+        };
+        match ret.entry(license) {
+            Occupied(entry) => entry.into_mut(),
+            Vacant(entry) => entry.insert(Vec::new()),
+        }.push(id);
+    }
+    Ok(ret)
+}
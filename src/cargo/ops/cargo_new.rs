@@ -7,7 +7,7 @@ use rustc_serialize::{Decodable, Decoder};
 use git2::Config as GitConfig;
 
 use util::{GitRepo, HgRepo, CargoResult, human, ChainError, internal};
-use util::Config;
+use util::{workspace, Config};
 
 #[derive(Copy, Debug, PartialEq)]
 pub enum VersionControl { Git, Hg, NoVcs }
@@ -16,6 +16,9 @@ pub struct NewOptions<'a> {
     pub version_control: Option<VersionControl>,
     pub bin: bool,
     pub path: &'a str,
+    /// Name of the package, defaulting to the name of the destination
+    /// directory.
+    pub name: Option<&'a str>,
 }
 
 impl Decodable for VersionControl {
@@ -32,6 +35,44 @@ impl Decodable for VersionControl {
     }
 }
 
+impl VersionControl {
+    /// Creates the directory at `path`, initializing a repository in it.
+    fn init(&self, path: &Path) -> CargoResult<()> {
+        match *self {
+            VersionControl::Git => { try!(GitRepo::init(path)); }
+            VersionControl::Hg => { try!(HgRepo::init(path)); }
+            VersionControl::NoVcs => {
+                try!(fs::mkdir(path, old_io::USER_RWX));
+            }
+        }
+        Ok(())
+    }
+
+    /// The name of the ignore file this VCS reads, if any.
+    fn ignore_file(&self) -> Option<&'static str> {
+        match *self {
+            VersionControl::Git => Some(".gitignore"),
+            VersionControl::Hg => Some(".hgignore"),
+            VersionControl::NoVcs => None,
+        }
+    }
+
+    /// Contents of the ignore file. Libraries don't check in their lock file
+    /// as it's ignored by anything depending on them.
+    fn ignore_contents(&self, bin: bool) -> String {
+        let (target, lock) = match *self {
+            // .hgignore defaults to regular expressions, so anchor them
+            VersionControl::Hg => ("^target/\n", "^Cargo.lock$\n"),
+            _ => ("target\n", "Cargo.lock\n"),
+        };
+        let mut ignore = target.to_string();
+        if !bin {
+            ignore.push_str(lock);
+        }
+        ignore
+    }
+}
+
 struct CargoNewConfig {
     name: Option<String>,
     email: Option<String>,
@@ -44,17 +85,70 @@ pub fn new(opts: NewOptions, config: &Config) -> CargoResult<()> {
         return Err(human(format!("Destination `{}` already exists",
                                  path.display())))
     }
-    let name = path.filename_str().unwrap();
+    let name = match opts.name {
+        Some(name) => name,
+        None => try!(path.filename_str().chain_error(|| {
+            human(format!("cannot create a project with a non-unicode name: \
+                           {}\nuse --name to override the crate name",
+                          path.display()))
+        })),
+    };
+    try!(check_name(name, opts.name.is_some()));
+    let workspace = try!(workspace::find_enclosing(&path));
+    try!(mk(config, &path, name, &opts, workspace.is_some()).chain_error(|| {
+        human(format!("Failed to create project `{}` at `{}`",
+                      name, path.display()))
+    }));
+
+    // Register the new package with the enclosing workspace, if any. Members
+    // share the output directory of the root, so nothing else is needed.
+    if let Some(root_manifest) = workspace {
+        try!(workspace::add_member(&root_manifest, &path).chain_error(|| {
+            human(format!("Failed to add `{}` to the workspace at `{}`",
+                          name, root_manifest.display()))
+        }));
+        try!(config.shell().status("Adding",
+            format!("`{}` as member of workspace at `{}`", name,
+                    root_manifest.dir_path().display())));
+    }
+    Ok(())
+}
+
+fn check_name(name: &str, explicit: bool) -> CargoResult<()> {
+    // If the name came from the directory, point the user at `--name`
+    let help = if explicit {
+        ""
+    } else {
+        "\nuse --name to override the crate name"
+    };
+    if name.is_empty() {
+        return Err(human(format!("Crate names cannot be empty{}", help)))
+    }
+    let blacklist = [
+        "abstract", "alignof", "as", "become", "box", "break", "const",
+        "continue", "crate", "do", "else", "enum", "extern", "false", "final",
+        "fn", "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod",
+        "move", "mut", "offsetof", "override", "priv", "proc", "pub", "pure",
+        "ref", "return", "self", "sizeof", "static", "struct", "super",
+        "trait", "true", "type", "typeof", "unsafe", "unsized", "use",
+        "virtual", "where", "while", "yield",
+    ];
+    if blacklist.contains(&name) {
+        return Err(human(format!("The name `{}` cannot be used as a crate \
+                                  name because it is a Rust keyword{}",
+                                 name, help)))
+    }
+    if name.chars().next().map_or(false, |c| c.is_digit(10)) {
+        return Err(human(format!("Crate names cannot start with a digit: \
+                                  `{}`{}", name, help)))
+    }
     for c in name.chars() {
         if c.is_alphanumeric() { continue }
         if c == '_' || c == '-' { continue }
-        return Err(human(&format!("Invalid character `{}` in crate name: `{}`",
-                                  c, name)));
+        return Err(human(format!("Invalid character `{}` in crate name: `{}`{}",
+                                 c, name, help)));
     }
-    mk(config, &path, name, &opts).chain_error(|| {
-        human(format!("Failed to create project `{}` at `{}`",
-                      name, path.display()))
-    })
+    Ok(())
 }
 
 fn existing_vcs_repo(path: &Path) -> bool {
@@ -62,34 +156,25 @@ fn existing_vcs_repo(path: &Path) -> bool {
 }
 
 fn mk(config: &Config, path: &Path, name: &str,
-      opts: &NewOptions) -> CargoResult<()> {
+      opts: &NewOptions, in_workspace: bool) -> CargoResult<()> {
     let cfg = try!(global_config(config));
-    let mut ignore = "target\n".to_string();
-    let in_existing_vcs_repo = existing_vcs_repo(&path.dir_path());
-    if !opts.bin {
-        ignore.push_str("Cargo.lock\n");
-    }
 
-    let vcs = match (opts.version_control, cfg.version_control, in_existing_vcs_repo) {
-        (None, None, false) => VersionControl::Git,
-        (None, Some(option), false) => option,
-        (Some(option), _, false) => option,
-        (_, _, true) => VersionControl::NoVcs,
+    // An explicit request always wins, otherwise we don't want to create a
+    // nested repository if we're already inside of one or of a workspace.
+    let vcs = match (opts.version_control, cfg.version_control) {
+        (Some(option), _) => option,
+        (None, _) if in_workspace || existing_vcs_repo(&path.dir_path()) => {
+            VersionControl::NoVcs
+        }
+        (None, Some(option)) => option,
+        (None, None) => VersionControl::Git,
     };
 
-    match vcs {
-        VersionControl::Git => {
-            try!(GitRepo::init(path));
-            try!(File::create(&path.join(".gitignore")).write_all(ignore.as_bytes()));
-        },
-        VersionControl::Hg => {
-            try!(HgRepo::init(path));
-            try!(File::create(&path.join(".hgignore")).write_all(ignore.as_bytes()));
-        },
-        VersionControl::NoVcs => {
-            try!(fs::mkdir(path, old_io::USER_RWX));
-        },
-    };
+    try!(vcs.init(path));
+    if let Some(file) = vcs.ignore_file() {
+        let ignore = vcs.ignore_contents(opts.bin);
+        try!(File::create(&path.join(file)).write_str(&ignore));
+    }
 
     let (author_name, email) = try!(discover_author());
     // Hoo boy, sure glad we've got exhaustivenes checking behind us.
@@ -122,6 +207,7 @@ fn main() {
         try!(File::create(&path.join("src/lib.rs")).write_str("\
 #[test]
 fn it_works() {
+    assert_eq!(2 + 2, 4);
 }
 "));
     }
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use core::{Dependency, Package, Registry, Source, Summary};
+use core::dependency::Kind;
+use core::registry::PackageRegistry;
+use ops;
+use sources::{PathSource, RegistrySource};
+use util::{CargoResult, ChainError, Config, internal};
+use util::workspace;
+
+pub struct OutdatedOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    /// Report the dependencies of every member of the workspace rather than
+    /// only those of the package itself
+    pub workspace: bool,
+}
+
+/// A dependency for which the registry has a newer version than the locked
+/// one.
+#[derive(RustcEncodable)]
+pub struct OutdatedDependency {
+    /// The name of the package declaring the dependency
+    pub package: String,
+    pub name: String,
+    /// `normal`, `dev` or `build`
+    pub kind: String,
+    pub locked: String,
+    /// The newest version matching the version requirement of the dependency
+    pub compatible: String,
+    pub latest: String,
+}
+
+/// Compares the locked versions of the registry dependencies of a package
+/// against the index of their registry. Like a build, this resolves the
+/// dependencies and writes the lockfile if there is none.
+pub fn outdated(manifest_path: &Path, opts: &OutdatedOptions)
+                -> CargoResult<Vec<OutdatedDependency>> {
+    let config = opts.config;
+    let packages = if opts.workspace {
+        let root = try!(workspace::root_manifest(manifest_path));
+        try!(workspace::packages(&root, config))
+    } else {
+        let mut source = try!(PathSource::for_path(&manifest_path.dir_path(),
+                                                   config));
+        try!(source.update());
+        vec![try!(source.root_package())]
+    };
+
+    // The members of a workspace are resolved together into the lockfile of
+    // the workspace, which has the dependencies of each of them
+    let mut registry = PackageRegistry::new(config);
+    try!(ops::resolve_pkg(&mut registry, &packages[0]));
+    let resolve = try!(ops::load_pkg_lockfile(&packages[0]));
+    let resolve = try!(resolve.chain_error(|| {
+        internal("the lockfile wasn't written")
+    }));
+
+    let mut sources = HashMap::new();
+    let mut ret = Vec::new();
+    for pkg in packages.iter() {
+        let locked_deps = resolve.deps(pkg.package_id()).map(|deps| {
+            deps.cloned().collect::<Vec<_>>()
+        }).unwrap_or(Vec::new());
+
+        for dep in dependencies(pkg).into_iter() {
+            let locked = match locked_deps.iter().find(|id| {
+                id.name() == dep.name() && id.source_id() == dep.source_id()
+            }) {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let source_id = dep.source_id();
+            if !sources.contains_key(source_id) {
+                let mut source = RegistrySource::new(source_id, config);
+                try!(source.update());
+                sources.insert(source_id.clone(), source);
+            }
+            let source = sources.get_mut(source_id).unwrap();
+
+            // Prereleases are only interesting to those already using one
+            let any = try!(Dependency::parse(dep.name(), None, source_id));
+            let prerelease = !locked.version().pre.is_empty();
+            let candidates = try!(source.query(&any)).into_iter().filter(|s| {
+                prerelease || s.version().pre.is_empty()
+            }).collect();
+            let latest = match newest(candidates) {
+                Some(latest) => latest,
+                None => continue,
+            };
+            if latest.version() <= locked.version() { continue }
+            let compatible = newest(try!(source.query(dep)))
+                                 .map(|s| s.version().clone())
+                                 .unwrap_or(locked.version().clone());
+
+            ret.push(OutdatedDependency {
+                package: pkg.name().to_string(),
+                name: dep.name().to_string(),
+                kind: match dep.kind() {
+                    Kind::Normal => "normal",
+                    Kind::Development => "dev",
+                    Kind::Build => "build",
+                }.to_string(),
+                locked: locked.version().to_string(),
+                compatible: compatible.to_string(),
+                latest: latest.version().to_string(),
+            });
+        }
+    }
+    Ok(ret)
+}
+
+/// Returns the registry dependencies of `pkg`, listing a dependency declared
+/// with the same name and kind several times, like for different platforms,
+/// only once.
+fn dependencies(pkg: &Package) -> Vec<&Dependency> {
+    let mut ret: Vec<&Dependency> = Vec::new();
+    for dep in pkg.dependencies().iter() {
+        if !dep.source_id().is_registry() { continue }
+        if ret.iter().any(|d| {
+            d.name() == dep.name() && d.kind() == dep.kind()
+        }) {
+            continue
+        }
+        ret.push(dep);
+    }
+    ret
+}
+
+fn newest(summaries: Vec<Summary>) -> Option<Summary> {
+    let mut ret: Option<Summary> = None;
+    for summary in summaries.into_iter() {
+        let newer = match ret {
+            Some(ref s) => summary.version() > s.version(),
+            None => true,
+        };
+        if newer { ret = Some(summary) }
+    }
+    ret
+}
@@ -1,19 +1,39 @@
-use std::old_io::{fs, File, USER_DIR};
+use std::old_io::{fs, File, USER_DIR, USER_EXECUTE};
 use std::old_io::fs::PathExtensions;
-use std::old_path;
 
+use git2;
+use rustc_serialize::json;
 use tar::Archive;
+use toml;
 use flate2::{GzBuilder, BestCompression};
 use flate2::reader::GzDecoder;
 
 use core::source::{Source, SourceId};
 use core::Package;
 use sources::PathSource;
-use util::{CargoResult, human, internal, ChainError, Config};
+use util::{CargoResult, human, internal, ChainError, Config, paths};
+use util::workspace;
+use util::toml as cargo_toml;
 use ops;
 
+/// Name of the generated file recording where a package was packaged from.
+const VCS_INFO_FILE: &'static str = ".cargo_vcs_info.json";
+
 struct Bomb { path: Option<Path> }
 
+#[derive(RustcEncodable)]
+struct VcsInfo {
+    git: GitVcsInfo,
+    /// Path of the package root relative to the root of the repository
+    path_in_vcs: String,
+}
+
+#[derive(RustcEncodable)]
+struct GitVcsInfo {
+    sha1: String,
+    dirty: bool,
+}
+
 impl Drop for Bomb {
     fn drop(&mut self) {
         match self.path.as_ref() {
@@ -23,44 +43,113 @@ impl Drop for Bomb {
     }
 }
 
+pub struct PackageOpts<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    /// Build the unpacked tarball to make sure it compiles
+    pub verify: bool,
+    /// Only print the files which would be archived
+    pub list: bool,
+    /// Warn about missing metadata
+    pub check_metadata: bool,
+    /// Package even if the working directory has uncommitted changes
+    pub allow_dirty: bool,
+}
+
 pub fn package(manifest_path: &Path,
-               config: &Config,
-               verify: bool,
-               list: bool,
-               metadata: bool) -> CargoResult<Option<Path>> {
+               opts: &PackageOpts) -> CargoResult<Option<Path>> {
+    package_with_overrides(manifest_path, opts, &[])
+}
+
+/// Packages all members of the workspace containing `manifest_path`, except
+/// for those with `publish = false`, returning the paths of the tarballs.
+///
+/// Each member is packaged after the members it depends on, and verified
+/// against their freshly packaged sources rather than their versions in the
+/// registry, which don't need to be published yet.
+pub fn package_workspace(manifest_path: &Path,
+                         opts: &PackageOpts) -> CargoResult<Vec<Path>> {
+    let config = opts.config;
+    if opts.list {
+        return Err(human("`--list` can't be used when packaging a workspace"))
+    }
+    let root = try!(workspace::root_manifest(manifest_path));
+    let packages = try!(workspace::packages(&root, config));
+
+    let mut overrides = Vec::new();
+    let mut ret = Vec::new();
+    for pkg in workspace::dependency_order(&packages).into_iter() {
+        if !pkg.manifest().publish() {
+            try!(config.shell().status("Skipping",
+                format!("{} (publish = false)", pkg.package_id())));
+            continue
+        }
+        let tarball = try!(package_with_overrides(pkg.manifest_path(), opts,
+                                                  &overrides[])
+                               .chain_error(|| {
+            human(format!("failed to package workspace member `{}`",
+                          pkg.name()))
+        }));
+        ret.extend(tarball.into_iter());
+        let unpacked = unpack_dir(pkg);
+        let manifest = paths::long_path(&unpacked.join("Cargo.toml"));
+        if opts.verify && manifest.exists() {
+            overrides.push(try!(SourceId::for_path(&unpacked)));
+        }
+    }
+    Ok(ret)
+}
+
+/// Packages the package at `manifest_path`, verifying it with the packages of
+/// the sources `overrides` in place of those of the same name.
+fn package_with_overrides(manifest_path: &Path, opts: &PackageOpts,
+                          overrides: &[SourceId]) -> CargoResult<Option<Path>> {
+    let config = opts.config;
     let mut src = try!(PathSource::for_path(&manifest_path.dir_path(),
                                             config));
     try!(src.update());
     let pkg = try!(src.root_package());
 
-    if metadata {
+    if opts.check_metadata {
         try!(check_metadata(&pkg, config));
     }
 
-    if list {
-        let root = pkg.manifest_path().dir_path();
-        let mut list: Vec<_> = try!(src.list_files(&pkg)).iter().map(|file| {
-            file.path_relative_from(&root).unwrap()
-        }).collect();
-        list.sort();
-        for file in list.iter() {
+    let filename = format!("package/{}-{}.crate", pkg.name(), pkg.version());
+    let dst = pkg.absolute_target_dir().join(filename);
+
+    let repo = git2::Repository::discover(&pkg.root()).ok();
+    let vcs_info = match repo {
+        Some(ref repo) => try!(vcs_info(&pkg, repo)),
+        None => None,
+    };
+
+    if opts.list {
+        if vcs_info.is_some() {
+            println!("{}", VCS_INFO_FILE);
+        }
+        for file in try!(package_files(&pkg, &src, &dst)).iter() {
             println!("{}", file.display());
         }
         return Ok(None)
     }
 
-    let filename = format!("package/{}-{}.crate", pkg.name(), pkg.version());
-    let dst = pkg.absolute_target_dir().join(filename);
+    if !opts.allow_dirty && vcs_info.is_some() {
+        try!(check_not_dirty(&pkg, repo.as_ref().unwrap()));
+    }
+
     if dst.exists() { return Ok(Some(dst)) }
 
     let mut bomb = Bomb { path: Some(dst.clone()) };
 
+    let manifest = try!(normalize_manifest(&pkg));
+
     try!(config.shell().status("Packaging", pkg.package_id().to_string()));
-    try!(tar(&pkg, &src, config, &dst).chain_error(|| {
+    let vcs_info = vcs_info.map(|info| json::encode(&info).unwrap());
+    try!(tar(&pkg, &src, config, &dst, manifest.as_ref().map(|s| s.as_slice()),
+             vcs_info.as_ref().map(|s| s.as_slice())).chain_error(|| {
         human("failed to prepare local package for uploading")
     }));
-    if verify {
-        try!(run_verify(config, &pkg, &dst).chain_error(|| {
+    if opts.verify {
+        try!(run_verify(config, &pkg, &dst, overrides).chain_error(|| {
             human("failed to verify package tarball")
         }))
     }
@@ -102,8 +191,230 @@ fn check_metadata(pkg: &Package, config: &Config) -> CargoResult<()> {
     Ok(())
 }
 
-fn tar(pkg: &Package, src: &PathSource, config: &Config,
-       dst: &Path) -> CargoResult<()> {
+/// Returns the manifest of `pkg` rewritten to the form it takes once the
+/// package is in a registry, or `None` if it can be archived as is.
+///
+/// Dependencies can't be located by path or git repository anymore, so those
+/// keys are removed and the version requirement is used instead. The
+/// `[workspace]` section is dropped as the members aren't part of the package,
+/// and fields inherited from it are replaced by their values.
+fn normalize_manifest(pkg: &Package) -> CargoResult<Option<String>> {
+    let manifest = pkg.manifest_path();
+    let contents = try!(File::open(manifest).read_to_string());
+    let mut table = try!(cargo_toml::parse(&contents, manifest));
+    let mut changed = table.remove("workspace").is_some();
+    changed = normalize_inherited(&mut table, pkg) || changed;
+    changed = try!(normalize_dependencies(&mut table)) || changed;
+    if let Some(&mut toml::Value::Table(ref mut targets)) =
+            table.get_mut("target") {
+        for (_, platform) in targets.iter_mut() {
+            if let toml::Value::Table(ref mut platform) = *platform {
+                changed = try!(normalize_dependencies(platform)) || changed;
+            }
+        }
+    }
+    if changed {
+        Ok(Some(toml::Value::Table(table).to_string()))
+    } else {
+        Ok(None)
+    }
+}
+
+fn normalize_inherited(table: &mut toml::Table, pkg: &Package) -> bool {
+    let md = pkg.manifest().metadata();
+    let mut changed = false;
+    for key in ["package", "project"].iter() {
+        let project = match table.get_mut(*key) {
+            Some(&mut toml::Value::Table(ref mut project)) => project,
+            _ => continue,
+        };
+        for field in ["version", "authors", "license", "edition"].iter() {
+            // Inherited fields are the only ones which are tables
+            match project.get(*field) {
+                Some(&toml::Value::Table(..)) => {}
+                _ => continue,
+            }
+            let value = match *field {
+                "version" => toml::Value::String(pkg.version().to_string()),
+                "authors" => {
+                    toml::Value::Array(md.authors.iter().map(|a| {
+                        toml::Value::String(a.clone())
+                    }).collect())
+                }
+                "license" => match md.license {
+                    Some(ref license) => toml::Value::String(license.clone()),
+                    None => continue,
+                },
+                _ => match md.edition {
+                    Some(ref edition) => toml::Value::String(edition.clone()),
+                    None => continue,
+                },
+            };
+            project.insert(field.to_string(), value);
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn normalize_dependencies(table: &mut toml::Table) -> CargoResult<bool> {
+    let mut changed = false;
+    for kind in ["dependencies", "dev-dependencies",
+                 "build-dependencies"].iter() {
+        let deps = match table.get_mut(*kind) {
+            Some(&mut toml::Value::Table(ref mut deps)) => deps,
+            _ => continue,
+        };
+        let mut unversioned = Vec::new();
+        for (name, dep) in deps.iter_mut() {
+            let dep = match *dep {
+                toml::Value::Table(ref mut dep) => dep,
+                _ => continue,
+            };
+            let mut local = false;
+            for key in ["path", "git", "branch", "tag", "rev"].iter() {
+                local = dep.remove(*key).is_some() || local;
+            }
+            if !local { continue }
+            changed = true;
+            if !dep.contains_key("version") {
+                unversioned.push(name.clone());
+            }
+        }
+        for name in unversioned.into_iter() {
+            // Development dependencies aren't needed to build the package,
+            // so they are simply left out
+            if *kind != "dev-dependencies" {
+                return Err(human(format!("all path and git dependencies must \
+                                          have a version specified when \
+                                          packaging.\ndependency `{}` does \
+                                          not specify a version", name)))
+            }
+            deps.remove(&name);
+        }
+    }
+    Ok(changed)
+}
+
+/// Returns the sorted list of files, relative to the package root, which end
+/// up in the tarball for `pkg` once include/exclude rules and VCS ignores have
+/// been applied.
+fn package_files(pkg: &Package, src: &PathSource,
+                 dst: &Path) -> CargoResult<Vec<Path>> {
+    let root = pkg.manifest_path().dir_path();
+    let mut ret = Vec::new();
+    for file in try!(src.list_files(pkg)).iter() {
+        if file == dst { continue }
+        let relative = try!(file.path_relative_from(&root).chain_error(|| {
+            internal(format!("file `{}` is not inside of package root `{}`",
+                             file.display(), root.display()))
+        }));
+        ret.push(relative);
+    }
+    ret.sort();
+    Ok(ret)
+}
+
+/// Returns whether the manifest of `pkg` is tracked by `repo`. A repository
+/// further up the tree which ignores or doesn't know about the package
+/// doesn't count.
+pub fn is_tracked(pkg: &Package, repo: &git2::Repository) -> bool {
+    let workdir = match repo.workdir() {
+        Some(dir) => dir,
+        None => return false,
+    };
+    let manifest = match pkg.manifest_path().path_relative_from(&workdir) {
+        Some(path) => path,
+        None => return false,
+    };
+    match repo.status_file(&manifest) {
+        Ok(s) => !s.intersects(git2::STATUS_IGNORED | git2::STATUS_WT_NEW),
+        Err(..) => false,
+    }
+}
+
+/// Returns the revision information of the repository `pkg` lives in, or
+/// `None` if the package isn't tracked by a repository with commits.
+fn vcs_info(pkg: &Package, repo: &git2::Repository)
+            -> CargoResult<Option<VcsInfo>> {
+    let workdir = match repo.workdir() {
+        Some(dir) => dir,
+        None => return Ok(None),
+    };
+    if !is_tracked(pkg, repo) { return Ok(None) }
+    let head = match repo.revparse_single("HEAD") {
+        Ok(head) => head,
+        Err(..) => return Ok(None),
+    };
+    let path_in_vcs = pkg.root().path_relative_from(&workdir)
+                         .unwrap_or(Path::new("."));
+    let path_in_vcs = if path_in_vcs == Path::new(".") {
+        String::new()
+    } else {
+        path_in_vcs.display().to_string().replace("\\", "/")
+    };
+    let dirty = try!(dirty_files(pkg, repo)).len() > 0;
+    Ok(Some(VcsInfo {
+        git: GitVcsInfo { sha1: head.id().to_string(), dirty: dirty },
+        path_in_vcs: path_in_vcs,
+    }))
+}
+
+/// Fails if files of `pkg` contain changes not yet committed into `repo`,
+/// suggesting the `--allow-dirty` flag.
+pub fn check_not_dirty(pkg: &Package,
+                       repo: &git2::Repository) -> CargoResult<()> {
+    let dirty = try!(dirty_files(pkg, repo));
+    if dirty.is_empty() { return Ok(()) }
+
+    let root = pkg.root();
+    let files = dirty.iter().map(|p| {
+        p.path_relative_from(&root).unwrap_or(p.clone()).display().to_string()
+    }).collect::<Vec<_>>();
+    Err(human(format!("{} {} in the working directory contain changes that \
+                       were not yet committed into git:\n\n{}\n\n\
+                       to proceed despite this, pass the `--allow-dirty` flag",
+                      files.len(),
+                      if files.len() == 1 {"file"} else {"files"},
+                      files.connect("\n"))))
+}
+
+/// Returns all files inside of `pkg` which are modified or untracked
+/// according to `repo`. Ignored files and build output, which never end up in
+/// a package, are not included.
+fn dirty_files(pkg: &Package, repo: &git2::Repository)
+               -> CargoResult<Vec<Path>> {
+    let workdir = match repo.workdir() {
+        Some(dir) => dir,
+        None => return Ok(Vec::new()),
+    };
+    let root = pkg.root();
+    let target = pkg.absolute_target_dir();
+    let lockfile = root.join("Cargo.lock");
+    let mut opts = git2::StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true)
+        .include_ignored(false);
+    let statuses = try!(repo.statuses(Some(&mut opts)));
+    let mut ret = Vec::new();
+    for entry in statuses.iter() {
+        let status = entry.status();
+        if status == git2::STATUS_CURRENT ||
+           status.contains(git2::STATUS_IGNORED) {
+            continue
+        }
+        let path = workdir.join(entry.path_bytes());
+        if !root.is_ancestor_of(&path) || target.is_ancestor_of(&path) ||
+           path == lockfile {
+            continue
+        }
+        ret.push(path);
+    }
+    ret.sort();
+    Ok(ret)
+}
+
+fn tar(pkg: &Package, src: &PathSource, config: &Config, dst: &Path,
+       manifest: Option<&str>, vcs_info: Option<&str>) -> CargoResult<()> {
 
     if dst.exists() {
         return Err(human(format!("destination already exists: {}",
@@ -114,64 +425,143 @@ fn tar(pkg: &Package, src: &PathSource, config: &Config,
 
     let tmpfile = try!(File::create(dst));
 
-    // Prepare the encoder and its header
-    let encoder = GzBuilder::new().filename(dst.filename().unwrap())
-                                  .writer(tmpfile, BestCompression);
+    // Prepare the encoder and its header. The gzip header's mtime is left
+    // at zero so the compressed output is reproducible as well.
+    let mut encoder = GzBuilder::new().filename(dst.filename().unwrap())
+                                      .writer(tmpfile, BestCompression);
 
-    // Put all package files into a compressed archive
-    let ar = Archive::new(encoder);
+    // Put all package files into a compressed archive, starting with the
+    // generated revision information
+    if let Some(info) = vcs_info {
+        let name = format!("{}-{}/{}", pkg.name(), pkg.version(),
+                           VCS_INFO_FILE);
+        try!(append_file(&mut encoder, &name, info.as_bytes(), false));
+    }
     let root = pkg.manifest_path().dir_path();
-    for file in try!(src.list_files(pkg)).iter() {
-        if file == dst { continue }
-        let relative = file.path_relative_from(&root).unwrap();
+    for relative in try!(package_files(pkg, src, dst)).iter() {
         let relative = try!(relative.as_str().chain_error(|| {
             human(format!("non-utf8 path in source directory: {}",
                           relative.display()))
         }));
-        let mut file = try!(File::open(file));
+        let path = root.join(relative);
+        let mut file = try!(File::open(&path));
         try!(config.shell().verbose(|shell| {
             shell.status("Archiving", &relative)
         }));
-        let path = format!("{}-{}{}{}", pkg.name(), pkg.version(),
-                           old_path::SEP, relative);
-        try!(ar.append(&path, &mut file).chain_error(|| {
+        let contents = match manifest {
+            Some(manifest) if relative == "Cargo.toml" => {
+                manifest.as_bytes().to_vec()
+            }
+            _ => try!(file.read_to_end()),
+        };
+        let stat = try!(file.stat());
+        let executable = stat.perm.intersects(USER_EXECUTE);
+        // Archives always use `/` as a separator, regardless of the platform
+        let name = format!("{}-{}/{}", pkg.name(), pkg.version(),
+                           relative.replace("\\", "/"));
+        let res = append_file(&mut encoder, &name, &contents, executable);
+        try!(res.chain_error(|| {
             internal(format!("could not archive source file `{}`", relative))
         }));
     }
-    try!(ar.finish());
+    // The end of an archive is marked by two empty blocks
+    try!(encoder.write_all(&[0; 1024]));
+    try!(encoder.finish());
     Ok(())
 }
 
-fn run_verify(config: &Config, pkg: &Package, tar: &Path)
-              -> CargoResult<()> {
+/// Appends a regular file to the tar stream `dst`.
+///
+/// The header only depends on the path, the size and whether the file is
+/// executable. Ownership and timestamps are normalized so that packaging the
+/// same sources always produces a byte-for-byte identical tarball.
+///
+/// Paths longer than the 100 bytes of the name field are split at a `/` into
+/// the name and the 155 byte prefix field of the ustar format.
+fn append_file<W: Writer>(dst: &mut W, path: &str, contents: &[u8],
+                          executable: bool) -> CargoResult<()> {
+    let (prefix, name) = try!(split_path(path).chain_error(|| {
+        human(format!("path `{}` is too long to be archived, as it can't be \
+                       split into a directory of at most 155 bytes and a \
+                       file name of at most 100 bytes", path))
+    }));
+    let mut header = [0u8; 512];
+    copy(&mut header[0..100], name.as_bytes());
+    octal(&mut header[100..108], if executable {0o755} else {0o644});
+    octal(&mut header[108..116], 0); // uid
+    octal(&mut header[116..124], 0); // gid
+    octal(&mut header[124..136], contents.len() as u64);
+    octal(&mut header[136..148], 0); // mtime
+    header[156] = b'0'; // regular file
+    copy(&mut header[257..263], b"ustar\0");
+    copy(&mut header[263..265], b"00");
+    copy(&mut header[345..500], prefix.as_bytes());
+
+    // The checksum is calculated with the checksum field filled with spaces
+    for b in header[148..156].iter_mut() { *b = b' '; }
+    let cksum = header.iter().fold(0, |sum, &b| sum + b as u64);
+    octal(&mut header[148..155], cksum);
+
+    try!(dst.write_all(&header));
+    try!(dst.write_all(contents));
+    let padding = (512 - contents.len() % 512) % 512;
+    try!(dst.write_all(&[0; 512][..padding]));
+    return Ok(());
+
+    fn copy(dst: &mut [u8], src: &[u8]) {
+        for (d, s) in dst.iter_mut().zip(src.iter()) {
+            *d = *s;
+        }
+    }
+
+    // Writes `val` as a zero-padded, NUL-terminated octal number
+    fn octal(dst: &mut [u8], val: u64) {
+        let len = dst.len() - 1;
+        let s = format!("{:01$o}", val, len);
+        copy(&mut dst[..len], s.as_bytes());
+        dst[len] = 0;
+    }
+
+    // Splits `path` into the prefix and name fields of a ustar header, using
+    // the longest prefix which fits so the name is as short as possible
+    fn split_path(path: &str) -> Option<(&str, &str)> {
+        if path.len() <= 100 { return Some(("", path)) }
+        path.char_indices().filter(|&(i, c)| {
+            c == '/' && i <= 155 && path.len() - i - 1 <= 100 &&
+                i + 1 < path.len()
+        }).map(|(i, _)| (&path[..i], &path[i + 1..])).last()
+    }
+}
+
+/// Returns the directory the tarball of `pkg` is unpacked to for verifying.
+fn unpack_dir(pkg: &Package) -> Path {
+    pkg.root().join(format!("target/package/{}-{}", pkg.name(),
+                            pkg.version()))
+}
+
+fn run_verify(config: &Config, pkg: &Package, tar: &Path,
+              overrides: &[SourceId]) -> CargoResult<()> {
     try!(config.shell().status("Verifying", pkg));
 
     let f = try!(GzDecoder::new(try!(File::open(tar))));
-    let dst = pkg.root().join(format!("target/package/{}-{}",
-                                      pkg.name(), pkg.version()));
-    if dst.exists() {
-        try!(fs::rmdir_recursive(&dst));
+    let dst = unpack_dir(pkg);
+    if paths::long_path(&dst).exists() {
+        try!(fs::rmdir_recursive(&paths::long_path(&dst)));
     }
     let mut archive = Archive::new(f);
-    try!(archive.unpack(&dst.dir_path()));
+    try!(archive.unpack(&paths::long_path(&dst.dir_path())));
+    try!(share_unpacked(pkg, &dst).chain_error(|| {
+        human(format!("failed to clone the sources of `{}`", pkg))
+    }));
     let manifest_path = dst.join("Cargo.toml");
 
-    // When packages are uploaded to the registry, all path dependencies are
-    // implicitly converted to registry-based dependencies, so we rewrite those
-    // dependencies here.
-    let registry = try!(SourceId::for_central(config));
-    let new_summary = pkg.summary().clone().map_dependencies(|d| {
-        if !d.source_id().is_path() { return d }
-        d.set_source_id(registry.clone())
-    });
-    let mut new_manifest = pkg.manifest().clone();
-    new_manifest.set_summary(new_summary);
-    new_manifest.set_target_dir(dst.join("target"));
-    let new_pkg = Package::new(new_manifest, &manifest_path,
-                               pkg.package_id().source_id());
-
-    // Now that we've rewritten all our path dependencies, compile it!
-    try!(ops::compile_pkg(&new_pkg, &ops::CompileOptions {
+    // The archived manifest has had its path dependencies converted to
+    // registry dependencies, so build exactly what would be uploaded.
+    let source_id = try!(SourceId::for_path(&dst));
+    let (new_pkg, _) = try!(ops::read_package(&manifest_path, &source_id,
+                                              config));
+
+    try!(ops::compile_pkg_with_overrides(&new_pkg, &ops::CompileOptions {
         env: "compile",
         config: config,
         jobs: None,
@@ -182,7 +572,34 @@ fn run_verify(config: &Config, pkg: &Package, tar: &Path)
         spec: None,
         lib_only: false,
         exec_engine: None,
-    }));
+        out_dir: None,
+        cap_lints: None,
+        hooks: false,
+    }, overrides.to_vec()));
 
     Ok(())
 }
+
+/// Replaces the files unpacked to `dst` which are unchanged from the sources
+/// of `pkg` with copy-on-write clones of those sources, so the verification
+/// build doesn't take up space for a second copy of the package. Nothing is
+/// done on filesystems without support for such clones.
+fn share_unpacked(pkg: &Package, dst: &Path) -> CargoResult<()> {
+    for path in try!(fs::walk_dir(dst)) {
+        if !path.is_file() { continue }
+        let src = pkg.root().join(path.path_relative_from(dst).unwrap());
+        if !src.is_file() { continue }
+        let unchanged = try!(File::open(&src).read_to_end()) ==
+                        try!(File::open(&path).read_to_end());
+        if !unchanged { continue }
+
+        let tmp = path.with_filename(format!("{}.cargo-clone",
+                                             path.filename_str().unwrap()));
+        if !paths::reflink(&src, &tmp) {
+            return Ok(())
+        }
+        try!(fs::chmod(&tmp, try!(fs::stat(&path)).perm));
+        try!(fs::rename(&tmp, &path));
+    }
+    Ok(())
+}
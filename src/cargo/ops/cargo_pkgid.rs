@@ -1,7 +1,10 @@
 use ops;
-use core::{Source, PackageIdSpec};
+use core::{Source, Package, PackageId, PackageIdSpec};
+use core::registry::PackageRegistry;
+use core::resolver::Method;
 use sources::{PathSource};
-use util::{CargoResult, human, Config};
+use util::{CargoResult, ChainError, human, Config};
+use util::workspace;
 
 pub fn pkgid(manifest_path: &Path,
              spec: Option<&str>,
@@ -11,16 +14,49 @@ pub fn pkgid(manifest_path: &Path,
     try!(source.update());
     let package = try!(source.root_package());
 
-    let lockfile = package.root().join("Cargo.lock");
-    let source_id = package.package_id().source_id();
-    let resolve = match try!(ops::load_lockfile(&lockfile, source_id)) {
-        Some(resolve) => resolve,
-        None => return Err(human("A Cargo.lock must exist for this command"))
+    let spec = match spec {
+        Some(spec) => spec,
+        None => return Ok(PackageIdSpec::from_package_id(package.package_id())),
     };
 
-    let pkgid = match spec {
-        Some(spec) => try!(resolve.query(spec)),
-        None => package.package_id(),
+    // Members of the workspace are not necessarily dependencies of the
+    // package, so they are looked up on their own.
+    if let Some(id) = try!(workspace_member(&package, spec, config)) {
+        return Ok(PackageIdSpec::from_package_id(&id))
+    }
+
+    // Without a lockfile the dependencies are resolved on the fly, but the
+    // result is not saved as a new lockfile.
+    let resolve = match try!(ops::load_pkg_lockfile(&package)) {
+        Some(resolve) => resolve,
+        None => {
+            let mut registry = PackageRegistry::new(config);
+            try!(ops::resolve_with_previous(&mut registry, &package,
+                                            Method::Everything, None, None))
+        }
     };
+
+    let pkgid = try!(resolve.query(spec));
     Ok(PackageIdSpec::from_package_id(pkgid))
 }
+
+/// Returns the ID of the single member of the workspace of `package` matching
+/// `spec`, if there is one.
+fn workspace_member(package: &Package, spec: &str, config: &Config)
+                    -> CargoResult<Option<PackageId>> {
+    let root = match try!(workspace::find_root(&package.root())) {
+        Some(root) => root,
+        None => return Ok(None),
+    };
+    let spec = try!(PackageIdSpec::parse(spec).chain_error(|| {
+        human(format!("invalid package id specification: `{}`", spec))
+    }));
+    let members = try!(workspace::packages(&root.join("Cargo.toml"), config));
+    let mut matching = members.iter().filter(|p| {
+        spec.matches(p.package_id())
+    });
+    match (matching.next(), matching.next()) {
+        (Some(member), None) => Ok(Some(member.package_id().clone())),
+        _ => Ok(None),
+    }
+}
@@ -3,8 +3,11 @@ use std::error::FromError;
 use std::old_io::fs::PathExtensions;
 use std::old_io::{self, File, fs};
 
+use git2;
+
 use core::{Package,Manifest,SourceId};
-use util::{self, CargoResult, human, Config, ChainError};
+use sources::path::is_ignored;
+use util::{self, CargoResult, human, coded_human, Config, ChainError};
 use util::important_paths::find_project_manifest_exact;
 use util::toml::{Layout, project_layout};
 
@@ -13,8 +16,9 @@ pub fn read_manifest(contents: &[u8], layout: Layout, source_id: &SourceId,
                      -> CargoResult<(Manifest, Vec<Path>)> {
     let root = layout.root.clone();
     util::toml::to_manifest(contents, source_id, layout, config).chain_error(|| {
-        human(format!("failed to parse manifest at `{}`",
-                      root.join("Cargo.toml").display()))
+        coded_human("manifest", format!("failed to parse manifest at `{}`",
+                                        root.join("Cargo.toml").display()),
+                    Vec::new())
     })
 }
 
@@ -38,11 +42,17 @@ pub fn read_packages(path: &Path, source_id: &SourceId, config: &Config)
 
     trace!("looking for root package: {}, source_id={}", path.display(), source_id);
 
+    // The ignore rules of the repository the source is in apply, unless the
+    // source itself is ignored
+    let repo = git2::Repository::discover(path).ok().and_then(|repo| {
+        if is_ignored(Some(&repo), path) {None} else {Some(repo)}
+    });
+
     try!(walk(path, |dir| {
         trace!("looking for child package: {}", dir.display());
 
-        // Don't recurse into git databases
-        if dir.filename_str() == Some(".git") { return Ok(false); }
+        // Don't recurse into git databases, or directories which are ignored
+        if dir != path && is_ignored(repo.as_ref(), dir) { return Ok(false); }
 
         // Don't automatically discover packages across git submodules
         if dir != path && dir.join(".git").exists() { return Ok(false); }
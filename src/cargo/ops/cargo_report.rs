@@ -0,0 +1,47 @@
+use term::color::BLACK;
+
+use core::source::Source;
+use ops;
+use sources::PathSource;
+use util::{CargoResult, human, Config};
+
+/// Prints the future incompatibility warnings of the last build of the
+/// package at `manifest_path`, only for the package named `package` if given.
+pub fn future_incompat_report(manifest_path: &Path, package: Option<&str>,
+                              config: &Config) -> CargoResult<()> {
+    let mut src = try!(PathSource::for_path(&manifest_path.dir_path(),
+                                            config));
+    try!(src.update());
+    let pkg = try!(src.root_package());
+    let target_dir = pkg.absolute_target_dir();
+    let report = match try!(ops::load_future_incompat(&target_dir)) {
+        Some(report) => report,
+        None => return Err(human("no future-incompat report was found, the \
+                                  report is written by builds like `cargo \
+                                  build`")),
+    };
+    let packages = report.packages.iter().filter(|p| {
+        package.map_or(true, |name| {
+            p.package.starts_with(&format!("{} ", name))
+        })
+    }).collect::<Vec<_>>();
+
+    let mut shell = config.shell();
+    if packages.is_empty() {
+        try!(shell.say("The last build found no code which future versions \
+                        of Rust will reject", BLACK));
+        return Ok(())
+    }
+    for package in packages.iter() {
+        try!(shell.say(format!("The package `{}` currently triggers the \
+                                following future incompatibility lints:",
+                               package.package), BLACK));
+        for warning in package.warnings.iter() {
+            for line in warning.lines() {
+                try!(shell.say(format!("> {}", line), BLACK));
+            }
+        }
+        try!(shell.say("", BLACK));
+    }
+    Ok(())
+}
@@ -46,20 +46,22 @@ pub fn run(manifest_path: &Path,
 
     let compile = try!(ops::compile(manifest_path, options));
     let dst = manifest_path.dir_path().join("target");
-    let dst = match options.target {
+    let dst = match root.manifest().build_target(options.target) {
         Some(target) => dst.join(target),
         None => dst,
     };
+    let name = format!("{}{}", bin.name(), compile.exe_suffix);
     let exe = match (bin.profile().dest(), bin.is_example()) {
-        (Some(s), true) => dst.join(s).join("examples").join(bin.name()),
-        (Some(s), false) => dst.join(s).join(bin.name()),
-        (None, true) => dst.join("examples").join(bin.name()),
-        (None, false) => dst.join(bin.name()),
+        (Some(s), true) => dst.join(s).join("examples").join(name),
+        (Some(s), false) => dst.join(s).join(name),
+        (None, true) => dst.join("examples").join(name),
+        (None, false) => dst.join(name),
     };
     let exe = match exe.path_relative_from(config.cwd()) {
         Some(path) => path,
         None => exe,
     };
+    try!(compile.check_runnable(&exe));
     let process = try!(try!(compile.target_process(exe, &root))
                               .into_process_builder())
                               .args(args)
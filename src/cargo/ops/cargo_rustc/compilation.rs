@@ -5,7 +5,7 @@ use std::old_path::BytesContainer;
 use semver::Version;
 
 use core::{PackageId, Package};
-use util::{self, CargoResult};
+use util::{self, CargoResult, human};
 
 use super::{CommandType, CommandPrototype};
 
@@ -40,6 +40,20 @@ pub struct Compilation {
     /// be passed to future invocations of programs.
     pub extra_env: HashMap<String, Option<String>>,
 
+    /// Environment variables from the `[env]` configuration table, which are
+    /// passed to the compiler, build scripts, and the programs built.
+    pub config_env: Vec<(String, String)>,
+
+    /// Command which binaries are run through, like an emulator, when they
+    /// were built for another platform than the host.
+    pub runner: Option<Vec<String>>,
+
+    /// The target triple the binaries were built for, if it isn't the host.
+    pub target: Option<String>,
+
+    /// Suffix of the file names of the binaries built, like `.exe` or `.wasm`.
+    pub exe_suffix: String,
+
     /// Top-level package that was compiled
     pub package: Package,
 }
@@ -54,6 +68,10 @@ impl Compilation {
             tests: Vec::new(),
             binaries: Vec::new(),
             extra_env: HashMap::new(),
+            config_env: Vec::new(),
+            runner: None,
+            target: None,
+            exe_suffix: String::new(),
             package: pkg.clone(),
         }
     }
@@ -69,10 +87,38 @@ impl Compilation {
     }
 
     /// See `process`.
+    ///
+    /// If a runner is configured for the target the program is run through
+    /// it, with the path of the program as the first argument.
     pub fn target_process<T: BytesContainer>(&self, cmd: T, pkg: &Package)
                                      -> CargoResult<CommandPrototype> {
-        let cmd = CString::from_slice(cmd.container_as_bytes());
-        self.process(CommandType::Target(cmd), pkg)
+        match self.runner {
+            Some(ref runner) => {
+                let program = CString::from_slice(runner[0].as_bytes());
+                Ok(try!(self.process(CommandType::Target(program), pkg))
+                       .args(&runner[1..]).arg(cmd))
+            }
+            None => {
+                let cmd = CString::from_slice(cmd.container_as_bytes());
+                self.process(CommandType::Target(cmd), pkg)
+            }
+        }
+    }
+
+    /// Makes sure the binary `exe` built for the target can be run. Binaries
+    /// built for WebAssembly can't be run natively, only through a runner like
+    /// a JavaScript shell.
+    pub fn check_runnable(&self, exe: &Path) -> CargoResult<()> {
+        let target = match self.target {
+            Some(ref target) => target,
+            None => return Ok(()),
+        };
+        if self.runner.is_some() || !super::is_wasm(target) { return Ok(()) }
+        Err(human(format!("`{}` was built for `{}` and can't be run \
+                           natively; configure a program to run it with, \
+                           like a JavaScript or WebAssembly shell, as \
+                           `target.{}.runner`", exe.display(), target,
+                          target)))
     }
 
     /// See `process`.
@@ -99,6 +145,9 @@ impl Compilation {
                                                 DynamicLibrary::envvar()));
         let mut cmd = try!(CommandPrototype::new(cmd)).env(
             DynamicLibrary::envvar(), Some(&search_path));
+        for &(ref k, ref v) in self.config_env.iter() {
+            cmd = cmd.env(k, Some(v));
+        }
         for (k, v) in self.extra_env.iter() {
             cmd = cmd.env(k, v.as_ref());
         }
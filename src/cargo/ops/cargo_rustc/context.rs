@@ -1,17 +1,23 @@
 use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::collections::BTreeMap;
 use std::collections::hash_map::HashMap;
 use std::str;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Sender, Receiver};
 
 use regex::Regex;
 
 use core::{SourceMap, Package, PackageId, PackageSet, Resolve, Target, Profile};
+use core::PackageIdSpec;
 use util::{self, CargoResult, ChainError, internal, Config, profile};
+use util::workspace;
 use util::human;
 
 use super::TargetConfig;
 use super::custom_build::BuildState;
 use super::fingerprint::Fingerprint;
+use super::job_queue::{Message, Warnings};
+use super::future_incompat::FutureIncompat;
 use super::layout::{Layout, LayoutProxy};
 use super::{Kind, Compilation, BuildConfig};
 use super::{ProcessEngine, ExecEngine};
@@ -31,6 +37,10 @@ pub struct Context<'a, 'b: 'a> {
     pub build_state: Arc<BuildState>,
     pub exec_engine: Arc<Box<ExecEngine>>,
     pub fingerprints: HashMap<(&'a PackageId, &'a Target, Kind), Fingerprint>,
+    pub warnings: Arc<Warnings>,
+    /// Where jobs send their output, to be printed by the job queue
+    pub output: Sender<Message>,
+    pub future_incompat: Arc<FutureIncompat>,
 
     env: &'a str,
     host: Layout,
@@ -43,6 +53,8 @@ pub struct Context<'a, 'b: 'a> {
     target_exe: String,
     requirements: HashMap<(&'a PackageId, &'a str), Platform>,
     build_config: BuildConfig,
+    workspace: Option<Vec<Path>>,
+    messages: Option<Receiver<Message>>,
 }
 
 impl<'a, 'b: 'a> Context<'a, 'b> {
@@ -64,6 +76,15 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
             try!(Context::filename_parts(None))
         };
         let target_triple = target.unwrap_or(config.rustc_host()).to_string();
+        let workspace = try!(workspace_dirs(root_pkg));
+        let mut compilation = Compilation::new(root_pkg);
+        compilation.config_env = build_config.env.clone();
+        compilation.exe_suffix = target_exe.clone();
+        if target_triple != config.rustc_host() {
+            compilation.runner = build_config.target.runner.clone();
+            compilation.target = Some(target_triple.clone());
+        }
+        let (tx, rx) = channel();
         Ok(Context {
             target_triple: target_triple,
             env: env,
@@ -78,14 +99,24 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
             host_dylib: host_dylib,
             host_exe: host_exe,
             requirements: HashMap::new(),
-            compilation: Compilation::new(root_pkg),
+            compilation: compilation,
             build_state: Arc::new(BuildState::new(build_config.clone(), deps)),
             build_config: build_config,
             exec_engine: Arc::new(Box::new(ProcessEngine) as Box<ExecEngine>),
             fingerprints: HashMap::new(),
+            warnings: Arc::new(Mutex::new(Vec::new())),
+            output: tx,
+            future_incompat: Arc::new(Mutex::new(BTreeMap::new())),
+            workspace: workspace,
+            messages: Some(rx),
         })
     }
 
+    /// Returns the receiving end of `output`, for the job queue.
+    pub fn take_messages(&mut self) -> Receiver<Message> {
+        self.messages.take().unwrap()
+    }
+
     /// Run `rustc` to discover the dylib prefix/suffix for the target
     /// specified as well as the exe suffix
     fn filename_parts(target: Option<&str>)
@@ -183,6 +214,9 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
         match pkg.targets().iter().find(|t| t.profile().is_custom_build()) {
             Some(custom_build) => {
                 self.build_requirements(pkg, custom_build, Platform::Plugin);
+                for &(dep, bin) in self.artifact_targets(pkg).iter() {
+                    self.build_requirements(dep, bin, Platform::Plugin);
+                }
             }
             None => {}
         }
@@ -252,12 +286,15 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
         let mut ret = Vec::new();
         if target.is_example() || target.is_bin() ||
            target.profile().is_test() {
-            ret.push(format!("{}{}", stem,
-                             if target.profile().is_for_host() {
-                                 &self.host_exe
-                             } else {
-                                 &self.target_exe
-                             }));
+            let for_host = target.profile().is_for_host();
+            let exe = if for_host {&self.host_exe} else {&self.target_exe};
+            ret.push(format!("{}{}", stem, exe));
+            // Emscripten writes the WebAssembly module next to the JavaScript
+            // loading it, with the dashes in its name replaced
+            if !for_host && super::is_wasm(&self.target_triple) &&
+               *exe == ".js" {
+                ret.push(format!("{}.wasm", stem.replace("-", "_")));
+            }
         } else {
             if target.is_dylib() {
                 let plugin = target.profile().is_for_host();
@@ -308,6 +345,29 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
         }).collect()
     }
 
+    /// For a package, return the binaries of its build dependencies which are
+    /// requested as artifacts. These are built for the host before the build
+    /// script of the package runs.
+    pub fn artifact_targets(&self, pkg: &Package)
+                            -> Vec<(&'a Package, &'a Target)> {
+        let deps = match self.resolve.deps(pkg.package_id()) {
+            None => return vec!(),
+            Some(deps) => deps,
+        };
+        let mut ret = Vec::new();
+        for dep in deps.map(|id| self.get_package(id)) {
+            let wanted = pkg.dependencies().iter().any(|d| {
+                d.name() == dep.name() && d.is_build() &&
+                    d.artifact() == Some("bin")
+            });
+            if !wanted { continue }
+            ret.extend(dep.targets().iter().filter(|t| {
+                self.is_relevant_artifact(*t)
+            }).map(|t| (dep, t)));
+        }
+        ret
+    }
+
     /// Gets a package for the given package id.
     pub fn get_package(&self, id: &PackageId) -> &'a Package {
         self.package_set.iter()
@@ -335,6 +395,16 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
         }
     }
 
+    /// Like `is_relevant_target`, but for the binaries of a package which are
+    /// requested as artifacts.
+    pub fn is_relevant_artifact(&self, target: &Target) -> bool {
+        target.is_bin() && match self.env {
+            "doc" | "test" | "doc-all" => target.profile().is_compile(),
+            _ => target.profile().env() == self.env &&
+                 !target.profile().is_test(),
+        }
+    }
+
     /// Get the user-specified linker for a particular host or target
     pub fn linker(&self, kind: Kind) -> Option<&str> {
         self.target_config(kind).linker.as_ref().map(|s| s.as_slice())
@@ -356,6 +426,40 @@ impl<'a, 'b: 'a> Context<'a, 'b> {
     /// Number of jobs specified for this build
     pub fn jobs(&self) -> u32 { self.build_config.jobs }
 
+    /// Level the lints of packages which aren't local are capped at
+    pub fn cap_lints(&self) -> Option<&str> {
+        self.build_config.cap_lints.as_ref().map(|s| &s[])
+    }
+
+    /// Whether the lints of `pkg` are capped at `allow`. Such a package is
+    /// still compiled with its lints capped at `warn`, so that the warnings
+    /// about code future versions of rustc will reject can be collected, but
+    /// its warnings aren't shown.
+    pub fn hides_lints(&self, pkg: &Package) -> bool {
+        self.cap_lints() == Some("allow") && !self.is_local(pkg)
+    }
+
+    /// Packages outside of the workspace allowed to run code on the host,
+    /// through a build script or as a compiler plugin, if they are restricted
+    pub fn script_allowlist(&self) -> Option<&[PackageIdSpec]> {
+        self.build_config.script_allowlist.as_ref().map(|l| &l[])
+    }
+
+    /// Whether build scripts are run in a sandbox
+    pub fn script_sandbox(&self) -> bool { self.build_config.script_sandbox }
+
+    /// Returns whether `pkg` is developed locally, as opposed to being a
+    /// third-party dependency. These are the members of the workspace, or
+    /// all path dependencies if the root package isn't part of a workspace.
+    pub fn is_local(&self, pkg: &Package) -> bool {
+        if pkg.package_id() == self.resolve.root() { return true }
+        if !pkg.package_id().source_id().is_path() { return false }
+        match self.workspace {
+            Some(ref dirs) => dirs.contains(&pkg.root()),
+            None => true,
+        }
+    }
+
     /// Requested (not actual) target for the build
     pub fn requested_target(&self) -> Option<&str> {
         self.build_config.requested_target.as_ref().map(|s| &s[])
@@ -405,3 +509,16 @@ impl Platform {
         }
     }
 }
+
+/// Returns the directories of the packages in the workspace `pkg` is a member
+/// of, or `None` if it doesn't belong to a workspace.
+fn workspace_dirs(pkg: &Package) -> CargoResult<Option<Vec<Path>>> {
+    let root = match try!(workspace::find_root(&pkg.root())) {
+        Some(root) => root,
+        None => return Ok(None),
+    };
+    let mut dirs = try!(workspace::members(&root.join("Cargo.toml")))
+                       .unwrap_or(Vec::new());
+    dirs.push(root);
+    Ok(Some(dirs))
+}
@@ -5,13 +5,14 @@ use std::old_io::{fs, USER_RWX, File};
 use std::str;
 use std::sync::Mutex;
 
-use core::{Package, Target, PackageId, PackageSet};
+use core::{Package, Target, PackageId, PackageSet, Verbosity};
 use util::{CargoResult, human, Human};
 use util::{internal, ChainError};
 
 use super::job::Work;
+use super::job_queue::Message;
 use super::{fingerprint, process, Kind, Context, Platform};
-use super::CommandType;
+use super::{CommandType, CommandPrototype};
 use util::Freshness;
 
 /// Contains the parsed output of a custom build script.
@@ -52,8 +53,14 @@ pub fn prepare(pkg: &Package, target: &Target, req: Platform,
     // Start preparing the process to execute, starting out with some
     // environment variables.
     let profile = target.profile();
-    let to_exec = CString::from_slice(to_exec.as_vec());
-    let p = try!(super::process(CommandType::Host(to_exec), pkg, target, cx));
+    let p = if cx.script_sandbox() {
+        let bwrap = CString::from_slice(b"bwrap");
+        let p = try!(super::process(CommandType::Host(bwrap), pkg, target, cx));
+        sandboxed(p, &build_output).arg(&to_exec)
+    } else {
+        let to_exec = CString::from_slice(to_exec.as_vec());
+        try!(super::process(CommandType::Host(to_exec), pkg, target, cx))
+    };
     let mut p = p.env("OUT_DIR", Some(&build_output))
                  .env("CARGO_MANIFEST_DIR", Some(pkg.manifest_path()
                                                     .dir_path()
@@ -80,6 +87,16 @@ pub fn prepare(pkg: &Package, target: &Target, req: Platform,
         None => {}
     }
 
+    // Binaries requested as artifacts of build dependencies have been built
+    // for the host by the time this script runs, so pass along their paths.
+    for &(dep, bin) in cx.artifact_targets(pkg).iter() {
+        let file = try!(cx.target_filenames(bin))[0].clone();
+        let path = cx.out_dir(dep, Kind::Host, bin).join(file);
+        p = p.env(&format!("CARGO_BIN_FILE_{}_{}", super::envify(dep.name()),
+                           super::envify(bin.name())),
+                  Some(path.display().to_string()));
+    }
+
     // Gather the set of native dependencies that this package has along with
     // some other variables to close over.
     //
@@ -106,6 +123,9 @@ pub fn prepare(pkg: &Package, target: &Target, req: Platform,
     try!(fs::mkdir_recursive(&cx.layout(pkg, Kind::Host).build(pkg), USER_RWX));
 
     let exec_engine = cx.exec_engine.clone();
+    let messages = cx.output.clone();
+    let print_output = cx.config.shell().get_verbosity() ==
+                           Verbosity::VeryVerbose;
 
     // Prepare the unit of "dirty work" which will actually run the custom build
     // command.
@@ -150,6 +170,18 @@ pub fn prepare(pkg: &Package, target: &Target, req: Platform,
             Human(e)
         }));
 
+        // With `-vv` everything the build command printed is shown, prefixed
+        // by the package it belongs to.
+        if print_output {
+            let stdout = String::from_utf8_lossy(&output.output);
+            let stderr = String::from_utf8_lossy(&output.error);
+            let mut lines = String::new();
+            for line in stdout.lines().chain(stderr.lines()) {
+                lines.push_str(&format!("[{}] {}\n", pkg_name, line));
+            }
+            messages.send(Message::Stdout(lines.into_bytes())).ok();
+        }
+
         // After the build command has finished running, we need to be sure to
         // remember all of its output so we can later discover precisely what it
         // was, even if we don't run the build command again (due to freshness).
@@ -203,6 +235,21 @@ pub fn prepare(pkg: &Package, target: &Target, req: Platform,
     Ok((dirty, fresh, freshness))
 }
 
+/// Adds the arguments to `bwrap` which run a build script in a sandbox: the
+/// whole file system is mounted read-only except for `out_dir`, which is also
+/// the directory for temporary files, and the script has no network access.
+/// The path of the script is to be added after these.
+fn sandboxed(bwrap: CommandPrototype, out_dir: &Path) -> CommandPrototype {
+    bwrap.env("TMPDIR", Some(out_dir))
+         .arg("--ro-bind").arg("/").arg("/")
+         .arg("--dev").arg("/dev")
+         .arg("--proc").arg("/proc")
+         .arg("--bind").arg(out_dir).arg(out_dir)
+         .arg("--unshare-net")
+         .arg("--die-with-parent")
+         .arg("--")
+}
+
 impl BuildState {
     pub fn new(config: super::BuildConfig,
                packages: &PackageSet) -> BuildState {
@@ -11,6 +11,27 @@ use util::{self, CargoResult, ProcessError, ProcessBuilder};
 pub trait ExecEngine: Send + Sync {
     fn exec(&self, CommandPrototype) -> Result<(), ProcessError>;
     fn exec_with_output(&self, CommandPrototype) -> Result<ProcessOutput, ProcessError>;
+
+    /// Like `exec_with_output`, but also calls `on_stdout` and `on_stderr`
+    /// with the output of the command as it's printed. Engines which can't
+    /// stream the output call them once, when the command has finished.
+    fn exec_with_streaming(&self, command: CommandPrototype,
+                           on_stdout: &mut FnMut(&[u8]),
+                           on_stderr: &mut FnMut(&[u8]))
+                           -> Result<ProcessOutput, ProcessError> {
+        let result = self.exec_with_output(command);
+        {
+            let output = match result {
+                Ok(ref output) => Some(output),
+                Err(ref e) => e.output.as_ref(),
+            };
+            if let Some(output) = output {
+                on_stdout(&output.output);
+                on_stderr(&output.error);
+            }
+        }
+        result
+    }
 }
 
 /// Default implementation of `ExecEngine`.
@@ -26,6 +47,14 @@ impl ExecEngine for ProcessEngine {
                         -> Result<ProcessOutput, ProcessError> {
         command.into_process_builder().unwrap().exec_with_output()
     }
+
+    fn exec_with_streaming(&self, command: CommandPrototype,
+                           on_stdout: &mut FnMut(&[u8]),
+                           on_stderr: &mut FnMut(&[u8]))
+                           -> Result<ProcessOutput, ProcessError> {
+        command.into_process_builder().unwrap()
+               .exec_with_streaming(on_stdout, on_stderr)
+    }
 }
 
 /// Prototype for a command that must be executed.
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::old_io::{self, fs, File, BufferedReader};
 use std::old_io::fs::PathExtensions;
@@ -50,16 +51,20 @@ pub fn prepare_target<'a, 'b>(cx: &mut Context<'a, 'b>,
     info!("fingerprint at: {}", loc.display());
 
     let fingerprint = try!(calculate(cx, pkg, target, kind));
-    let is_fresh = try!(is_fresh(&loc, &fingerprint));
+    let mut stale = try!(stale_reason(&loc, &fingerprint));
 
     let root = cx.out_dir(pkg, kind, target);
     let mut missing_outputs = false;
     if !target.profile().is_doc() {
-        for filename in try!(cx.target_filenames(target)).iter() {
+        let filenames = try!(cx.target_filenames(target));
+        for (i, filename) in filenames.iter().enumerate() {
             let dst = root.join(filename);
             missing_outputs |= !dst.exists();
 
+            // Only the first file of a test is what runs the tests, the others
+            // are loaded by it
             if target.profile().is_test() {
+                if i > 0 { continue }
                 cx.compilation.tests.push((target.name().to_string(), dst));
             } else if target.is_bin() {
                 cx.compilation.binaries.push(dst);
@@ -73,7 +78,11 @@ pub fn prepare_target<'a, 'b>(cx: &mut Context<'a, 'b>,
         }
     }
 
-    Ok(prepare(is_fresh && !missing_outputs, loc, fingerprint))
+    if stale.is_none() && missing_outputs {
+        stale = Some("some of its output files are missing");
+    }
+    try!(report(cx, &format!("{} ({})", pkg, target.name()), stale));
+    Ok(prepare(stale.is_none(), loc, fingerprint))
 }
 
 /// A fingerprint can be considered to be a "short string" representing the
@@ -108,6 +117,8 @@ pub struct Fingerprint {
 enum LocalFingerprint {
     Precalculated(String),
     MtimeBased(Option<u64>, Path),
+    /// The newest modification time of the files, if already known
+    FilesBased(Option<u64>, Vec<Path>),
 }
 
 impl Fingerprint {
@@ -123,6 +134,11 @@ impl Fingerprint {
                 debug!("resolving: {}", p.display());
                 try!(fs::stat(p)).modified.to_string()
             }
+            LocalFingerprint::FilesBased(Some(n), _) => n.to_string(),
+            LocalFingerprint::FilesBased(None, ref files) => {
+                util::paths::newest_mtime(files.clone(), None).unwrap_or(0)
+                                                              .to_string()
+            }
         };
         debug!("inputs: {} {} {:?}", known, self.extra, deps);
         Ok(util::short_hash(&(known, &self.extra, &deps)))
@@ -162,7 +178,8 @@ fn calculate<'a, 'b>(cx: &mut Context<'a, 'b>,
         v
     });
     let extra = util::short_hash(&(cx.config.rustc_version(), target, &features,
-                                   cx.profile(target)));
+                                   cx.profile(target),
+                                   &cx.compilation.config_env));
 
     // Next, recursively calculate the fingerprint for all of our dependencies.
     let deps = try!(cx.dep_targets(pkg, target).into_iter().map(|(p, t)| {
@@ -225,6 +242,10 @@ fn use_dep_info(pkg: &Package, target: &Target) -> bool {
 ///
 /// The currently implemented solution is option (1), although it is planned to
 /// migrate to option (2) in the near future.
+///
+/// As path packages may be huge, their listing of files is cached, and the
+/// search for the newest file stops as soon as a file newer than the newest
+/// one the last time around is found, see `files_fingerprint`.
 pub fn prepare_build_cmd(cx: &mut Context, pkg: &Package, kind: Kind,
                          target: Option<&Target>) -> CargoResult<Preparation> {
     if target.is_none() {
@@ -238,14 +259,31 @@ pub fn prepare_build_cmd(cx: &mut Context, pkg: &Package, kind: Kind,
 
     info!("fingerprint at: {}", loc.display());
 
-    let new_fingerprint = try!(calculate_build_cmd_fingerprint(cx, pkg));
+    let files = {
+        let source = cx.sources.get(pkg.package_id().source_id())
+                               .expect("BUG: Missing package source");
+        try!(source.cached_files(pkg, &new.join("build-files")))
+    };
+    let (local, modified) = match files {
+        Some(files) => try!(files_fingerprint(files, &new.join("build-mtime"))),
+        None => {
+            let s = try!(calculate_build_cmd_fingerprint(cx, pkg));
+            (LocalFingerprint::Precalculated(s), false)
+        }
+    };
     let new_fingerprint = Fingerprint {
-        extra: String::new(),
+        extra: util::short_hash(&cx.compilation.config_env),
         deps: Vec::new(),
-        local: LocalFingerprint::Precalculated(new_fingerprint),
+        local: local,
     };
 
-    let is_fresh = try!(is_fresh(&loc, &new_fingerprint));
+    let stale = if modified {
+        Some("some of its files have changed")
+    } else {
+        try!(stale_reason(&loc, &new_fingerprint))
+    };
+    try!(report(cx, &format!("{} (build script)", pkg), stale));
+    let is_fresh = stale.is_none();
 
     // The new custom build command infrastructure handles its own output
     // directory as part of freshness.
@@ -288,7 +326,7 @@ fn prepare(is_fresh: bool, loc: Path, fingerprint: Fingerprint) -> Preparation {
         let fingerprint = try!(fingerprint.resolve(true).chain_error(|| {
             internal("failed to resolve a pending fingerprint")
         }));
-        try!(File::create(&loc).write_str(&fingerprint));
+        try!(File::create(&util::long_path(&loc)).write_str(&fingerprint));
         Ok(())
     });
 
@@ -306,25 +344,79 @@ pub fn dep_info_loc(cx: &Context, pkg: &Package, target: &Target,
     dir(cx, pkg, kind).join(format!("dep-{}", filename(target)))
 }
 
-fn is_fresh(loc: &Path, new_fingerprint: &Fingerprint) -> CargoResult<bool> {
-    let mut file = match File::open(loc) {
+/// Returns the location the compiler output of a target is cached at.
+pub fn diagnostics_loc(cx: &Context, pkg: &Package, target: &Target,
+                       kind: Kind) -> Path {
+    dir(cx, pkg, kind).join(format!("output-{}", filename(target)))
+}
+
+/// Returns why the fingerprint at `loc` is out of date, or `None` if it is
+/// fresh.
+fn stale_reason(loc: &Path, new_fingerprint: &Fingerprint)
+                -> CargoResult<Option<&'static str>> {
+    let mut file = match File::open(&util::long_path(loc)) {
         Ok(file) => file,
-        Err(..) => return Ok(false),
+        Err(..) => return Ok(Some("it has not been built before")),
     };
 
     let old_fingerprint = try!(file.read_to_string());
     let new_fingerprint = match new_fingerprint.resolve(false) {
         Ok(s) => s,
-        Err(..) => return Ok(false),
+        Err(..) => return Ok(Some("some of its inputs have changed")),
     };
 
     trace!("old fingerprint: {}", old_fingerprint);
     trace!("new fingerprint: {}", new_fingerprint);
 
-    Ok(old_fingerprint == new_fingerprint)
+    if old_fingerprint == new_fingerprint {
+        Ok(None)
+    } else {
+        Ok(Some("its fingerprint has changed"))
+    }
+}
+
+/// Prints the freshness decision for `unit` when running with `-vv`.
+fn report(cx: &Context, unit: &str,
+          stale: Option<&'static str>) -> CargoResult<()> {
+    try!(cx.config.shell().very_verbose(|s| {
+        match stale {
+            Some(reason) => {
+                s.status("Dirty", format!("{}: {}", unit, reason))
+            }
+            None => s.status("Fresh", format!("{}: fingerprint is up to date",
+                                              unit)),
+        }
+    }));
+    Ok(())
 }
 
 fn calculate_target_mtime(dep_info: &Path) -> CargoResult<Option<u64>> {
+    let files = match try!(parse_dep_info(dep_info)) {
+        Some(files) => files,
+        None => return Ok(None),
+    };
+    let mtime = try!(fs::stat(&util::long_path(dep_info))).modified;
+    for file in files.iter() {
+        match fs::stat(&util::long_path(file)) {
+            Ok(stat) if stat.modified <= mtime => {}
+            Ok(stat) => {
+                info!("stale: {} -- {} vs {}", file.display(), stat.modified,
+                      mtime);
+                return Ok(None)
+            }
+            _ => {
+                info!("stale: {} -- missing", file.display());
+                return Ok(None)
+            }
+        }
+    }
+
+    Ok(Some(mtime))
+}
+
+/// Returns the absolute paths of the files listed in the dep-info file at
+/// `dep_info`, or `None` if there is no such file.
+fn parse_dep_info(dep_info: &Path) -> CargoResult<Option<Vec<Path>>> {
     macro_rules! fs_try {
         ($e:expr) => (match $e { Ok(e) => e, Err(..) => return Ok(None) })
     }
@@ -336,13 +428,13 @@ fn calculate_target_mtime(dep_info: &Path) -> CargoResult<Option<u64>> {
         Some(Ok(line)) => line,
         _ => return Ok(None),
     };
-    let mtime = try!(fs::stat(dep_info)).modified;
     let pos = try!(line.find_str(": ").chain_error(|| {
         internal(format!("dep-info not in an understood format: {}",
                          dep_info.display()))
     }));
     let deps = &line[pos + 2..];
 
+    let mut files = Vec::new();
     let mut deps = deps.split(' ').map(|s| s.trim()).filter(|s| !s.is_empty());
     loop {
         let mut file = match deps.next() {
@@ -354,17 +446,76 @@ fn calculate_target_mtime(dep_info: &Path) -> CargoResult<Option<u64>> {
             file.push(' ');
             file.push_str(deps.next().unwrap())
         }
-        match fs::stat(&cwd.join(&file)) {
-            Ok(stat) if stat.modified <= mtime => {}
-            Ok(stat) => {
-                info!("stale: {} -- {} vs {}", file, stat.modified, mtime);
-                return Ok(None)
-            }
-            _ => { info!("stale: {} -- missing", file); return Ok(None) }
+        files.push(cwd.join(&file));
+    }
+    Ok(Some(files))
+}
+
+/// Returns the dep-info files of the packages using them which `target` of
+/// `pkg` transitively depends on.
+pub fn dep_info_of_deps(cx: &Context, pkg: &Package, target: &Target,
+                        kind: Kind) -> Vec<Path> {
+    let mut ret = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = cx.dep_targets(pkg, target);
+    while let Some((pkg, target)) = stack.pop() {
+        if !visited.insert(pkg.package_id()) { continue }
+        if use_dep_info(pkg, target) {
+            let kind = if target.profile().is_for_host() {
+                Kind::Host
+            } else {
+                kind
+            };
+            ret.push(dep_info_loc(cx, pkg, target, kind));
         }
+        stack.extend(cx.dep_targets(pkg, target).into_iter());
     }
+    ret
+}
 
-    Ok(Some(mtime))
+/// Writes a Makefile-style dep-info file at `dst`, stating that each of
+/// `outputs` depends on every file listed in the dep-info files `dep_infos`.
+/// All paths are absolute, with spaces escaped.
+pub fn write_output_dep_info(dst: &Path, outputs: &[Path],
+                             dep_infos: &[Path]) -> CargoResult<()> {
+    let mut files = Vec::new();
+    for dep_info in dep_infos.iter() {
+        for file in try!(parse_dep_info(dep_info)).unwrap_or(Vec::new())
+                                                  .into_iter() {
+            if !files.contains(&file) { files.push(file) }
+        }
+    }
+    fn escape(path: &Path) -> String {
+        path.display().to_string().replace(" ", "\\ ")
+    }
+    let outputs = outputs.iter().map(escape).collect::<Vec<_>>();
+    let files = files.iter().map(escape).collect::<Vec<_>>();
+    try!(File::create(dst).write_str(&format!("{}: {}\n", outputs.connect(" "),
+                                              files.connect(" "))));
+    Ok(())
+}
+
+/// Calculates the local fingerprint of a unit of a path package from the
+/// newest modification time of its `files`, returning whether one of them is
+/// known to have been modified.
+///
+/// The newest time is saved at `hint`, so that the next time around the
+/// search can stop as soon as a newer file is found. The fingerprint is then
+/// only calculated once the unit has been rebuilt.
+fn files_fingerprint(files: Vec<Path>, hint: &Path)
+                     -> CargoResult<(LocalFingerprint, bool)> {
+    let last = File::open(hint).read_to_string().ok().and_then(|s| {
+        s.trim().parse::<u64>().ok()
+    });
+    let _ = fs::unlink(hint);
+    match util::paths::newest_mtime(files.clone(), last) {
+        Some(max) => {
+            // Failing to save the hint only makes the next build slower
+            let _ = File::create(hint).write_str(&max.to_string());
+            Ok((LocalFingerprint::FilesBased(Some(max), files), false))
+        }
+        None => Ok((LocalFingerprint::FilesBased(None, files), true)),
+    }
 }
 
 fn calculate_build_cmd_fingerprint(cx: &Context, pkg: &Package)
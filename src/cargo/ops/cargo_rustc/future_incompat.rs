@@ -0,0 +1,126 @@
+//! Collection of the warnings about code which future versions of rustc will
+//! reject, for `cargo report future-incompat`.
+//!
+//! The warnings of every unit of a build, including replayed warnings of fresh
+//! units, are collected while building, and saved to the target directory
+//! once the build finished, next to the fingerprints of the packages built
+//! for the host. The report therefore always describes the last build.
+
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry::{Occupied, Vacant};
+use std::old_io::fs::PathExtensions;
+use std::old_io::{File, fs, USER_DIR};
+use std::sync::Mutex;
+
+use rustc_serialize::json;
+
+use util::{CargoResult, ChainError, Config, human};
+
+/// Name of the report in the fingerprint directory of the target directory.
+const REPORT_FILE: &'static str = "future-incompat-report.json";
+
+/// Phrases rustc uses in the notes of lints which will become hard errors.
+const MARKERS: &'static [&'static str] = &[
+    "will become a hard error in a future release",
+    "previously accepted by the compiler but is being phased out",
+];
+
+/// The future incompatibility warnings of each package built so far, by the
+/// name and version of the package.
+pub type FutureIncompat = Mutex<BTreeMap<String, Vec<String>>>;
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct FutureIncompatReport {
+    pub packages: Vec<FutureIncompatPackage>,
+}
+
+#[derive(RustcEncodable, RustcDecodable)]
+pub struct FutureIncompatPackage {
+    /// Name and version of the package, like `foo v0.1.0`
+    pub package: String,
+    /// The warnings as printed by rustc
+    pub warnings: Vec<String>,
+}
+
+/// Records the future incompatibility warnings in the compiler output
+/// `diagnostics` of a unit of `pkg`.
+pub fn record(report: &FutureIncompat, pkg: &str, diagnostics: &[u8]) {
+    let diagnostics = String::from_utf8_lossy(diagnostics);
+
+    // Each warning or error starts a new block, which carries on with the
+    // notes about it
+    let mut blocks = Vec::new();
+    for line in diagnostics.lines() {
+        let is_warning = line.starts_with("warning: ") ||
+                         line.contains(" warning: ");
+        let is_error = line.starts_with("error") || line.contains(" error: ");
+        if is_warning || is_error {
+            blocks.push((is_warning, vec![line]));
+        } else if let Some(&mut (_, ref mut block)) = blocks.last_mut() {
+            block.push(line);
+        }
+    }
+
+    let mut report = report.lock().unwrap();
+    for (is_warning, block) in blocks.into_iter() {
+        let future = block.iter().any(|l| {
+            MARKERS.iter().any(|m| l.contains(*m))
+        });
+        if !is_warning || !future { continue }
+        let warning = block.connect("\n").trim_right().to_string();
+        let warnings = match report.entry(pkg.to_string()) {
+            Occupied(e) => e.into_mut(),
+            Vacant(e) => e.insert(Vec::new()),
+        };
+        // Units of the same package, like a library and its tests, report the
+        // same warnings
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+}
+
+/// Saves the warnings collected during a build to `target_dir`, and points at
+/// the report if there are any.
+pub fn save(report: &FutureIncompat, target_dir: &Path,
+            config: &Config) -> CargoResult<()> {
+    let report = report.lock().unwrap();
+    let packages = report.iter().map(|(pkg, warnings)| {
+        FutureIncompatPackage {
+            package: pkg.clone(),
+            warnings: warnings.clone(),
+        }
+    }).collect::<Vec<_>>();
+    let names = packages.iter().map(|p| p.package.as_slice())
+                        .collect::<Vec<_>>().connect(", ");
+    let json = json::encode(&FutureIncompatReport {
+        packages: packages,
+    }).unwrap();
+
+    let dir = target_dir.join(".fingerprint");
+    try!(fs::mkdir_recursive(&dir, USER_DIR));
+    let path = dir.join(REPORT_FILE);
+    try!(File::create(&path).write_str(&json).chain_error(|| {
+        human(format!("failed to write `{}`", path.display()))
+    }));
+
+    if report.is_empty() { return Ok(()) }
+    let mut shell = config.shell();
+    try!(shell.warn(format!("warning: the following packages contain code \
+                             that will be rejected by a future version of \
+                             Rust: {}", names)));
+    try!(shell.warn("note: to see what the problems were, use `cargo report \
+                     future-incompat`"));
+    Ok(())
+}
+
+/// Loads the report of the last build in `target_dir`, if there was one.
+pub fn load(target_dir: &Path) -> CargoResult<Option<FutureIncompatReport>> {
+    let path = target_dir.join(".fingerprint").join(REPORT_FILE);
+    if !path.exists() { return Ok(None) }
+    let contents = try!(File::open(&path).read_to_string());
+    let report = try!(json::decode(&contents).chain_error(|| {
+        human(format!("failed to parse `{}`", path.display()))
+    }));
+    Ok(Some(report))
+}
@@ -1,13 +1,14 @@
 use std::collections::HashSet;
 use std::collections::hash_map::HashMap;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
-use std::sync::TaskPool;
+use std::sync::{Arc, Mutex, TaskPool};
 use std::sync::mpsc::{channel, Sender, Receiver};
-use term::color::YELLOW;
+use term::color::{BLACK, YELLOW};
 
 use core::{Package, PackageId, Resolve, PackageSet};
-use util::{Config, DependencyQueue, Fresh, Dirty, Freshness};
+use util::{Config, DependencyQueue, Fresh, Dirty, Freshness, Progress};
 use util::{CargoResult, Dependency, profile};
+use util::jobserver::JobServer;
 
 use super::job::Job;
 
@@ -29,8 +30,19 @@ pub struct JobQueue<'a, 'b> {
     state: HashMap<&'a PackageId, Freshness>,
     ignored: HashSet<&'a PackageId>,
     printed: HashSet<&'a PackageId>,
+    /// Number of units of work enqueued and finished, for the progress bar
+    total: usize,
+    finished: usize,
+    /// The jobserver dirty jobs take a token from before running, if any
+    jobserver: Option<Arc<JobServer>>,
+    warnings: Arc<Warnings>,
 }
 
+/// The number of warnings each unit of compilation produced, along with the
+/// name of its package and a description of the unit such as `lib`.
+pub type Warnings = Mutex<Vec<(String, String, usize)>>;
+
+
 /// A helper structure for metadata about the state of a building package.
 struct PendingBuild {
     /// Number of jobs currently active
@@ -38,6 +50,8 @@ struct PendingBuild {
     /// Current freshness state of this package. Any dirty target within a
     /// package will cause the entire package to become dirty.
     fresh: Freshness,
+    /// Whether the stage has no jobs, so there is no unit of work to count
+    empty: bool,
 }
 
 /// Current stage of compilation for an individual package.
@@ -60,12 +74,22 @@ pub enum Stage {
     BinaryTests,
 }
 
-type Message = (PackageId, Stage, Freshness, CargoResult<()>);
+/// Messages sent to the queue by running jobs.
+///
+/// Jobs can't print through the shell themselves, so the output of the
+/// compiler and of build scripts is sent here to be printed by the queue.
+pub enum Message {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    Finish(PackageId, Stage, Freshness, CargoResult<()>),
+}
 
 impl<'a, 'b> JobQueue<'a, 'b> {
-    pub fn new(resolve: &'a Resolve, packages: &'a PackageSet, jobs: u32)
-               -> JobQueue<'a, 'b> {
-        let (tx, rx) = channel();
+    pub fn new(resolve: &'a Resolve, packages: &'a PackageSet, jobs: u32,
+               jobserver: Option<Arc<JobServer>>,
+               warnings: Arc<Warnings>,
+               tx: Sender<Message>,
+               rx: Receiver<Message>) -> JobQueue<'a, 'b> {
         JobQueue {
             pool: TaskPool::new(jobs as usize),
             queue: DependencyQueue::new(),
@@ -78,6 +102,10 @@ impl<'a, 'b> JobQueue<'a, 'b> {
             state: HashMap::new(),
             ignored: HashSet::new(),
             printed: HashSet::new(),
+            total: 0,
+            finished: 0,
+            jobserver: jobserver,
+            warnings: warnings,
         }
     }
 
@@ -92,6 +120,7 @@ impl<'a, 'b> JobQueue<'a, 'b> {
         };
 
         // Add the package to the dependency graph
+        self.total += jobs.len();
         self.queue.enqueue(&(self.resolve, self.packages), Fresh,
                            (pkg.package_id(), stage),
                            (pkg, jobs));
@@ -108,11 +137,16 @@ impl<'a, 'b> JobQueue<'a, 'b> {
     /// possible along each dependency chain.
     pub fn execute(&mut self, config: &Config) -> CargoResult<()> {
         let _p = profile::start("executing the job graph");
+        let mut progress = Progress::new("Building", config);
 
         // Iteratively execute the dependency graph. Each turn of this loop will
         // schedule as much work as possible and then wait for one job to finish,
         // possibly scheduling more work afterwards.
+        //
+        // The progress bar is cleared while scheduling as that's when status
+        // lines are printed, and redrawn while waiting.
         while self.queue.len() > 0 {
+            if let Some(ref mut p) = progress { try!(p.clear(config)); }
             loop {
                 match self.queue.dequeue() {
                     Some((fresh, (_, stage), (pkg, jobs))) => {
@@ -123,10 +157,24 @@ impl<'a, 'b> JobQueue<'a, 'b> {
                 }
             }
 
+            if let Some(ref mut p) = progress {
+                let active = self.active_names();
+                try!(p.tick(self.finished, self.total, &active, config));
+            }
+
             // Now that all possible work has been scheduled, wait for a piece
-            // of work to finish. If any package fails to build then we stop
+            // of work to finish, printing any output of the running jobs in
+            // the meantime. If any package fails to build then we stop
             // scheduling work as quickly as possibly.
-            let (id, stage, fresh, result) = self.rx.recv().unwrap();
+            let (id, stage, fresh, result) = match self.rx.recv().unwrap() {
+                Message::Finish(id, stage, fresh, result) => {
+                    (id, stage, fresh, result)
+                }
+                msg => {
+                    try!(print_output(msg, &mut progress, config));
+                    continue
+                }
+            };
             info!("  end: {} {:?}", id, stage);
             let id = *self.state.keys().find(|&k| *k == &id).unwrap();
             self.active -= 1;
@@ -135,27 +183,65 @@ impl<'a, 'b> JobQueue<'a, 'b> {
                     let state = &mut self.pending[(id, stage)];
                     state.amt -= 1;
                     state.fresh = state.fresh.combine(fresh);
+                    if !state.empty {
+                        self.finished += 1;
+                    }
                     if state.amt == 0 {
                         self.queue.finish(&(id, stage), state.fresh);
                     }
                 }
                 Err(e) => {
+                    if let Some(ref mut p) = progress { try!(p.clear(config)); }
                     if self.active > 0 {
                         try!(config.shell().say(
                                     "Build failed, waiting for other \
                                      jobs to finish...", YELLOW));
-                        for _ in self.rx.iter().take(self.active as usize) {}
+                        while self.active > 0 {
+                            match self.rx.recv().unwrap() {
+                                Message::Finish(..) => self.active -= 1,
+                                msg => try!(print_output(msg, &mut progress,
+                                                         config)),
+                            }
+                        }
                     }
                     return Err(e)
                 }
             }
         }
 
+        if let Some(ref mut p) = progress { try!(p.clear(config)); }
         trace!("rustc jobs completed");
 
+        self.print_warning_summary(config)
+    }
+
+    /// Prints how many warnings each unit produced, as they may have long
+    /// scrolled out of view by the end of the build.
+    fn print_warning_summary(&self, config: &Config) -> CargoResult<()> {
+        let warnings = self.warnings.lock().unwrap();
+        if warnings.is_empty() { return Ok(()) }
+        let mut shell = config.shell();
+        for &(ref pkg, ref unit, count) in warnings.iter() {
+            try!(shell.warn(format!("warning: `{}` ({}) generated {} warning{}",
+                                    pkg, unit, count,
+                                    if count == 1 {""} else {"s"})));
+        }
+        try!(shell.err().say("note: warnings of crates which are not \
+                              rebuilt are replayed, so they are printed by \
+                              every build until fixed", BLACK));
         Ok(())
     }
 
+    /// Returns the names of the packages with jobs currently running.
+    fn active_names(&self) -> String {
+        let mut names = self.pending.iter().filter(|&(_, state)| {
+            state.amt > 0
+        }).map(|(&(id, _), _)| id.name()).collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names.connect(", ")
+    }
+
     /// Execute a stage of compilation for a package.
     ///
     /// The input freshness is from `dequeue()` and indicates the combined
@@ -174,6 +260,7 @@ impl<'a, 'b> JobQueue<'a, 'b> {
         self.pending.insert((pkg.package_id(), stage), PendingBuild {
             amt: amt,
             fresh: fresh,
+            empty: njobs == 0,
         });
 
         let mut total_fresh = fresh.combine(self.state[pkg.package_id()]);
@@ -184,8 +271,26 @@ impl<'a, 'b> JobQueue<'a, 'b> {
             let my_tx = self.tx.clone();
             let id = id.clone();
             let (desc_tx, desc_rx) = channel();
+            let jobserver = self.jobserver.clone();
             self.pool.execute(move|| {
-                my_tx.send((id, stage, fresh, job.run(fresh, desc_tx))).unwrap();
+                // Fresh jobs don't do any work worth a token
+                let token = match jobserver {
+                    Some(ref server) if fresh == Dirty => {
+                        match JobServer::acquire(server) {
+                            Ok(token) => Some(token),
+                            Err(e) => {
+                                let msg = Message::Finish(id, stage, fresh,
+                                                          Err(e));
+                                my_tx.send(msg).unwrap();
+                                return
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                let result = job.run(fresh, desc_tx);
+                drop(token);
+                my_tx.send(Message::Finish(id, stage, fresh, result)).unwrap();
             });
             // only the first message of each job is processed
             match desc_rx.recv() {
@@ -197,7 +302,7 @@ impl<'a, 'b> JobQueue<'a, 'b> {
         // If no work was scheduled, make sure that a message is actually send
         // on this channel.
         if njobs == 0 {
-            self.tx.send((id, stage, fresh, Ok(()))).unwrap();
+            self.tx.send(Message::Finish(id, stage, fresh, Ok(()))).unwrap();
         }
 
         // Print out some nice progress information
@@ -230,6 +335,25 @@ impl<'a, 'b> JobQueue<'a, 'b> {
     }
 }
 
+/// Prints output sent by a running job through the shell.
+fn print_output(msg: Message, progress: &mut Option<Progress>,
+                config: &Config) -> CargoResult<()> {
+    let (output, stdout) = match msg {
+        Message::Stdout(output) => (output, true),
+        Message::Stderr(output) => (output, false),
+        Message::Finish(..) => return Ok(()),
+    };
+    if output.is_empty() { return Ok(()) }
+    if let Some(ref mut p) = *progress { try!(p.clear(config)); }
+    let mut shell = config.shell();
+    if stdout {
+        try!(shell.out().write_all(&output));
+    } else {
+        try!(shell.err().write_all(&output));
+    }
+    Ok(())
+}
+
 impl<'a> Dependency for (&'a PackageId, Stage) {
     type Context = (&'a Resolve, &'a PackageSet);
 
@@ -268,10 +392,19 @@ impl<'a> Dependency for (&'a PackageId, Stage) {
             // own custom build command is actually built, and then we need to
             // wait for all our dependencies to finish their custom build
             // commands themselves (as they may provide input to us).
+            //
+            // Binaries of build dependencies requested as artifacts are run by
+            // the build command, so they need to be built as well.
             Stage::RunCustomBuild => {
                 let mut base = vec![(id, Stage::BuildCustomBuild)];
-                base.extend(deps.filter(|&(_, dep)| dep.is_transitive())
-                                .map(|(id, _)| (id, Stage::RunCustomBuild)));
+                for (dep_id, dep) in deps {
+                    if dep.is_transitive() {
+                        base.push((dep_id, Stage::RunCustomBuild));
+                    }
+                    if dep.is_build() && dep.artifact().is_some() {
+                        base.push((dep_id, Stage::Binaries));
+                    }
+                }
                 base
             }
 
@@ -43,12 +43,20 @@
 //!     # Hidden directory that holds all of the fingerprint files for all
 //!     # packages
 //!     .fingerprint/
+//!
+//!     # When a target is requested with `--target`, everything compiled for
+//!     # it is placed in a directory of the same layout named after the
+//!     # target, even if it's the host. The directories above then only hold
+//!     # what is compiled for the host, like build scripts and plugins, so
+//!     # they're shared with builds which don't request a target.
+//!     $triple/
 //! ```
 
 use std::old_io::fs::PathExtensions;
 use std::old_io::{self, fs, IoResult};
 
 use core::Package;
+use util;
 use util::hex::short_hash;
 
 pub struct Layout {
@@ -91,8 +99,9 @@ impl Layout {
     }
 
     pub fn prepare(&mut self) -> IoResult<()> {
-        if !self.root.exists() {
-            try!(fs::mkdir_recursive(&self.root, old_io::USER_RWX));
+        let root = util::long_path(&self.root);
+        if !root.exists() {
+            try!(fs::mkdir_recursive(&root, old_io::USER_RWX));
         }
 
         try!(mkdir(&self.deps));
@@ -104,8 +113,9 @@ impl Layout {
         return Ok(());
 
         fn mkdir(dir: &Path) -> IoResult<()> {
+            let dir = util::long_path(dir);
             if !dir.exists() {
-                try!(fs::mkdir(dir, old_io::USER_DIR));
+                try!(fs::mkdir(&dir, old_io::USER_DIR));
             }
             Ok(())
         }
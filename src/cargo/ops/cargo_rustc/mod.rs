@@ -1,17 +1,24 @@
 use std::collections::{HashSet, HashMap};
 use std::dynamic_lib::DynamicLibrary;
+use std::env;
 use std::ffi::CString;
 use std::old_io::fs::{self, PathExtensions};
+use std::old_io::File;
 use std::os;
 use std::old_path;
 use std::sync::Arc;
+use std::sync::mpsc::Sender;
 
-use core::{SourceMap, Package, PackageId, PackageSet, Target, Resolve};
-use util::{self, CargoResult, human, caused_human};
+use regex::Regex;
+
+use core::{SourceMap, Package, PackageId, PackageIdSpec, PackageSet, Target};
+use core::Resolve;
+use util::{self, CargoResult, ProcessError, human, caused_human};
 use util::{Config, internal, ChainError, Fresh, profile, join_paths};
+use util::jobserver::JobServer;
 
 use self::job::{Job, Work};
-use self::job_queue::{JobQueue, Stage};
+use self::job_queue::{JobQueue, Stage, Warnings, Message};
 
 pub use self::compilation::Compilation;
 pub use self::context::Context;
@@ -19,12 +26,15 @@ pub use self::context::Platform;
 pub use self::engine::{CommandPrototype, CommandType, ExecEngine, ProcessEngine};
 pub use self::layout::{Layout, LayoutProxy};
 pub use self::custom_build::{BuildOutput, BuildMap};
+pub use self::future_incompat::{FutureIncompatReport, FutureIncompatPackage};
+pub use self::future_incompat::load as load_future_incompat;
 
 mod context;
 mod compilation;
 mod custom_build;
 mod engine;
 mod fingerprint;
+mod future_incompat;
 mod job;
 mod job_queue;
 mod layout;
@@ -39,12 +49,31 @@ pub struct BuildConfig {
     pub target: TargetConfig,
     pub jobs: u32,
     pub requested_target: Option<String>,
+    /// Level lints of packages outside of the workspace are capped at, if
+    /// any (`build.cap-lints`)
+    pub cap_lints: Option<String>,
+    /// Environment variables from the `[env]` configuration table, set for
+    /// every process of the build
+    pub env: Vec<(String, String)>,
+    /// The only packages outside of the workspace which may run code on the
+    /// host, through a build script or as a compiler plugin, if restricted
+    /// (`build.script-allowlist`)
+    pub script_allowlist: Option<Vec<PackageIdSpec>>,
+    /// Whether build scripts run in a sandbox without network access which
+    /// can only write to their output directory (`build.script-sandbox`)
+    pub script_sandbox: bool,
+    /// Whether cargo announces a jobserver of its own when no `make` runs it
+    /// (`build.jobserver`)
+    pub jobserver: bool,
 }
 
 #[derive(Clone, Default)]
 pub struct TargetConfig {
     pub ar: Option<String>,
     pub linker: Option<String>,
+    /// Command which binaries built for the target are run through, when it
+    /// isn't the host (`target.$triple.runner`)
+    pub runner: Option<Vec<String>>,
     pub overrides: HashMap<String, BuildOutput>,
 }
 
@@ -95,6 +124,12 @@ pub fn rustc_new_version() -> CargoResult<(String, String)> {
     Ok((output, triple))
 }
 
+/// Whether `triple` is a WebAssembly target, whose binaries can only be run
+/// through a runner.
+pub fn is_wasm(triple: &str) -> bool {
+    triple.starts_with("wasm32-") || triple.starts_with("wasm64-")
+}
+
 // This is a temporary assert that ensures the consistency of the arguments
 // given the current limitations of Cargo. The long term fix is to have each
 // Target know the absolute path to the build location.
@@ -140,6 +175,7 @@ pub fn compile_targets<'a, 'b>(env: &str,
         deps.iter().find(|p| p.package_id() == resolve.root()).unwrap()
     };
     let host_layout = Layout::new(root, None, dest);
+    let own_jobserver = build_config.jobserver;
     let target_layout = build_config.requested_target.as_ref().map(|target| {
         layout::Layout::new(root, Some(&target[]), dest)
     });
@@ -150,8 +186,28 @@ pub fn compile_targets<'a, 'b>(env: &str,
     if let Some(exec_engine) = exec_engine {
         cx.exec_engine = exec_engine.clone();
     }
+    try!(check_host_code(&cx, deps));
+
+    // Coordinate with the jobserver of a `make` running cargo, or announce one
+    // of our own to the compiler and build scripts if asked to, as only recent
+    // versions of make understand it
+    let jobserver = match JobServer::from_env() {
+        Some(server) => Some(server),
+        None if own_jobserver && cx.jobs() > 1 => {
+            let path = env::temp_dir().join(format!("cargo-jobserver-{}",
+                                                    os::getpid()));
+            try!(JobServer::new(cx.jobs(), &path))
+        }
+        None => None,
+    };
+    let jobserver = jobserver.map(Arc::new);
+    let makeflags = jobserver.as_ref().and_then(|s| s.makeflags());
 
-    let mut queue = JobQueue::new(cx.resolve, deps, cx.jobs());
+    let messages = cx.take_messages();
+    let mut queue = JobQueue::new(cx.resolve, deps, cx.jobs(),
+                                  jobserver,
+                                  cx.warnings.clone(),
+                                  cx.output.clone(), messages);
 
     // First ensure that the destination directory exists
     try!(cx.prepare(pkg));
@@ -164,13 +220,19 @@ pub fn compile_targets<'a, 'b>(env: &str,
     each_dep(pkg, &cx, |dep| {
         compiled.insert(dep.package_id().clone());
     });
+    let artifacts = deps.iter().flat_map(|p| {
+        cx.artifact_targets(p).into_iter()
+    }).map(|(a, _)| a.package_id()).collect::<HashSet<_>>();
     for dep in deps.iter() {
         if dep == pkg { continue }
 
-        // Only compile lib targets for dependencies
+        // Only compile lib targets for dependencies, along with the binaries
+        // requested as artifacts by build scripts
+        let is_artifact = artifacts.contains(dep.package_id());
         let targets = dep.targets().iter().filter(|target| {
             target.profile().is_custom_build() ||
-                cx.is_relevant_target(*target)
+                cx.is_relevant_target(*target) ||
+                (is_artifact && cx.is_relevant_artifact(*target))
         }).collect::<Vec<&Target>>();
 
         if targets.len() == 0 && dep.package_id() != resolve.root() {
@@ -184,7 +246,30 @@ pub fn compile_targets<'a, 'b>(env: &str,
     try!(compile(targets, pkg, true, &mut cx, &mut queue));
 
     // Now that we've figured out everything that we're going to do, do it!
-    try!(queue.execute(cx.config));
+    // The jobserver only exists while building, so it isn't announced to the
+    // processes run with the resulting compilation. It's added to the flags a
+    // user already set in `MAKEFLAGS`.
+    let vars = ["MAKEFLAGS", "CARGO_MAKEFLAGS"];
+    if let Some(ref flags) = makeflags {
+        let all = match env::var_string("MAKEFLAGS") {
+            Ok(user) => format!("{} {}", user, flags),
+            Err(..) => flags.clone(),
+        };
+        cx.compilation.extra_env.insert(vars[0].to_string(), Some(all));
+        cx.compilation.extra_env.insert(vars[1].to_string(),
+                                        Some(flags.clone()));
+    }
+    let result = queue.execute(cx.config);
+    if makeflags.is_some() {
+        for var in vars.iter() {
+            cx.compilation.extra_env.remove(*var);
+        }
+    }
+    try!(result);
+    if cx.env() != "doc" {
+        try!(future_incompat::save(&cx.future_incompat,
+                                   &pkg.absolute_target_dir(), cx.config));
+    }
 
     let out_dir = cx.layout(pkg, Kind::Target).build_out(pkg)
                     .display().to_string();
@@ -269,6 +354,21 @@ fn compile<'a, 'b>(targets: &[&'a Target], pkg: &'a Package,
                 try!(work.call(desc_tx.clone()));
                 dirty.call(desc_tx)
             });
+            let loc = fingerprint::diagnostics_loc(cx, pkg, target, kind);
+            let warnings = cx.warnings.clone();
+            let output = cx.output.clone();
+            let future = cx.future_incompat.clone();
+            let name = pkg.name().to_string();
+            let id = format!("{} v{}", pkg.name(), pkg.version());
+            let unit = unit_description(target);
+            let show = !cx.hides_lints(pkg);
+            let fresh = Work::new(move |desc_tx| {
+                let diagnostics = try!(replay_diagnostics(&loc, &warnings,
+                                                          &output, &name,
+                                                          &unit, show));
+                future_incompat::record(&future, &id, &diagnostics);
+                fresh.call(desc_tx)
+            });
             dst.push((job(dirty, fresh), freshness));
         }
 
@@ -307,6 +407,7 @@ fn compile<'a, 'b>(targets: &[&'a Target], pkg: &'a Package,
                 cx.build_state.outputs.lock().unwrap().contains_key(&key) {
                     continue
                 }
+            try!(check_script_allowed(cx, pkg));
             let (dirty, fresh, freshness) =
                 try!(custom_build::prepare(pkg, target, req, cx));
             run_custom.push((job(dirty, fresh), freshness));
@@ -327,6 +428,112 @@ fn compile<'a, 'b>(targets: &[&'a Target], pkg: &'a Package,
     Ok(())
 }
 
+/// Fails if `build.script-allowlist` restricts which packages may run build
+/// scripts, and `pkg` isn't one of them. Packages of the workspace are always
+/// allowed to.
+fn check_script_allowed(cx: &Context, pkg: &Package) -> CargoResult<()> {
+    let allowlist = match cx.script_allowlist() {
+        Some(allowlist) => allowlist,
+        None => return Ok(()),
+    };
+    if cx.is_local(pkg) ||
+       allowlist.iter().any(|spec| spec.matches(pkg.package_id())) {
+        return Ok(())
+    }
+    Err(human(format!("`{}` has a build script, but it isn't allowed to run \
+                       as it doesn't match `build.script-allowlist`; review \
+                       the build script and add the package to the list to \
+                       allow it", pkg.package_id())))
+}
+
+/// Fails before anything is compiled if `build.script-allowlist` restricts
+/// which packages may run code on the host, and packages of the graph which
+/// aren't in it have a build script or are compiler plugins. Build scripts
+/// whose output is overridden with `target.$triple.$links` don't run, so
+/// they don't count.
+fn check_host_code(cx: &Context, deps: &PackageSet) -> CargoResult<()> {
+    let allowlist = match cx.script_allowlist() {
+        Some(allowlist) => allowlist,
+        None => return Ok(()),
+    };
+    let overridden = cx.build_state.outputs.lock().unwrap().keys()
+                       .map(|&(ref id, _)| id.clone())
+                       .collect::<HashSet<PackageId>>();
+    let mut denied = deps.iter().filter(|pkg| {
+        let runs_code = pkg.targets().iter().any(|t| {
+            t.profile().is_for_host() &&
+                !(t.profile().is_custom_build() &&
+                  overridden.contains(pkg.package_id()))
+        });
+        runs_code && !cx.is_local(*pkg) &&
+            !allowlist.iter().any(|spec| spec.matches(pkg.package_id()))
+    }).map(|pkg| pkg.package_id().to_string()).collect::<Vec<_>>();
+    if denied.is_empty() { return Ok(()) }
+    denied.sort();
+    Err(human(format!("these packages run code on the host, but don't match \
+                       `build.script-allowlist`:\n  {}\nreview their build \
+                       scripts and plugins and add them to the list to allow \
+                       them", denied.connect("\n  "))))
+}
+
+/// Describes the kind of `target` in the warning summary.
+fn unit_description(target: &Target) -> String {
+    if target.profile().is_custom_build() {
+        "build script".to_string()
+    } else if target.is_lib() {
+        if target.profile().is_test() {"lib test"} else {"lib"}.to_string()
+    } else if target.is_example() {
+        format!("example \"{}\"", target.name())
+    } else if target.profile().is_test() {
+        format!("test \"{}\"", target.name())
+    } else {
+        format!("bin \"{}\"", target.name())
+    }
+}
+
+/// Records the number of warnings in the compiler output `diagnostics` of a
+/// unit for the summary at the end of the build.
+fn record_warnings(warnings: &Warnings, name: &str, unit: &str,
+                   diagnostics: &[u8]) {
+    // Only the first line of a diagnostic is counted, which is either
+    // `file:line:col: line:col warning: ...` or `warning: ...` for warnings
+    // without a location. Any other line may be quoted source code.
+    let header = Regex::new(r"^(\S.*:\d+:\d+: \d+:\d+ )?warning: ").unwrap();
+    let count = String::from_utf8_lossy(diagnostics).lines().filter(|l| {
+        header.is_match(l)
+    }).count();
+    if count > 0 {
+        warnings.lock().unwrap().push((name.to_string(), unit.to_string(),
+                                       count));
+    }
+}
+
+/// Saves the compiler output of a unit at `loc`, so it can be replayed when
+/// the unit is fresh in later builds.
+fn cache_diagnostics(loc: &Path, diagnostics: &[u8]) -> CargoResult<()> {
+    if !diagnostics.is_empty() {
+        try!(File::create(loc).write_all(diagnostics));
+    } else if loc.exists() {
+        try!(fs::unlink(loc));
+    }
+    Ok(())
+}
+
+/// Prints the compiler output cached at `loc` for a fresh unit, unless `show`
+/// is false, so that its warnings stay visible until they are fixed. The
+/// output is returned as well.
+fn replay_diagnostics(loc: &Path, warnings: &Warnings,
+                      output: &Sender<Message>, name: &str,
+                      unit: &str, show: bool) -> CargoResult<Vec<u8>> {
+    if !loc.exists() { return Ok(Vec::new()) }
+    let diagnostics = try!(File::open(loc).read_to_end());
+    if show {
+        record_warnings(warnings, name, unit, &diagnostics);
+        output.send(Message::Stderr(diagnostics.clone())).ok();
+    }
+    Ok(diagnostics)
+}
+
 fn rustc(package: &Package, target: &Target,
          cx: &mut Context, req: Platform)
          -> CargoResult<Vec<(Work, Kind)> >{
@@ -337,10 +544,14 @@ fn rustc(package: &Package, target: &Target,
 
     return rustcs.into_iter().map(|(rustc, kind)| {
         let name = package.name().to_string();
-        let is_path_source = package.package_id().source_id().is_path();
-        let show_warnings = package.package_id() == cx.resolve.root() ||
-                            is_path_source;
-        let rustc = if show_warnings {rustc} else {rustc.arg("-Awarnings")};
+        let show = !cx.hides_lints(package);
+        let rustc = match cx.cap_lints() {
+            Some(..) if !show => rustc.arg("--cap-lints").arg("warn"),
+            Some(level) if !cx.is_local(package) => {
+                rustc.arg("--cap-lints").arg(level)
+            }
+            _ => rustc,
+        };
         let exec_engine = cx.exec_engine.clone();
 
         let filenames = try!(cx.target_filenames(target));
@@ -364,6 +575,30 @@ fn rustc(package: &Package, target: &Target,
         let rustc_dep_info_loc = root.join(target.file_stem()).with_extension("d");
         let dep_info_loc = fingerprint::dep_info_loc(cx, package, target, kind);
         let cwd = cx.config.cwd().clone();
+        let warnings = cx.warnings.clone();
+        let compiler_output = cx.output.clone();
+        let future = cx.future_incompat.clone();
+        let id = format!("{} v{}", package.name(), package.version());
+        let unit = unit_description(target);
+        let diagnostics_loc = fingerprint::diagnostics_loc(cx, package, target,
+                                                           kind);
+
+        // The artifacts of the package being built get a dep-info file next to
+        // them, for build systems wrapping cargo to know when to run it again
+        let output_dep_info = if package.package_id() == cx.resolve.root() &&
+                                 !target.profile().is_custom_build() &&
+                                 !target.profile().is_test() {
+            let stem = target.file_stem();
+            let name = filenames[0].replace(&stem, target.name());
+            let outputs = filenames.iter().map(|f| root.join(f))
+                                   .collect::<Vec<_>>();
+            let mut dep_infos = vec![dep_info_loc.clone()];
+            dep_infos.extend(fingerprint::dep_info_of_deps(cx, package, target,
+                                                           kind).into_iter());
+            Some((root.join(name).with_extension("d"), outputs, dep_infos))
+        } else {
+            None
+        };
 
         Ok((Work::new(move |desc_tx| {
             let mut rustc = rustc;
@@ -390,18 +625,62 @@ fn rustc(package: &Package, target: &Target,
             }
 
             desc_tx.send(rustc.to_string()).ok();
-            try!(exec_engine.exec(rustc).chain_error(|| {
+            let (diagnostics, result) = exec_capturing(&**exec_engine, rustc,
+                                                       &compiler_output, show);
+            if show {
+                record_warnings(&warnings, &name, &unit, &diagnostics);
+            }
+            future_incompat::record(&future, &id, &diagnostics);
+            try!(result.chain_error(|| {
                 human(format!("Could not compile `{}`.", name))
             }));
+            try!(cache_diagnostics(&diagnostics_loc, &diagnostics));
 
             try!(fs::rename(&rustc_dep_info_loc, &dep_info_loc));
             try!(fingerprint::append_current_dir(&dep_info_loc, &cwd));
+            if let Some((ref dst, ref outputs, ref infos)) = output_dep_info {
+                try!(fingerprint::write_output_dep_info(dst, outputs,
+                                                        infos));
+            }
 
             Ok(())
 
         }), kind))
     }).collect();
 
+    // Runs the compiler with its output captured, so its diagnostics can be
+    // counted and cached, sending the output to the queue to be printed as
+    // it's produced. Unless `show` is set, the diagnostics are only printed
+    // if the compiler fails.
+    fn exec_capturing(exec_engine: &ExecEngine, rustc: CommandPrototype,
+                      compiler_output: &Sender<Message>, show: bool)
+                      -> (Vec<u8>, Result<(), ProcessError>) {
+        let result = exec_engine.exec_with_streaming(rustc, &mut |out: &[u8]| {
+            compiler_output.send(Message::Stdout(out.to_vec())).ok();
+        }, &mut |err: &[u8]| {
+            if show {
+                compiler_output.send(Message::Stderr(err.to_vec())).ok();
+            }
+        });
+        match result {
+            Ok(output) => (output.error, Ok(())),
+            Err(mut e) => {
+                // The output has been printed as it normally would be, so it
+                // doesn't need to be a part of the error as well
+                if let Some(pos) = e.desc.find_str("\n--- ") {
+                    e.desc.truncate(pos);
+                }
+                let diagnostics = e.output.take().map(|o| o.error)
+                                   .unwrap_or(Vec::new());
+                if !show {
+                    let err = Message::Stderr(diagnostics.clone());
+                    compiler_output.send(err).ok();
+                }
+                (diagnostics, Err(e))
+            }
+        }
+    }
+
     // Add all relevant -L and -l flags from dependencies (now calculated and
     // present in `state`) to the command provided
     fn add_native_deps(mut rustc: CommandPrototype,
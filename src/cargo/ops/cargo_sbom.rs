@@ -0,0 +1,278 @@
+//! Generation of software bills of materials for `cargo sbom`.
+//!
+//! A bill of materials lists every package in the resolved dependency graph
+//! of a package along with its version, license, source and checksum, and the
+//! dependencies between the packages. It is generated in either the SPDX or
+//! the CycloneDX JSON format.
+
+use std::collections::BTreeMap;
+use std::old_io::File;
+use rustc_serialize::hex::ToHex;
+use rustc_serialize::json::Json;
+use time;
+
+use core::registry::PackageRegistry;
+use core::{Package, PackageId, Resolve};
+use ops;
+use sources::RegistrySource;
+use util::{CargoResult, ChainError, Config, Sha256, human};
+
+/// The formats a bill of materials can be generated in.
+#[derive(Copy, PartialEq, Debug)]
+pub enum SbomFormat {
+    /// SPDX 2.2, as JSON
+    Spdx,
+    /// CycloneDX 1.4, as JSON
+    CycloneDx,
+}
+
+/// Generates the bill of materials for the package of the manifest at
+/// `manifest_path`. Like a build, this resolves the dependencies and writes
+/// the lockfile if there is none. Dev-dependencies aren't part of what is
+/// shipped, so they are left out.
+pub fn sbom(manifest_path: &Path, format: SbomFormat,
+            config: &Config) -> CargoResult<Json> {
+    let mut registry = PackageRegistry::new(config);
+    let (package, resolve) = try!(ops::resolve_manifest(&mut registry,
+                                                        manifest_path,
+                                                        false));
+    let ids = resolve.iter().cloned().collect::<Vec<_>>();
+    let packages = try!(registry.get(&ids).chain_error(|| {
+        human("Unable to get packages from source")
+    }));
+
+    let mut components = Vec::new();
+    for pkg in packages.iter() {
+        let checksum = try!(checksum(pkg.package_id(), config));
+        components.push((pkg, checksum));
+    }
+    components.sort_by(|a, b| a.0.package_id().cmp(b.0.package_id()));
+
+    Ok(match format {
+        SbomFormat::Spdx => spdx(&package, &components, &resolve),
+        SbomFormat::CycloneDx => cyclonedx(&package, &components, &resolve),
+    })
+}
+
+/// Returns the SHA-256 checksum of the `.crate` file of a registry package.
+/// Packages from other sources aren't distributed as a single file, so they
+/// have no checksum.
+fn checksum(id: &PackageId, config: &Config) -> CargoResult<Option<String>> {
+    let source_id = id.source_id();
+    if !source_id.is_registry() { return Ok(None) }
+    let path = RegistrySource::new(source_id, config).crate_file(id);
+    let contents = try!(File::open(&path).read_to_end().chain_error(|| {
+        human(format!("failed to read `{}`", path.display()))
+    }));
+    let mut state = Sha256::new();
+    state.update(&contents);
+    Ok(Some(state.finish().to_hex()))
+}
+
+fn object(pairs: Vec<(&str, Json)>) -> Json {
+    Json::Object(pairs.into_iter().map(|(k, v)| (k.to_string(), v))
+                      .collect::<BTreeMap<_, _>>())
+}
+
+fn string(s: &str) -> Json { Json::String(s.to_string()) }
+
+fn timestamp() -> String {
+    time::now_utc().rfc3339().to_string()
+}
+
+/// Returns the package URL of `id`, telling where the package comes from
+/// unless it's from crates.io.
+fn purl(id: &PackageId) -> String {
+    // Qualifier values are percent-encoded
+    fn encode(s: &str) -> String {
+        let mut ret = String::new();
+        for b in s.bytes() {
+            match b as char {
+                'a'...'z' | 'A'...'Z' | '0'...'9' |
+                '-' | '.' | '_' | '~' | ':' | '/' => ret.push(b as char),
+                _ => ret.push_str(&format!("%{:02X}", b)),
+            }
+        }
+        ret
+    }
+
+    let purl = format!("pkg:cargo/{}@{}", id.name(), id.version());
+    let source_id = id.source_id();
+    if source_id.is_default_registry() {
+        purl
+    } else if source_id.is_registry() {
+        format!("{}?repository_url={}", purl,
+                encode(&source_id.url().to_string()))
+    } else if source_id.is_git() {
+        let mut url = format!("git+{}", source_id.url());
+        if let Some(rev) = source_id.precise() {
+            url.push_str(&format!("@{}", rev));
+        }
+        format!("{}?vcs_url={}", purl, encode(&url))
+    } else {
+        format!("{}?download_url={}", purl,
+                encode(&source_id.url().to_string()))
+    }
+}
+
+fn spdx(root: &Package, components: &[(&Package, Option<String>)],
+        resolve: &Resolve) -> Json {
+    // Identifiers may only contain letters, numbers, `.` and `-`
+    fn spdx_id(id: &PackageId) -> String {
+        let id = format!("SPDXRef-Package-{}-{}", id.name(), id.version());
+        id.chars().map(|c| {
+            if c.is_alphanumeric() || c == '.' || c == '-' { c } else { '-' }
+        }).collect()
+    }
+
+    let packages = components.iter().map(|&(pkg, ref checksum)| {
+        let id = pkg.package_id();
+        let metadata = pkg.manifest().metadata();
+        let source_id = id.source_id();
+        let download = if source_id.is_path() {
+            "NOASSERTION".to_string()
+        } else {
+            source_id.to_url()
+        };
+        let mut fields = vec![
+            ("SPDXID", Json::String(spdx_id(id))),
+            ("name", string(id.name())),
+            ("versionInfo", Json::String(id.version().to_string())),
+            ("downloadLocation", Json::String(download)),
+            ("licenseConcluded", string("NOASSERTION")),
+            ("licenseDeclared", string(metadata.license.as_ref()
+                                               .map(|s| &s[])
+                                               .unwrap_or("NOASSERTION"))),
+            ("copyrightText", string("NOASSERTION")),
+            ("filesAnalyzed", Json::Boolean(false)),
+        ];
+        if let Some(ref checksum) = *checksum {
+            fields.push(("checksums", Json::Array(vec![object(vec![
+                ("algorithm", string("SHA256")),
+                ("checksumValue", string(checksum)),
+            ])])));
+        }
+        object(fields)
+    }).collect();
+
+    let mut relationships = vec![object(vec![
+        ("spdxElementId", string("SPDXRef-DOCUMENT")),
+        ("relationshipType", string("DESCRIBES")),
+        ("relatedSpdxElement", Json::String(spdx_id(root.package_id()))),
+    ])];
+    for &(pkg, _) in components.iter() {
+        for dep in deps(resolve, pkg.package_id()).into_iter() {
+            relationships.push(object(vec![
+                ("spdxElementId", Json::String(spdx_id(pkg.package_id()))),
+                ("relationshipType", string("DEPENDS_ON")),
+                ("relatedSpdxElement", Json::String(spdx_id(dep))),
+            ]));
+        }
+    }
+
+    // The namespace has to be unique to this document, not only to the
+    // package, so it's made unique with a hash of the time of its creation
+    let name = format!("{}-{}", root.name(), root.package_id().version());
+    let created = timestamp();
+    let mut state = Sha256::new();
+    state.update(format!("{} {} {}", name, created,
+                         time::precise_time_ns()).as_bytes());
+    let namespace = format!("https://spdx.org/spdxdocs/{}-{}", name,
+                            &state.finish().to_hex()[..16]);
+    object(vec![
+        ("spdxVersion", string("SPDX-2.2")),
+        ("dataLicense", string("CC0-1.0")),
+        ("SPDXID", string("SPDXRef-DOCUMENT")),
+        ("name", Json::String(name.clone())),
+        ("documentNamespace", Json::String(namespace)),
+        ("creationInfo", object(vec![
+            ("created", Json::String(created)),
+            ("creators", Json::Array(vec![
+                Json::String(format!("Tool: cargo-{}",
+                                     env!("CARGO_PKG_VERSION"))),
+            ])),
+        ])),
+        ("packages", Json::Array(packages)),
+        ("relationships", Json::Array(relationships)),
+    ])
+}
+
+fn cyclonedx(root: &Package, components: &[(&Package, Option<String>)],
+             resolve: &Resolve) -> Json {
+    fn component(pkg: &Package, checksum: &Option<String>) -> Json {
+        let id = pkg.package_id();
+        let metadata = pkg.manifest().metadata();
+        let kind = if pkg.targets().iter().any(|t| t.is_lib()) {
+            "library"
+        } else {
+            "application"
+        };
+        let mut fields = vec![
+            ("type", string(kind)),
+            ("bom-ref", Json::String(id.to_string())),
+            ("name", string(id.name())),
+            ("version", Json::String(id.version().to_string())),
+            ("purl", Json::String(purl(id))),
+        ];
+        if let Some(ref license) = metadata.license {
+            fields.push(("licenses", Json::Array(vec![object(vec![
+                ("expression", string(license)),
+            ])])));
+        }
+        if let Some(ref checksum) = *checksum {
+            fields.push(("hashes", Json::Array(vec![object(vec![
+                ("alg", string("SHA-256")),
+                ("content", string(checksum)),
+            ])])));
+        }
+        let source_id = id.source_id();
+        if !source_id.is_path() {
+            let kind = if source_id.is_git() {"vcs"} else {"distribution"};
+            fields.push(("externalReferences", Json::Array(vec![object(vec![
+                ("type", string(kind)),
+                ("url", Json::String(source_id.to_url())),
+            ])])));
+        }
+        object(fields)
+    }
+
+    let root_component = components.iter().find(|&&(pkg, _)| {
+        pkg.package_id() == root.package_id()
+    }).map(|&(pkg, ref checksum)| component(pkg, checksum)).unwrap();
+    let others = components.iter().filter(|&&(pkg, _)| {
+        pkg.package_id() != root.package_id()
+    }).map(|&(pkg, ref checksum)| component(pkg, checksum)).collect();
+    let dependencies = components.iter().map(|&(pkg, _)| {
+        let depends_on = deps(resolve, pkg.package_id()).into_iter().map(|id| {
+            Json::String(id.to_string())
+        }).collect();
+        object(vec![
+            ("ref", Json::String(pkg.package_id().to_string())),
+            ("dependsOn", Json::Array(depends_on)),
+        ])
+    }).collect();
+
+    object(vec![
+        ("bomFormat", string("CycloneDX")),
+        ("specVersion", string("1.4")),
+        ("version", Json::U64(1)),
+        ("metadata", object(vec![
+            ("timestamp", Json::String(timestamp())),
+            ("tools", Json::Array(vec![object(vec![
+                ("name", string("cargo")),
+                ("version", string(env!("CARGO_PKG_VERSION"))),
+            ])])),
+            ("component", root_component),
+        ])),
+        ("components", Json::Array(others)),
+        ("dependencies", Json::Array(dependencies)),
+    ])
+}
+
+/// Returns the sorted dependencies of `id` in `resolve`.
+fn deps<'a>(resolve: &'a Resolve, id: &PackageId) -> Vec<&'a PackageId> {
+    let mut deps = resolve.deps(id).map(|deps| deps.collect::<Vec<_>>())
+                          .unwrap_or(Vec::new());
+    deps.sort();
+    deps
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+use std::old_io::{fs, File, USER_DIR};
+use std::old_io::fs::PathExtensions;
+
+use toml;
+
+use core::manifest::TargetKind;
+use ops;
+use util::{CargoResult, ProcessError, human, ChainError, Config, short_hash};
+use util::toml as cargo_toml;
+
+/// Runs the single-file script at `path` with `args`.
+///
+/// The script is built as the binary of a package synthesized for it in
+/// `$CARGO_HOME/script`, whose manifest is taken from a code block marked as
+/// `cargo` in the leading `//!` doc comment of the script. The package lives
+/// in a directory named after a hash of the script's contents, so the build of
+/// an unchanged script is reused.
+pub fn script(path: &Path, options: &ops::CompileOptions,
+              args: &[String]) -> CargoResult<Option<ProcessError>> {
+    let manifest_path = try!(prepare(path, options.config).chain_error(|| {
+        human(format!("failed to prepare the script `{}`", path.display()))
+    }));
+    ops::run(&manifest_path, TargetKind::Bin, None, options, args)
+}
+
+fn prepare(path: &Path, config: &Config) -> CargoResult<Path> {
+    let path = config.cwd().join(path);
+    let contents = try!(File::open(&path).read_to_string());
+
+    let mut table = match try!(embedded_manifest(&contents)) {
+        Some(manifest) => try!(cargo_toml::parse(&manifest, &path)),
+        None => BTreeMap::new(),
+    };
+    for key in ["package", "project", "bin", "lib"].iter() {
+        if table.contains_key(*key) {
+            return Err(human(format!("the manifest of a script can't have a \
+                                      `{}` section, it is generated by cargo",
+                                      key)))
+        }
+    }
+    absolute_path_dependencies(&mut table, &path.dir_path());
+
+    let name = package_name(&path);
+    let mut package = BTreeMap::new();
+    package.insert("name".to_string(), toml::Value::String(name.clone()));
+    package.insert("version".to_string(),
+                   toml::Value::String("0.0.0".to_string()));
+    package.insert("authors".to_string(), toml::Value::Array(Vec::new()));
+    table.insert("package".to_string(), toml::Value::Table(package));
+    let mut bin = BTreeMap::new();
+    bin.insert("name".to_string(), toml::Value::String(name));
+    bin.insert("path".to_string(),
+               toml::Value::String(path.display().to_string()));
+    table.insert("bin".to_string(),
+                 toml::Value::Array(vec![toml::Value::Table(bin)]));
+
+    let dir = config.home().join("script").join(short_hash(&contents));
+    let manifest_path = dir.join("Cargo.toml");
+    let manifest = toml::Value::Table(table).to_string();
+    let unchanged = manifest_path.exists() &&
+        try!(File::open(&manifest_path).read_to_string()) == manifest;
+    if !unchanged {
+        try!(fs::mkdir_recursive(&dir, USER_DIR));
+        try!(File::create(&manifest_path).write_str(&manifest));
+    }
+    Ok(manifest_path)
+}
+
+/// Returns the contents of the code block marked as `cargo` in the leading
+/// `//!` doc comment of a script, if there is one.
+fn embedded_manifest(contents: &str) -> CargoResult<Option<String>> {
+    let mut doc = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if i == 0 && line.starts_with("#!") && !line.starts_with("#![") {
+            continue
+        }
+        if !line.starts_with("//!") {
+            if line.is_empty() && doc.is_empty() { continue }
+            break
+        }
+        let line = &line[3..];
+        doc.push(if line.starts_with(" ") {&line[1..]} else {line});
+    }
+
+    let mut manifest = Vec::new();
+    let mut in_block = false;
+    for line in doc.into_iter() {
+        if !in_block {
+            in_block = line.trim() == "```cargo";
+        } else if line.trim() == "```" {
+            return Ok(Some(manifest.connect("\n")))
+        } else {
+            manifest.push(line);
+        }
+    }
+    if in_block {
+        Err(human("the `cargo` code block in the doc comment of the script \
+                   is not closed"))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Makes the paths of path dependencies relative to the directory of the
+/// script, rather than to the directory of the synthesized package.
+fn absolute_path_dependencies(table: &mut toml::Table, dir: &Path) {
+    for kind in ["dependencies", "dev-dependencies",
+                 "build-dependencies"].iter() {
+        let deps = match table.get_mut(*kind) {
+            Some(&mut toml::Value::Table(ref mut deps)) => deps,
+            _ => continue,
+        };
+        for (_, dep) in deps.iter_mut() {
+            let dep = match *dep {
+                toml::Value::Table(ref mut dep) => dep,
+                _ => continue,
+            };
+            let path = match dep.get("path") {
+                Some(&toml::Value::String(ref path)) => dir.join(path),
+                _ => continue,
+            };
+            dep.insert("path".to_string(),
+                       toml::Value::String(path.display().to_string()));
+        }
+    }
+}
+
+/// The name of the package of a script is the name of the file, with
+/// characters which can't be a part of a crate name replaced by `_`.
+fn package_name(path: &Path) -> String {
+    let stem = path.filestem_str().unwrap_or("");
+    let name = stem.chars().map(|c| {
+        if c.is_alphanumeric() || c == '-' || c == '_' {c} else {'_'}
+    }).collect::<String>();
+    if name.is_empty() {"script".to_string()} else {name}
+}
@@ -0,0 +1,419 @@
+use std::collections::{HashMap, HashSet};
+use std::old_io::File;
+
+use semver::Version;
+use toml;
+
+use core::{Package, PackageId, SourceId};
+use core::registry::PackageRegistry;
+use ops;
+use util::{CargoResult, ChainError, Config, ToSemver, human};
+use util::toml as cargo_toml;
+use util::workspace;
+
+pub struct SetVersionOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    /// The version to set, if not bumping the current one
+    pub version: Option<&'a str>,
+    /// `major`, `minor` or `patch`
+    pub bump: Option<&'a str>,
+    /// The name of the workspace member to change, if not the package at the
+    /// manifest path
+    pub spec: Option<&'a str>,
+    pub workspace: bool,
+}
+
+#[derive(Copy)]
+enum Bump { Major, Minor, Patch }
+
+enum Change {
+    To(Version),
+    Bump(Bump),
+}
+
+impl Change {
+    fn apply(&self, current: &Version) -> Version {
+        let bump = match *self {
+            Change::To(ref version) => return version.clone(),
+            Change::Bump(bump) => bump,
+        };
+        let (major, minor, patch) = match bump {
+            Bump::Major => (current.major + 1, 0, 0),
+            Bump::Minor => (current.major, current.minor + 1, 0),
+            Bump::Patch => (current.major, current.minor, current.patch + 1),
+        };
+        Version {
+            major: major,
+            minor: minor,
+            patch: patch,
+            pre: Vec::new(),
+            build: Vec::new(),
+        }
+    }
+}
+
+/// Changes the version of the package at `manifest_path`, of another member
+/// of its workspace, or of all of them.
+///
+/// The manifests are edited textually so their formatting is preserved. A
+/// version inherited from `[workspace.package]` is changed there, and with it
+/// the version of every member inheriting it. Requirements of path
+/// dependencies on the changed packages are updated to the new versions, and
+/// so are the existing lock files of the workspace.
+pub fn set_version(manifest_path: &Path,
+                   opts: &SetVersionOptions) -> CargoResult<()> {
+    let change = match (opts.version, opts.bump) {
+        (Some(version), None) => {
+            Change::To(try!(version.to_semver().map_err(human)))
+        }
+        (None, Some("major")) => Change::Bump(Bump::Major),
+        (None, Some("minor")) => Change::Bump(Bump::Minor),
+        (None, Some("patch")) => Change::Bump(Bump::Patch),
+        (None, Some(bump)) => {
+            return Err(human(format!("`--bump` must be `major`, `minor` or \
+                                      `patch`, not `{}`", bump)))
+        }
+        _ => {
+            return Err(human("exactly one of a version or `--bump` must be \
+                              given"))
+        }
+    };
+
+    if opts.workspace && opts.spec.is_some() {
+        return Err(human("`-p` can't be used with `--workspace`"))
+    }
+
+    let root = try!(workspace::find_root(&manifest_path.dir_path()))
+                   .map(|root| root.join("Cargo.toml"));
+    let packages = try!(load(manifest_path, root.as_ref(), opts.config));
+    let selected = if opts.workspace {
+        if root.is_none() {
+            return Err(human(format!("`{}` is not a member of a workspace",
+                                     manifest_path.display())))
+        }
+        packages.iter().collect::<Vec<_>>()
+    } else if let Some(name) = opts.spec {
+        let pkg = try!(packages.iter().find(|p| p.name() == name)
+                               .chain_error(|| {
+            human(format!("package `{}` is not a member of the workspace",
+                          name))
+        }));
+        vec![pkg]
+    } else {
+        packages.iter().filter(|p| p.manifest_path() == manifest_path)
+                .collect()
+    };
+
+    // Every edit is made before any manifest is written, so that a failure
+    // leaves the workspace as it was
+    let mut changed = HashMap::new();
+    let mut edits = Edits { files: Vec::new() };
+    let mut inherited_set = false;
+    for pkg in selected.iter() {
+        let version = change.apply(pkg.version());
+        if try!(inherits_version(pkg)) {
+            // Members inheriting the version change together
+            if inherited_set { continue }
+            inherited_set = true;
+            for other in packages.iter() {
+                if try!(inherits_version(other)) {
+                    changed.insert(other.name().to_string(), version.clone());
+                }
+            }
+            let root = root.as_ref().unwrap();
+            let version = version.to_string();
+            try!(edits.edit(root, |section| section == "workspace.package",
+                            "version", |_| version.clone()));
+        } else {
+            changed.insert(pkg.name().to_string(), version.clone());
+            let version = version.to_string();
+            try!(edits.edit(pkg.manifest_path(), |section| {
+                section == "package" || section == "project"
+            }, "version", |_| version.clone()));
+        }
+    }
+    let changed = packages.iter().filter_map(|pkg| {
+        match changed.get(pkg.name()) {
+            Some(version) if version != pkg.version() => {
+                Some((pkg.name().to_string(), version.clone()))
+            }
+            _ => None,
+        }
+    }).collect::<HashMap<_, _>>();
+    if changed.len() == 0 { return Ok(()) }
+
+    for pkg in packages.iter() {
+        try!(update_requirements(pkg, &changed, &mut edits));
+    }
+    try!(edits.write());
+    for pkg in packages.iter() {
+        if let Some(version) = changed.get(pkg.name()) {
+            try!(opts.config.shell().status("Updating",
+                                            format!("{} v{} -> v{}",
+                                                    pkg.name(),
+                                                    pkg.version(), version)));
+        }
+    }
+
+    // The members of a workspace share the lockfile of its root, which comes
+    // first
+    let updated = try!(load(manifest_path, root.as_ref(), opts.config));
+    update_lockfile(&updated[0], opts.config)
+}
+
+/// Loads the members of the workspace with the root manifest `root`, or just
+/// the package at `manifest_path` if it isn't part of a workspace.
+fn load(manifest_path: &Path, root: Option<&Path>,
+        config: &Config) -> CargoResult<Vec<Package>> {
+    match root {
+        Some(root) => workspace::packages(root, config),
+        None => {
+            let source_id = try!(SourceId::for_path(&manifest_path.dir_path()));
+            let (pkg, _) = try!(ops::read_package(manifest_path, &source_id,
+                                                  config));
+            Ok(vec![pkg])
+        }
+    }
+}
+
+fn inherits_version(pkg: &Package) -> CargoResult<bool> {
+    let table = try!(parse(pkg.manifest_path()));
+    let version = table.get("package").or_else(|| table.get("project"))
+                       .and_then(|p| p.lookup("version"));
+    Ok(match version {
+        Some(&toml::Value::Table(..)) => true,
+        _ => false,
+    })
+}
+
+/// Points the version requirements of the path dependencies of `pkg` on the
+/// packages in `changed` at their new versions, keeping a leading `^`, `~` or
+/// `=`.
+fn update_requirements(pkg: &Package, changed: &HashMap<String, Version>,
+                       edits: &mut Edits) -> CargoResult<()> {
+    let manifest = pkg.manifest_path();
+    let table = try!(parse(manifest));
+    let mut requirements = Vec::new();
+    collect_requirements(&table, "", changed, &mut requirements);
+    if let Some(&toml::Value::Table(ref targets)) = table.get("target") {
+        for (platform, target) in targets.iter() {
+            if let toml::Value::Table(ref target) = *target {
+                let prefix = format!("target.{}.", platform);
+                collect_requirements(target, &prefix, changed,
+                                     &mut requirements);
+            }
+        }
+    }
+
+    for requirement in requirements.iter() {
+        let version = changed.get(&requirement.name).unwrap();
+        // The dependency has a section of its own, or is an inline table
+        let section = format!("{}.{}", requirement.kind, requirement.name);
+        if !try!(edits.replace(manifest, |s| s == &section[], "version",
+                               |old| requirement_on(old, version))) {
+            let key = format!("{}.version", requirement.name);
+            try!(edits.edit(manifest, |s| s == &requirement.kind[], &key,
+                            |old| requirement_on(old, version)));
+        }
+    }
+    Ok(())
+}
+
+/// The requirement `old` pointed at `version`, with the same operator.
+fn requirement_on(old: &str, version: &Version) -> String {
+    let op = match old.chars().next() {
+        Some(c) if c == '^' || c == '~' || c == '=' => c.to_string(),
+        _ => String::new(),
+    };
+    format!("{}{}", op, version)
+}
+
+/// A version requirement on the package `name`, in the dependencies of
+/// `kind`, like `dependencies` or `target.<platform>.dev-dependencies`.
+struct Requirement {
+    name: String,
+    kind: String,
+}
+
+/// Collects the version requirements of the path dependencies in `table` on
+/// packages in `changed`.
+fn collect_requirements(table: &toml::Table, prefix: &str,
+                        changed: &HashMap<String, Version>,
+                        requirements: &mut Vec<Requirement>) {
+    for kind in ["dependencies", "dev-dependencies",
+                 "build-dependencies"].iter() {
+        let deps = match table.get(*kind) {
+            Some(&toml::Value::Table(ref deps)) => deps,
+            _ => continue,
+        };
+        for (name, dep) in deps.iter() {
+            let dep = match *dep {
+                toml::Value::Table(ref dep) => dep,
+                _ => continue,
+            };
+            if !changed.contains_key(name) || !dep.contains_key("path") {
+                continue
+            }
+            if dep.get("version").and_then(|v| v.as_str()).is_none() {
+                continue
+            }
+            requirements.push(Requirement {
+                name: name.clone(),
+                kind: format!("{}{}", prefix, kind),
+            });
+        }
+    }
+}
+
+/// Re-resolves the path dependencies in the lock file of `pkg`, if it has
+/// one, keeping everything else locked. For a workspace member, that is the
+/// lock file of the whole workspace.
+fn update_lockfile(pkg: &Package, config: &Config) -> CargoResult<()> {
+    let previous = match try!(ops::load_pkg_lockfile_guide(pkg)) {
+        Some(resolve) => resolve,
+        None => return Ok(()),
+    };
+    let to_avoid = previous.iter().filter(|id| {
+        id.source_id().is_path()
+    }).collect::<HashSet<&PackageId>>();
+    let mut registry = PackageRegistry::new(config);
+    let (_, resolve) = try!(ops::resolve_for_lockfile(&mut registry, pkg,
+                                                      Some(&previous),
+                                                      Some(&to_avoid), None));
+    ops::write_pkg_lockfile(pkg, &resolve)
+}
+
+/// The manifests being edited, which are only written once all of the edits
+/// succeeded.
+struct Edits {
+    files: Vec<(Path, String)>,
+}
+
+impl Edits {
+    /// Replaces the string value of `key` in the sections of `manifest` for
+    /// which `section` returns true with the string `value` returns for the
+    /// old value. A `key` of the form `<table>.<key>` is looked up in the
+    /// inline table `<table>`. Fails if the key isn't found in any of them.
+    fn edit<F, G>(&mut self, manifest: &Path, section: F, key: &str,
+                  value: G) -> CargoResult<()>
+        where F: FnMut(&str) -> bool, G: FnMut(&str) -> String
+    {
+        if try!(self.replace(manifest, section, key, value)) {
+            return Ok(())
+        }
+        let key = key.split('.').last().unwrap();
+        Err(human(format!("could not find the `{}` key to change in {}", key,
+                          manifest.display())))
+    }
+
+    /// Like `edit`, but returns whether the key was found instead of failing.
+    fn replace<F, G>(&mut self, manifest: &Path, mut section: F, key: &str,
+                     mut value: G) -> CargoResult<bool>
+        where F: FnMut(&str) -> bool, G: FnMut(&str) -> String
+    {
+        if !self.files.iter().any(|&(ref path, _)| path == manifest) {
+            let contents = try!(File::open(manifest).read_to_string());
+            self.files.push((manifest.clone(), contents));
+        }
+        let &mut (_, ref mut contents) = self.files.iter_mut().find(|f| {
+            f.0 == *manifest
+        }).unwrap();
+        let (table, key) = match key.find('.') {
+            Some(i) => (Some(&key[..i]), &key[i + 1..]),
+            None => (None, key),
+        };
+
+        let mut in_section = false;
+        let mut found = false;
+        let mut lines = Vec::new();
+        for line in contents.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("[") {
+                let name = trimmed.trim_matches(|c: char| {
+                    c == '[' || c == ']'
+                });
+                in_section = section(name.trim());
+            } else if in_section {
+                let start = match table {
+                    Some(table) if is_key(trimmed, table) => {
+                        inline_value_start(line, key)
+                    }
+                    Some(..) => None,
+                    None if is_key(trimmed, key) => {
+                        let rest = trimmed[key.len()..].trim_left()[1..]
+                                          .trim_left();
+                        Some(line.trim_right().len() - rest.len())
+                    }
+                    None => None,
+                };
+                let edited = start.and_then(|start| {
+                    replace_str(&line[start..], &mut value).map(|rest| {
+                        format!("{}{}", &line[..start], rest)
+                    })
+                });
+                if let Some(edited) = edited {
+                    lines.push(edited);
+                    found = true;
+                    continue
+                }
+            }
+            lines.push(line.to_string());
+        }
+        if !found { return Ok(false) }
+
+        let mut out = lines.connect("\n");
+        out.push_str("\n");
+        *contents = out;
+        Ok(true)
+    }
+
+    fn write(&self) -> CargoResult<()> {
+        for &(ref manifest, ref contents) in self.files.iter() {
+            try!(File::create(manifest).write_str(contents));
+        }
+        Ok(())
+    }
+}
+
+/// Returns where the value of `key` starts in the inline table on `line`.
+fn inline_value_start(line: &str, key: &str) -> Option<usize> {
+    let mut from = match line.find('{') {
+        Some(i) => i,
+        None => return None,
+    };
+    while let Some(i) = line[from..].find(key) {
+        let start = from + i;
+        let before = line[..start].trim_right();
+        let after = line[start + key.len()..].trim_left();
+        if (before.ends_with("{") || before.ends_with(",")) &&
+           after.starts_with("=") {
+            return Some(line.len() - after[1..].trim_left().len())
+        }
+        from = start + key.len();
+    }
+    None
+}
+
+/// Replaces the quoted string `s` starts with by the one `value` returns for
+/// it, keeping whatever follows, like a comment.
+fn replace_str<G>(s: &str, value: &mut G) -> Option<String>
+    where G: FnMut(&str) -> String
+{
+    let quote = match s.chars().next() {
+        Some(c) if c == '"' || c == '\'' => c,
+        _ => return None,
+    };
+    s[1..].find(quote).map(|end| {
+        format!("{}{}{}{}", quote, value(&s[1..end + 1]), quote,
+                &s[end + 2..])
+    })
+}
+
+fn is_key(line: &str, key: &str) -> bool {
+    line.starts_with(key) && line[key.len()..].trim_left().starts_with("=")
+}
+
+fn parse(manifest: &Path) -> CargoResult<toml::Table> {
+    let contents = try!(File::open(manifest).read_to_string());
+    cargo_toml::parse(&contents, manifest)
+}
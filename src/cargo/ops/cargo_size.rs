@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::cmp::Ordering;
+use std::num;
+use std::old_io::fs::PathExtensions;
+use std::str;
+
+use ops::Compilation;
+use util::{CargoResult, Config, human, process, ChainError};
+
+/// The size of an artifact of a build, broken down by section and by crate.
+#[derive(RustcEncodable, Debug)]
+pub struct ArtifactSize {
+    pub path: String,
+    /// Size of the file
+    pub file_size: u64,
+    /// Sections of the artifact, largest first, as reported by `size`
+    pub sections: Vec<SizeEntry>,
+    /// The total size of the symbols of each crate, largest first, as far as
+    /// the symbols reported by `nm` could be attributed to a crate
+    pub crates: Vec<SizeEntry>,
+}
+
+#[derive(RustcEncodable, Debug)]
+pub struct SizeEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Name under which symbols which aren't Rust symbols, like those of C
+/// libraries or of the linker, are attributed.
+const OTHER: &'static str = "[other]";
+
+/// Analyzes the size of the binaries, dynamic libraries and static libraries
+/// of the package a compilation was for. Artifacts the tools can't analyze,
+/// like those of formats `size` and `nm` don't know, are reported with their
+/// file size only, after a warning.
+pub fn size_report(compilation: &Compilation,
+                   config: &Config) -> CargoResult<Vec<ArtifactSize>> {
+    let libs = compilation.libraries.get(compilation.package.package_id());
+    let libs = libs.map(|libs| &libs[]).unwrap_or(&[]);
+    let mut ret = Vec::new();
+    for path in compilation.binaries.iter().chain(libs.iter()) {
+        if path.filename_str().map_or(false, |f| f.ends_with(".rlib")) {
+            continue
+        }
+        let file_size = try!(path.stat()).size;
+        let shown = path.path_relative_from(config.cwd())
+                        .unwrap_or(path.clone());
+        let analysis = sections(path).and_then(|sections| {
+            crates(path).map(|crates| (sections, crates))
+        });
+        let (sections, crates) = match analysis {
+            Ok(analysis) => analysis,
+            Err(e) => {
+                try!(config.shell().warn(format!("warning: could not analyze \
+                                                  the size of `{}`: {}",
+                                                 shown.display(), e)));
+                (Vec::new(), Vec::new())
+            }
+        };
+        ret.push(ArtifactSize {
+            path: shown.display().to_string(),
+            file_size: file_size,
+            sections: sections,
+            crates: crates,
+        });
+    }
+    Ok(ret)
+}
+
+/// Runs `size -A` on `path`, whose output lists each section with its size
+/// and address.
+fn sections(path: &Path) -> CargoResult<Vec<SizeEntry>> {
+    let output = try!(try!(process("size")).arg("-A").arg(path)
+                                           .exec_with_output());
+    let output = try!(str::from_utf8(&output.output).chain_error(|| {
+        human("`size` printed invalid UTF-8")
+    }));
+    let mut ret = Vec::new();
+    for line in output.lines().skip(2) {
+        let mut parts = line.words();
+        let (name, size) = match (parts.next(), parts.next()) {
+            (Some(name), Some(size)) => (name, size),
+            _ => continue,
+        };
+        if name == "Total" { continue }
+        if let Some(size) = size.parse::<u64>().ok() {
+            if size > 0 {
+                ret.push(SizeEntry { name: name.to_string(), size: size });
+            }
+        }
+    }
+    Ok(sorted(ret))
+}
+
+/// Runs `nm -S` on `path`, whose output lists each symbol with its address,
+/// size, type and name, and sums up the sizes of the symbols by crate.
+fn crates(path: &Path) -> CargoResult<Vec<SizeEntry>> {
+    let output = try!(try!(process("nm")).arg("-S").arg(path)
+                                         .exec_with_output());
+    let output = String::from_utf8_lossy(&output.output).into_owned();
+    let mut sizes = HashMap::new();
+    for line in output.lines() {
+        let parts = line.words().collect::<Vec<_>>();
+        if parts.len() != 4 { continue }
+        let size = match num::from_str_radix::<u64>(parts[1], 16).ok() {
+            Some(size) => size,
+            None => continue,
+        };
+        let krate = crate_of(parts[3]).unwrap_or(OTHER).to_string();
+        match sizes.entry(krate) {
+            Occupied(e) => *e.into_mut() += size,
+            Vacant(e) => { e.insert(size); }
+        }
+    }
+    Ok(sorted(sizes.into_iter().map(|(name, size)| {
+        SizeEntry { name: name, size: size }
+    }).collect()))
+}
+
+/// Returns the crate a mangled Rust symbol like `_ZN3foo3bar17h0123E` is
+/// defined in, which is the first of the length-prefixed parts of its path.
+fn crate_of(symbol: &str) -> Option<&str> {
+    let rest = if symbol.starts_with("__ZN") {
+        &symbol[4..]
+    } else if symbol.starts_with("_ZN") {
+        &symbol[3..]
+    } else {
+        return None
+    };
+    let digits = rest.chars().take_while(|c| c.is_digit(10)).count();
+    let len = match rest[..digits].parse::<usize>().ok() {
+        Some(len) => len,
+        None => return None,
+    };
+    if rest.len() < digits + len { return None }
+    Some(&rest[digits..digits + len])
+}
+
+fn sorted(mut entries: Vec<SizeEntry>) -> Vec<SizeEntry> {
+    entries.sort_by(|a, b| {
+        match b.size.cmp(&a.size) {
+            Ordering::Equal => a.name.cmp(&b.name),
+            ordering => ordering,
+        }
+    });
+    entries
+}
@@ -2,7 +2,7 @@
 use core::Source;
 use sources::PathSource;
 use ops::{self, ExecEngine, ProcessEngine};
-use util::{CargoResult, ProcessError};
+use util::{CargoResult, ProcessError, ChainError, human};
 
 pub struct TestOptions<'a, 'b: 'a> {
     pub compile_opts: ops::CompileOptions<'a, 'b>,
@@ -33,6 +33,10 @@ pub fn run_tests(manifest_path: &Path,
             Some(path) => path,
             None => exe.clone(),
         };
+        try!(compile.check_runnable(&to_display).chain_error(|| {
+            human("the tests were built, but can't be run; use `--no-run` to \
+                   only build them")
+        }));
         let cmd = try!(compile.target_process(exe, &compile.package))
                   .args(test_args);
         try!(config.shell().concise(|shell| {
@@ -0,0 +1,247 @@
+//! Rendering of the resolved dependency graph as a tree for `cargo tree`.
+//!
+//! With the `features` edge kind, the edges of the tree go through the
+//! features which were activated, so the path from the root to a feature of a
+//! package shows which packages and features caused it to be activated.
+
+use std::collections::{HashMap, HashSet};
+
+use core::registry::PackageRegistry;
+use core::resolver::Method;
+use core::{Dependency, Package, PackageId, Resolve, Source};
+use ops;
+use sources::PathSource;
+use util::{CargoResult, ChainError, Config, human};
+
+/// The kinds of edges a tree can be made of.
+#[derive(Copy, PartialEq, Debug)]
+pub enum TreeEdges {
+    /// Edges from packages to their dependencies
+    Normal,
+    /// Edges from packages to the features of their dependencies they
+    /// activate, and from features to their package and the features they
+    /// activate in turn
+    Features,
+}
+
+pub struct TreeOptions<'a, 'b: 'a> {
+    pub config: &'a Config<'b>,
+    pub edges: TreeEdges,
+    pub features: &'a [String],
+    pub no_default_features: bool,
+    pub target: Option<&'a str>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Node<'a> {
+    Package(&'a PackageId),
+    Feature(&'a PackageId, String),
+}
+
+struct Graph<'a> {
+    resolve: &'a Resolve,
+    packages: HashMap<&'a PackageId, &'a Package>,
+    edges: TreeEdges,
+    platform: &'a str,
+    /// Features of the root package activated from the command line, or by
+    /// default
+    requested: Vec<String>,
+}
+
+/// Renders the dependency graph of the package of the manifest at
+/// `manifest_path` as a tree. Dependencies are resolved for the features and
+/// the target given, like for a build with them. Packages and features which
+/// already appeared in the tree are marked with `(*)` instead of being
+/// repeated.
+pub fn tree(manifest_path: &Path,
+            options: &TreeOptions) -> CargoResult<String> {
+    let config = options.config;
+    let mut source = try!(PathSource::for_path(&manifest_path.dir_path(),
+                                               config));
+    try!(source.update());
+    let package = try!(source.root_package());
+
+    let features = options.features.iter().flat_map(|s| {
+        s.split(' ')
+    }).filter(|s| !s.is_empty()).map(|s| s.to_string()).collect::<Vec<_>>();
+    let platform = package.manifest().build_target(options.target)
+                          .unwrap_or(config.rustc_host()).to_string();
+
+    let mut registry = PackageRegistry::new(config);
+    let resolve = try!(ops::resolve_pkg(&mut registry, &package));
+    let method = Method::Required(true, &features,
+                                  !options.no_default_features,
+                                  Some(&platform));
+    let resolve = try!(ops::resolve_with_previous(&mut registry, &package,
+                                                  method, Some(&resolve),
+                                                  None));
+    let ids = resolve.iter().cloned().collect::<Vec<_>>();
+    let packages = try!(registry.get(&ids).chain_error(|| {
+        human("Unable to get packages from source")
+    }));
+
+    let mut requested = features.clone();
+    let has_default = package.summary().features().contains_key("default");
+    if !options.no_default_features && has_default {
+        requested.push("default".to_string());
+    }
+    let graph = Graph {
+        resolve: &resolve,
+        packages: packages.iter().map(|p| (p.package_id(), p)).collect(),
+        edges: options.edges,
+        platform: &platform,
+        requested: requested,
+    };
+
+    let mut out = String::new();
+    let mut visited = HashSet::new();
+    graph.render(Node::Package(resolve.root()), "", "", &mut visited,
+                 &mut out);
+    Ok(out)
+}
+
+impl<'a> Graph<'a> {
+    fn render(&self, node: Node<'a>, prefix: &str, child_prefix: &str,
+              visited: &mut HashSet<Node<'a>>, out: &mut String) {
+        out.push_str(prefix);
+        out.push_str(&self.label(&node));
+        let children = self.children(&node);
+        if !visited.insert(node) {
+            if !children.is_empty() { out.push_str(" (*)"); }
+            out.push('\n');
+            return
+        }
+        out.push('\n');
+
+        let last = children.len();
+        for (i, child) in children.into_iter().enumerate() {
+            let (prefix, next) = if i + 1 == last {
+                ("`-- ", "    ")
+            } else {
+                ("|-- ", "|   ")
+            };
+            self.render(child, &format!("{}{}", child_prefix, prefix),
+                        &format!("{}{}", child_prefix, next), visited, out);
+        }
+    }
+
+    fn label(&self, node: &Node<'a>) -> String {
+        match *node {
+            Node::Package(id) => id.to_string(),
+            Node::Feature(id, ref feature) => {
+                format!("{} feature \"{}\"", id.name(), feature)
+            }
+        }
+    }
+
+    fn children(&self, node: &Node<'a>) -> Vec<Node<'a>> {
+        let mut ret = Vec::new();
+        match *node {
+            Node::Package(id) => {
+                let pkg = self.packages[id];
+                let is_root = id == self.resolve.root();
+                if self.edges == TreeEdges::Features && is_root {
+                    for feature in self.requested.iter() {
+                        ret.push(Node::Feature(id, feature.clone()));
+                    }
+                }
+                for dep in self.active_deps(pkg).into_iter() {
+                    // Optional dependencies are shown under the feature which
+                    // activated them
+                    if self.edges == TreeEdges::Features && dep.is_optional() {
+                        continue
+                    }
+                    ret.extend(self.dep_edges(id, dep).into_iter());
+                }
+            }
+            Node::Feature(id, ref feature) => {
+                let pkg = self.packages[id];
+                ret.push(Node::Package(id));
+                let activates = pkg.summary().features().get(feature);
+                for f in activates.iter().flat_map(|f| f.iter()) {
+                    if f.starts_with("dep:") {
+                        for dep in self.active_deps(pkg).into_iter() {
+                            if dep.name() == &f[4..] {
+                                ret.extend(self.dep_edges(id, dep).into_iter());
+                            }
+                        }
+                        continue
+                    }
+                    let mut parts = f.splitn(1, '/');
+                    let name = parts.next().unwrap().trim_right_matches('?');
+                    let feat = match parts.next() {
+                        Some(feat) => feat,
+                        None => {
+                            ret.push(Node::Feature(id, f.clone()));
+                            continue
+                        }
+                    };
+                    let deps = self.active_deps(pkg);
+                    let dep = deps.iter().find(|d| d.name() == name);
+                    let dep_id = dep.and_then(|d| self.resolved(id, *d));
+                    if let Some(dep_id) = dep_id {
+                        ret.push(Node::Feature(dep_id, feat.to_string()));
+                    }
+                }
+                // A feature named after an optional dependency activates it
+                if activates.is_none() {
+                    for dep in self.active_deps(pkg).into_iter() {
+                        if dep.is_optional() && dep.name() == *feature {
+                            ret.extend(self.dep_edges(id, dep).into_iter());
+                        }
+                    }
+                }
+            }
+        }
+        let mut seen = HashSet::new();
+        ret.retain(|n| seen.insert(n.clone()));
+        ret
+    }
+
+    /// The edges for the dependency `dep` of `id`: the features it activates,
+    /// or just the package depended on if it activates none.
+    fn dep_edges(&self, id: &'a PackageId,
+                 dep: &Dependency) -> Vec<Node<'a>> {
+        let dep_id = match self.resolved(id, dep) {
+            Some(dep_id) => dep_id,
+            None => return Vec::new(),
+        };
+        if self.edges == TreeEdges::Normal {
+            return vec![Node::Package(dep_id)]
+        }
+        let mut ret = dep.features().iter().map(|f| {
+            Node::Feature(dep_id, f.clone())
+        }).collect::<Vec<_>>();
+        let has_default = self.packages[dep_id].summary().features()
+                              .contains_key("default");
+        if dep.uses_default_features() && has_default {
+            ret.push(Node::Feature(dep_id, "default".to_string()));
+        }
+        if ret.is_empty() {
+            ret.push(Node::Package(dep_id));
+        }
+        ret
+    }
+
+    /// The dependencies of `pkg` which are a part of the graph: those for the
+    /// platform, and dev-dependencies of the root package only.
+    fn active_deps(&self, pkg: &'a Package) -> Vec<&'a Dependency> {
+        let id = pkg.package_id();
+        let is_root = id == self.resolve.root();
+        pkg.dependencies().iter().filter(|d| {
+            (d.is_transitive() || is_root) &&
+                d.is_active_for_platform(self.platform) &&
+                self.resolved(id, *d).is_some()
+        }).collect()
+    }
+
+    fn resolved(&self, id: &PackageId,
+                dep: &Dependency) -> Option<&'a PackageId> {
+        let deps = match self.resolve.deps(id) {
+            Some(deps) => deps,
+            None => return None,
+        };
+        let mut deps = deps.filter(|d| d.name() == dep.name());
+        deps.next()
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::old_io::fs::PathExtensions;
+
+use rustc_serialize::{Encodable, Encoder};
+
+use core::{Package, SourceId};
+use ops;
+use util::{CargoResult, CargoError, Config};
+use util::workspace;
+
+/// The outcome of checking a manifest and, for a workspace root, the
+/// manifests of its members.
+#[derive(Debug)]
+pub struct ProjectReport {
+    pub success: bool,
+    pub errors: Vec<Problem>,
+    pub warnings: Vec<Problem>,
+}
+
+#[derive(RustcEncodable, Debug)]
+pub struct Problem {
+    /// The manifest the problem was found in, if one was found at all
+    pub manifest: Option<String>,
+    pub message: String,
+}
+
+/// The report keeps the keys `cargo verify-project` always printed, a
+/// `success` of `"true"` or the first error as `invalid`, so that what parses
+/// them keeps working.
+impl Encodable for ProjectReport {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        s.emit_struct("ProjectReport", 3, |s| {
+            match self.errors.first() {
+                Some(error) if !self.success => {
+                    try!(s.emit_struct_field("invalid", 0, |s| {
+                        error.message.encode(s)
+                    }));
+                }
+                _ => {
+                    try!(s.emit_struct_field("success", 0, |s| {
+                        "true".encode(s)
+                    }));
+                }
+            }
+            try!(s.emit_struct_field("errors", 1, |s| self.errors.encode(s)));
+            s.emit_struct_field("warnings", 2, |s| self.warnings.encode(s))
+        })
+    }
+}
+
+impl ProjectReport {
+    /// A report of a single error which prevented any manifest from being
+    /// checked.
+    pub fn invalid(message: &str) -> ProjectReport {
+        ProjectReport {
+            success: false,
+            errors: vec![Problem {
+                manifest: None,
+                message: message.to_string(),
+            }],
+            warnings: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, manifest: &Path, message: String) {
+        self.success = false;
+        self.errors.push(Problem {
+            manifest: Some(manifest.display().to_string()),
+            message: message,
+        });
+    }
+
+    fn warning(&mut self, manifest: &Path, message: String) {
+        self.warnings.push(Problem {
+            manifest: Some(manifest.display().to_string()),
+            message: message,
+        });
+    }
+}
+
+/// Checks the manifest at `manifest_path` without building anything.
+///
+/// Besides everything which prevents the manifest from being loaded, like
+/// invalid version requirements, this reports unused keys, targets whose
+/// source file is missing and binaries sharing a name. A workspace root has
+/// its members checked as well, and a package inside of a workspace which
+/// isn't one of its members is warned about.
+pub fn verify_project(manifest_path: &Path,
+                      config: &Config) -> CargoResult<ProjectReport> {
+    let mut report = ProjectReport {
+        success: true,
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let dir = manifest_path.dir_path();
+    verify_manifest(manifest_path, config, &mut report);
+
+    match workspace::members(manifest_path) {
+        Ok(Some(members)) => {
+            for member in members.iter().filter(|m| **m != dir) {
+                let manifest = member.join("Cargo.toml");
+                // The root's manifest already warns about missing members
+                if manifest.exists() {
+                    verify_manifest(&manifest, config, &mut report);
+                }
+            }
+        }
+        Ok(None) => {}
+        // Already reported when loading the manifest
+        Err(..) => return Ok(report),
+    }
+
+    match workspace::find_enclosing(&dir) {
+        Ok(Some(root)) => {
+            if try!(workspace::find_root(&dir)).is_none() {
+                report.warning(manifest_path,
+                               format!("the package is inside of the \
+                                        workspace at `{}` but isn't one of \
+                                        its members", root.display()));
+            }
+        }
+        Ok(None) => {}
+        Err(e) => report.error(manifest_path, innermost(&*e)),
+    }
+
+    Ok(report)
+}
+
+fn verify_manifest(manifest: &Path, config: &Config,
+                   report: &mut ProjectReport) {
+    let pkg = match load(manifest, config) {
+        Ok(pkg) => pkg,
+        Err(e) => return report.error(manifest, innermost(&*e)),
+    };
+    for warning in pkg.manifest().warnings().iter() {
+        report.warning(manifest, warning.clone());
+    }
+
+    // Each target shows up once per profile, so only report each file once
+    let mut missing = HashSet::new();
+    for target in pkg.targets().iter() {
+        let path = pkg.root().join(target.src_path());
+        if !path.exists() && missing.insert(path.clone()) {
+            report.error(manifest, format!("target `{}` has no source file \
+                                            at `{}`", target.name(),
+                                           path.display()));
+        }
+    }
+
+    // A binary has exactly one target built with the `compile` profile
+    let mut names = HashSet::new();
+    for target in pkg.targets().iter() {
+        let profile = target.profile();
+        if !target.is_bin() || profile.env() != "compile" ||
+           profile.is_custom_build() {
+            continue
+        }
+        if !names.insert(target.name()) {
+            report.error(manifest, format!("more than one binary is named \
+                                            `{}`", target.name()));
+        }
+    }
+}
+
+fn load(manifest: &Path, config: &Config) -> CargoResult<Package> {
+    let source_id = try!(SourceId::for_path(&manifest.dir_path()));
+    let (pkg, _) = try!(ops::read_package(manifest, &source_id, config));
+    Ok(pkg)
+}
+
+/// The message of the last human error in `err`'s chain, which is the one
+/// describing most precisely what's wrong with the manifest, rather than the
+/// error of a library it's chained to.
+fn innermost(err: &CargoError) -> String {
+    let mut message = err.to_string();
+    let mut err = err;
+    while let Some(cause) = err.cargo_cause() {
+        if cause.is_human() {
+            message = cause.to_string();
+        }
+        err = cause;
+    }
+    message
+}
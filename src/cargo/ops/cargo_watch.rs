@@ -0,0 +1,102 @@
+use std::collections::HashSet;
+use std::old_io::fs::PathExtensions;
+use std::old_io::timer;
+use std::time::Duration;
+
+use core::source::Source;
+use sources::PathSource;
+use util::{CargoResult, CliResult, Config};
+
+/// How often (in milliseconds) the source files are checked for changes.
+const POLL_INTERVAL: i64 = 500;
+
+/// How long (in milliseconds) the source files need to stay unchanged after a
+/// change before the command runs again. Editors and tools like `git
+/// checkout` often write several files, or a file several times, in a row.
+const DEBOUNCE: i64 = 200;
+
+/// The modification times of the source files of the packages watched.
+type Snapshot = Vec<(Path, u64)>;
+
+/// Runs `f`, and runs it again every time a source file of the package at
+/// `manifest_path` or of its path dependencies changes, clearing the screen
+/// in between. The files watched are those whose modification times are
+/// fingerprinted to know when a package has to be rebuilt.
+///
+/// This never returns unless printing fails. Errors of `f` are printed, and
+/// `f` is run again after the next change.
+pub fn watch<F>(manifest_path: &Path, config: &Config,
+                mut f: F) -> CargoResult<()>
+    where F: FnMut() -> CliResult<Option<()>>
+{
+    let mut first = true;
+    loop {
+        if !first {
+            try!(config.shell().out().clear_screen());
+        }
+        first = false;
+
+        if let Err(e) = f() {
+            ::handle_error(e, &mut config.shell());
+        }
+
+        let mut current = snapshot(manifest_path, config);
+        try!(config.shell().status("Watching",
+                                   "for changes, press Ctrl-C to stop"));
+        loop {
+            timer::sleep(Duration::milliseconds(POLL_INTERVAL));
+            let next = snapshot(manifest_path, config);
+            if next != current { current = next; break }
+        }
+        loop {
+            timer::sleep(Duration::milliseconds(DEBOUNCE));
+            let next = snapshot(manifest_path, config);
+            if next == current { break }
+            current = next;
+        }
+    }
+}
+
+fn snapshot(manifest_path: &Path, config: &Config) -> Snapshot {
+    // While a manifest is broken, the files of the packages aren't known, so
+    // only the root manifest is watched until it changes
+    source_files(manifest_path, config).unwrap_or_else(|_| {
+        vec![(manifest_path.clone(), mtime(manifest_path))]
+    })
+}
+
+fn source_files(manifest_path: &Path,
+                config: &Config) -> CargoResult<Snapshot> {
+    // The sources are loaded again each time, as the manifests may have
+    // changed which packages and files there are. Path dependencies outside
+    // of the directory of the root package are sources of their own.
+    let mut dirs = vec![manifest_path.dir_path()];
+    let mut visited = HashSet::new();
+    let mut ret = Vec::new();
+    while let Some(dir) = dirs.pop() {
+        if !visited.insert(dir.clone()) { continue }
+        let mut src = try!(PathSource::for_path(&dir, config));
+        try!(src.update());
+        for pkg in try!(src.read_packages()).iter() {
+            for file in try!(src.list_files(pkg)).into_iter() {
+                let time = mtime(&file);
+                ret.push((file, time));
+            }
+            for dep in pkg.dependencies().iter() {
+                if !dep.source_id().is_path() { continue }
+                match dep.source_id().url().to_file_path() {
+                    Ok(path) => dirs.push(path),
+                    Err(..) => {}
+                }
+            }
+        }
+    }
+    ret.sort_by(|a, b| a.0.as_vec().cmp(b.0.as_vec()));
+    ret.dedup();
+    Ok(ret)
+}
+
+/// Files removed in the meantime count as changed as well.
+fn mtime(file: &Path) -> u64 {
+    file.stat().map(|s| s.modified).unwrap_or(0)
+}
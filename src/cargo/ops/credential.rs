@@ -0,0 +1,414 @@
+//! Storage of registry API tokens.
+//!
+//! By default tokens are saved in plain text in `$CARGO_HOME/credentials` by
+//! `cargo login`, a file which is read along with the configuration files.
+//! Alternatively the `registry.credential-provider` key can name a program
+//! which is responsible for storing tokens instead:
+//!
+//! ```toml
+//! [registry]
+//! credential-provider = ["pass", "cargo-credential"]
+//! ```
+//!
+//! Cargo appends one of the actions `get`, `store` or `erase` to the command
+//! line and sets `CARGO_REGISTRY_INDEX_URL` to the index of the registry the
+//! token belongs to. For `get` the provider prints the token on stdout, for
+//! `store` the token is written to the provider's stdin.
+//!
+//! The special provider `cargo:keychain` uses the keychain of the operating
+//! system: `security` on OS X, the Credential Manager on Windows and
+//! `secret-tool` elsewhere. When there is no keychain available, tokens are
+//! kept in the credentials file.
+
+use std::collections::BTreeMap;
+use std::old_io::fs::{self, PathExtensions, File};
+use std::old_io::process::{Command, ProcessOutput};
+use std::old_io;
+
+use toml;
+
+use util::{CargoResult, ChainError, Config, human};
+use util::config::ConfigValue;
+use util::toml as cargo_toml;
+
+/// Name of the built-in provider using the keychain of the system.
+const KEYCHAIN: &'static str = "cargo:keychain";
+
+/// Service name under which tokens are saved in the system keychain.
+const SERVICE: &'static str = "cargo-registry";
+
+/// Where tokens are saved.
+pub enum Provider {
+    /// The `registry.token` key of `$CARGO_HOME/credentials`
+    File,
+    /// The keychain of the operating system
+    Keychain,
+    /// An external program and its leading arguments
+    Process(Vec<String>),
+}
+
+impl Provider {
+    pub fn from_config(config: &Config) -> CargoResult<Provider> {
+        let key = "registry.credential-provider";
+        let cmd = match try!(config.get(key)) {
+            Some(ConfigValue::List(list, _)) => {
+                list.into_iter().map(|(s, _)| s).collect::<Vec<_>>()
+            }
+            Some(ConfigValue::String(s, _)) => {
+                s.split(' ').filter(|s| !s.is_empty())
+                 .map(|s| s.to_string()).collect()
+            }
+            Some(val) => return config.expected("list", key, val),
+            None => return Ok(Provider::File),
+        };
+        if cmd.is_empty() {
+            return Err(human(format!("`{}` must name a program", key)))
+        }
+        if cmd.len() == 1 && cmd[0] == KEYCHAIN {
+            return Ok(Provider::Keychain)
+        }
+        Ok(Provider::Process(cmd))
+    }
+}
+
+/// Returns the token saved for the registry at `index`, if any.
+pub fn get(config: &Config, index: &str) -> CargoResult<Option<String>> {
+    match try!(Provider::from_config(config)) {
+        Provider::File => get_file(config),
+        // A token which isn't in the keychain may still have been saved
+        // before switching to it
+        Provider::Keychain => match keychain("get", index, None) {
+            Some(Ok(Some(token))) => Ok(Some(token)),
+            _ => get_file(config),
+        },
+        Provider::Process(cmd) => {
+            let output = try!(run(&cmd, "get", index, None));
+            Ok(token_from(&output))
+        }
+    }
+}
+
+/// Returns the token saved by `cargo login`, looking at the files it saves
+/// tokens in only, not at the whole configuration.
+fn get_file(config: &Config) -> CargoResult<Option<String>> {
+    match try!(read_token(&credentials_path(config))) {
+        Some(token) => Ok(Some(token)),
+        None => read_token(&config.home().join("config")),
+    }
+}
+
+fn read_token(path: &Path) -> CargoResult<Option<String>> {
+    if !path.is_file() { return Ok(None) }
+    let contents = try!(File::open(path).read_to_string());
+    let toml = try!(cargo_toml::parse(&contents, path));
+    Ok(match toml.get("registry") {
+        Some(&toml::Value::Table(ref registry)) => match registry.get("token") {
+            Some(&toml::Value::String(ref token)) => Some(token.clone()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn token_from(output: &ProcessOutput) -> Option<String> {
+    let token = String::from_utf8_lossy(&output.output).trim().to_string();
+    if token.is_empty() { None } else { Some(token) }
+}
+
+/// Saves `token` as the token of the registry at `index`.
+pub fn store(config: &Config, index: &str, token: &str) -> CargoResult<()> {
+    match try!(Provider::from_config(config)) {
+        Provider::File => store_file(config, token),
+        Provider::Keychain => match keychain("store", index, Some(token)) {
+            Some(Ok(..)) => Ok(()),
+            Some(Err(err)) => {
+                Err(human(format!("failed to save the token in the system \
+                                   keychain:\n{}", err)))
+            }
+            None => {
+                try!(config.shell().warn("warning: no system keychain is \
+                                          available, saving the token in the \
+                                          configuration file instead"));
+                store_file(config, token)
+            }
+        },
+        Provider::Process(cmd) => {
+            try!(run(&cmd, "store", index, Some(token)));
+            Ok(())
+        }
+    }
+}
+
+/// Removes the token of the registry at `index`, returning whether there
+/// was one to remove as far as that can be told.
+pub fn erase(config: &Config, index: &str) -> CargoResult<bool> {
+    match try!(Provider::from_config(config)) {
+        Provider::File => erase_file(config),
+        Provider::Keychain => {
+            let erased = match keychain("erase", index, None) {
+                Some(Ok(..)) => true,
+                _ => false,
+            };
+            Ok(try!(erase_file(config)) || erased)
+        }
+        Provider::Process(cmd) => {
+            try!(run(&cmd, "erase", index, None));
+            Ok(true)
+        }
+    }
+}
+
+/// Describes where the token which is used when no token is saved comes
+/// from, like the environment or a project's configuration, if there is one.
+/// `erase` doesn't change those.
+pub fn unsaved_source(config: &Config) -> CargoResult<Option<String>> {
+    let path = match try!(config.get_string("registry.token")) {
+        Some((_, path)) => path,
+        None => return Ok(None),
+    };
+    if !path.is_file() { return Ok(Some(path.display().to_string())) }
+    // The configuration was loaded before the token may have been erased
+    if try!(read_token(&path)).is_none() { return Ok(None) }
+    Ok(Some(format!("`{}`", path.display())))
+}
+
+/// The file `cargo login` saves tokens in. It is kept apart from the
+/// configuration so that it can be readable by its owner only.
+fn credentials_path(config: &Config) -> Path {
+    config.home().join("credentials")
+}
+
+fn erase_file(config: &Config) -> CargoResult<bool> {
+    // Older versions of cargo saved tokens in the configuration file itself
+    let legacy = try!(edit_token(&config.home().join("config"), None));
+    Ok(try!(edit_token(&credentials_path(config), None)) || legacy)
+}
+
+fn store_file(config: &Config, token: &str) -> CargoResult<()> {
+    let path = credentials_path(config);
+    try!(edit_token(&path, Some(token)));
+    try!(fs::chmod(&path, old_io::USER_READ | old_io::USER_WRITE));
+    Ok(())
+}
+
+/// Sets or removes the `registry.token` key of the file at `path`, leaving the
+/// rest of the file alone, and returns whether it had a token before.
+///
+/// Only the file itself is read: the merged configuration also holds values
+/// of the project, the environment and `--config`, none of which belong here.
+fn edit_token(path: &Path, token: Option<&str>) -> CargoResult<bool> {
+    let contents = if path.exists() {
+        try!(File::open(path).read_to_string())
+    } else if token.is_none() {
+        return Ok(false)
+    } else {
+        String::new()
+    };
+    let mut toml = try!(cargo_toml::parse(&contents, path));
+    let mut registry = match toml.remove("registry") {
+        Some(toml::Value::Table(table)) => table,
+        Some(..) => {
+            return Err(human(format!("`registry` in `{}` is not a table",
+                                     path.display())))
+        }
+        None => BTreeMap::new(),
+    };
+    let had_token = match token {
+        Some(token) => {
+            let token = toml::Value::String(token.to_string());
+            registry.insert("token".to_string(), token).is_some()
+        }
+        None => registry.remove("token").is_some(),
+    };
+    if token.is_none() && !had_token { return Ok(false) }
+    if !registry.is_empty() {
+        toml.insert("registry".to_string(), toml::Value::Table(registry));
+    }
+
+    try!(fs::mkdir_recursive(&path.dir_path(), old_io::USER_DIR));
+    let mut out = try!(File::create(path));
+    try!(out.write_str(&toml::Value::Table(toml).to_string()));
+    Ok(had_token)
+}
+
+fn run(cmd: &[String], action: &str, index: &str,
+       input: Option<&str>) -> CargoResult<ProcessOutput> {
+    let mut command = Command::new(&cmd[0]);
+    command.args(&cmd[1..]).arg(action)
+           .env("CARGO_REGISTRY_INDEX_URL", index);
+    let desc = format!("{} {}", cmd.connect(" "), action);
+    let mut child = try!(command.spawn().chain_error(|| {
+        human(format!("failed to run credential provider `{}`", desc))
+    }));
+    if let Some(input) = input {
+        try!(child.stdin.as_mut().unwrap().write_str(input));
+    }
+    drop(child.stdin.take());
+    let output = try!(child.wait_with_output());
+    if !output.status.success() {
+        return Err(human(format!("credential provider `{}` failed ({}):\n{}",
+                                 desc, output.status,
+                                 String::from_utf8_lossy(&output.error))))
+    }
+    Ok(output)
+}
+
+/// The outcome of `action` on the system keychain: the token found by `get`,
+/// or the error of the keychain. `None` if there is no keychain on this
+/// system.
+type KeychainResult = Option<Result<Option<String>, String>>;
+
+/// Runs the system keychain tool for `action`.
+#[cfg(unix)]
+fn keychain(action: &str, index: &str, token: Option<&str>) -> KeychainResult {
+    let (mut command, input) = keychain_command(action, index, token);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(..) => return None,
+    };
+    if let Some(input) = input {
+        let _ = child.stdin.as_mut().unwrap().write_str(&input);
+    }
+    drop(child.stdin.take());
+    let output = match child.wait_with_output() {
+        Ok(output) => output,
+        Err(..) => return None,
+    };
+    if output.status.success() {
+        Some(Ok(token_from(&output)))
+    } else {
+        Some(Err(String::from_utf8_lossy(&output.error).into_owned()))
+    }
+}
+
+/// Returns the command performing `action` on the keychain, and what to write
+/// to its stdin. The token is never passed as an argument, where any user
+/// listing processes could see it.
+#[cfg(target_os = "macos")]
+fn keychain_command(action: &str, index: &str,
+                    token: Option<&str>) -> (Command, Option<String>) {
+    let mut cmd = Command::new("security");
+    match action {
+        "get" => { cmd.arg("find-generic-password").arg("-w"); }
+        "store" => {
+            // `security -i` reads the command to run from stdin instead
+            let line = format!("add-generic-password -U -s {} -a {} -w {}\n",
+                               quote(SERVICE), quote(index),
+                               quote(token.unwrap_or("")));
+            cmd.arg("-i");
+            return (cmd, Some(line))
+        }
+        _ => { cmd.arg("delete-generic-password"); }
+    }
+    cmd.arg("-s").arg(SERVICE).arg("-a").arg(index);
+    (cmd, None)
+}
+
+/// Quotes an argument of a command read by `security -i`.
+#[cfg(target_os = "macos")]
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace("\\", "\\\\").replace("\"", "\\\""))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn keychain_command(action: &str, index: &str,
+                    token: Option<&str>) -> (Command, Option<String>) {
+    let mut cmd = Command::new("secret-tool");
+    match action {
+        "get" => { cmd.arg("lookup"); }
+        "store" => {
+            cmd.arg("store").arg("--label")
+               .arg(format!("cargo registry token for {}", index));
+        }
+        _ => { cmd.arg("clear"); }
+    }
+    cmd.arg("service").arg(SERVICE).arg("index").arg(index);
+    (cmd, token.map(|s| s.to_string()))
+}
+
+/// Uses the Windows Credential Manager, where tokens are generic credentials
+/// named `cargo-registry:<index>`.
+#[cfg(windows)]
+fn keychain(action: &str, index: &str, token: Option<&str>) -> KeychainResult {
+    use std::old_io::IoError;
+    use std::ptr;
+    use std::slice;
+    use libc::{c_int, c_void};
+
+    #[repr(C)]
+    struct Credential {
+        flags: u32,
+        kind: u32,
+        target_name: *mut u16,
+        comment: *mut u16,
+        last_written: [u32; 2],
+        blob_size: u32,
+        blob: *mut u8,
+        persist: u32,
+        attribute_count: u32,
+        attributes: *mut c_void,
+        target_alias: *mut u16,
+        user_name: *mut u16,
+    }
+
+    const CRED_TYPE_GENERIC: u32 = 1;
+    const CRED_PERSIST_LOCAL_MACHINE: u32 = 2;
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn CredReadW(target: *const u16, kind: u32, flags: u32,
+                     cred: *mut *mut Credential) -> c_int;
+        fn CredWriteW(cred: *const Credential, flags: u32) -> c_int;
+        fn CredDeleteW(target: *const u16, kind: u32, flags: u32) -> c_int;
+        fn CredFree(buf: *mut c_void);
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.utf16_units().chain(Some(0).into_iter()).collect()
+    }
+
+    let mut target = wide(&format!("{}:{}", SERVICE, index));
+    let ok = unsafe {
+        match action {
+            "get" => {
+                let mut cred = ptr::null_mut();
+                if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0,
+                             &mut cred) == 0 {
+                    return Some(Err(IoError::last_error().to_string()))
+                }
+                let token = {
+                    let blob = slice::from_raw_buf(&((*cred).blob as *const u8),
+                                                   (*cred).blob_size as usize);
+                    String::from_utf8_lossy(blob).into_owned()
+                };
+                CredFree(cred as *mut c_void);
+                return Some(Ok(Some(token)))
+            }
+            "store" => {
+                let token = token.unwrap_or("");
+                let mut user = wide(index);
+                let cred = Credential {
+                    flags: 0,
+                    kind: CRED_TYPE_GENERIC,
+                    target_name: target.as_mut_ptr(),
+                    comment: ptr::null_mut(),
+                    last_written: [0, 0],
+                    blob_size: token.len() as u32,
+                    blob: token.as_ptr() as *mut u8,
+                    persist: CRED_PERSIST_LOCAL_MACHINE,
+                    attribute_count: 0,
+                    attributes: ptr::null_mut(),
+                    target_alias: ptr::null_mut(),
+                    user_name: user.as_mut_ptr(),
+                };
+                CredWriteW(&cred, 0)
+            }
+            _ => CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0),
+        }
+    };
+    if ok == 0 {
+        Some(Err(IoError::last_error().to_string()))
+    } else {
+        Some(Ok(None))
+    }
+}
@@ -1,21 +1,48 @@
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry::{Occupied, Vacant};
 use std::old_io::File;
 
 use rustc_serialize::{Encodable, Decodable};
 use toml::{self, Encoder, Value};
 
-use core::{Resolve, resolver, Package, SourceId};
-use util::{CargoResult, ChainError, human};
+use core::{Resolve, resolver, Package, PackageId, SourceId};
+use util::{CargoResult, ChainError, Config, human};
 use util::toml as cargo_toml;
+use util::workspace;
 
+/// Returns the path of the lockfile of `pkg`. The members of a workspace share
+/// the lockfile at the root of the workspace.
+pub fn lockfile_path(pkg: &Package) -> CargoResult<Path> {
+    let root = try!(workspace::find_root(&pkg.root()));
+    Ok(root.unwrap_or(pkg.root()).join("Cargo.lock"))
+}
+
+/// Loads the lockfile of `pkg`, which is the lockfile of the whole workspace
+/// for a workspace member.
 pub fn load_pkg_lockfile(pkg: &Package) -> CargoResult<Option<Resolve>> {
-    let lockfile = pkg.manifest_path().dir_path().join("Cargo.lock");
+    let lockfile = try!(lockfile_path(pkg));
     let source_id = pkg.package_id().source_id();
     load_lockfile(&lockfile, source_id).chain_error(|| {
         human(format!("failed to parse lock file at: {}", lockfile.display()))
     })
 }
 
+/// Loads the lockfile of `pkg` like `load_pkg_lockfile`, but only to guide a
+/// fresh resolution, which is why merge conflicts in it are accepted.
+pub fn load_pkg_lockfile_guide(pkg: &Package) -> CargoResult<Option<Resolve>> {
+    let lockfile = try!(lockfile_path(pkg));
+    let source_id = pkg.package_id().source_id();
+    read_lockfile(&lockfile, source_id, true).chain_error(|| {
+        human(format!("failed to parse lock file at: {}", lockfile.display()))
+    })
+}
+
 pub fn load_lockfile(path: &Path, sid: &SourceId) -> CargoResult<Option<Resolve>> {
+    read_lockfile(path, sid, false)
+}
+
+fn read_lockfile(path: &Path, sid: &SourceId, guide: bool)
+                 -> CargoResult<Option<Resolve>> {
     // If there is no lockfile, return none.
     let mut f = match File::open(path) {
         Ok(f) => f,
@@ -24,14 +51,73 @@ pub fn load_lockfile(path: &Path, sid: &SourceId) -> CargoResult<Option<Resolve>
 
     let s = try!(f.read_to_string());
 
-    let table = toml::Value::Table(try!(cargo_toml::parse(&s, path)));
+    // A lockfile with merge conflicts is read as the union of both sides of
+    // the conflict, which guides a fresh resolution to the versions either
+    // side already locked, after which the lockfile is rewritten without
+    // them. The union isn't a resolve anything else can rely on though.
+    match split_conflicts(&s) {
+        Some(..) if !guide => {
+            Err(human("the lock file has merge conflicts, which building the \
+                       project or running `cargo generate-lockfile` resolves"))
+        }
+        Some((ours, theirs)) => {
+            let mut resolve = try!(parse_lockfile(&ours, path, sid));
+            resolve.merge(&try!(parse_lockfile(&theirs, path, sid)));
+            Ok(Some(resolve))
+        }
+        None => Ok(Some(try!(parse_lockfile(&s, path, sid)))),
+    }
+}
+
+fn parse_lockfile(s: &str, path: &Path, sid: &SourceId)
+                  -> CargoResult<Resolve> {
+    let table = toml::Value::Table(try!(cargo_toml::parse(s, path)));
     let mut d = toml::Decoder::new(table);
     let v: resolver::EncodableResolve = try!(Decodable::decode(&mut d));
-    Ok(Some(try!(v.to_resolve(sid))))
+    v.to_resolve(sid)
 }
 
+/// Splits a lockfile containing git's merge conflict markers into the two
+/// sides of the conflicts, or returns `None` if there are no conflicts.
+///
+/// The common ancestor of a conflict, as written with `merge.conflictstyle`
+/// set to `diff3`, is dropped.
+fn split_conflicts(s: &str) -> Option<(String, String)> {
+    #[derive(PartialEq)]
+    enum Side { Both, Ours, Base, Theirs }
+
+    let mut side = Side::Both;
+    let mut conflicts = false;
+    let mut ours = String::new();
+    let mut theirs = String::new();
+    for line in s.lines() {
+        if line.starts_with("<<<<<<<") && side == Side::Both {
+            side = Side::Ours;
+            conflicts = true;
+        } else if line.starts_with("|||||||") && side == Side::Ours {
+            side = Side::Base;
+        } else if line.starts_with("=======") && side != Side::Both {
+            side = Side::Theirs;
+        } else if line.starts_with(">>>>>>>") && side == Side::Theirs {
+            side = Side::Both;
+        } else {
+            if side != Side::Theirs && side != Side::Base {
+                ours.push_str(line);
+                ours.push_str("\n");
+            }
+            if side != Side::Ours && side != Side::Base {
+                theirs.push_str(line);
+                theirs.push_str("\n");
+            }
+        }
+    }
+    if conflicts {Some((ours, theirs))} else {None}
+}
+
+/// Writes the lockfile of `pkg`. For a workspace member, `resolve` must be the
+/// resolve of the whole workspace, as returned by `ops::resolve_for_lockfile`.
 pub fn write_pkg_lockfile(pkg: &Package, resolve: &Resolve) -> CargoResult<()> {
-    let loc = pkg.root().join("Cargo.lock");
+    let loc = try!(lockfile_path(pkg));
     write_lockfile(&loc, resolve)
 }
 
@@ -69,6 +155,66 @@ pub fn write_lockfile(dst: &Path, resolve: &Resolve) -> CargoResult<()> {
     Ok(())
 }
 
+/// Prints which packages were added to, updated in or removed from the lock
+/// file when `resolve` replaces `previous`, so changes to the lock file are
+/// visible rather than silent.
+pub fn print_lockfile_changes(config: &Config, previous: &Resolve,
+                              resolve: &Resolve) -> CargoResult<()> {
+    // Packages are paired up by name and source, so that a package whose
+    // version changed is reported as updated rather than removed and added
+    let mut changes = BTreeMap::new();
+    let removed = previous.iter().filter(|a| {
+        !resolve.iter().any(|b| same(*a, b))
+    });
+    let added = resolve.iter().filter(|a| {
+        !previous.iter().any(|b| same(*a, b))
+    });
+    for (id, is_added) in removed.map(|id| (id, false))
+                                 .chain(added.map(|id| (id, true))) {
+        if id == resolve.root() || id == previous.root() { continue }
+        let key = (id.name().to_string(), id.source_id().url().to_string());
+        let entry = match changes.entry(key) {
+            Occupied(entry) => entry.into_mut(),
+            Vacant(entry) => entry.insert((Vec::new(), Vec::new())),
+        };
+        if is_added {entry.1.push(id)} else {entry.0.push(id)}
+    }
+
+    let mut shell = config.shell();
+    for (_, (removed, added)) in changes.into_iter() {
+        if removed.len() == 1 && added.len() == 1 {
+            let (old, new) = (removed[0], added[0]);
+            // Git dependencies may move to another revision of the same
+            // version, in which case the revisions are printed instead
+            let msg = if old.version() == new.version() {
+                format!("{} v{} ({} -> {})", old.name(), old.version(),
+                        old.source_id(), new.source_id())
+            } else {
+                format!("{} v{} -> v{}", old.name(), old.version(),
+                        new.version())
+            };
+            try!(shell.status("Updating", msg));
+            continue
+        }
+        for id in removed.iter() {
+            try!(shell.status("Removing",
+                              format!("{} v{}", id.name(), id.version())));
+        }
+        for id in added.iter() {
+            try!(shell.status("Adding",
+                              format!("{} v{}", id.name(), id.version())));
+        }
+    }
+    Ok(())
+}
+
+// Package ids of git dependencies are equal regardless of their revision, but
+// a change of revision is still a change of the lock file
+fn same(a: &PackageId, b: &PackageId) -> bool {
+    a == b && (!a.source_id().is_git() ||
+               a.source_id().precise() == b.source_id().precise())
+}
+
 fn emit_package(dep: &toml::Table, out: &mut String) {
     out.push_str(&format!("name = {}\n", lookup(dep, "name")));
     out.push_str(&format!("version = {}\n", lookup(dep, "version")));
@@ -1,40 +1,82 @@
+pub use self::cargo_audit::{audit, audit_on_build, Advisory};
+pub use self::cargo_cache::{gc, verify_cache, CacheOptions};
 pub use self::cargo_clean::{clean, CleanOptions};
-pub use self::cargo_compile::{compile, compile_pkg, CompileOptions};
+pub use self::cargo_compile::{compile, compile_pkg, compile_workspace};
+pub use self::cargo_compile::compile_pkg_with_overrides;
+pub use self::cargo_compile::CompileOptions;
 pub use self::cargo_read_manifest::{read_manifest,read_package,read_packages};
 pub use self::cargo_rustc::{compile_targets, Compilation, Layout, Kind, rustc_version};
 pub use self::cargo_rustc::{Context, LayoutProxy};
 pub use self::cargo_rustc::Platform;
 pub use self::cargo_rustc::{BuildOutput, BuildConfig, TargetConfig};
 pub use self::cargo_rustc::{CommandType, CommandPrototype, ExecEngine, ProcessEngine};
+pub use self::cargo_rustc::{FutureIncompatReport, FutureIncompatPackage};
+pub use self::cargo_rustc::load_future_incompat;
 pub use self::cargo_run::run;
 pub use self::cargo_new::{new, NewOptions, VersionControl};
 pub use self::cargo_doc::{doc, DocOptions};
 pub use self::cargo_generate_lockfile::{generate_lockfile};
 pub use self::cargo_generate_lockfile::{update_lockfile};
 pub use self::cargo_generate_lockfile::UpdateOptions;
-pub use self::lockfile::{load_lockfile, load_pkg_lockfile};
+pub use self::lockfile::{load_lockfile, load_pkg_lockfile, lockfile_path};
+pub use self::lockfile::load_pkg_lockfile_guide;
 pub use self::lockfile::{write_lockfile, write_pkg_lockfile};
+pub use self::lockfile::print_lockfile_changes;
 pub use self::cargo_test::{run_tests, run_benches, TestOptions};
-pub use self::cargo_package::package;
+pub use self::cargo_package::{package, package_workspace, PackageOpts};
+pub use self::cargo_package::{check_not_dirty, is_tracked};
+pub use self::cargo_fix::{fix, FixOptions};
 pub use self::registry::{publish, registry_configuration, RegistryConfig};
-pub use self::registry::{registry_login, search, http_proxy, http_handle};
-pub use self::registry::{modify_owners, yank, OwnersOptions};
+pub use self::registry::{publish_workspace, PublishOpts};
+pub use self::registry::{registry_login, registry_logout, search};
+pub use self::registry::named_registry_index;
+pub use self::registry::{http_proxy, http_handle, registry_auth_token};
+pub use self::registry::{http_tls, needs_custom_http, HttpTls};
+pub use self::registry::{http_timeouts, HttpTimeout};
+pub use self::registry::{modify_owners, yank, OwnersOptions, YankOptions};
 pub use self::cargo_fetch::{fetch};
+pub use self::cargo_info::{info, InfoOptions, CrateInfo, CrateDependency};
+pub use self::cargo_licenses::licenses;
+pub use self::cargo_outdated::{outdated, OutdatedOptions, OutdatedDependency};
 pub use self::cargo_pkgid::pkgid;
-pub use self::resolve::{resolve_pkg, resolve_with_previous};
+pub use self::cargo_report::future_incompat_report;
+pub use self::cargo_sbom::{sbom, SbomFormat};
+pub use self::cargo_script::script;
+pub use self::cargo_set_version::{set_version, SetVersionOptions};
+pub use self::cargo_size::{size_report, ArtifactSize, SizeEntry};
+pub use self::cargo_tree::{tree, TreeOptions, TreeEdges};
+pub use self::cargo_verify_project::{verify_project, ProjectReport, Problem};
+pub use self::cargo_watch::watch;
+pub use self::resolve::{resolve_pkg, resolve_with_previous, resolve_with_trace};
+pub use self::resolve::{resolve_for_lockfile, resolve_manifest};
 
+mod cargo_audit;
+mod cargo_cache;
 mod cargo_clean;
 mod cargo_compile;
 mod cargo_doc;
 mod cargo_fetch;
+mod cargo_fix;
 mod cargo_generate_lockfile;
+mod cargo_info;
+mod cargo_licenses;
 mod cargo_new;
+mod cargo_outdated;
 mod cargo_package;
 mod cargo_pkgid;
 mod cargo_read_manifest;
+mod cargo_report;
 mod cargo_run;
+mod cargo_sbom;
+mod cargo_script;
+mod cargo_set_version;
+mod cargo_size;
 mod cargo_rustc;
 mod cargo_test;
+mod cargo_tree;
+mod cargo_verify_project;
+mod cargo_watch;
+mod credential;
 mod lockfile;
 mod registry;
 mod resolve;
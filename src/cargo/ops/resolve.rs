@@ -1,26 +1,105 @@
 use std::collections::{HashMap, HashSet};
 
-use core::{Package, PackageId, SourceId};
+use core::{Package, PackageId, Source, SourceId};
 use core::registry::PackageRegistry;
 use core::resolver::{self, Resolve, Method};
 use ops;
-use util::CargoResult;
+use sources::PathSource;
+use util::{CargoResult, ChainError, internal};
+use util::workspace;
 
 /// Resolve all dependencies for the specified `package` using the previous
 /// lockfile as a guide if present.
 ///
 /// This function will also generate a write the result of resolution as a new
-/// lockfile.
+/// lockfile, printing how it differs from the previous one.
 pub fn resolve_pkg(registry: &mut PackageRegistry, package: &Package)
                    -> CargoResult<Resolve> {
-    let prev = try!(ops::load_pkg_lockfile(package));
-    let resolve = try!(resolve_with_previous(registry, package,
-                                             Method::Everything,
-                                             prev.as_ref(), None));
-    try!(ops::write_pkg_lockfile(package, &resolve));
+    let prev = try!(ops::load_pkg_lockfile_guide(package));
+    let (resolve, lockfile) = try!(resolve_for_lockfile(registry, package,
+                                                        prev.as_ref(), None,
+                                                        None));
+    if let Some(ref prev) = prev {
+        try!(ops::print_lockfile_changes(registry.config(), prev, &lockfile));
+    }
+    try!(ops::write_pkg_lockfile(package, &lockfile));
     Ok(resolve)
 }
 
+/// Resolves the dependencies of the package of the manifest at
+/// `manifest_path` like `resolve_pkg`, returning the package along with its
+/// resolve.
+///
+/// Unless `dev_deps` is set, the resolve is narrowed down to the packages a
+/// build of the package with its default features uses, leaving out its
+/// dev-dependencies.
+pub fn resolve_manifest(registry: &mut PackageRegistry, manifest_path: &Path,
+                        dev_deps: bool) -> CargoResult<(Package, Resolve)> {
+    let mut source = try!(PathSource::for_path(&manifest_path.dir_path(),
+                                               registry.config()));
+    try!(source.update());
+    let package = try!(source.root_package());
+
+    let resolve = try!(resolve_pkg(registry, &package));
+    if dev_deps { return Ok((package, resolve)) }
+    let method = Method::Required(false, &[], true, None);
+    let resolve = try!(resolve_with_previous(registry, &package, method,
+                                             Some(&resolve), None));
+    Ok((package, resolve))
+}
+
+/// Resolves `package` like `resolve_with_trace`, returning its resolve along
+/// with the resolve to write to its lockfile.
+///
+/// The two are the same unless `package` is a member of a workspace. The
+/// workspace shares one lockfile, so every member is resolved, and the
+/// lockfile is the union of their resolves rooted at the root of the
+/// workspace.
+pub fn resolve_for_lockfile<'a>(registry: &mut PackageRegistry,
+                                package: &Package,
+                                previous: Option<&'a Resolve>,
+                                to_avoid: Option<&HashSet<&'a PackageId>>,
+                                mut trace: Option<&mut Vec<String>>)
+                                -> CargoResult<(Resolve, Resolve)> {
+    let root = match try!(workspace::find_root(&package.root())) {
+        Some(root) => root,
+        None => {
+            let resolve = try!(resolve_with_trace(registry, package,
+                                                  Method::Everything,
+                                                  previous, to_avoid, trace));
+            return Ok((resolve.clone(), resolve))
+        }
+    };
+
+    let members = try!(workspace::packages(&root.join("Cargo.toml"),
+                                           registry.config()));
+    let mut ours = None;
+    let mut lockfile: Option<Resolve> = None;
+    for member in members.iter() {
+        let member = if member.package_id() == package.package_id() {
+            package
+        } else {
+            member
+        };
+        let trace = trace.as_mut().map(|t| &mut **t);
+        let resolve = try!(resolve_with_trace(registry, member,
+                                              Method::Everything, previous,
+                                              to_avoid, trace));
+        lockfile = Some(match lockfile {
+            Some(mut lockfile) => { lockfile.extend(&resolve); lockfile }
+            None => resolve.clone(),
+        });
+        if member.package_id() == package.package_id() {
+            ours = Some(resolve);
+        }
+    }
+    let ours = try!(ours.chain_error(|| {
+        internal(format!("`{}` is not a member of the workspace at `{}`",
+                         package, root.display()))
+    }));
+    Ok((ours, lockfile.unwrap()))
+}
+
 /// Resolve all dependencies for a package using an optional previous instance
 /// of resolve to guide the resolution process.
 ///
@@ -36,6 +115,18 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
                                  previous: Option<&'a Resolve>,
                                  to_avoid: Option<&HashSet<&'a PackageId>>)
                                  -> CargoResult<Resolve> {
+    resolve_with_trace(registry, package, method, previous, to_avoid, None)
+}
+
+/// Like `resolve_with_previous`, but also records why the resolver selected
+/// or rejected each candidate version in `trace`, if given.
+pub fn resolve_with_trace<'a>(registry: &mut PackageRegistry,
+                              package: &Package,
+                              method: Method,
+                              previous: Option<&'a Resolve>,
+                              to_avoid: Option<&HashSet<&'a PackageId>>,
+                              trace: Option<&mut Vec<String>>)
+                              -> CargoResult<Resolve> {
     let root = package.package_id().source_id().clone();
     try!(registry.add_sources(&[root]));
 
@@ -83,6 +174,14 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
             //    ranges. To deal with this, we only actually lock a dependency
             //    to the previously resolved version if the dependency listed
             //    still matches the locked version.
+            //
+            //    Dependencies which are new to the manifest, or whose
+            //    requirement no longer matches their locked version, are
+            //    locked to any package of the previous resolve which
+            //    satisfies them. Only if there is none does the source need
+            //    to be updated, so adding a dependency which is already in the
+            //    lockfile doesn't hit the network, and only the packages
+            //    which no longer satisfy the manifest change versions.
             for node in r.iter().filter(|p| keep(p, to_avoid, &to_avoid_sources)) {
                 let deps = r.deps(node).into_iter().flat_map(|i| i)
                             .filter(|p| keep(p, to_avoid, &to_avoid_sources))
@@ -90,22 +189,36 @@ pub fn resolve_with_previous<'a>(registry: &mut PackageRegistry,
                 registry.register_lock(node.clone(), deps);
             }
 
-            let map = r.deps(r.root()).into_iter().flat_map(|i| i).filter(|p| {
+            // The previous resolve may be the lockfile of a workspace, whose
+            // root is only one of its members
+            let id = package.package_id();
+            let node = r.iter().find(|p| {
+                p.name() == id.name() && p.source_id() == id.source_id()
+            }).unwrap_or(r.root());
+            let map = r.deps(node).into_iter().flat_map(|i| i).filter(|p| {
                 keep(p, to_avoid, &to_avoid_sources)
             }).map(|d| {
                 (d.name(), d)
             }).collect::<HashMap<_, _>>();
             summary.map_dependencies(|d| {
                 match map.get(d.name()) {
-                    Some(&lock) if d.matches_id(lock) => d.lock_to(lock),
-                    _ => d,
+                    Some(&lock) if d.matches_id(lock) => return d.lock_to(lock),
+                    _ => {}
+                }
+                let lock = r.iter().filter(|p| {
+                    keep(p, to_avoid, &to_avoid_sources)
+                }).find(|p| d.matches_id(*p));
+                match lock {
+                    Some(lock) => d.lock_to(lock),
+                    None => d,
                 }
             })
         }
         None => summary,
     };
 
-    let mut resolved = try!(resolver::resolve(&summary, method, registry));
+    let mut resolved = try!(resolver::resolve_with_trace(&summary, method,
+                                                         registry, trace));
     match previous {
         Some(r) => resolved.copy_metadata(r),
         None => {}
@@ -1,4 +1,5 @@
 pub use self::utils::{GitRemote, GitDatabase, GitCheckout, GitRevision, fetch};
+pub use self::utils::fetch_with_token;
 pub use self::source::{GitSource, canonicalize_url};
 mod utils;
 mod source;
@@ -6,7 +6,7 @@ use url::{self, Url};
 use core::source::{Source, SourceId};
 use core::GitReference;
 use core::{Package, PackageId, Summary, Registry, Dependency};
-use util::{CargoResult, Config, to_hex};
+use util::{CargoResult, Config, FileLock, paths, to_hex};
 use sources::PathSource;
 use sources::git::utils::{GitRemote, GitRevision};
 
@@ -160,6 +160,13 @@ impl<'a, 'b> Registry for GitSource<'a, 'b> {
 
 impl<'a, 'b> Source for GitSource<'a, 'b> {
     fn update(&mut self) -> CargoResult<()> {
+        // The database and checkouts are shared by all projects using this
+        // repository, so make sure no other cargo updates them concurrently
+        let lock_path = self.db_path.dir_path().join(
+            format!("{}.lock", self.db_path.filename_str().unwrap()));
+        let _lock = try!(FileLock::acquire(&lock_path,
+            &format!("git repository `{}`", self.remote.url()), self.config));
+
         let actual_rev = self.remote.rev_for(&self.db_path, &self.reference);
         let should_update = actual_rev.is_err() ||
                             self.source_id.precise().is_none();
@@ -169,14 +176,28 @@ impl<'a, 'b> Source for GitSource<'a, 'b> {
                 format!("git repository `{}`", self.remote.url())));
 
             trace!("updating git source `{:?}`", self.remote);
-            let repo = try!(self.remote.checkout(&self.db_path));
-            let rev = try!(repo.rev_for(&self.reference));
+            let repo = try!(self.remote.checkout(&self.db_path, self.config));
+            let rev = match repo.rev_for(&self.reference) {
+                Ok(rev) => rev,
+                // The revision may be older than the shallow history
+                Err(..) if repo.is_shallow() => {
+                    try!(self.remote.unshallow(&repo, self.config));
+                    try!(repo.rev_for(&self.reference))
+                }
+                Err(e) => return Err(e),
+            };
             (repo, rev)
         } else {
             (try!(self.remote.db_at(&self.db_path)), actual_rev.unwrap())
         };
 
-        try!(repo.copy_to(actual_rev.clone(), &self.checkout_path));
+        try!(repo.copy_to(actual_rev.clone(), &self.checkout_path,
+                          self.config));
+
+        // Record the use of both the database and the checkout so that
+        // `cargo cache` doesn't collect them
+        paths::mark_used(self.config.home(), &self.db_path);
+        paths::mark_used(self.config.home(), &self.checkout_path);
 
         let source_id = self.source_id.with_precise(Some(actual_rev.to_string()));
         let path_source = PathSource::new(&self.checkout_path, &source_id,
@@ -6,7 +6,7 @@ use url::Url;
 use git2::{self, ObjectType};
 
 use core::GitReference;
-use util::{CargoResult, ChainError, human, ToUrl, internal};
+use util::{CargoResult, ChainError, Config, human, ToUrl, internal, process};
 
 #[derive(PartialEq, Clone, Debug)]
 #[allow(missing_copy_implementations)]
@@ -108,16 +108,17 @@ impl GitRemote {
         db.rev_for(reference)
     }
 
-    pub fn checkout(&self, into: &Path) -> CargoResult<GitDatabase> {
+    pub fn checkout(&self, into: &Path,
+                    config: &Config) -> CargoResult<GitDatabase> {
         let repo = match git2::Repository::open(into) {
             Ok(repo) => {
-                try!(self.fetch_into(&repo).chain_error(|| {
+                try!(self.fetch_into(&repo, config).chain_error(|| {
                     internal(format!("failed to fetch into {}", into.display()))
                 }));
                 repo
             }
             Err(..) => {
-                try!(self.clone_into(into).chain_error(|| {
+                try!(self.clone_into(into, config).chain_error(|| {
                     internal(format!("failed to clone into: {}", into.display()))
                 }))
             }
@@ -135,23 +136,43 @@ impl GitRemote {
         })
     }
 
-    fn fetch_into(&self, dst: &git2::Repository) -> CargoResult<()> {
-        // Create a local anonymous remote in the repository to fetch the url
+    /// Fetches the branches of the remote into the database `dst`.
+    ///
+    /// With `net.git-shallow` only their latest commits are fetched, which is
+    /// only done for the databases of git dependencies. A database which was
+    /// fetched that way gets its full history once the key is unset.
+    fn fetch_into(&self, dst: &git2::Repository,
+                  config: &Config) -> CargoResult<()> {
         let url = self.url.to_string();
         let refspec = "refs/heads/*:refs/heads/*";
-        fetch(dst, &url, refspec)
+        if try!(config_bool(config, "net.git-shallow")) {
+            fetch_with_cli(dst, &url, refspec, &["--depth", "1"], None, config)
+        } else if dst.path().join("shallow").exists() {
+            fetch_with_cli(dst, &url, refspec, &["--unshallow"], None, config)
+        } else {
+            fetch(dst, &url, refspec, config)
+        }
     }
 
-    fn clone_into(&self, dst: &Path) -> CargoResult<git2::Repository> {
-        let url = self.url.to_string();
+    fn clone_into(&self, dst: &Path,
+                  config: &Config) -> CargoResult<git2::Repository> {
         if dst.exists() {
             try!(rmdir_recursive(dst));
         }
         try!(mkdir_recursive(dst, USER_DIR));
         let repo = try!(git2::Repository::init_bare(dst));
-        try!(fetch(&repo, &url, "refs/heads/*:refs/heads/*"));
+        try!(self.fetch_into(&repo, config));
         Ok(repo)
     }
+
+    /// Fetches the full history into the shallow database `db`, for when a
+    /// revision which isn't part of the shallow history is needed.
+    pub fn unshallow(&self, db: &GitDatabase,
+                     config: &Config) -> CargoResult<()> {
+        let url = self.url.to_string();
+        fetch_with_cli(&db.repo, &url, "refs/heads/*:refs/heads/*",
+                       &["--unshallow"], None, config)
+    }
 }
 
 impl GitDatabase {
@@ -159,21 +180,28 @@ impl GitDatabase {
         &self.path
     }
 
-    pub fn copy_to(&self, rev: GitRevision, dest: &Path)
+    /// Returns whether only part of the history was fetched into this
+    /// database, see `net.git-shallow`.
+    pub fn is_shallow(&self) -> bool {
+        self.path.join("shallow").exists()
+    }
+
+    pub fn copy_to(&self, rev: GitRevision, dest: &Path, config: &Config)
                    -> CargoResult<GitCheckout> {
         let checkout = match git2::Repository::open(dest) {
             Ok(repo) => {
                 let checkout = GitCheckout::new(dest, self, rev, repo);
-                if !checkout.is_fresh() {
-                    try!(checkout.fetch());
+                let unshallow = checkout.is_shallow() && !self.is_shallow();
+                if !checkout.is_fresh() || unshallow {
+                    try!(checkout.fetch(config));
                     try!(checkout.reset());
                     assert!(checkout.is_fresh());
                 }
                 checkout
             }
-            Err(..) => try!(GitCheckout::clone_into(dest, self, rev)),
+            Err(..) => try!(GitCheckout::clone_into(dest, self, rev, config)),
         };
-        try!(checkout.update_submodules().chain_error(|| {
+        try!(checkout.update_submodules(config).chain_error(|| {
             internal("failed to update submodules")
         }));
         Ok(checkout)
@@ -230,16 +258,28 @@ impl<'a> GitCheckout<'a> {
     }
 
     fn clone_into(into: &Path, database: &'a GitDatabase,
-                  revision: GitRevision)
+                  revision: GitRevision, config: &Config)
                   -> CargoResult<GitCheckout<'a>>
     {
-        let repo = try!(GitCheckout::clone_repo(database.path(), into));
+        let repo = if database.is_shallow() {
+            // libgit2 can't clone shallow repositories, so the checkout is
+            // created empty and filled by the git executable
+            try!(GitCheckout::prepare_dir(into));
+            let repo = try!(git2::Repository::init(into));
+            let url = try!(database.path().to_url().map_err(human));
+            try!(fetch_with_cli(&repo, &url.to_string(),
+                                "refs/heads/*:refs/heads/*", &[], None,
+                                config));
+            repo
+        } else {
+            try!(GitCheckout::clone_repo(database.path(), into))
+        };
         let checkout = GitCheckout::new(into, database, revision, repo);
         try!(checkout.reset());
         Ok(checkout)
     }
 
-    fn clone_repo(source: &Path, into: &Path) -> CargoResult<git2::Repository> {
+    fn prepare_dir(into: &Path) -> CargoResult<()> {
         let dirname = into.dir_path();
 
         try!(mkdir_recursive(&dirname, USER_DIR).chain_error(|| {
@@ -251,6 +291,11 @@ impl<'a> GitCheckout<'a> {
                 human(format!("Couldn't rmdir {}", into.display()))
             }));
         }
+        Ok(())
+    }
+
+    fn clone_repo(source: &Path, into: &Path) -> CargoResult<git2::Repository> {
+        try!(GitCheckout::prepare_dir(into));
 
         let url = try!(source.to_url().map_err(human));
         let url = url.to_string();
@@ -261,6 +306,10 @@ impl<'a> GitCheckout<'a> {
         Ok(repo)
     }
 
+    fn is_shallow(&self) -> bool {
+        self.repo.path().join("shallow").exists()
+    }
+
     fn is_fresh(&self) -> bool {
         match self.repo.revparse_single("HEAD") {
             Ok(head) => head.id().to_string() == self.revision.to_string(),
@@ -268,12 +317,22 @@ impl<'a> GitCheckout<'a> {
         }
     }
 
-    fn fetch(&self) -> CargoResult<()> {
+    fn fetch(&self, config: &Config) -> CargoResult<()> {
         info!("fetch {}", self.repo.path().display());
         let url = try!(self.database.path.to_url().map_err(human));
         let url = url.to_string();
         let refspec = "refs/heads/*:refs/heads/*";
-        try!(fetch(&self.repo, &url, refspec));
+        // The database is local, so the git CLI is only needed if libgit2
+        // can't read it, or to complete the history of a checkout of a
+        // database which was shallow before
+        if self.database.is_shallow() {
+            try!(fetch_with_cli(&self.repo, &url, refspec, &[], None, config));
+        } else if self.is_shallow() {
+            try!(fetch_with_cli(&self.repo, &url, refspec, &["--unshallow"],
+                                None, config));
+        } else {
+            try!(fetch_with_libgit2(&self.repo, &url, refspec, None));
+        }
         Ok(())
     }
 
@@ -284,10 +343,28 @@ impl<'a> GitCheckout<'a> {
         Ok(())
     }
 
-    fn update_submodules(&self) -> CargoResult<()> {
-        return update_submodules(&self.repo);
+    /// Checks out the submodules of the repository according to the
+    /// `net.git-submodules` configuration: `recursive` (the default) checks
+    /// out all submodules, `shallow` only the ones of the repository itself
+    /// but not their nested submodules, and `none` skips them altogether.
+    fn update_submodules(&self, config: &Config) -> CargoResult<()> {
+        let depth = match try!(config.get_string("net.git-submodules")) {
+            Some((ref mode, _)) if *mode == "recursive" => None,
+            Some((ref mode, _)) if *mode == "shallow" => Some(1),
+            Some((ref mode, _)) if *mode == "none" => return Ok(()),
+            Some((mode, path)) => {
+                return Err(human(format!("invalid `net.git-submodules` value \
+                                          `{}` in {}; expected `recursive`, \
+                                          `shallow` or `none`",
+                                         mode, path.display())))
+            }
+            None => None,
+        };
+        return update_submodules(&self.repo, depth, config);
 
-        fn update_submodules(repo: &git2::Repository) -> CargoResult<()> {
+        fn update_submodules(repo: &git2::Repository, depth: Option<u32>,
+                             config: &Config) -> CargoResult<()> {
+            if depth == Some(0) { return Ok(()) }
             info!("update submodules for: {}", repo.path().display());
 
             for mut child in try!(repo.submodules()).into_iter() {
@@ -319,27 +396,34 @@ impl<'a> GitCheckout<'a> {
                     }
                     Err(..) => {
                         let path = repo.path().dir_path().join(child.path());
-                        try!(git2::Repository::clone(url, &path))
+                        // Cloning over ssh with libgit2 wouldn't check the
+                        // key of the host, so the fetch below does it all
+                        if is_ssh(url) {
+                            try!(git2::Repository::init(&path))
+                        } else {
+                            try!(git2::Repository::clone(url, &path))
+                        }
                     }
                 };
 
                 // Fetch data from origin and reset to the head commit
                 let refspec = "refs/heads/*:refs/heads/*";
-                try!(fetch(&repo, url, refspec).chain_error(|| {
+                try!(fetch(&repo, url, refspec, config).chain_error(|| {
                     internal(format!("failed to fetch submodule `{}` from {}",
                                      child.name().unwrap_or(""), url))
                 }));
 
                 let obj = try!(repo.find_object(head, None));
                 try!(repo.reset(&obj, git2::ResetType::Hard, None, None, None));
-                try!(update_submodules(&repo));
+                try!(update_submodules(&repo, depth.map(|d| d - 1), config));
             }
             Ok(())
         }
     }
 }
 
-fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F)
+fn with_authentication<T, F>(url: &str, cfg: &git2::Config,
+                             token: Option<&str>, mut f: F)
                              -> CargoResult<T>
     where F: FnMut(&mut git2::Credentials) -> CargoResult<T>
 {
@@ -348,29 +432,43 @@ fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F)
     // We check the `allowed` types of credentials, and we try to do as much as
     // possible based on that:
     //
-    // * Prioritize SSH keys from the local ssh agent as they're likely the most
-    //   reliable. The username here is prioritized from the credential
-    //   callback, then from whatever is configured in git itself, and finally
-    //   we fall back to the generic user of `git`.
+    // * If a username/password is allowed and a registry token was given, it
+    //   is tried once as the password.
     //
     // * If a username/password is allowed, then we fallback to git2-rs's
     //   implementation of the credential helper. This is what is configured
     //   with `credential.helper` in git, and is the interface for the OSX
-    //   keychain, for example.
+    //   keychain, for example. It's consulted once, like the git CLI does.
     //
     // * After the above two have failed, we just kinda grapple attempting to
     //   return *something*.
-    let mut cred_helper = git2::CredentialHelper::new(url);
-    cred_helper.config(cfg);
+    //
+    // Every attempt is recorded so that a failure can explain what was tried.
     let mut cred_error = false;
+    let mut tried_token = false;
+    let mut tried_helper = false;
+    let mut attempts = Vec::new();
     let ret = f(&mut |url, username, allowed| {
-        let creds = if allowed.contains(git2::SSH_KEY) {
-            let user = username.map(|s| s.to_string())
-                               .or_else(|| cred_helper.username.clone())
-                               .unwrap_or("git".to_string());
-            git2::Cred::ssh_key_from_agent(&user)
-        } else if allowed.contains(git2::USER_PASS_PLAINTEXT) {
+        let creds = if allowed.contains(git2::USER_PASS_PLAINTEXT) &&
+                       token.is_some() && !tried_token {
+            tried_token = true;
+            attempts.push("the registry token".to_string());
+            git2::Cred::userpass_plaintext(username.unwrap_or("cargo"),
+                                           token.unwrap())
+        } else if allowed.contains(git2::USER_PASS_PLAINTEXT) &&
+                  !tried_helper {
+            // If the server rejects what the helper returned it would be
+            // asked again for the same credentials, so it's only tried once
+            tried_helper = true;
+            attempts.push(match cfg.get_str("credential.helper") {
+                Ok(helper) => format!("the git credential helper `{}`",
+                                      helper),
+                Err(..) => "the git credential helper, but \
+                            `credential.helper` is not configured".to_string(),
+            });
             git2::Cred::credential_helper(cfg, url, username)
+        } else if allowed.contains(git2::USER_PASS_PLAINTEXT) {
+            Err(git2::Error::from_str("the credentials were rejected"))
         } else if allowed.contains(git2::DEFAULT) {
             git2::Cred::default()
         } else {
@@ -381,18 +479,80 @@ fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F)
     });
     if cred_error {
         ret.chain_error(|| {
-            human("Failed to authenticate when downloading repository")
+            let mut msg = format!("Failed to authenticate when downloading \
+                                   repository `{}`", url);
+            if attempts.is_empty() {
+                msg.push_str("\nno authentication methods were available");
+            } else {
+                msg.push_str("\nattempted to authenticate with:");
+                for attempt in attempts.iter() {
+                    msg.push_str(&format!("\n  {}", attempt));
+                }
+            }
+            if attempts.iter().any(|a| a.contains("credential helper")) {
+                msg.push_str("\nmake sure that `git fetch` works for this \
+                              repository, which uses the same credential \
+                              helper configuration");
+            }
+            human(msg)
         })
     } else {
         ret
     }
 }
 
-pub fn fetch(repo: &git2::Repository, url: &str,
-             refspec: &str) -> CargoResult<()> {
+/// Fetches `refspec` from `url` into `repo`.
+///
+/// This is done with libgit2 unless `net.git-fetch-with-cli` is set, in which
+/// case the `git` executable is used so that all of its protocols and
+/// authentication methods are available.
+///
+/// Repositories fetched over ssh always use the `git` executable, as libgit2
+/// doesn't verify the host keys of ssh servers. OpenSSH checks them against
+/// `~/.ssh/known_hosts` and refuses to connect to a host presenting another
+/// key than the known one, and it tries ssh-agent and the default keys in
+/// `~/.ssh` for authentication. Submodules cloned over ssh are fetched the same
+/// way.
+pub fn fetch(repo: &git2::Repository, url: &str, refspec: &str,
+             config: &Config) -> CargoResult<()> {
+    fetch_with_token(repo, url, refspec, None, config)
+}
+
+/// Like `fetch`, but offers `token` as the password if the server asks for
+/// one, as private registries may do for their index.
+pub fn fetch_with_token(repo: &git2::Repository, url: &str, refspec: &str,
+                        token: Option<&str>,
+                        config: &Config) -> CargoResult<()> {
+    let with_cli = try!(config_bool(config, "net.git-fetch-with-cli"));
+    if with_cli || is_ssh(url) {
+        fetch_with_cli(repo, url, refspec, &[], token, config)
+    } else {
+        fetch_with_libgit2(repo, url, refspec, token)
+    }
+}
+
+/// Whether `url` is fetched over ssh, including the scp-like `host:path` form
+/// which is common for submodules.
+fn is_ssh(url: &str) -> bool {
+    if ["ssh://", "git+ssh://", "ssh+git://"].iter().any(|s| {
+        url.starts_with(*s)
+    }) {
+        return true
+    }
+    // A drive letter like `C:` is a local path
+    match url.find(':') {
+        Some(i) if i > 1 && !url.contains("://") => {
+            url.find('/').map_or(true, |slash| i < slash)
+        }
+        _ => false,
+    }
+}
+
+fn fetch_with_libgit2(repo: &git2::Repository, url: &str, refspec: &str,
+                      token: Option<&str>) -> CargoResult<()> {
     // Create a local anonymous remote in the repository to fetch the url
 
-    with_authentication(url, &try!(repo.config()), |f| {
+    with_authentication(url, &try!(repo.config()), token, |f| {
         let mut cb = git2::RemoteCallbacks::new();
         cb.credentials(|a, b, c| f(a, b, c));
         let mut remote = try!(repo.remote_anonymous(&url, Some(refspec)));
@@ -402,3 +562,58 @@ pub fn fetch(repo: &git2::Repository, url: &str,
         Ok(())
     })
 }
+
+fn config_bool(config: &Config, key: &str) -> CargoResult<bool> {
+    match try!(config.get(key)) {
+        Some(val) => Ok(try!(val.boolean()).0),
+        None => Ok(false),
+    }
+}
+
+/// A credential helper offering the registry token in the environment as the
+/// password, which keeps the token out of the arguments of git.
+const TOKEN_HELPER: &'static str = "credential.helper=!f() { \
+    test \"$1\" = get && echo username=cargo && \
+    echo \"password=$CARGO_REGISTRY_GIT_TOKEN\"; }; f";
+
+fn fetch_with_cli(repo: &git2::Repository, url: &str, refspec: &str,
+                  args: &[&str], token: Option<&str>,
+                  config: &Config) -> CargoResult<()> {
+    let mut cmd = try!(process("git"));
+    // Git would ask the credential helpers configured for it first, so they
+    // are cleared for the token to be the credential offered
+    if let Some(token) = token {
+        cmd = cmd.arg("-c").arg("credential.helper=")
+                 .arg("-c").arg(TOKEN_HELPER)
+                 .env("CARGO_REGISTRY_GIT_TOKEN", Some(token));
+    }
+    let mut cmd = cmd.arg("fetch");
+    for arg in args.iter() {
+        cmd = cmd.arg(*arg);
+    }
+    let cmd = cmd.arg("--tags").arg("--force").arg("--update-head-ok")
+                 .arg(url).arg(refspec).arg("refs/tags/*:refs/tags/*")
+                 .env("GIT_DIR", Some(repo.path()))
+                 .cwd(repo.path().clone());
+    try!(config.shell().verbose(|shell| {
+        shell.status("Running", &cmd.to_string())
+    }));
+    match cmd.exec() {
+        Ok(()) => Ok(()),
+        // It couldn't be run at all
+        Err(ref e) if e.exit.is_none() => {
+            let why = if is_ssh(url) {
+                "ssh repositories are fetched with it to verify the keys of \
+                 their hosts"
+            } else {
+                "`net.git-fetch-with-cli` is set"
+            };
+            Err(human(format!("failed to run the git executable to fetch \
+                               `{}`, which is required as {}; make sure git \
+                               is installed and in PATH", url, why)))
+        }
+        Err(e) => Err(e).chain_error(|| {
+            human(format!("failed to fetch `{}` with the git executable", url))
+        }),
+    }
+}
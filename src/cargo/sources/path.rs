@@ -1,12 +1,15 @@
 use std::cmp;
+use std::collections::HashSet;
 use std::fmt::{self, Debug, Formatter};
 use std::old_io::fs::{self, PathExtensions};
+use std::old_io::{File, FileType};
 use glob::Pattern;
 use git2;
 
 use core::{Package, PackageId, Summary, SourceId, Source, Dependency, Registry};
 use ops;
 use util::{CargoResult, internal, internal_error, human, ChainError, Config};
+use util::{paths, realpath};
 
 pub struct PathSource<'a, 'b: 'a> {
     id: SourceId,
@@ -71,6 +74,10 @@ impl<'a, 'b> PathSource<'a, 'b> {
     /// The basic assumption of this method is that all files in the directory
     /// are relevant for building this package, but it also contains logic to
     /// use other methods like .gitignore to filter the list of files.
+    ///
+    /// Symlinks are followed, both to files and to directories, as long as
+    /// they lead to somewhere inside of the package root. Dangling symlinks
+    /// and symlinks leading out of the package are errors.
     pub fn list_files(&self, pkg: &Package) -> CargoResult<Vec<Path>> {
         let root = pkg.manifest_path().dir_path();
 
@@ -92,27 +99,23 @@ impl<'a, 'b> PathSource<'a, 'b> {
             }
         };
 
-        // If this package is a git repository, then we really do want to query
-        // the git repository as it takes into account items such as .gitignore.
-        // We're not quite sure where the git repository is, however, so we do a
-        // bit of a probe.
-        //
-        // We check all packages in this source that are ancestors of the
-        // specified package (including the same package) to see if they're at
-        // the root of the git repository. This isn't always true, but it'll get
-        // us there most of the time!.
-        let repo = self.packages.iter()
-                       .map(|pkg| pkg.root())
-                       .filter(|path| path.is_ancestor_of(&root))
-                       .filter_map(|path| git2::Repository::open(&path).ok())
-                       .next();
+        // If this package is tracked by a git repository, then we really do
+        // want to query the git repository as it takes into account items such
+        // as .gitignore. Otherwise the directory is walked, still skipping
+        // what the repository it's in ignores, if any.
+        let repo = git2::Repository::discover(&root).ok();
         match repo {
-            Some(repo) => self.list_files_git(pkg, repo, &mut filter),
-            None => self.list_files_walk(pkg, filter),
+            Some(ref repo) if ops::is_tracked(pkg, repo) => {
+                self.list_files_git(pkg, repo, &mut filter)
+            }
+            Some(ref repo) if !is_ignored(Some(repo), &root) => {
+                self.list_files_walk(pkg, Some(repo), filter)
+            }
+            _ => self.list_files_walk(pkg, None, filter),
         }
     }
 
-    fn list_files_git<F>(&self, pkg: &Package, repo: git2::Repository,
+    fn list_files_git<F>(&self, pkg: &Package, repo: &git2::Repository,
                          filter: &mut F)
                          -> CargoResult<Vec<Path>>
         where F: FnMut(&Path) -> bool
@@ -125,17 +128,38 @@ impl<'a, 'b> PathSource<'a, 'b> {
         };
         let pkg_path = pkg.manifest_path().dir_path();
 
+        // New files which aren't ignored are as much a part of the package as
+        // the files which are already tracked
+        let mut files = index.iter().map(|e| e.path).collect::<Vec<_>>();
+        let mut opts = git2::StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true)
+            .include_ignored(false);
+        // Only the package's own directory needs scanning, which matters in
+        // large repositories holding many packages
+        if let Some(rel) = pkg_path.path_relative_from(&root) {
+            if rel != Path::new(".") {
+                opts.pathspec(rel.as_vec().to_vec());
+            }
+        }
+        for entry in try!(repo.statuses(Some(&mut opts))).iter() {
+            if entry.status().contains(git2::STATUS_WT_NEW) {
+                files.push(entry.path_bytes().to_vec());
+            }
+        }
+
         let mut ret = Vec::new();
-        'outer: for entry in index.iter() {
-            let fname = &entry.path[];
+        'outer: for fname in files.iter() {
+            let fname = &fname[];
             let file_path = root.join(fname);
 
-            // Filter out files outside this package.
+            // Filter out files outside this package, and files which were
+            // deleted without the deletion being staged.
             if !pkg_path.is_ancestor_of(&file_path) { continue }
+            if fs::lstat(&file_path).is_err() { continue }
 
             // Filter out Cargo.lock and target always
-            if fname == b"Cargo.lock" { continue }
-            if fname == b"target" { continue }
+            if file_path == pkg_path.join("Cargo.lock") { continue }
+            if pkg_path.join("target").is_ancestor_of(&file_path) { continue }
 
             // Filter out sub-packages of this package
             for other_pkg in self.packages.iter().filter(|p| *p != pkg) {
@@ -146,6 +170,15 @@ impl<'a, 'b> PathSource<'a, 'b> {
                 }
             }
 
+            // The index only has the link for symlinked directories, so the
+            // files inside of them are found like without a repository
+            if try!(check_symlink(&file_path, &pkg_path)) &&
+               file_path.is_dir() {
+                try!(walk(&file_path, &mut ret, false, &pkg_path, Some(repo),
+                          filter));
+                continue
+            }
+
             // TODO: the `entry` has a mode we should be able to look at instead
             //       of just calling stat() again
             if file_path.is_dir() {
@@ -159,7 +192,7 @@ impl<'a, 'b> PathSource<'a, 'b> {
                     Ok(repo) => repo,
                     Err(..) => continue,
                 };
-                let files = try!(self.list_files_git(pkg, repo, filter));
+                let files = try!(self.list_files_git(pkg, &repo, filter));
                 ret.extend(files.into_iter());
             } else if (*filter)(&file_path) {
                 // We found a file!
@@ -170,41 +203,107 @@ impl<'a, 'b> PathSource<'a, 'b> {
         Ok(ret)
     }
 
-    fn list_files_walk<F>(&self, pkg: &Package, mut filter: F)
+    fn list_files_walk<F>(&self, pkg: &Package,
+                          repo: Option<&git2::Repository>, mut filter: F)
                           -> CargoResult<Vec<Path>>
         where F: FnMut(&Path) -> bool
     {
         let mut ret = Vec::new();
         for pkg in self.packages.iter().filter(|p| *p == pkg) {
             let loc = pkg.manifest_path().dir_path();
-            try!(walk(&loc, &mut ret, true, &mut filter));
+            try!(walk(&loc, &mut ret, true, &loc, repo, &mut filter));
         }
-        return Ok(ret);
-
-        fn walk<F>(path: &Path, ret: &mut Vec<Path>,
-                   is_root: bool, filter: &mut F) -> CargoResult<()>
-            where F: FnMut(&Path) -> bool
-        {
-            if !path.is_dir() {
-                if (*filter)(path) {
-                    ret.push(path.clone());
-                }
-                return Ok(())
-            }
-            // Don't recurse into any sub-packages that we have
-            if !is_root && path.join("Cargo.toml").exists() { return Ok(()) }
-            for dir in try!(fs::readdir(path)).iter() {
-                match (is_root, dir.filename_str()) {
-                    (_,    Some(".git")) |
-                    (true, Some("target")) |
-                    (true, Some("Cargo.lock")) => continue,
-                    _ => {}
-                }
-                try!(walk(dir, ret, false, filter));
-            }
-            return Ok(())
+        Ok(ret)
+    }
+}
+
+/// Names of files which are never a part of a package found by walking its
+/// directory: the metadata of version control systems, and files left behind
+/// by operating systems. Editor backups and swap files are recognized by
+/// their names as well.
+static IGNORED: &'static [&'static str] = &[
+    ".git", ".hg", ".svn", ".bzr", "_darcs", ".DS_Store", "Thumbs.db",
+];
+
+/// Returns whether `path` isn't a part of packages it's in: whether the
+/// ignore rules of `repo` exclude it, or whether it's on the fallback list of
+/// VCS metadata and temporary files which apply without a repository.
+pub fn is_ignored(repo: Option<&git2::Repository>, path: &Path) -> bool {
+    let name = path.filename_str().unwrap_or("");
+    if IGNORED.contains(&name) || name.ends_with("~") ||
+       name.ends_with(".swp") || name.ends_with(".swo") ||
+       name.starts_with(".#") ||
+       (name.len() > 1 && name.starts_with("#") && name.ends_with("#")) {
+        return true
+    }
+    let (repo, workdir) = match repo {
+        Some(repo) => match repo.workdir() {
+            Some(workdir) => (repo, workdir),
+            None => return false,
+        },
+        None => return false,
+    };
+    match path.path_relative_from(&workdir) {
+        Some(ref relative) => repo.status_should_ignore(relative)
+                                  .unwrap_or(false),
+        None => false,
+    }
+}
+
+/// Lists the files underneath `path` which pass `filter` into `ret`, skipping
+/// sub-packages and files ignored by `repo`. `root` is the package root.
+fn walk<F>(path: &Path, ret: &mut Vec<Path>, is_root: bool, root: &Path,
+           repo: Option<&git2::Repository>, filter: &mut F) -> CargoResult<()>
+    where F: FnMut(&Path) -> bool
+{
+    try!(check_symlink(path, root));
+    if !path.is_dir() {
+        if (*filter)(path) {
+            ret.push(path.clone());
         }
+        return Ok(())
     }
+    // Don't recurse into any sub-packages that we have
+    if !is_root && path.join("Cargo.toml").exists() { return Ok(()) }
+    for dir in try!(fs::readdir(path)).iter() {
+        match (is_root, dir.filename_str()) {
+            (true, Some("target")) |
+            (true, Some("Cargo.lock")) => continue,
+            _ => {}
+        }
+        if is_ignored(repo, dir) { continue }
+        try!(walk(dir, ret, false, root, repo, filter));
+    }
+    Ok(())
+}
+
+/// Fails if `path` is a symlink which can't be followed: one which dangles,
+/// which leads out of the package root `root`, or which leads to a directory
+/// `path` was reached through, which would be walked forever. Returns whether
+/// `path` is a symlink.
+fn check_symlink(path: &Path, root: &Path) -> CargoResult<bool> {
+    match fs::lstat(path) {
+        Ok(ref stat) if stat.kind == FileType::Symlink => {}
+        _ => return Ok(false),
+    }
+    let target = try!(realpath(path));
+    let real_root = try!(realpath(root));
+    let walked = real_root.join(path.path_relative_from(root).unwrap());
+    if !target.exists() {
+        return Err(human(format!("`{}` is a broken symlink",
+                                 path.display())))
+    }
+    if !real_root.is_ancestor_of(&target) {
+        return Err(human(format!("`{}` is a symlink to `{}`, which is outside \
+                                  of the package root `{}`", path.display(),
+                                 target.display(), root.display())))
+    }
+    if target.is_dir() && target.is_ancestor_of(&walked) {
+        return Err(human(format!("`{}` is a symlink to `{}`, which leads back \
+                                  to the symlink itself", path.display(),
+                                 target.display())))
+    }
+    Ok(true)
 }
 
 impl<'a, 'b> Debug for PathSource<'a, 'b> {
@@ -252,17 +351,89 @@ impl<'a, 'b> Source for PathSource<'a, 'b> {
             return Err(internal_error("BUG: source was not updated", ""));
         }
 
-        let mut max = 0;
-        for file in try!(self.list_files(pkg)).iter() {
-            // An fs::stat error here is either because path is a
-            // broken symlink, a permissions error, or a race
-            // condition where this path was rm'ed - either way,
-            // we can ignore the error and treat the path's mtime
-            // as 0.
-            warn!("{} {}", file.stat().map(|s| s.modified).unwrap_or(0), file.display());
-            max = cmp::max(max, file.stat().map(|s| s.modified).unwrap_or(0));
-        }
+        // Broken symlinks were already rejected, so an fs::stat error here is
+        // either a permissions error or a race condition where this path was
+        // rm'ed - either way, we can ignore the error and treat the path's
+        // mtime as 0.
+        let max = paths::newest_mtime(try!(self.list_files(pkg)), None)
+                        .unwrap_or(0);
         trace!("fingerprint {}: {}", self.path.display(), max);
         Ok(max.to_string())
     }
+
+    fn cached_files(&self, pkg: &Package,
+                    cache: &Path) -> CargoResult<Option<Vec<Path>>> {
+        if !self.updated {
+            return Err(internal_error("BUG: source was not updated", ""));
+        }
+        if let Some(files) = read_listing(cache) {
+            return Ok(Some(files))
+        }
+        let files = try!(self.list_files(pkg));
+        // Failing to save the listing only makes the next build slower
+        let _ = write_listing(cache, pkg, &files);
+        Ok(Some(files))
+    }
+}
+
+/// Reads the listing of the files of a package saved at `cache` by
+/// `write_listing`, if it's still valid.
+///
+/// Adding, removing or renaming a file changes the modification time of the
+/// directory it's in, so the listing is saved along with the modification
+/// times of the directories the files are in, and of the files which decide
+/// what is listed: the manifest, ignore files and the git index. As long as
+/// none of these changed, neither did the listing.
+fn read_listing(cache: &Path) -> Option<Vec<Path>> {
+    let contents = match File::open(cache).read_to_string() {
+        Ok(contents) => contents,
+        Err(..) => return None,
+    };
+    let mut lines = contents.lines();
+    for line in lines.by_ref().take_while(|l| !l.is_empty()) {
+        let mut parts = line.splitn(1, ' ');
+        let mtime = parts.next().and_then(|s| s.parse::<u64>().ok());
+        let path = parts.next().map(Path::new);
+        match (mtime, path) {
+            (Some(mtime), Some(ref path)) => match fs::stat(path) {
+                Ok(ref stat) if stat.modified == mtime => {}
+                _ => return None,
+            },
+            _ => return None,
+        }
+    }
+    Some(lines.map(Path::new).collect())
+}
+
+/// Saves the listing `files` of the files of `pkg` at `cache`, see
+/// `read_listing`.
+fn write_listing(cache: &Path, pkg: &Package,
+                 files: &[Path]) -> CargoResult<()> {
+    let root = pkg.root();
+    let mut stamps = HashSet::new();
+    stamps.insert(pkg.manifest_path().clone());
+    if let Ok(repo) = git2::Repository::discover(&root) {
+        stamps.insert(repo.path().join("index"));
+    }
+    for file in files.iter() {
+        if file.filename_str() == Some(".gitignore") {
+            stamps.insert(file.clone());
+        }
+        let mut dir = file.dir_path();
+        while root.is_ancestor_of(&dir) && stamps.insert(dir.clone()) {
+            if !dir.pop() { break }
+        }
+    }
+
+    let mut out = String::new();
+    for path in stamps.iter() {
+        let mtime = try!(fs::stat(path)).modified;
+        out.push_str(&format!("{} {}\n", mtime, path.display()));
+    }
+    out.push_str("\n");
+    for file in files.iter() {
+        out.push_str(&format!("{}\n", file.display()));
+    }
+    try!(File::create(cache).write_str(&out));
+    Ok(())
 }
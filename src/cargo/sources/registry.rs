@@ -158,9 +158,10 @@
 //!         ...
 //! ```
 
-use std::old_io::{self, fs, File};
+use std::ascii::AsciiExt;
+use std::old_io::{self, fs, File, FileType};
 use std::old_io::fs::PathExtensions;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use curl::http;
 use flate2::reader::GzDecoder;
@@ -168,17 +169,21 @@ use git2;
 use rustc_serialize::hex::ToHex;
 use rustc_serialize::json;
 use tar::Archive;
-use url::Url;
+use url::{Url, UrlParser};
 
 use core::{Source, SourceId, PackageId, Package, Summary, Registry};
 use core::dependency::{Dependency, Kind};
 use sources::{PathSource, git};
-use util::{CargoResult, Config, internal, ChainError, ToUrl, human};
-use util::{hex, Sha256};
+use util::{CargoResult, Config, internal, ChainError, ToUrl, human, process};
+use util::config::ConfigValue;
+use util::{hex, paths, Sha256};
 use ops;
 
 static DEFAULT: &'static str = "https://github.com/rust-lang/crates.io-index";
 
+/// The file in unpacked sources recording the checksums of their files.
+const CHECKSUMS_FILE: &'static str = ".cargo-checksums.json";
+
 pub struct RegistrySource<'a, 'b:'a> {
     source_id: SourceId,
     checkout_path: Path,
@@ -188,8 +193,10 @@ pub struct RegistrySource<'a, 'b:'a> {
     handle: Option<http::Handle>,
     sources: Vec<PathSource<'a, 'b>>,
     hashes: HashMap<(String, String), String>, // (name, vers) => cksum
+    licenses: HashMap<(String, String), String>, // (name, vers) => license
     cache: HashMap<String, Vec<(Summary, bool)>>,
     updated: bool,
+    token: Option<Option<String>>,
 }
 
 #[derive(RustcDecodable)]
@@ -212,6 +219,7 @@ struct RegistryPackage {
     features: HashMap<String, Vec<String>>,
     cksum: String,
     yanked: Option<bool>,
+    license: Option<String>,
 }
 
 #[derive(RustcDecodable)]
@@ -240,8 +248,10 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
             handle: None,
             sources: Vec::new(),
             hashes: HashMap::new(),
+            licenses: HashMap::new(),
             cache: HashMap::new(),
             updated: false,
+            token: None,
         }
     }
 
@@ -260,6 +270,80 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
         DEFAULT.to_string()
     }
 
+    /// Returns the path the `.crate` file of `pkg` is downloaded to.
+    pub fn crate_file(&self, pkg: &PackageId) -> Path {
+        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
+        self.cache_path.join(filename)
+    }
+
+    /// Returns the mirrors listed in the configuration key `key`, which are
+    /// tried in order if the registry itself can't be reached. Mirrors only
+    /// apply to the registry configured in `registry.index`.
+    fn mirrors(&self, key: &str) -> CargoResult<Vec<String>> {
+        if *self.source_id.url() != try!(RegistrySource::url(self.config)) {
+            return Ok(Vec::new())
+        }
+        match try!(self.config.get(key)) {
+            Some(ConfigValue::String(s, _)) => {
+                Ok(s.split(' ').filter(|s| !s.is_empty())
+                    .map(|s| s.to_string()).collect())
+            }
+            Some(ConfigValue::List(list, _)) => {
+                Ok(list.into_iter().map(|(s, _)| s).collect())
+            }
+            Some(val) => self.config.expected("list", key, val),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the keyrings listed in `registry.trusted-keys`, which the
+    /// signatures of downloaded crates are verified against, along with
+    /// whether unsigned crates are denied (`registry.unsigned`). Like mirrors,
+    /// signatures only apply to the registry configured in `registry.index`.
+    fn signature_policy(&self) -> CargoResult<(Vec<Path>, bool)> {
+        if *self.source_id.url() != try!(RegistrySource::url(self.config)) {
+            return Ok((Vec::new(), false))
+        }
+        let keys = match try!(self.config.get("registry.trusted-keys")) {
+            Some(ConfigValue::List(list, _)) => {
+                // Relative to the directory containing the `.cargo` directory
+                list.into_iter().map(|(s, path)| {
+                    path.dir_path().dir_path().join(s)
+                }).collect()
+            }
+            Some(val) => return self.config.expected("list",
+                                                     "registry.trusted-keys",
+                                                     val),
+            None => Vec::new(),
+        };
+        let deny = match try!(self.config.get_string("registry.unsigned")) {
+            Some((ref s, _)) if s.as_slice() == "warn" => false,
+            Some((ref s, _)) if s.as_slice() == "deny" => true,
+            Some((s, path)) => {
+                return Err(human(format!("registry.unsigned must be either \
+                                          warn or deny, but found `{}` in {}",
+                                         s, path.display())))
+            }
+            None => false,
+        };
+        if deny && keys.is_empty() {
+            return Err(human("registry.unsigned is deny, but there are no \
+                              registry.trusted-keys to verify signatures \
+                              with"))
+        }
+        Ok((keys, deny))
+    }
+
+    /// Returns the license expression the index lists for `pkg`, if any.
+    ///
+    /// This only reads the local checkout of the index, so the source should
+    /// have been updated before.
+    pub fn license(&mut self, pkg: &PackageId) -> CargoResult<Option<String>> {
+        try!(self.summaries(pkg.name()));
+        Ok(self.licenses.get(&(pkg.name().to_string(),
+                               pkg.version().to_string())).cloned())
+    }
+
     /// Decode the configuration stored within the registry.
     ///
     /// This requires that the index has been at least checked out.
@@ -293,16 +377,25 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
     /// will only succeed if the HTTP download was successful and the file is
     /// then ready for inspection.
     ///
-    /// No action is taken if the package is already downloaded.
-    fn download_package(&mut self, pkg: &PackageId, url: &Url)
+    /// No action is taken if the package is already downloaded. The token of
+    /// the registry isn't sent to mirrors.
+    ///
+    /// If `registry.trusted-keys` is configured, the detached signature at the
+    /// URL of the crate with `.sig` appended is verified before the crate is
+    /// saved, so that it's never unpacked otherwise.
+    fn download_package(&mut self, pkg: &PackageId, url: &Url, mirror: bool)
                         -> CargoResult<Path> {
         // TODO: should discover from the S3 redirect
-        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
-        let dst = self.cache_path.join(filename);
-        if dst.exists() { return Ok(dst) }
+        let dst = self.crate_file(pkg);
+        if dst.exists() {
+            paths::mark_used(self.config.home(), &dst);
+            return Ok(dst)
+        }
         try!(self.config.shell().status("Downloading", pkg));
 
         try!(fs::mkdir_recursive(&dst.dir_path(), old_io::USER_DIR));
+        let (keys, deny_unsigned) = try!(self.signature_policy());
+        let token = if mirror {None} else {try!(self.auth_token())};
         let handle = match self.handle {
             Some(ref mut handle) => handle,
             None => {
@@ -311,7 +404,8 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
             }
         };
         // TODO: don't download into memory (curl-rust doesn't expose it)
-        let resp = try!(handle.get(url.to_string()).follow_redirects(true).exec());
+        let token = token.as_ref().map(|s| &s[]);
+        let resp = try!(get(handle, url, token));
         if resp.get_code() != 200 && resp.get_code() != 0 {
             return Err(internal(format!("Failed to get 200 reponse from {}\n{}",
                                         url, resp)))
@@ -332,8 +426,51 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
             return Err(human(format!("Failed to verify the checksum of `{}`",
                                      pkg)))
         }
+        if keys.is_empty() {
+            try!(File::create(&dst).write_all(resp.get_body()));
+            return Ok(dst)
+        }
 
-        try!(File::create(&dst).write_all(resp.get_body()));
+        let sig_url = try!(format!("{}.sig", url).to_url().map_err(internal));
+        // Local registries have no status codes to tell a missing signature
+        let local_missing = sig_url.scheme.as_slice() == "file" &&
+                            !sig_url.to_file_path().map(|p| p.exists())
+                                    .unwrap_or(false);
+        let sig = if local_missing {
+            None
+        } else {
+            Some(try!(get(handle, &sig_url, token)))
+        };
+        let part = dst.with_extension("crate.part");
+        try!(File::create(&part).write_all(resp.get_body()));
+        let code = sig.as_ref().map(|sig| sig.get_code()).unwrap_or(404);
+        let signed = match code {
+            // Storage services commonly answer 403 for missing objects
+            404 | 403 if deny_unsigned => {
+                Err(human(format!("`{}` isn't signed, and unsigned crates are \
+                                   denied by `registry.unsigned`", pkg)))
+            }
+            404 | 403 => Ok(false),
+            0 | 200 => {
+                let sig = sig.as_ref().unwrap().get_body();
+                verify_signature(&part, sig, &keys[]).map(|()| {
+                    true
+                }).chain_error(|| {
+                    human(format!("Failed to verify the signature of `{}`",
+                                  pkg))
+                })
+            }
+            _ => Err(internal(format!("Failed to get 200 reponse from {}\n{}",
+                                      sig_url, sig.unwrap()))),
+        };
+        if signed.is_err() {
+            let _ = fs::unlink(&part);
+        }
+        if !try!(signed) {
+            try!(self.config.shell().warn(format!("warning: `{}` isn't signed",
+                                                  pkg)));
+        }
+        try!(fs::rename(&part, &dst));
         Ok(dst)
     }
 
@@ -345,33 +482,82 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
                       -> CargoResult<Path> {
         let dst = self.src_path.join(format!("{}-{}", pkg.name(),
                                              pkg.version()));
-        if dst.join(".cargo-ok").exists() { return Ok(dst) }
+        if paths::long_path(&dst.join(".cargo-ok")).exists() {
+            paths::mark_used(self.config.home(), &dst);
+            if try!(modified_since_unpacked(&dst)).is_some() {
+                try!(self.config.shell().warn(format!("warning: the sources \
+                    of `{}` in `{}` were modified after they were unpacked, \
+                    run `cargo verify-cache --fix` to unpack them again",
+                    pkg, dst.display())));
+            }
+            return Ok(dst)
+        }
 
         try!(fs::mkdir_recursive(&dst.dir_path(), old_io::USER_DIR));
-        let f = try!(File::open(&tarball));
-        let gz = try!(GzDecoder::new(f));
-        let mut tar = Archive::new(gz);
-        try!(tar.unpack(&dst.dir_path()));
-        try!(File::create(&dst.join(".cargo-ok")));
+        let cksum = self.hashes.get(&(pkg.name().to_string(),
+                                      pkg.version().to_string()))
+                               .cloned().unwrap_or(String::new());
+
+        // The same crate may already have been unpacked from another registry,
+        // for example one configured under a different URL. Its files are
+        // then linked rather than unpacked once more.
+        let linked = match self.find_unpacked(pkg, &cksum) {
+            Some(src) => {
+                let ok = paths::link_tree(&src, &dst).is_ok();
+                if !ok && paths::long_path(&dst).exists() {
+                    try!(fs::rmdir_recursive(&paths::long_path(&dst)));
+                }
+                ok
+            }
+            None => false,
+        };
+        if !linked {
+            let f = try!(File::open(&tarball));
+            let gz = try!(GzDecoder::new(f));
+            let mut tar = Archive::new(gz);
+            try!(tar.unpack(&paths::long_path(&dst.dir_path())));
+        }
+        // Markers linked along with the sources must not be shared
+        let _ = fs::unlink(&paths::long_path(&dst.join(".cargo-ok")));
+        let _ = fs::unlink(&paths::long_path(&dst.join(CHECKSUMS_FILE)));
+
+        // Sources are never meant to be edited in place, as builds using them
+        // wouldn't be reproducible elsewhere.
+        try!(make_read_only(&dst).chain_error(|| {
+            human(format!("failed to make `{}` read-only", dst.display()))
+        }));
+        let checksums = json::encode(&try!(checksum_files(&dst))).unwrap();
+        try!(File::create(&dst.join(CHECKSUMS_FILE)).write_str(&checksums));
+        try!(File::create(&dst.join(".cargo-ok")).write_str(&cksum));
         Ok(dst)
     }
 
+    /// Looks for an unmodified copy of `pkg` with the checksum `cksum` which
+    /// was unpacked completely from the `.crate` file of another registry.
+    fn find_unpacked(&self, pkg: &PackageId, cksum: &str) -> Option<Path> {
+        if cksum.is_empty() { return None }
+        let dirs = match fs::readdir(&self.src_path.dir_path()) {
+            Ok(dirs) => dirs,
+            Err(..) => return None,
+        };
+        dirs.into_iter().filter(|dir| *dir != self.src_path).map(|dir| {
+            dir.join(format!("{}-{}", pkg.name(), pkg.version()))
+        }).find(|src| {
+            let ok = File::open(&src.join(".cargo-ok")).read_to_string();
+            let same = match ok {
+                Ok(ref contents) => contents.trim() == cksum,
+                Err(..) => false,
+            };
+            same && modified_since_unpacked(src).ok() == Some(None)
+        })
+    }
+
     /// Parse the on-disk metadata for the package provided
     fn summaries(&mut self, name: &str) -> CargoResult<&Vec<(Summary, bool)>> {
         if self.cache.contains_key(name) {
             return Ok(self.cache.get(name).unwrap());
         }
-        // see module comment for why this is structured the way it is
-        let path = self.checkout_path.clone();
-        let fs_name = name.chars().map(|c| c.to_lowercase()).collect::<String>();
-        let path = match fs_name.len() {
-            1 => path.join("1").join(fs_name),
-            2 => path.join("2").join(fs_name),
-            3 => path.join("3").join(&fs_name[..1]).join(fs_name),
-            _ => path.join(&fs_name[0..2])
-                     .join(&fs_name[2..4])
-                     .join(fs_name),
-        };
+        let path = index_file(&self.checkout_path, name);
         let summaries = match File::open(&path) {
             Ok(mut f) => {
                 let contents = try!(f.read_to_string());
@@ -400,13 +586,16 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
     fn parse_registry_package(&mut self, line: &str)
                               -> CargoResult<(Summary, bool)> {
         let RegistryPackage {
-            name, vers, cksum, deps, features, yanked
+            name, vers, cksum, deps, features, yanked, license
         } = try!(json::decode::<RegistryPackage>(line));
         let pkgid = try!(PackageId::new(&name, &vers, &self.source_id));
         let deps: CargoResult<Vec<Dependency>> = deps.into_iter().map(|dep| {
             self.parse_registry_dependency(dep)
         }).collect();
         let deps = try!(deps);
+        if let Some(license) = license {
+            self.licenses.insert((name.clone(), vers.clone()), license);
+        }
         self.hashes.insert((name, vers), cksum);
         Ok((try!(Summary::new(pkgid, deps, features)), yanked.unwrap_or(false)))
     }
@@ -433,6 +622,17 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
               .set_kind(kind))
     }
 
+    /// Returns the token to authenticate requests to the registry with, if it
+    /// requires one.
+    fn auth_token(&mut self) -> CargoResult<Option<String>> {
+        if self.token.is_none() {
+            let token = try!(ops::registry_auth_token(self.config,
+                                                      &self.source_id));
+            self.token = Some(token);
+        }
+        Ok(self.token.clone().unwrap())
+    }
+
     /// Actually perform network operations to update the registry
     fn do_update(&mut self) -> CargoResult<()> {
         if self.updated { return Ok(()) }
@@ -441,12 +641,36 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
              format!("registry `{}`", self.source_id.url())));
         let repo = try!(self.open());
 
-        // git fetch origin
+        // git fetch origin, falling back to the mirrors of the index. The
+        // token is only ever sent to the registry itself.
         let url = self.source_id.url().to_string();
         let refspec = "refs/heads/*:refs/remotes/origin/*";
-        try!(git::fetch(&repo, &url, refspec).chain_error(|| {
+        let token = try!(self.auth_token());
+        let mirrors = try!(self.mirrors("registry.mirrors"));
+        let mut result = git::fetch_with_token(&repo, &url, refspec,
+                                               token.as_ref().map(|s| &s[]),
+                                               self.config).chain_error(|| {
             internal(format!("failed to fetch `{}`", url))
-        }));
+        });
+        let mut last = &url;
+        for mirror in mirrors.iter() {
+            if result.is_ok() { break }
+            try!(self.config.shell().warn(format!("warning: failed to fetch \
+                                                   `{}`, trying mirror `{}`",
+                                                  last, mirror)));
+            last = mirror;
+            result = git::fetch(&repo, mirror, refspec,
+                                self.config).chain_error(|| {
+                internal(format!("failed to fetch mirror `{}`", mirror))
+            });
+            if result.is_ok() {
+                try!(self.config.shell().verbose(|s| {
+                    s.status("Using", format!("mirror `{}` of registry `{}`",
+                                              mirror, url))
+                }));
+            }
+        }
+        try!(result);
 
         // git reset --hard origin/master
         let reference = "refs/remotes/origin/master";
@@ -460,6 +684,21 @@ impl<'a, 'b> RegistrySource<'a, 'b> {
     }
 }
 
+/// Returns the file listing the versions of the crate `name` in the index
+/// checked out at `index`.
+pub fn index_file(index: &Path, name: &str) -> Path {
+    // see module comment for why this is structured the way it is
+    let fs_name = name.chars().map(|c| c.to_lowercase()).collect::<String>();
+    match fs_name.len() {
+        1 => index.join("1").join(fs_name),
+        2 => index.join("2").join(fs_name),
+        3 => index.join("3").join(&fs_name[..1]).join(fs_name),
+        _ => index.join(&fs_name[0..2])
+                  .join(&fs_name[2..4])
+                  .join(fs_name),
+    }
+}
+
 impl<'a, 'b> Registry for RegistrySource<'a, 'b> {
     fn query(&mut self, dep: &Dependency) -> CargoResult<Vec<Summary>> {
         // If this is a precise dependency, then it came from a lockfile and in
@@ -511,23 +750,50 @@ impl<'a, 'b> Source for RegistrySource<'a, 'b> {
         if self.source_id.precise() != Some("locked") {
             try!(self.do_update());
         }
+        if self.checkout_path.exists() {
+            paths::mark_used(self.config.home(), &self.checkout_path);
+        }
         Ok(())
     }
 
     fn download(&mut self, packages: &[PackageId]) -> CargoResult<()> {
         let config = try!(self.config());
-        let url = try!(config.dl.to_url().map_err(internal));
+        let mut dls = vec![config.dl];
+        dls.extend(try!(self.mirrors("registry.dl-mirrors")).into_iter());
         for package in packages.iter() {
             if self.source_id != *package.source_id() { continue }
 
-            let mut url = url.clone();
-            url.path_mut().unwrap().push(package.name().to_string());
-            url.path_mut().unwrap().push(package.version().to_string());
-            url.path_mut().unwrap().push("download".to_string());
-            let path = try!(self.download_package(package, &url).chain_error(|| {
-                internal(format!("Failed to download package `{}` from {}",
-                                 package, url))
-            }));
+            // Downloads from mirrors are verified against the checksum in the
+            // index just like those from the registry itself.
+            let mut result = Err(internal("no download location"));
+            for (i, dl) in dls.iter().enumerate() {
+                let mut url = try!(dl.to_url().map_err(internal));
+                url.path_mut().unwrap().push(package.name().to_string());
+                url.path_mut().unwrap().push(package.version().to_string());
+                url.path_mut().unwrap().push("download".to_string());
+                if i > 0 {
+                    try!(self.config.shell().warn(format!("warning: failed \
+                                                           to download `{}`, \
+                                                           trying mirror `{}`",
+                                                          package, dl)));
+                }
+                let mirror = i > 0;
+                result = self.download_package(package, &url,
+                                               mirror).chain_error(|| {
+                    internal(format!("Failed to download package `{}` from {}",
+                                     package, url))
+                });
+                if result.is_ok() {
+                    if i > 0 {
+                        try!(self.config.shell().verbose(|s| {
+                            s.status("Using", format!("mirror `{}` for `{}`",
+                                                      dl, package))
+                        }));
+                    }
+                    break
+                }
+            }
+            let path = try!(result);
             let path = try!(self.unpack_package(package, path).chain_error(|| {
                 internal(format!("Failed to unpack package `{}`", package))
             }));
@@ -547,6 +813,132 @@ impl<'a, 'b> Source for RegistrySource<'a, 'b> {
     }
 
     fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
-        Ok(pkg.package_id().version().to_string())
+        // Unpacked sources are read-only, but they can still be edited by
+        // hand, in which case the edited sources are what gets built.
+        let version = pkg.package_id().version().to_string();
+        match try!(modified_since_unpacked(pkg.root())) {
+            Some(digest) => Ok(format!("{}-modified-{}", version, digest)),
+            None => Ok(version),
+        }
+    }
+}
+
+/// Removes the write permissions of all files within `dir`.
+fn make_read_only(dir: &Path) -> old_io::IoResult<()> {
+    for path in try!(fs::walk_dir(&paths::long_path(dir))) {
+        let stat = try!(fs::lstat(&path));
+        if stat.kind != FileType::RegularFile { continue }
+        let writable = old_io::USER_WRITE | old_io::GROUP_WRITE |
+                       old_io::OTHER_WRITE;
+        try!(fs::chmod(&path, stat.perm - writable));
+    }
+    Ok(())
+}
+
+/// Most redirects followed by `get`.
+const MAX_REDIRECTS: u32 = 10;
+
+/// Fetches `url`, sending `token` along as the `Authorization` header.
+///
+/// Redirects are followed by hand so that the token is only sent to the origin
+/// of `url`, and never to wherever the registry redirects to, which commonly is
+/// a CDN or the storage service of another party.
+fn get(handle: &mut http::Handle, url: &Url,
+       token: Option<&str>) -> CargoResult<http::Response> {
+    let token = match token {
+        Some(token) => token,
+        None => {
+            let req = handle.get(url.to_string()).follow_redirects(true);
+            return Ok(try!(req.exec()))
+        }
+    };
+    let mut target = url.clone();
+    for _ in range(0, MAX_REDIRECTS) {
+        let mut req = handle.get(target.to_string());
+        if same_origin(url, &target) {
+            req = req.header("Authorization", token);
+        }
+        let resp = try!(req.exec());
+        match resp.get_code() {
+            301 | 302 | 303 | 307 | 308 => {}
+            _ => return Ok(resp),
+        }
+        let location = resp.get_headers().iter().find(|&(name, _)| {
+            name.eq_ignore_ascii_case("location")
+        }).and_then(|(_, values)| values.get(0)).map(|s| s.clone());
+        let location = try!(location.chain_error(|| {
+            internal(format!("redirect without a location from {}", target))
+        }));
+        target = try!(UrlParser::new().base_url(&target).parse(&location)
+                                      .map_err(|e| {
+            internal(format!("invalid redirect from {} to `{}`: {}", target,
+                             location, e))
+        }));
+    }
+    Err(internal(format!("too many redirects fetching {}", url)))
+}
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme == b.scheme && a.host() == b.host() &&
+        a.port_or_default() == b.port_or_default()
+}
+
+/// Verifies the detached signature `sig` of `file` with `gpgv`, against the
+/// public keys in `keyrings`.
+fn verify_signature(file: &Path, sig: &[u8],
+                    keyrings: &[Path]) -> CargoResult<()> {
+    let sig_path = file.with_extension("sig");
+    try!(File::create(&sig_path).write_all(sig));
+    let mut cmd = try!(process("gpgv"));
+    for keyring in keyrings.iter() {
+        cmd = cmd.arg("--keyring").arg(keyring);
+    }
+    let res = cmd.arg(&sig_path).arg(file).exec_with_output();
+    let _ = fs::unlink(&sig_path);
+    try!(res);
+    Ok(())
+}
+
+/// Returns a digest of the files in the sources unpacked to `dir` if any of
+/// them were modified, added or removed after the sources were unpacked,
+/// according to the checksums recorded when unpacking them.
+fn modified_since_unpacked(dir: &Path) -> CargoResult<Option<String>> {
+    if !paths::long_path(&dir.join(".cargo-ok")).exists() { return Ok(None) }
+    // Sources unpacked before checksums were recorded can't be checked
+    let recorded = File::open(&paths::long_path(&dir.join(CHECKSUMS_FILE)))
+                       .read_to_string();
+    let recorded = match recorded {
+        Ok(contents) => contents,
+        Err(..) => return Ok(None),
+    };
+    let recorded: BTreeMap<String, String> = try!(json::decode(&recorded));
+    let current = try!(checksum_files(dir));
+    if current == recorded { return Ok(None) }
+
+    let mut state = Sha256::new();
+    for (path, cksum) in current.iter() {
+        state.update(path.as_bytes());
+        state.update(cksum.as_bytes());
+    }
+    Ok(Some(state.finish().to_hex()))
+}
+
+/// Returns the SHA-256 checksums of the files of the sources unpacked to
+/// `dir`, by their path relative to `dir`.
+fn checksum_files(dir: &Path) -> CargoResult<BTreeMap<String, String>> {
+    let root = paths::long_path(dir);
+    let mut ret = BTreeMap::new();
+    for path in try!(fs::walk_dir(&root)) {
+        let name = path.filename_str().unwrap_or("");
+        if name == ".cargo-ok" || name == CHECKSUMS_FILE { continue }
+        if try!(fs::lstat(&path)).kind == FileType::Directory { continue }
+        let relative = path.path_relative_from(&root).unwrap();
+        let contents = try!(File::open(&path).read_to_end());
+        let mut state = Sha256::new();
+        state.update(&contents);
+        let name = relative.str_components().map(|c| c.unwrap_or(""))
+                           .collect::<Vec<_>>().connect("/");
+        ret.insert(name, state.finish().to_hex());
     }
+    Ok(ret)
 }
@@ -1,5 +1,7 @@
+use std::ascii::AsciiExt;
 use std::cell::{RefCell, RefMut, Ref, Cell};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::collections::BTreeMap;
 use std::collections::hash_map::{HashMap};
 use std::env;
 use std::fmt;
@@ -9,9 +11,9 @@ use std::old_io;
 
 use rustc_serialize::{Encodable,Encoder};
 use toml;
-use core::MultiShell;
+use core::{MultiShell, Verbosity, ColorConfig, ErrorFormat};
 use ops;
-use util::{CargoResult, ChainError, internal, human};
+use util::{CargoResult, ChainError, internal, human, realpath};
 
 use util::toml as cargo_toml;
 
@@ -25,6 +27,8 @@ pub struct Config<'a> {
     rustc_host: String,
     values: RefCell<HashMap<String, ConfigValue>>,
     values_loaded: Cell<bool>,
+    /// Values given with `--config` on the command line
+    cli_values: RefCell<HashMap<String, ConfigValue>>,
     cwd: Path,
 }
 
@@ -33,6 +37,13 @@ impl<'a> Config<'a> {
         let cwd = try!(env::current_dir().chain_error(|| {
             human("couldn't get the current directory of the process")
         }));
+        Config::with_cwd(shell, cwd)
+    }
+
+    /// Creates a configuration as if cargo was run in `cwd`, which is where
+    /// the search for configuration files starts.
+    pub fn with_cwd(shell: &'a mut MultiShell,
+                    cwd: Path) -> CargoResult<Config<'a>> {
         let (rustc_version, rustc_host) = try!(ops::rustc_version());
 
         Ok(Config {
@@ -46,6 +57,7 @@ impl<'a> Config<'a> {
             cwd: cwd,
             values: RefCell::new(HashMap::new()),
             values_loaded: Cell::new(false),
+            cli_values: RefCell::new(HashMap::new()),
         })
     }
 
@@ -75,6 +87,71 @@ impl<'a> Config<'a> {
         self.shell.borrow_mut()
     }
 
+    /// Configures the shell from the `--verbose`, `--quiet` and `--color`
+    /// flags of a command, where `verbose` is the number of times the first
+    /// was passed. Without `--color` the `term.color` key is consulted.
+    pub fn configure_shell(&self, verbose: u32, quiet: bool,
+                           color: &Option<String>) -> CargoResult<()> {
+        let verbosity = match (verbose, quiet) {
+            (0, false) => Verbosity::Normal,
+            (0, true) => Verbosity::Quiet,
+            (1, false) => Verbosity::Verbose,
+            (_, false) => Verbosity::VeryVerbose,
+            (_, true) => {
+                return Err(human("cannot pass both --verbose and --quiet"))
+            }
+        };
+        let (color, source) = match *color {
+            Some(ref color) => (color.clone(), "--color".to_string()),
+            None => match try!(self.get_string("term.color")) {
+                Some((color, path)) => {
+                    (color, format!("`term.color` in {}", path.display()))
+                }
+                None => ("auto".to_string(), String::new()),
+            },
+        };
+        let color = match color.as_slice() {
+            "auto" => ColorConfig::Auto,
+            "always" => ColorConfig::Always,
+            "never" => ColorConfig::Never,
+            _ => {
+                return Err(human(format!("{} must be one of auto, always or \
+                                          never, but found `{}`",
+                                         source, color)))
+            }
+        };
+        let mut shell = self.shell();
+        shell.set_verbosity(verbosity);
+        shell.set_color_config(color);
+        Ok(())
+    }
+
+    /// Sets how errors aborting cargo are printed, from `--error-format` if
+    /// given or `term.error-format` otherwise.
+    pub fn configure_error_format(&self, format: &Option<String>)
+                                  -> CargoResult<()> {
+        let (format, source) = match *format {
+            Some(ref format) => (format.clone(), "--error-format".to_string()),
+            None => match try!(self.get_string("term.error-format")) {
+                Some((format, path)) => {
+                    (format, format!("`term.error-format` in {}",
+                                     path.display()))
+                }
+                None => return Ok(()),
+            },
+        };
+        let format = match format.as_slice() {
+            "human" => ErrorFormat::Human,
+            "json" => ErrorFormat::Json,
+            _ => {
+                return Err(human(format!("{} must be one of human or json, \
+                                          but found `{}`", source, format)))
+            }
+        };
+        self.shell().set_error_format(format);
+        Ok(())
+    }
+
     /// Return the output of `rustc -v verbose`
     pub fn rustc_version(&self) -> &str { &self.rustc_version }
 
@@ -91,39 +168,95 @@ impl<'a> Config<'a> {
 
     pub fn cwd(&self) -> &Path { &self.cwd }
 
-    pub fn get(&self, key: &str) -> CargoResult<Option<ConfigValue>> {
-        let vals = try!(self.values());
-        let mut parts = key.split('.').enumerate();
-        let mut val = match vals.get(parts.next().unwrap().1) {
-            Some(val) => val,
-            None => return Ok(None),
-        };
-        for (i, part) in parts {
-            match *val {
-                CV::Table(ref map, _) => {
-                    val = match map.get(part) {
-                        Some(val) => val,
-                        None => return Ok(None),
-                    }
+    /// Sets the values given with `--config KEY=VALUE` on the command line,
+    /// which take precedence over all other configuration. Values are parsed
+    /// as TOML, and later arguments override earlier ones.
+    pub fn set_cli_values(&self, args: &[String]) -> CargoResult<()> {
+        let path = Path::new("--config");
+        let mut cfg = CV::Table(HashMap::new(), path.clone());
+        // Values which are merged first take precedence
+        for arg in args.iter().rev() {
+            let (key, value) = match arg.find('=') {
+                Some(i) => (arg[..i].trim(), arg[i + 1..].trim()),
+                None => {
+                    return Err(human(format!("--config argument `{}` must be \
+                                              of the form KEY=VALUE", arg)))
                 }
-                CV::Integer(_, ref path) |
-                CV::String(_, ref path) |
-                CV::List(_, ref path) |
-                CV::Boolean(_, ref path) => {
-                    let idx = key.split('.').take(i)
-                                 .fold(0, |n, s| n + s.len()) + i - 1;
-                    let key_so_far = &key[..idx];
-                    return Err(human(format!("expected table for configuration \
-                                              key `{}`, but found {} in {}",
-                                             key_so_far, val.desc(),
-                                             path.display())));
+            };
+            // Errors only mention the key of credentials
+            let arg = if is_secret(key) {
+                format!("{}=<redacted>", key)
+            } else {
+                arg.to_string()
+            };
+            let toml = format!("value = {}", value);
+            let parsed = cargo_toml::parse(&toml, &path);
+            let mut table = try!(parsed.chain_error(|| {
+                human(format!("failed to parse the value of --config argument \
+                               `{}` as TOML, strings need to be quoted", arg))
+            }));
+            let mut value = try!(CV::from_toml(&path,
+                                               table.remove("value").unwrap()));
+            for part in key.split('.').rev() {
+                let mut map = HashMap::new();
+                map.insert(part.to_string(), value);
+                value = CV::Table(map, path.clone());
+            }
+            try!(cfg.merge(value).chain_error(|| {
+                human(format!("failed to merge --config argument `{}`", arg))
+            }));
+        }
+        *self.cli_values.borrow_mut() = match cfg {
+            CV::Table(map, _) => map,
+            _ => unreachable!(),
+        };
+        Ok(())
+    }
+
+    /// Returns the value of the configuration key `key`, like `build.jobs`.
+    ///
+    /// Values given with `--config` take precedence, followed by the
+    /// environment variable named after the key, like `CARGO_BUILD_JOBS`, and
+    /// then the configuration files. Tables given with `--config` are laid
+    /// over the tables of the files, key by key.
+    pub fn get(&self, key: &str) -> CargoResult<Option<ConfigValue>> {
+        match try!(lookup(&self.cli_values.borrow(), key)) {
+            Some(cli @ CV::Table(..)) => {
+                return Ok(Some(match try!(lookup(&*try!(self.values()), key)) {
+                    Some(file) => overlay(file, cli),
+                    None => cli,
+                }))
+            }
+            Some(val) => return Ok(Some(val)),
+            None => {}
+        }
+        if let Some((val, path)) = self.get_env(key) {
+            // The type of the value can only be told from its contents, where
+            // lists are TOML arrays
+            if val.starts_with("[") {
+                let toml = format!("value = {}", val);
+                if let Ok(mut table) = cargo_toml::parse(&toml, &path) {
+                    let list = table.remove("value").unwrap();
+                    return CV::from_toml(&path, list).map(Some)
                 }
             }
+            return Ok(Some(if val == "true" || val == "false" {
+                CV::Boolean(val == "true", path)
+            } else {
+                match val.parse() {
+                    Ok(i) => CV::Integer(i, path),
+                    Err(..) => CV::String(val, path),
+                }
+            }))
         }
-        Ok(Some(val.clone()))
+        lookup(&*try!(self.values()), key)
     }
 
     pub fn get_string(&self, key: &str) -> CargoResult<Option<(String, Path)>> {
+        // Strings in the environment may look like numbers, like tokens
+        if try!(lookup(&self.cli_values.borrow(), key)).is_none() {
+            if let Some(pair) = self.get_env(key) { return Ok(Some(pair)) }
+        }
         match try!(self.get(key)) {
             Some(CV::String(i, path)) => Ok(Some((i, path))),
             Some(val) => self.expected("string", key, val),
@@ -148,6 +281,56 @@ impl<'a> Config<'a> {
         }
     }
 
+    /// Returns every configured key, like `build.jobs`, along with its value
+    /// after applying `--config` and environment overrides.
+    ///
+    /// Keys which are only set in the environment are not included, as the
+    /// names of environment variables can't be mapped back to keys.
+    pub fn list(&self) -> CargoResult<BTreeMap<String, ConfigValue>> {
+        let mut keys = Vec::new();
+        leaf_keys(&self.cli_values.borrow(), "", &mut keys);
+        leaf_keys(&*try!(self.values()), "", &mut keys);
+        let mut ret = BTreeMap::new();
+        for key in keys.into_iter() {
+            if let Some(val) = try!(self.get(&key)) {
+                ret.insert(key, val);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Returns the values given with `--config` as the environment variables
+    /// overriding them, like `CARGO_BUILD_JOBS=4`, to pass them on to other
+    /// processes. Lists are given as TOML arrays, like `["a", "b"]`.
+    pub fn cli_values_env(&self) -> Vec<(String, String)> {
+        let vals = self.cli_values.borrow();
+        let mut keys = Vec::new();
+        leaf_keys(&vals, "", &mut keys);
+        keys.into_iter().filter_map(|key| {
+            let val = match lookup(&vals, &key) {
+                Ok(Some(val)) => val,
+                _ => return None,
+            };
+            let val = match val {
+                CV::String(s, _) => s,
+                CV::Integer(i, _) => i.to_string(),
+                CV::Boolean(b, _) => b.to_string(),
+                list @ CV::List(..) => list.into_toml().to_string(),
+                CV::Table(..) => return None,
+            };
+            Some((env_key(&key), val))
+        }).collect()
+    }
+
+    /// Returns the value of the environment variable overriding the key `key`,
+    /// along with a description of where it was defined.
+    fn get_env(&self, key: &str) -> Option<(String, Path)> {
+        let var = env_key(key);
+        env::var_string(&var).ok().map(|val| {
+            (val, Path::new(format!("environment variable `{}`", var)))
+        })
+    }
+
     pub fn expected<T>(&self, ty: &str, key: &str, val: CV) -> CargoResult<T> {
         val.expected(ty).map_err(|e| {
             human(format!("invalid configuration for key `{}`\n{}", key, e))
@@ -157,19 +340,13 @@ impl<'a> Config<'a> {
     fn load_values(&self) -> CargoResult<()> {
         let mut cfg = CV::Table(HashMap::new(), Path::new("."));
 
-        try!(walk_tree(&self.cwd, |mut file| {
-            let path = file.path().clone();
-            let contents = try!(file.read_to_string());
-            let table = try!(cargo_toml::parse(&contents, &path).chain_error(|| {
-                human(format!("could not parse TOML configuration in `{}`",
-                              path.display()))
-            }));
-            let toml = toml::Value::Table(table);
-            let value = try!(CV::from_toml(&path, toml).chain_error(|| {
-                human(format!("failed to load TOML configuration from `{}`",
-                              path.display()))
-            }));
-            try!(cfg.merge(value));
+        let mut loaded = Vec::new();
+        try!(walk_tree(&self.cwd, |file| {
+            let value = try!(load_file(file.path(), &mut Vec::new(),
+                                       &mut loaded));
+            if let Some(value) = value {
+                try!(cfg.merge(value));
+            }
             Ok(())
         }).chain_error(|| human("Couldn't load Cargo configuration")));
 
@@ -363,7 +540,7 @@ impl ConfigValue {
                              self.definition_path().display())))
     }
 
-    fn into_toml(self) -> toml::Value {
+    pub fn into_toml(self) -> toml::Value {
         match self {
             CV::Boolean(s, _) => toml::Value::Boolean(s),
             CV::String(s, _) => toml::Value::String(s),
@@ -379,12 +556,164 @@ impl ConfigValue {
     }
 }
 
+/// Collects the dotted names of the values in `vals` which aren't tables,
+/// prefixed with `prefix`.
+fn leaf_keys(vals: &HashMap<String, ConfigValue>, prefix: &str,
+             keys: &mut Vec<String>) {
+    for (name, val) in vals.iter() {
+        let key = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        match *val {
+            CV::Table(ref map, _) => leaf_keys(map, &key, keys),
+            _ => keys.push(key),
+        }
+    }
+}
+
+/// Returns whether the configuration key `key` holds a credential, like
+/// `registry.token`, whose value must not be printed.
+pub fn is_secret(key: &str) -> bool {
+    key.split('.').last() == Some("token")
+}
+
+/// Returns `base` with the values of `over` laid over it. Tables are combined
+/// key by key, and any other value of `over` replaces the one of `base`.
+fn overlay(base: ConfigValue, over: ConfigValue) -> ConfigValue {
+    match (base, over) {
+        (CV::Table(mut base, _), CV::Table(over, path)) => {
+            for (key, value) in over.into_iter() {
+                let value = match base.remove(&key) {
+                    Some(old) => overlay(old, value),
+                    None => value,
+                };
+                base.insert(key, value);
+            }
+            CV::Table(base, path)
+        }
+        (_, over) => over,
+    }
+}
+
+fn lookup(vals: &HashMap<String, ConfigValue>,
+          key: &str) -> CargoResult<Option<ConfigValue>> {
+    let mut parts = key.split('.').enumerate();
+    let mut val = match vals.get(parts.next().unwrap().1) {
+        Some(val) => val,
+        None => return Ok(None),
+    };
+    for (i, part) in parts {
+        match *val {
+            CV::Table(ref map, _) => {
+                val = match map.get(part) {
+                    Some(val) => val,
+                    None => return Ok(None),
+                }
+            }
+            CV::Integer(_, ref path) |
+            CV::String(_, ref path) |
+            CV::List(_, ref path) |
+            CV::Boolean(_, ref path) => {
+                let idx = key.split('.').take(i)
+                             .fold(0, |n, s| n + s.len()) + i - 1;
+                let key_so_far = &key[..idx];
+                return Err(human(format!("expected table for configuration \
+                                          key `{}`, but found {} in {}",
+                                         key_so_far, val.desc(),
+                                         path.display())));
+            }
+        }
+    }
+    Ok(Some(val.clone()))
+}
+
+/// Returns the name of the environment variable overriding the configuration
+/// key `key`, e.g. `CARGO_NET_GIT_SUBMODULES` for `net.git-submodules`.
+pub fn env_key(key: &str) -> String {
+    format!("CARGO_{}", key.replace(".", "_").replace("-", "_")
+                           .to_ascii_uppercase())
+}
+
 fn homedir() -> Option<Path> {
     let cargo_home = env::var_string("CARGO_HOME").map(|p| Path::new(p)).ok();
     let user_home = env::home_dir().map(|p| p.join(".cargo"));
     return cargo_home.or(user_home);
 }
 
+/// Loads the configuration file at `path` along with the files listed in its
+/// `include` key, relative to the directory the file is really in after
+/// following symlinks. The including file overrides the files it includes,
+/// and later includes override earlier ones.
+///
+/// `stack` holds the files currently being loaded, to detect cycles, and
+/// `loaded` all files loaded so far. A file which is already loaded is not
+/// loaded again, as its values already take precedence, and `None` is
+/// returned.
+fn load_file(path: &Path, stack: &mut Vec<Path>,
+             loaded: &mut Vec<Path>) -> CargoResult<Option<CV>> {
+    let path = try!(realpath(path).chain_error(|| {
+        human(format!("failed to read configuration file `{}`",
+                      path.display()))
+    }));
+    let path = &path;
+    if stack.contains(path) {
+        let cycle = stack.iter().chain(Some(path).into_iter())
+                         .map(|p| p.display().to_string())
+                         .collect::<Vec<_>>();
+        return Err(human(format!("configuration files include each other: {}",
+                                 cycle.connect(" -> "))))
+    }
+    if loaded.contains(path) { return Ok(None) }
+    loaded.push(path.clone());
+    let contents = try!(File::open(path).read_to_string().chain_error(|| {
+        human(format!("failed to read configuration file `{}`",
+                      path.display()))
+    }));
+    let mut table = try!(cargo_toml::parse(&contents, path).chain_error(|| {
+        human(format!("could not parse TOML configuration in `{}`",
+                      path.display()))
+    }));
+    let includes = match table.remove("include") {
+        None => Vec::new(),
+        Some(toml::Value::String(s)) => vec![s],
+        Some(toml::Value::Array(list)) => {
+            try!(list.into_iter().map(|v| match v {
+                toml::Value::String(s) => Ok(s),
+                v => Err(human(format!("expected a string in `include` of \
+                                        `{}`, but found a {}",
+                                       path.display(), v.type_str()))),
+            }).collect::<CargoResult<Vec<_>>>())
+        }
+        Some(v) => {
+            return Err(human(format!("`include` in `{}` must be a string or a \
+                                      list of strings, but found a {}",
+                                     path.display(), v.type_str())))
+        }
+    };
+    let toml = toml::Value::Table(table);
+    let mut value = try!(CV::from_toml(path, toml).chain_error(|| {
+        human(format!("failed to load TOML configuration from `{}`",
+                      path.display()))
+    }));
+
+    stack.push(path.clone());
+    for include in includes.iter().rev() {
+        let include = path.dir_path().join(include);
+        if !include.exists() {
+            return Err(human(format!("configuration file `{}` included by \
+                                      `{}` does not exist", include.display(),
+                                     path.display())))
+        }
+        if let Some(included) = try!(load_file(&include, stack, loaded)) {
+            try!(value.merge(included));
+        }
+    }
+    stack.pop();
+    Ok(Some(value))
+}
+
 fn walk_tree<F>(pwd: &Path, mut walk: F) -> CargoResult<()>
     where F: FnMut(File) -> CargoResult<()>
 {
@@ -415,6 +744,13 @@ fn walk_tree<F>(pwd: &Path, mut walk: F) -> CargoResult<()>
         }
     }
 
+    // Tokens saved by `cargo login` are kept apart from the configuration
+    let credentials = home.join("credentials");
+    if credentials.exists() {
+        let file = try!(File::open(&credentials));
+        try!(walk(file));
+    }
+
     Ok(())
 }
 
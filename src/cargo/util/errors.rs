@@ -20,6 +20,23 @@ pub type CargoResult<T> = Result<T, Box<CargoError>>;
 pub trait CargoError: Error + Send {
     fn is_human(&self) -> bool { false }
     fn cargo_cause(&self) -> Option<&CargoError>{ None }
+    /// A short identifier of the kind of error, like `network`, for tools
+    /// reading `--error-format json`.
+    fn code(&self) -> Option<&'static str> { None }
+    /// The locations within files, like manifests, the error is about.
+    fn spans(&self) -> Vec<ErrorSpan> { Vec::new() }
+}
+
+/// A location within a file which an error is about. Lines and columns start
+/// at 1.
+#[derive(Clone, Debug, RustcEncodable)]
+pub struct ErrorSpan {
+    pub file: String,
+    pub line_start: usize,
+    pub column_start: usize,
+    pub line_end: usize,
+    pub column_end: usize,
+    pub label: String,
 }
 
 impl fmt::Debug for Box<CargoError> {
@@ -36,6 +53,8 @@ impl Error for Box<CargoError> {
 impl CargoError for Box<CargoError> {
     fn is_human(&self) -> bool { (**self).is_human() }
     fn cargo_cause(&self) -> Option<&CargoError> { (**self).cargo_cause() }
+    fn code(&self) -> Option<&'static str> { (**self).code() }
+    fn spans(&self) -> Vec<ErrorSpan> { (**self).spans() }
 }
 
 // =============================================================================
@@ -93,6 +112,8 @@ impl<E: fmt::Display> fmt::Display for ChainedError<E> {
 impl<E: CargoError> CargoError for ChainedError<E> {
     fn is_human(&self) -> bool { self.error.is_human() }
     fn cargo_cause(&self) -> Option<&CargoError> { Some(&*self.cause) }
+    fn code(&self) -> Option<&'static str> { self.error.code() }
+    fn spans(&self) -> Vec<ErrorSpan> { self.error.spans() }
 }
 
 // =============================================================================
@@ -131,6 +152,8 @@ struct ConcreteCargoError {
     detail: Option<String>,
     cause: Option<Box<Error+Send>>,
     is_human: bool,
+    code: Option<&'static str>,
+    spans: Vec<ErrorSpan>,
 }
 
 impl fmt::Display for ConcreteCargoError {
@@ -161,6 +184,8 @@ impl CargoError for ConcreteCargoError {
     fn is_human(&self) -> bool {
         self.is_human
     }
+    fn code(&self) -> Option<&'static str> { self.code }
+    fn spans(&self) -> Vec<ErrorSpan> { self.spans.clone() }
 }
 
 // =============================================================================
@@ -182,6 +207,8 @@ impl<E: fmt::Display> fmt::Display for Human<E> {
 impl<E: CargoError> CargoError for Human<E> {
     fn is_human(&self) -> bool { true }
     fn cargo_cause(&self) -> Option<&CargoError> { self.0.cargo_cause() }
+    fn code(&self) -> Option<&'static str> { self.0.code() }
+    fn spans(&self) -> Vec<ErrorSpan> { self.0.spans() }
 }
 
 // =============================================================================
@@ -258,17 +285,31 @@ impl<E: CargoError> FromError<Human<E>> for Box<CargoError> {
     fn from_error(t: Human<E>) -> Box<CargoError> { Box::new(t) }
 }
 
-impl CargoError for semver::ReqParseError {}
-impl CargoError for IoError {}
-impl CargoError for git2::Error {}
-impl CargoError for json::DecoderError {}
-impl CargoError for curl::ErrCode {}
-impl CargoError for ProcessError {}
-impl CargoError for CliError {}
-impl CargoError for toml::Error {}
-impl CargoError for toml::DecodeError {}
-impl CargoError for url::ParseError {}
-impl CargoError for str::Utf8Error {}
+macro_rules! coded_error {
+    ($($p:ty => $code:expr,)*) => (
+        $(impl CargoError for $p {
+            fn code(&self) -> Option<&'static str> { Some($code) }
+        })*
+    )
+}
+
+coded_error! {
+    semver::ReqParseError => "semver",
+    IoError => "io",
+    git2::Error => "git",
+    json::DecoderError => "json",
+    curl::ErrCode => "network",
+    ProcessError => "process",
+    toml::Error => "toml",
+    toml::DecodeError => "toml",
+    url::ParseError => "url",
+    str::Utf8Error => "utf8",
+}
+
+impl CargoError for CliError {
+    fn code(&self) -> Option<&'static str> { self.error.code() }
+    fn spans(&self) -> Vec<ErrorSpan> { self.error.spans() }
+}
 
 // =============================================================================
 // Construction helpers
@@ -313,7 +354,9 @@ pub fn internal_error(error: &str, detail: &str) -> Box<CargoError> {
         description: error.to_string(),
         detail: Some(detail.to_string()),
         cause: None,
-        is_human: false
+        is_human: false,
+        code: None,
+        spans: Vec::new(),
     })
 }
 
@@ -322,7 +365,9 @@ pub fn internal<S: fmt::Display>(error: S) -> Box<CargoError> {
         description: error.to_string(),
         detail: None,
         cause: None,
-        is_human: false
+        is_human: false,
+        code: None,
+        spans: Vec::new(),
     })
 }
 
@@ -331,7 +376,9 @@ pub fn human<S: fmt::Display>(error: S) -> Box<CargoError> {
         description: error.to_string(),
         detail: None,
         cause: None,
-        is_human: true
+        is_human: true,
+        code: None,
+        spans: Vec::new(),
     })
 }
 
@@ -340,6 +387,22 @@ pub fn caused_human<S: fmt::Display, E: Error + Send>(error: S, cause: E) -> Box
         description: error.to_string(),
         detail: None,
         cause: Some(Box::new(cause) as Box<Error + Send>),
-        is_human: true
+        is_human: true,
+        code: None,
+        spans: Vec::new(),
+    })
+}
+
+/// Like `human`, but with a `code` classifying the error and the `spans` in
+/// files it is about.
+pub fn coded_human<S: fmt::Display>(code: &'static str, error: S,
+                                    spans: Vec<ErrorSpan>) -> Box<CargoError> {
+    Box::new(ConcreteCargoError {
+        description: error.to_string(),
+        detail: None,
+        cause: None,
+        is_human: true,
+        code: Some(code),
+        spans: spans,
     })
 }
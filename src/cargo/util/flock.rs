@@ -0,0 +1,130 @@
+//! Advisory locks shared between concurrent cargo processes.
+//!
+//! A lock is a directory next to the resource it protects, as creating a
+//! directory either succeeds or fails atomically on all platforms. The lock
+//! is released by removing the directory again when the `FileLock` is
+//! dropped.
+//!
+//! The directory holds a `pid` file naming the process holding the lock,
+//! which is touched regularly for as long as the lock is held. A lock is
+//! taken over once that process is gone, or once the file hasn't been touched
+//! for a while, as the process holding it was killed or hung.
+
+use std::old_io::{self, fs, File, USER_DIR};
+use std::old_io::fs::PathExtensions;
+use std::old_io::timer;
+use std::os;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::Thread;
+use std::time::Duration;
+
+use util::{paths, CargoResult, ChainError, Config, human};
+
+/// How often (in milliseconds) the `pid` file of a held lock is touched.
+const HEARTBEAT: i64 = 60 * 1000;
+
+/// Locks whose `pid` file hasn't been touched for this long (in milliseconds)
+/// are assumed to be left behind.
+const STALE_AFTER: u64 = 10 * 60 * 1000;
+
+pub struct FileLock {
+    path: Path,
+    /// Tells the thread touching the lock to stop
+    released: Arc<AtomicBool>,
+}
+
+impl FileLock {
+    /// Acquires the lock at `path`, blocking until any other process holding
+    /// it releases it. `what` describes the locked resource in the message
+    /// printed while waiting.
+    pub fn acquire(path: &Path, what: &str,
+                   config: &Config) -> CargoResult<FileLock> {
+        try!(fs::mkdir_recursive(&path.dir_path(), USER_DIR));
+        let mut waiting = false;
+        loop {
+            match fs::mkdir(path, USER_DIR) {
+                Ok(()) => {
+                    return FileLock::hold(path).chain_error(|| {
+                        human(format!("failed to lock {}", what))
+                    })
+                }
+                Err(ref e) if e.kind == old_io::PathAlreadyExists ||
+                              path.exists() => {}
+                Err(e) => {
+                    return Err(e).chain_error(|| {
+                        human(format!("failed to lock {}", what))
+                    })
+                }
+            }
+            if is_stale(path) {
+                let _ = fs::rmdir_recursive(path);
+                continue
+            }
+            if !waiting {
+                waiting = true;
+                try!(config.shell().status("Blocking",
+                    format!("waiting for another cargo process to release \
+                             the lock on {}", what)));
+            }
+            timer::sleep(Duration::milliseconds(100));
+        }
+    }
+
+    /// Records this process in the freshly created lock at `path`, and keeps
+    /// touching the record until the lock is released.
+    fn hold(path: &Path) -> CargoResult<FileLock> {
+        let pid = path.join("pid");
+        let record = File::create(&pid).write_str(&os::getpid().to_string());
+        try!(record.map_err(|e| {
+            let _ = fs::rmdir_recursive(path);
+            e
+        }));
+        let released = Arc::new(AtomicBool::new(false));
+        let stop = released.clone();
+        Thread::spawn(move || {
+            loop {
+                timer::sleep(Duration::milliseconds(HEARTBEAT));
+                if stop.load(Ordering::SeqCst) { break }
+                let now = paths::now_ms();
+                if fs::change_file_times(&pid, now, now).is_err() { break }
+            }
+        });
+        Ok(FileLock { path: path.clone(), released: released })
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        self.released.store(true, Ordering::SeqCst);
+        let _ = fs::rmdir_recursive(&self.path);
+    }
+}
+
+/// Returns whether the lock at `path` was left behind, by a process which is
+/// gone or which stopped touching it.
+fn is_stale(path: &Path) -> bool {
+    let pid = path.join("pid");
+    let holder = File::open(&pid).read_to_string().ok().and_then(|s| {
+        s.trim().parse().ok()
+    });
+    if holder.and_then(is_running) == Some(false) { return true }
+
+    // The `pid` file doesn't exist for a moment after the lock is created
+    let touched = fs::stat(&pid).or_else(|_| fs::stat(path));
+    match touched {
+        Ok(stat) => paths::now_ms().saturating_sub(stat.modified) > STALE_AFTER,
+        Err(..) => false,
+    }
+}
+
+/// Returns whether the process `pid` is running, if that can be told.
+#[cfg(unix)]
+fn is_running(pid: i32) -> Option<bool> {
+    use libc;
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } == 0 { return Some(true) }
+    Some(os::errno() as libc::c_int != libc::ESRCH)
+}
+
+#[cfg(windows)]
+fn is_running(_pid: i32) -> Option<bool> { None }
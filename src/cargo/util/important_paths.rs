@@ -31,13 +31,28 @@ pub fn find_project_manifest(pwd: &Path, file: &str) -> CargoResult<Path> {
 }
 
 /// Find the root Cargo.toml
+///
+/// If `manifest_path` is given, as with the `--manifest-path` option of the
+/// commands, it is used instead of searching upwards from the current
+/// directory.
 pub fn find_root_manifest_for_cwd(manifest_path: Option<String>)
                                   -> CargoResult<Path> {
     let cwd = try!(env::current_dir().chain_error(|| {
         human("Couldn't determine the current working directory")
     }));
     match manifest_path {
-        Some(path) => Ok(cwd.join(path)),
+        Some(path) => {
+            let manifest = cwd.join(path);
+            if manifest.filename_str() != Some("Cargo.toml") {
+                return Err(human("the manifest-path must be a path to a \
+                                  Cargo.toml file"))
+            }
+            if !manifest.exists() {
+                return Err(human(format!("manifest path `{}` does not exist",
+                                         manifest.display())))
+            }
+            Ok(manifest)
+        }
         None => find_project_manifest(&cwd, "Cargo.toml"),
     }
 }
@@ -0,0 +1,241 @@
+//! The jobserver protocol of GNU make.
+//!
+//! A jobserver is a pipe holding one byte, a token, for every job which may
+//! run besides the one each process may always run. A job is started by
+//! reading a token from the pipe and the token is written back once the job
+//! is done, so that `make -j8` running cargo, cargo itself and the `make`s run
+//! by build scripts together run at most 8 jobs at a time.
+//!
+//! When run by `make`, cargo takes its tokens from the jobserver announced in
+//! `MAKEFLAGS`. Otherwise, if `build.jobserver` is set, it creates a jobserver
+//! of its own with as many tokens as it may run jobs, and announces it to the
+//! compiler and build scripts in turn. Processes spawned by cargo don't
+//! inherit any file descriptors, so that jobserver is a named pipe, which is
+//! only supported by GNU make 4.4 and later.
+
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use libc::c_int;
+
+use util::{CargoResult, ChainError, human};
+
+pub struct JobServer {
+    read: c_int,
+    write: c_int,
+    /// The named pipe of a jobserver created by cargo, removed when dropped
+    fifo: Option<Path>,
+    /// Whether the job this process may run without a token isn't running
+    implicit: Mutex<bool>,
+}
+
+/// The right to run a job, returned to the jobserver when dropped.
+pub struct Token {
+    server: Arc<JobServer>,
+    /// The byte read from the pipe, or `None` for the implicit token
+    byte: Option<u8>,
+}
+
+impl JobServer {
+    /// Connects to the jobserver announced by a `make` running cargo, if any.
+    ///
+    /// Both the `--jobserver-auth=R,W` (or older `--jobserver-fds=R,W`) form
+    /// naming inherited file descriptors and the `--jobserver-auth=fifo:PATH`
+    /// form naming a named pipe are understood. Descriptors which aren't open
+    /// are ignored, as `make` doesn't pass them to commands it doesn't know to
+    /// be recursive.
+    pub fn from_env() -> Option<JobServer> {
+        let flags = ["CARGO_MAKEFLAGS", "MAKEFLAGS", "MFLAGS"].iter()
+                        .filter_map(|v| env::var_string(v).ok())
+                        .next();
+        let flags = match flags {
+            Some(flags) => flags,
+            None => return None,
+        };
+        let auth = flags.split(' ').filter_map(|flag| {
+            if flag.starts_with("--jobserver-auth=") {
+                Some(&flag["--jobserver-auth=".len()..])
+            } else if flag.starts_with("--jobserver-fds=") {
+                Some(&flag["--jobserver-fds=".len()..])
+            } else {
+                None
+            }
+        }).last();
+        let auth = match auth {
+            Some(auth) => auth,
+            None => return None,
+        };
+
+        let (read, write) = if auth.starts_with("fifo:") {
+            match imp::open_fifo(&Path::new(&auth["fifo:".len()..])) {
+                Ok(fd) => (fd, fd),
+                Err(..) => return None,
+            }
+        } else {
+            let mut fds = auth.splitn(1, ',').map(|fd| fd.parse::<c_int>());
+            match (fds.next(), fds.next()) {
+                (Some(Ok(read)), Some(Ok(write))) => (read, write),
+                _ => return None,
+            }
+        };
+        if !imp::is_open(read) || !imp::is_open(write) { return None }
+        Some(JobServer {
+            read: read,
+            write: write,
+            fifo: None,
+            implicit: Mutex::new(true),
+        })
+    }
+
+    /// Creates a jobserver allowing `jobs` jobs to run at once, with its named
+    /// pipe at `path`. Returns `None` on platforms without named pipes.
+    pub fn new(jobs: u32, path: &Path) -> CargoResult<Option<JobServer>> {
+        let fd = match try!(imp::create_fifo(path).chain_error(|| {
+            human(format!("failed to create the jobserver at `{}`",
+                          path.display()))
+        })) {
+            Some(fd) => fd,
+            None => return Ok(None),
+        };
+        let server = JobServer {
+            read: fd,
+            write: fd,
+            fifo: Some(path.clone()),
+            implicit: Mutex::new(true),
+        };
+        for _ in range(1, jobs) {
+            try!(imp::write_byte(fd, b'|'));
+        }
+        Ok(Some(server))
+    }
+
+    /// The `MAKEFLAGS` announcing this jobserver to the processes cargo runs,
+    /// if cargo created it. Those of a `make` running cargo are passed on as
+    /// they are.
+    pub fn makeflags(&self) -> Option<String> {
+        self.fifo.as_ref().map(|fifo| {
+            format!("-j --jobserver-auth=fifo:{}", fifo.display())
+        })
+    }
+
+    /// Blocks until a job may be run.
+    pub fn acquire(server: &Arc<JobServer>) -> CargoResult<Token> {
+        {
+            let mut implicit = server.implicit.lock().unwrap();
+            if *implicit {
+                *implicit = false;
+                return Ok(Token { server: server.clone(), byte: None })
+            }
+        }
+        let byte = try!(imp::read_byte(server.read).chain_error(|| {
+            human("failed to acquire a token from the jobserver")
+        }));
+        Ok(Token { server: server.clone(), byte: Some(byte) })
+    }
+}
+
+impl Drop for JobServer {
+    fn drop(&mut self) {
+        if let Some(ref fifo) = self.fifo {
+            imp::close(self.read);
+            let _ = ::std::old_io::fs::unlink(fifo);
+        }
+    }
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        match self.byte {
+            Some(byte) => { let _ = imp::write_byte(self.server.write, byte); }
+            None => { *self.server.implicit.lock().unwrap() = true; }
+        }
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::CString;
+    use std::old_io::{fs, IoError, IoResult};
+    use std::os;
+    use libc::{self, c_int, c_char, mode_t};
+
+    const F_GETFD: c_int = 1;
+    extern {
+        fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+        fn mkfifo(path: *const c_char, mode: mode_t) -> c_int;
+    }
+
+    pub fn is_open(fd: c_int) -> bool {
+        unsafe { fcntl(fd, F_GETFD) != -1 }
+    }
+
+    // Opening the pipe for reading and writing keeps it from reaching its end
+    // while no job is running, and opening it from blocking.
+    pub fn open_fifo(path: &Path) -> IoResult<c_int> {
+        let path = CString::from_slice(path.as_vec());
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDWR, 0) };
+        if fd < 0 { Err(IoError::last_error()) } else { Ok(fd) }
+    }
+
+    // A pipe left behind by a cargo which had the same pid is replaced.
+    pub fn create_fifo(path: &Path) -> IoResult<Option<c_int>> {
+        let c_path = CString::from_slice(path.as_vec());
+        if unsafe { mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+            if os::errno() as c_int != libc::EEXIST {
+                return Err(IoError::last_error())
+            }
+            try!(fs::unlink(path));
+            if unsafe { mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(IoError::last_error())
+            }
+        }
+        open_fifo(path).map(Some)
+    }
+
+    pub fn read_byte(fd: c_int) -> IoResult<u8> {
+        let mut byte = [0u8];
+        loop {
+            let n = unsafe {
+                libc::read(fd, byte.as_mut_ptr() as *mut libc::c_void, 1)
+            };
+            if n == 1 { return Ok(byte[0]) }
+            if n < 0 && os::errno() as c_int == libc::EINTR { continue }
+            return Err(IoError::last_error())
+        }
+    }
+
+    pub fn write_byte(fd: c_int, byte: u8) -> IoResult<()> {
+        let byte = [byte];
+        loop {
+            let n = unsafe {
+                libc::write(fd, byte.as_ptr() as *const libc::c_void, 1)
+            };
+            if n == 1 { return Ok(()) }
+            if n < 0 && os::errno() as c_int == libc::EINTR { continue }
+            return Err(IoError::last_error())
+        }
+    }
+
+    pub fn close(fd: c_int) {
+        unsafe { libc::close(fd); }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::old_io::{IoResult, standard_error, OtherIoError};
+    use libc::c_int;
+
+    pub fn is_open(_fd: c_int) -> bool { false }
+    pub fn open_fifo(_path: &Path) -> IoResult<c_int> {
+        Err(standard_error(OtherIoError))
+    }
+    pub fn create_fifo(_path: &Path) -> IoResult<Option<c_int>> { Ok(None) }
+    pub fn read_byte(_fd: c_int) -> IoResult<u8> {
+        Err(standard_error(OtherIoError))
+    }
+    pub fn write_byte(_fd: c_int, _byte: u8) -> IoResult<()> {
+        Err(standard_error(OtherIoError))
+    }
+    pub fn close(_fd: c_int) {}
+}
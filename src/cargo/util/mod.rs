@@ -3,8 +3,10 @@ pub use self::process_builder::{process, ProcessBuilder};
 pub use self::errors::{CargoResult, CargoError, ChainError, CliResult};
 pub use self::errors::{CliError, ProcessError};
 pub use self::errors::{process_error, internal_error, internal, human};
-pub use self::errors::{Human, caused_human};
-pub use self::paths::{realpath, join_paths};
+pub use self::errors::{Human, caused_human, coded_human, ErrorSpan};
+pub use self::flock::FileLock;
+pub use self::paths::{realpath, join_paths, disk_usage, human_size, long_path};
+pub use self::progress::Progress;
 pub use self::lev_distance::{lev_distance};
 pub use self::hex::{to_hex, short_hash};
 pub use self::dependency_queue::{DependencyQueue, Fresh, Dirty, Freshness};
@@ -17,16 +19,21 @@ pub use self::sha256::Sha256;
 
 pub mod config;
 pub mod errors;
+pub mod flock;
 pub mod graph;
 pub mod hex;
 pub mod important_paths;
+pub mod jobserver;
 pub mod paths;
 pub mod process_builder;
 pub mod profile;
+pub mod progress;
 pub mod to_semver;
 pub mod to_url;
 pub mod toml;
+pub mod usage;
 pub mod lev_distance;
+pub mod workspace;
 mod dependency_queue;
 mod sha256;
 mod vcs;
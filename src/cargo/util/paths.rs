@@ -1,8 +1,14 @@
+use std::cmp;
 use std::env;
-use std::old_io::fs;
-use std::old_io;
+use std::old_io::fs::{self, PathExtensions};
+use std::old_io::{self, File, FileType};
 use std::old_path::BytesContainer;
 use std::os;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, TaskPool};
+
+use time;
 
 use util::{human, CargoResult};
 
@@ -44,6 +50,41 @@ pub fn realpath(original: &Path) -> old_io::IoResult<Path> {
     return Ok(result);
 }
 
+/// Returns the newest modification time among `files`, which are looked at on
+/// as many threads as there are CPUs, as a package may have a lot of them.
+/// Files which can't be looked at count as never modified.
+///
+/// If `limit` is given, `None` is returned as soon as a file modified after
+/// it is found, without looking at the rest.
+pub fn newest_mtime(files: Vec<Path>, limit: Option<u64>) -> Option<u64> {
+    let threads = cmp::max(1, cmp::min(os::num_cpus(), files.len() / 1000));
+    let pool = TaskPool::new(threads);
+    let (tx, rx) = channel();
+    let found = Arc::new(AtomicBool::new(false));
+    let chunk = files.len() / threads + 1;
+    let mut files = files.into_iter();
+    for _ in 0..threads {
+        let files = files.by_ref().take(chunk).collect::<Vec<_>>();
+        let (tx, found) = (tx.clone(), found.clone());
+        pool.execute(move || {
+            let mut max = 0;
+            for file in files.iter() {
+                if found.load(Ordering::SeqCst) { break }
+                let mtime = file.stat().map(|s| s.modified).unwrap_or(0);
+                if limit.map_or(false, |limit| mtime > limit) {
+                    found.store(true, Ordering::SeqCst);
+                    break
+                }
+                max = cmp::max(max, mtime);
+            }
+            let _ = tx.send(max);
+        });
+    }
+    drop(tx);
+    let max = rx.iter().fold(0, cmp::max);
+    if found.load(Ordering::SeqCst) {None} else {Some(max)}
+}
+
 #[allow(deprecated)] // need an OsStr-based Command first
 pub fn join_paths<T: BytesContainer>(paths: &[T], env: &str)
                                      -> CargoResult<Vec<u8>> {
@@ -52,3 +93,212 @@ pub fn join_paths<T: BytesContainer>(paths: &[T], env: &str)
                        Does ${} have an unterminated quote character?", e, env))
     })
 }
+
+/// Returns the number of files and the total size of all files underneath
+/// `path`, including `path` itself.
+pub fn disk_usage(path: &Path) -> old_io::IoResult<(u64, u64)> {
+    if !path.is_dir() {
+        let stat = try!(fs::lstat(path));
+        return Ok((1, stat.size))
+    }
+    let mut files = 0;
+    let mut bytes = 0;
+    for entry in try!(fs::walk_dir(path)) {
+        let stat = try!(fs::lstat(&entry));
+        if stat.kind == FileType::Directory { continue }
+        files += 1;
+        bytes += stat.size;
+    }
+    Ok((files, bytes))
+}
+
+/// Formats a number of bytes for humans, e.g. `1.5MiB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&'static str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{}B", bytes)
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", size, UNITS[unit])
+}
+
+/// Records that the cache entry at `path` in the cargo home `home` has just
+/// been used.
+///
+/// The time is kept in a marker file at the same relative path in
+/// `home/.last-use`, so that the cached files themselves are left alone. The
+/// timestamps are consulted by `cargo cache` to figure out what can be garbage
+/// collected. Failing to record a use only affects garbage collection, so
+/// errors are only logged.
+pub fn mark_used(home: &Path, path: &Path) {
+    let marker = match last_use_marker(home, path) {
+        Some(marker) => marker,
+        None => return,
+    };
+    let res = fs::mkdir_recursive(&marker.dir_path(), old_io::USER_DIR)
+                 .and_then(|()| File::create(&marker)).and_then(|_| {
+        let now = now_ms();
+        fs::change_file_times(&marker, now, now)
+    });
+    if let Err(e) = res {
+        debug!("failed to record the use of {}: {}", path.display(), e);
+    }
+}
+
+/// Returns the time, in milliseconds since the epoch, at which the cache
+/// entry at `path` was last used according to `mark_used`, falling back to
+/// its modification time.
+pub fn last_used(home: &Path, path: &Path) -> old_io::IoResult<u64> {
+    match last_use_marker(home, path) {
+        Some(ref marker) if marker.exists() => {
+            Ok(try!(fs::stat(marker)).modified)
+        }
+        _ => Ok(try!(fs::stat(path)).modified),
+    }
+}
+
+/// The file recording the last use of the cache entry at `path`, if it is
+/// inside of the cargo home `home`.
+pub fn last_use_marker(home: &Path, path: &Path) -> Option<Path> {
+    if !home.is_ancestor_of(path) || home == path { return None }
+    path.path_relative_from(home).map(|rel| home.join(".last-use").join(rel))
+}
+
+/// Milliseconds since the epoch.
+pub fn now_ms() -> u64 {
+    let now = time::get_time();
+    (now.sec as u64) * 1000 + (now.nsec as u64) / 1000000
+}
+
+/// Makes `dst` a copy of the file `src` which shares its storage with `src`
+/// where the filesystem supports copy-on-write clones, like btrfs or XFS.
+/// Both files can still be modified independently. A regular copy is made on
+/// other filesystems.
+pub fn clone_file(src: &Path, dst: &Path) -> old_io::IoResult<()> {
+    if reflink(src, dst) {
+        let perm = try!(fs::stat(src)).perm;
+        fs::chmod(dst, perm)
+    } else {
+        fs::copy(src, dst)
+    }
+}
+
+/// Hard links `dst` to the file `src`, falling back to `clone_file` if that
+/// isn't possible, for example because they are on different filesystems.
+///
+/// As hard links share their contents this must only be used for files which
+/// are not modified afterwards.
+pub fn link_or_copy(src: &Path, dst: &Path) -> old_io::IoResult<()> {
+    let (src, dst) = (&long_path(src), &long_path(dst));
+    if dst.exists() {
+        try!(fs::unlink(dst));
+    }
+    match fs::link(src, dst) {
+        Ok(()) => Ok(()),
+        Err(..) => clone_file(src, dst),
+    }
+}
+
+/// Recreates the directory `src` at `dst` with `link_or_copy`.
+pub fn link_tree(src: &Path, dst: &Path) -> old_io::IoResult<()> {
+    let (src, dst) = (&long_path(src), &long_path(dst));
+    try!(fs::mkdir_recursive(dst, old_io::USER_DIR));
+    for path in try!(fs::walk_dir(src)) {
+        let target = dst.join(path.path_relative_from(src).unwrap());
+        if try!(fs::lstat(&path)).kind == FileType::Directory {
+            try!(fs::mkdir_recursive(&target, old_io::USER_DIR));
+        } else {
+            try!(link_or_copy(&path, &target));
+        }
+    }
+    Ok(())
+}
+
+/// Makes `dst` a copy-on-write clone of the file `src`, returning whether
+/// the filesystem supported that. Only Linux is supported for now.
+#[cfg(target_os = "linux")]
+pub fn reflink(src: &Path, dst: &Path) -> bool {
+    use std::ffi::CString;
+    use libc;
+
+    const FICLONE: libc::c_ulong = 0x40049409;
+    extern {
+        fn ioctl(fd: libc::c_int, request: libc::c_ulong, ...) -> libc::c_int;
+    }
+
+    let src_c = CString::from_slice(src.as_vec());
+    let dst_c = CString::from_slice(dst.as_vec());
+    unsafe {
+        let from = libc::open(src_c.as_ptr(), libc::O_RDONLY, 0);
+        if from < 0 { return false }
+        let to = libc::open(dst_c.as_ptr(),
+                            libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+                            0o644);
+        if to < 0 {
+            libc::close(from);
+            return false
+        }
+        let ok = ioctl(to, FICLONE, from) == 0;
+        libc::close(from);
+        libc::close(to);
+        if !ok {
+            let _ = fs::unlink(dst);
+        }
+        ok
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reflink(_src: &Path, _dst: &Path) -> bool { false }
+
+/// Returns `path` in the extended-length form `\\?\C:\...` on Windows.
+///
+/// Such paths aren't limited to `MAX_PATH` characters, which deep workspaces
+/// easily exceed, and may contain file names which are otherwise reserved,
+/// like `aux.rs`. They must be absolute, so relative paths are joined to the
+/// current directory first. Other platforms have no such limits, so `path` is
+/// returned unchanged.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> Path {
+    let path = match env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(..) => return path.clone(),
+    };
+    let s = match path.as_str() {
+        Some(s) => s.to_string(),
+        None => return path,
+    };
+    if s.starts_with("\\\\?\\") {
+        path
+    } else if s.starts_with("\\\\") {
+        Path::new(format!("\\\\?\\UNC\\{}", &s[2..]))
+    } else {
+        Path::new(format!("\\\\?\\{}", s))
+    }
+}
+
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> Path { path.clone() }
+
+#[cfg(all(test, windows))]
+mod tests {
+    use super::long_path;
+
+    #[test]
+    fn long_paths_are_extended_length() {
+        assert_eq!(long_path(&Path::new("C:\\foo\\aux.rs")).as_str(),
+                   Some("\\\\?\\C:\\foo\\aux.rs"));
+        assert_eq!(long_path(&Path::new("\\\\server\\share\\foo")).as_str(),
+                   Some("\\\\?\\UNC\\server\\share\\foo"));
+        let long = Path::new("\\\\?\\C:\\foo");
+        assert_eq!(long_path(&long), long);
+        let relative = long_path(&Path::new("foo"));
+        assert!(relative.as_str().unwrap().starts_with("\\\\?\\"));
+        assert!(relative.as_str().unwrap().ends_with("\\foo"));
+    }
+}
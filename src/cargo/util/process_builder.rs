@@ -2,8 +2,11 @@ use std::collections::HashMap;
 use std::env;
 use std::ffi::CString;
 use std::fmt::{self, Formatter};
+use std::old_io::BufferedReader;
 use std::old_io::process::{Command, ProcessOutput, InheritFd};
 use std::old_path::BytesContainer;
+use std::sync::mpsc::channel;
+use std::thread::Thread;
 
 use util::{CargoResult, ProcessError, process_error};
 
@@ -96,6 +99,68 @@ impl ProcessBuilder {
         }
     }
 
+    /// Like `exec_with_output`, but also calls `on_stdout` and `on_stderr`
+    /// with each line of output as soon as the process prints it.
+    pub fn exec_with_streaming(&self, on_stdout: &mut FnMut(&[u8]),
+                               on_stderr: &mut FnMut(&[u8]))
+                               -> Result<ProcessOutput, ProcessError> {
+        let mut child = try!(self.build_command().spawn().map_err(|e| {
+            process_error(&format!("Could not execute process `{}`",
+                               self.debug_string()),
+                          Some(e), None, None)
+        }));
+
+        // Both pipes are read at once so that neither fills up and blocks the
+        // process, with the lines sent back here in the order they're read.
+        let (tx, rx) = channel();
+        let pipes = vec![(true, child.stdout.take().map(|p| Box::new(p) as
+                                                         Box<Reader + Send>)),
+                         (false, child.stderr.take().map(|p| Box::new(p) as
+                                                          Box<Reader + Send>))];
+        for (is_stdout, pipe) in pipes.into_iter() {
+            let pipe = match pipe { Some(pipe) => pipe, None => continue };
+            let tx = tx.clone();
+            Thread::spawn(move || {
+                let mut reader = BufferedReader::new(pipe);
+                while let Ok(line) = reader.read_until(b'\n') {
+                    if tx.send((is_stdout, line)).is_err() { break }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        for (is_stdout, line) in rx.iter() {
+            if is_stdout {
+                on_stdout(&line);
+                stdout.push_all(&line);
+            } else {
+                on_stderr(&line);
+                stderr.push_all(&line);
+            }
+        }
+
+        let status = try!(child.wait().map_err(|e| {
+            process_error(&format!("Could not execute process `{}`",
+                               self.debug_string()),
+                          Some(e), None, None)
+        }));
+        let output = ProcessOutput {
+            status: status,
+            output: stdout,
+            error: stderr,
+        };
+
+        if output.status.success() {
+            Ok(output)
+        } else {
+            Err(process_error(&format!("Process didn't exit successfully: `{}`",
+                                       self.debug_string()),
+                              None, Some(&output.status), Some(&output)))
+        }
+    }
+
     pub fn build_command(&self) -> Command {
         let mut command = Command::new(&self.program);
         command.cwd(&self.cwd);
@@ -0,0 +1,73 @@
+//! A progress bar drawn on the last line of stderr.
+//!
+//! The bar is only shown when stderr is a terminal and the shell is at the
+//! normal verbosity. It is redrawn in place, so it has to be cleared before
+//! anything else is printed to the terminal.
+
+use std::env;
+use std::iter::repeat;
+
+use core::Verbosity;
+use util::{CargoResult, Config};
+
+/// Width of the bar itself, excluding the brackets.
+const BAR_WIDTH: usize = 25;
+
+pub struct Progress {
+    name: String,
+    width: usize,
+    drawn: bool,
+}
+
+impl Progress {
+    /// Returns a progress bar labelled `name`, or `None` if it shouldn't be
+    /// shown.
+    pub fn new(name: &str, config: &Config) -> Option<Progress> {
+        if config.shell().get_verbosity() != Verbosity::Normal { return None }
+        // The shell's own stream is checked, as it may not be the process's
+        // stderr when cargo is used as a library
+        if !config.shell().err().is_tty() { return None }
+        match env::var_string("TERM") {
+            Ok(ref term) if term.as_slice() != "dumb" => {}
+            _ => return None,
+        }
+        let width = env::var_string("COLUMNS").ok().and_then(|s| {
+            s.parse().ok()
+        }).and_then(|w| if w > 0 {Some(w)} else {None}).unwrap_or(80);
+        Some(Progress { name: name.to_string(), width: width, drawn: false })
+    }
+
+    /// Draws the bar for `cur` out of `max` steps, followed by `msg`.
+    pub fn tick(&mut self, cur: usize, max: usize, msg: &str,
+                config: &Config) -> CargoResult<()> {
+        let filled = if max == 0 { BAR_WIDTH } else { BAR_WIDTH * cur / max };
+        let mut bar = repeat('=').take(filled).collect::<String>();
+        if filled < BAR_WIDTH {
+            bar.push('>');
+            bar.extend(repeat(' ').take(BAR_WIDTH - filled - 1));
+        }
+        let line = format!("{:>12} [{}] {}/{}: {}", self.name, bar, cur, max,
+                           msg);
+        // Never wrap, or the line can't be cleared again
+        let width = self.width.saturating_sub(1);
+        let line = line.chars().take(width).collect::<String>();
+
+        let mut shell = config.shell();
+        let err = shell.err();
+        try!(err.write_str(&format!("\r{}\x1b[K", line)));
+        try!(err.flush());
+        self.drawn = true;
+        Ok(())
+    }
+
+    /// Removes the bar from the terminal, if it is drawn.
+    pub fn clear(&mut self, config: &Config) -> CargoResult<()> {
+        if !self.drawn { return Ok(()) }
+        let mut shell = config.shell();
+        let err = shell.err();
+        try!(err.write_str("\r\x1b[K"));
+        try!(err.flush());
+        self.drawn = false;
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 
 use std::fmt;
+use std::old_io::File;
 use std::old_io::fs::{self, PathExtensions};
 use std::slice;
 use std::str;
 use std::default::Default;
+use glob::Pattern;
 use toml;
 use semver;
 use rustc_serialize::{Decodable, Decoder};
@@ -15,6 +17,8 @@ use core::dependency::Kind;
 use core::manifest::{LibKind, Profile, ManifestMetadata};
 use core::package_id::Metadata;
 use util::{CargoResult, human, ToUrl, ToSemver, ChainError, Config};
+use util::{coded_human, ErrorSpan, lev_distance};
+use util::workspace;
 
 /// Representation of the projects file layout.
 ///
@@ -104,13 +108,16 @@ pub fn to_manifest(contents: &[u8],
     let root = try!(parse(contents, &manifest));
     let mut d = toml::Decoder::new(toml::Value::Table(root));
     let manifest: TomlManifest = try!(Decodable::decode(&mut d).map_err(|e| {
-        human(e.to_string())
+        let span = e.field.as_ref().and_then(|field| {
+            key_span(contents, field, &manifest, &e.to_string())
+        });
+        coded_human("toml", e.to_string(), span.into_iter().collect())
     }));
 
     let pair = try!(manifest.to_manifest(source_id, &layout, config));
     let (mut manifest, paths) = pair;
     match d.toml {
-        Some(ref toml) => add_unused_keys(&mut manifest, toml, "".to_string()),
+        Some(ref toml) => add_unused_keys(&mut manifest, toml, &[]),
         None => {}
     }
     if manifest.targets().iter()
@@ -121,24 +128,110 @@ pub fn to_manifest(contents: &[u8],
     }
     return Ok((manifest, paths));
 
-    fn add_unused_keys(m: &mut Manifest, toml: &toml::Value, key: String) {
+    // Whatever the decoder left behind wasn't used. Unknown keys are
+    // reported as a whole, with a suggestion if they look like a typo of a
+    // known one, rather than once for each of the values they contain.
+    fn add_unused_keys(m: &mut Manifest, toml: &toml::Value, path: &[String]) {
         match *toml {
             toml::Value::Table(ref table) => {
+                let known = known_keys(path);
                 for (k, v) in table.iter() {
-                    add_unused_keys(m, v, if key.len() == 0 {
-                        k.clone()
-                    } else {
-                        key.clone() + "." + k
-                    })
+                    let mut child = path.to_vec();
+                    child.push(k.clone());
+                    let normalized = k.replace("_", "-");
+                    match known {
+                        Some(known) if !known.contains(&&normalized[]) => {
+                            let suggestion = suggestion(&normalized, known);
+                            unused_key(m, &child, suggestion)
+                        }
+                        _ => add_unused_keys(m, v, &child),
+                    }
                 }
             }
             toml::Value::Array(ref arr) => {
                 for v in arr.iter() {
-                    add_unused_keys(m, v, key.clone());
+                    add_unused_keys(m, v, path);
                 }
             }
-            _ => m.add_warning(format!("unused manifest key: {}", key)),
+            _ => unused_key(m, path, None),
+        }
+    }
+
+    fn unused_key(m: &mut Manifest, path: &[String], suggestion: Option<&str>) {
+        let key = path.connect(".");
+        m.add_warning(match suggestion {
+            Some(s) => format!("unused manifest key: {} (did you mean `{}`?)",
+                               key, s),
+            None => format!("unused manifest key: {}", key),
+        })
+    }
+
+    fn suggestion<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+        known.iter().map(|k| (lev_distance(key, k), *k))
+             .filter(|&(d, _)| d < 4 && d <= key.len() / 2)
+             .min_by(|&(d, _)| d)
+             .map(|(_, k)| k)
+    }
+}
+
+// The keys of the tables of a manifest, which need to be kept in sync with the
+// `Toml*` structures below.
+static MANIFEST_KEYS: &'static [&'static str] = &[
+    "package", "project", "profile", "lib", "bin", "example", "test", "bench",
+    "dependencies", "dev-dependencies", "build-dependencies", "features",
+    "target", "workspace",
+];
+static PACKAGE_KEYS: &'static [&'static str] = &[
+    "name", "version", "authors", "build", "links", "exclude", "include",
+    "default-target", "forced-target", "publish", "description", "homepage",
+    "documentation", "readme", "keywords", "license", "license-file",
+    "repository", "edition",
+];
+static PROFILES: &'static [&'static str] = &[
+    "test", "doc", "bench", "dev", "release",
+];
+static PROFILE_KEYS: &'static [&'static str] = &[
+    "opt-level", "lto", "codegen-units", "debug", "rpath",
+];
+static TARGET_KEYS: &'static [&'static str] = &[
+    "name", "crate-type", "path", "test", "doctest", "bench", "doc", "plugin",
+    "harness",
+];
+static DEPENDENCY_KEYS: &'static [&'static str] = &[
+    "version", "path", "git", "branch", "tag", "rev", "features", "optional",
+    "default-features", "artifact",
+];
+static PLATFORM_KEYS: &'static [&'static str] = &["dependencies"];
+static WORKSPACE_KEYS: &'static [&'static str] = &[
+    "members", "exclude", "package",
+];
+static WORKSPACE_PACKAGE_KEYS: &'static [&'static str] = &[
+    "version", "authors", "license", "edition",
+];
+static INHERITED_KEYS: &'static [&'static str] = &["workspace"];
+
+/// Returns the keys the table at `path` in a manifest may contain, or `None`
+/// if its keys are names, like those of dependencies or features.
+fn known_keys(path: &[String]) -> Option<&'static [&'static str]> {
+    let path = path.iter().map(|k| k.replace("_", "-")).collect::<Vec<_>>();
+    if path.len() == 0 { return Some(MANIFEST_KEYS) }
+    match (&path[0][], path.len()) {
+        ("package", 1) | ("project", 1) => Some(PACKAGE_KEYS),
+        ("package", 2) | ("project", 2) => Some(INHERITED_KEYS),
+        ("profile", 1) => Some(PROFILES),
+        ("profile", 2) => Some(PROFILE_KEYS),
+        ("lib", 1) | ("bin", 1) | ("example", 1) |
+        ("test", 1) | ("bench", 1) => Some(TARGET_KEYS),
+        ("dependencies", 2) |
+        ("dev-dependencies", 2) |
+        ("build-dependencies", 2) => Some(DEPENDENCY_KEYS),
+        ("target", 2) => Some(PLATFORM_KEYS),
+        ("target", 4) if path[2] == "dependencies" => Some(DEPENDENCY_KEYS),
+        ("workspace", 1) => Some(WORKSPACE_KEYS),
+        ("workspace", 2) if path[1] == "package" => {
+            Some(WORKSPACE_PACKAGE_KEYS)
         }
+        _ => None,
     }
 }
 
@@ -149,6 +242,7 @@ pub fn parse(toml: &str, file: &Path) -> CargoResult<toml::Table> {
         None => {}
     }
     let mut error_str = format!("could not parse input as TOML\n");
+    let mut spans = Vec::new();
     for error in parser.errors.iter() {
         let (loline, locol) = parser.to_linecol(error.lo);
         let (hiline, hicol) = parser.to_linecol(error.hi);
@@ -162,8 +256,67 @@ pub fn parse(toml: &str, file: &Path) -> CargoResult<toml::Table> {
                                         "".to_string()
                                     },
                                     error.desc));
+        spans.push(ErrorSpan {
+            file: file.display().to_string(),
+            line_start: loline + 1,
+            column_start: locol + 1,
+            line_end: hiline + 1,
+            column_end: hicol + 1,
+            label: error.desc.clone(),
+        });
+    }
+    Err(coded_human("toml-parse", error_str, spans))
+}
+
+/// Finds the line defining the key at the dotted path `field`, like
+/// `project.name` or `bin.1.path`, so that errors in decoding it can point
+/// there. Keys which are missing point at the header of their table instead.
+fn key_span(contents: &str, field: &str, file: &Path,
+            label: &str) -> Option<ErrorSpan> {
+    let (table, key) = match field.rfind('.') {
+        Some(i) => (&field[..i], &field[i + 1..]),
+        None => ("", field),
+    };
+    // Tables in arrays of tables are named by their index
+    let (table, index) = match table.rfind('.') {
+        Some(i) => match table[i + 1..].parse::<usize>() {
+            Ok(index) => (&table[..i], Some(index)),
+            Err(..) => (table, None),
+        },
+        None => (table, None),
+    };
+    let mut current = String::new();
+    let mut seen = 0;
+    let mut header = None;
+    for (i, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        let column = line.len() - line.trim_left().len() + 1;
+        let span = ErrorSpan {
+            file: file.display().to_string(),
+            line_start: i + 1,
+            column_start: column,
+            line_end: i + 1,
+            column_end: column + trimmed.len(),
+            label: label.to_string(),
+        };
+        if trimmed.starts_with("[") {
+            current = trimmed.trim_matches(|c: char| c == '[' || c == ']')
+                             .trim().to_string();
+            if current.as_slice() != table { continue }
+            seen += 1;
+            if index.map_or(true, |index| index + 1 == seen) {
+                header = Some(span);
+            }
+            continue
+        }
+        if current.as_slice() != table { continue }
+        if index.map_or(false, |index| index + 1 != seen) { continue }
+        if trimmed.starts_with(key) &&
+           trimmed[key.len()..].trim_left().starts_with("=") {
+            return Some(span)
+        }
     }
-    Err(human(error_str))
+    header
 }
 
 type TomlLibTarget = TomlTarget;
@@ -194,6 +347,7 @@ pub struct DetailedTomlDependency {
     features: Option<Vec<String>>,
     optional: Option<bool>,
     default_features: Option<bool>,
+    artifact: Option<String>,
 }
 
 #[derive(RustcDecodable)]
@@ -211,6 +365,71 @@ pub struct TomlManifest {
     build_dependencies: Option<HashMap<String, TomlDependency>>,
     features: Option<HashMap<String, Vec<String>>>,
     target: Option<HashMap<String, TomlPlatform>>,
+    workspace: Option<TomlWorkspace>,
+    hooks: Option<TomlHooks>,
+}
+
+/// The `[hooks]` section, with commands given as a string of space-separated
+/// arguments or as a list of arguments.
+#[derive(RustcDecodable)]
+pub struct TomlHooks {
+    pre_build: Option<ManyOrOne<String>>,
+    post_build: Option<ManyOrOne<String>>,
+}
+
+#[derive(RustcDecodable)]
+pub struct TomlWorkspace {
+    members: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    package: Option<TomlWorkspacePackage>,
+}
+
+/// The fields of `[workspace.package]`, which members can inherit.
+#[derive(RustcDecodable, Clone, Default)]
+pub struct TomlWorkspacePackage {
+    version: Option<TomlVersion>,
+    authors: Option<Vec<String>>,
+    license: Option<String>,
+    edition: Option<String>,
+}
+
+/// A field of `[package]` which is either set by the manifest itself or
+/// inherited from `[workspace.package]` with `workspace = true`.
+#[derive(RustcDecodable)]
+pub enum MaybeWorkspace<T> {
+    Defined(T),
+    Workspace(TomlInheritedField),
+}
+
+#[derive(RustcDecodable)]
+pub struct TomlInheritedField {
+    workspace: bool,
+}
+
+impl<T: Clone> MaybeWorkspace<T> {
+    fn is_inherited(&self) -> bool {
+        match *self {
+            MaybeWorkspace::Defined(..) => false,
+            MaybeWorkspace::Workspace(..) => true,
+        }
+    }
+
+    fn resolve(&self, key: &str, inherited: Option<&T>) -> CargoResult<T> {
+        match *self {
+            MaybeWorkspace::Defined(ref t) => Ok(t.clone()),
+            MaybeWorkspace::Workspace(ref field) => {
+                if !field.workspace {
+                    return Err(human(format!("`package.{}.workspace` can \
+                                              only be `true`", key)))
+                }
+                inherited.map(|t| t.clone()).chain_error(|| {
+                    human(format!("`package.{}` is inherited from the \
+                                   workspace, but `workspace.package.{}` \
+                                   isn't set", key, key))
+                })
+            }
+        }
+    }
 }
 
 #[derive(RustcDecodable, Clone, Default)]
@@ -250,12 +469,15 @@ impl<T> ManyOrOne<T> {
 #[derive(RustcDecodable)]
 pub struct TomlProject {
     name: String,
-    version: TomlVersion,
-    authors: Vec<String>,
+    version: MaybeWorkspace<TomlVersion>,
+    authors: MaybeWorkspace<Vec<String>>,
     build: Option<String>,
     links: Option<String>,
     exclude: Option<Vec<String>>,
     include: Option<Vec<String>>,
+    default_target: Option<String>,
+    forced_target: Option<String>,
+    publish: Option<bool>,
 
     // package metadata
     description: Option<String>,
@@ -263,11 +485,13 @@ pub struct TomlProject {
     documentation: Option<String>,
     readme: Option<String>,
     keywords: Option<Vec<String>>,
-    license: Option<String>,
+    license: Option<MaybeWorkspace<String>>,
     license_file: Option<String>,
     repository: Option<String>,
+    edition: Option<MaybeWorkspace<String>>,
 }
 
+#[derive(Clone)]
 pub struct TomlVersion {
     version: semver::Version,
 }
@@ -282,13 +506,6 @@ impl Decodable for TomlVersion {
     }
 }
 
-impl TomlProject {
-    pub fn to_package_id(&self, source_id: &SourceId) -> CargoResult<PackageId> {
-        PackageId::new(&self.name, self.version.version.clone(),
-                       source_id)
-    }
-}
-
 struct Context<'a, 'b, 'c: 'b> {
     deps: &'a mut Vec<Dependency>,
     source_id: &'a SourceId,
@@ -378,7 +595,46 @@ impl TomlManifest {
             human("No `package` or `project` section found.")
         }));
 
-        let pkgid = try!(project.to_package_id(source_id));
+        // Fields inherited from the workspace are resolved up front
+        let inherits = project.version.is_inherited() ||
+                       project.authors.is_inherited() ||
+                       project.license.as_ref().map_or(false, |l| {
+                           l.is_inherited()
+                       }) ||
+                       project.edition.as_ref().map_or(false, |e| {
+                           e.is_inherited()
+                       });
+        let ws = if inherits {
+            try!(self.workspace_package(layout))
+        } else {
+            Default::default()
+        };
+        let version = try!(project.version.resolve("version",
+                                                   ws.version.as_ref()));
+        let authors = try!(project.authors.resolve("authors",
+                                                   ws.authors.as_ref()));
+        let license = match project.license {
+            Some(ref license) => {
+                Some(try!(license.resolve("license", ws.license.as_ref())))
+            }
+            None => None,
+        };
+        let edition = match project.edition {
+            Some(ref edition) => {
+                let edition = try!(edition.resolve("edition",
+                                                   ws.edition.as_ref()));
+                if edition.as_slice() != "2015" {
+                    return Err(human(format!("`package.edition` must be \
+                                              `2015`, but found `{}`",
+                                             edition)))
+                }
+                Some(edition)
+            }
+            None => None,
+        };
+
+        let pkgid = try!(PackageId::new(&project.name, version.version,
+                                        source_id));
         let metadata = pkgid.generate_metadata();
 
         // If we have no lib at all, use the inferred lib if available
@@ -499,8 +755,9 @@ impl TomlManifest {
             homepage: project.homepage.clone(),
             documentation: project.documentation.clone(),
             readme: project.readme.clone(),
-            authors: project.authors.clone(),
-            license: project.license.clone(),
+            authors: authors,
+            license: license,
+            edition: edition,
             license_file: project.license_file.clone(),
             repository: project.repository.clone(),
             keywords: project.keywords.clone().unwrap_or(Vec::new()),
@@ -513,6 +770,25 @@ impl TomlManifest {
                                          include,
                                          project.links.clone(),
                                          metadata);
+        manifest.set_default_target(project.default_target.clone());
+        manifest.set_forced_target(project.forced_target.clone());
+        manifest.set_publish(project.publish.unwrap_or(true));
+        if let Some(ref hooks) = self.hooks {
+            let mut map = HashMap::new();
+            for &(name, ref cmd) in [("pre-build", &hooks.pre_build),
+                                     ("post-build", &hooks.post_build)].iter() {
+                let args = match **cmd {
+                    Some(ManyOrOne::One(ref s)) => {
+                        s.split(' ').filter(|s| !s.is_empty())
+                         .map(|s| s.to_string()).collect()
+                    }
+                    Some(ManyOrOne::Many(ref v)) => v.clone(),
+                    None => continue,
+                };
+                map.insert(name.to_string(), args);
+            }
+            manifest.set_hooks(map);
+        }
         if used_deprecated_lib {
             manifest.add_warning(format!("the [[lib]] section has been \
                                           deprecated in favor of [lib]"));
@@ -522,8 +798,60 @@ impl TomlManifest {
                                                    `license-file` is necessary"));
         }
 
+        if let Some(ref ws) = self.workspace {
+            for member in ws.members.iter().flat_map(|m| m.iter()) {
+                if workspace::is_glob(member) { continue }
+                if !layout.root.join(member).join("Cargo.toml").exists() {
+                    manifest.add_warning(format!("workspace member `{}` does \
+                                                  not contain a Cargo.toml",
+                                                 member));
+                }
+            }
+            for pattern in ws.exclude.iter().flat_map(|e| e.iter()) {
+                try!(Pattern::new(pattern).map_err(|e| {
+                    human(format!("could not parse pattern `{}` of \
+                                   `workspace.exclude`: {}", pattern, e))
+                }));
+            }
+        }
+
+        // Members of a workspace share the output directory of its root
+        if source_id.is_path() {
+            if let Some(root) = try!(workspace::find_root(&layout.root)) {
+                manifest.set_target_dir(root.join("target"));
+            }
+        }
+
         Ok((manifest, nested_paths))
     }
+
+    /// Returns the `[workspace.package]` table of the workspace the package
+    /// at `layout` is a member of, which is this manifest's own if it's the
+    /// root.
+    fn workspace_package(&self, layout: &Layout)
+                         -> CargoResult<TomlWorkspacePackage> {
+        let root = try!(workspace::find_root(&layout.root));
+        let root = try!(root.chain_error(|| {
+            human("`package` inherits fields from the workspace, but isn't \
+                   a member of one")
+        }));
+        if root == layout.root {
+            let ws = self.workspace.as_ref().and_then(|w| w.package.clone());
+            return Ok(ws.unwrap_or_default())
+        }
+
+        let manifest = root.join("Cargo.toml");
+        let contents = try!(File::open(&manifest).read_to_string());
+        let table = try!(parse(&contents, &manifest));
+        let package = table.get("workspace").and_then(|w| w.lookup("package"));
+        match package {
+            Some(package) => toml::decode(package.clone()).chain_error(|| {
+                human(format!("`workspace.package` in {} is invalid",
+                              manifest.display()))
+            }),
+            None => Ok(Default::default()),
+        }
+    }
 }
 
 fn process_dependencies<F>(cx: &mut Context,
@@ -572,6 +900,19 @@ fn process_dependencies<F>(cx: &mut Context,
                      .set_features(details.features.unwrap_or(Vec::new()))
                      .set_default_features(details.default_features.unwrap_or(true))
                      .set_optional(details.optional.unwrap_or(false));
+        if let Some(ref artifact) = details.artifact {
+            if artifact.as_slice() != "bin" {
+                return Err(human(format!("dependency `{}` requests the \
+                                          unknown artifact `{}`, only `bin` \
+                                          is supported", n, artifact)))
+            }
+            if !dep.is_build() {
+                return Err(human(format!("dependency `{}` requests an \
+                                          artifact, which is only supported \
+                                          for build-dependencies", n)))
+            }
+        }
+        let dep = dep.set_artifact(details.artifact.clone());
         cx.deps.push(dep);
     }
 
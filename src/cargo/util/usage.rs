@@ -0,0 +1,238 @@
+//! Structured documentation of commands, parsed from their docopt usage
+//! strings.
+//!
+//! The usage string of a command is the one place its options are declared,
+//! so anything describing the options of a command, like shell completions
+//! or manual pages, is generated from it rather than maintained separately.
+
+/// The parsed usage string of a command.
+pub struct Usage {
+    /// The summary of the command preceding the `Usage:` section
+    pub about: Option<String>,
+    /// The usage patterns, like `cargo build [options]`
+    pub patterns: Vec<String>,
+    /// The options listed in the `Options:` section
+    pub options: Vec<Opt>,
+    /// The text following the options, with paragraphs separated by blank
+    /// lines
+    pub description: Option<String>,
+}
+
+/// An option of a command.
+pub struct Opt {
+    /// The short form of the option, like `-p`
+    pub short: Option<String>,
+    /// The long form of the option, like `--package`
+    pub long: Option<String>,
+    /// The name of the value the option takes, if it takes one
+    pub value: Option<String>,
+    /// Whether the option can be passed more than once, like `-vv`
+    pub repeatable: bool,
+    pub help: String,
+}
+
+enum Section { About, Patterns, Options, Description }
+
+impl Usage {
+    pub fn parse(usage: &str) -> Usage {
+        let mut ret = Usage {
+            about: None,
+            patterns: Vec::new(),
+            options: Vec::new(),
+            description: None,
+        };
+        let mut about = Vec::new();
+        let mut description = Vec::new();
+        let mut section = Section::About;
+
+        for line in usage.lines() {
+            let trimmed = line.trim();
+            let indented = line.starts_with(" ");
+            match trimmed {
+                "Usage:" => { section = Section::Patterns; continue }
+                "Options:" => { section = Section::Options; continue }
+                _ => {}
+            }
+            // A line which isn't indented ends the patterns and options
+            match section {
+                Section::Patterns | Section::Options
+                    if !indented && !trimmed.is_empty() => {
+                    section = Section::Description;
+                }
+                _ => {}
+            }
+            match section {
+                Section::About => about.push(trimmed),
+                Section::Patterns => {
+                    if !trimmed.is_empty() {
+                        ret.patterns.push(trimmed.to_string());
+                    }
+                }
+                Section::Options => {
+                    if trimmed.starts_with("-") {
+                        ret.options.push(Opt::parse(trimmed));
+                    } else if !trimmed.is_empty() {
+                        // The help of the previous option continues
+                        if let Some(opt) = ret.options.last_mut() {
+                            opt.help.push_str(" ");
+                            opt.help.push_str(trimmed);
+                        }
+                    }
+                }
+                Section::Description => description.push(line.trim_right()),
+            }
+        }
+
+        let about = about.connect(" ");
+        let about = about.trim();
+        if !about.is_empty() { ret.about = Some(about.to_string()) }
+        let description = description.connect("\n");
+        let description = description.trim();
+        if !description.is_empty() {
+            ret.description = Some(description.to_string());
+        }
+        ret
+    }
+
+    /// Renders the manual page of the command `name`, like `cargo-build`, in
+    /// the roff format understood by `man`.
+    pub fn man_page(&self, name: &str, version: &str) -> String {
+        let mut s = format!(".TH {} 1 \"\" \"{}\" \"Cargo Manual\"\n",
+                            name.to_uppercase(), version);
+        s.push_str(".SH NAME\n");
+        match self.about {
+            Some(ref about) => {
+                s.push_str(&format!("{} \\- {}\n", roff(name), roff(about)))
+            }
+            None => s.push_str(&format!("{}\n", roff(name))),
+        }
+
+        s.push_str(".SH SYNOPSIS\n.nf\n");
+        for pattern in self.patterns.iter() {
+            s.push_str(&format!("{}\n", roff(pattern)));
+        }
+        s.push_str(".fi\n");
+
+        if let Some(ref description) = self.description {
+            s.push_str(".SH DESCRIPTION\n");
+            for paragraph in description.split_str("\n\n") {
+                if paragraph.lines().all(|l| l.starts_with(" ")) {
+                    // Indented paragraphs are examples, keep them verbatim
+                    s.push_str(".IP\n.nf\n");
+                    for line in paragraph.lines() {
+                        s.push_str(&format!("{}\n", roff(line.trim())));
+                    }
+                    s.push_str(".fi\n");
+                } else {
+                    s.push_str(&format!(".PP\n{}\n", roff(paragraph)));
+                }
+            }
+        }
+
+        if !self.options.is_empty() {
+            s.push_str(".SH OPTIONS\n");
+            for opt in self.options.iter() {
+                let names = opt.short.iter().chain(opt.long.iter())
+                               .map(|n| format!("\\fB{}\\fR", roff(n)))
+                               .collect::<Vec<_>>();
+                let mut line = names.connect(", ");
+                if let Some(ref value) = opt.value {
+                    line.push_str(&format!(" \\fI{}\\fR", roff(value)));
+                }
+                if opt.repeatable { line.push_str(" ...") }
+                s.push_str(&format!(".TP\n{}\n{}\n", line, roff(&opt.help)));
+            }
+        }
+        s
+    }
+
+    /// Renders the manual page of the command `name` as plain text, for
+    /// systems without `man`.
+    pub fn text(&self, name: &str) -> String {
+        let mut s = String::from_str("NAME\n");
+        match self.about {
+            Some(ref about) => {
+                s.push_str(&format!("    {} - {}\n", name, about))
+            }
+            None => s.push_str(&format!("    {}\n", name)),
+        }
+
+        s.push_str("\nSYNOPSIS\n");
+        for pattern in self.patterns.iter() {
+            s.push_str(&format!("    {}\n", pattern));
+        }
+
+        if let Some(ref description) = self.description {
+            s.push_str("\nDESCRIPTION\n");
+            for line in description.lines() {
+                if line.is_empty() {
+                    s.push_str("\n");
+                } else {
+                    s.push_str(&format!("    {}\n", line));
+                }
+            }
+        }
+
+        if !self.options.is_empty() {
+            s.push_str("\nOPTIONS\n");
+            for opt in self.options.iter() {
+                let mut line = opt.short.iter().chain(opt.long.iter())
+                                  .map(|n| &n[]).collect::<Vec<_>>()
+                                  .connect(", ");
+                if let Some(ref value) = opt.value {
+                    line.push_str(&format!(" {}", value));
+                }
+                if opt.repeatable { line.push_str(" ...") }
+                s.push_str(&format!("    {}\n        {}\n", line, opt.help));
+            }
+        }
+        s
+    }
+}
+
+/// Escapes `s` so it's printed verbatim by roff.
+fn roff(s: &str) -> String {
+    s.replace("\\", "\\e").replace("-", "\\-").lines().map(|line| {
+        if line.starts_with(".") || line.starts_with("'") {
+            format!("\\&{}", line)
+        } else {
+            line.to_string()
+        }
+    }).collect::<Vec<_>>().connect("\n")
+}
+
+impl Opt {
+    /// Parses an option line like `-p SPEC, --package SPEC  Package to build`.
+    fn parse(line: &str) -> Opt {
+        // The help is separated from the option by at least two spaces
+        let (spec, help) = match line.find("  ") {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+        let mut opt = Opt {
+            short: None,
+            long: None,
+            value: None,
+            repeatable: false,
+            help: help.to_string(),
+        };
+        let spec = spec.replace(",", " ").replace("=", " ");
+        for word in spec.split(' ').filter(|s| !s.is_empty()) {
+            if word == "..." {
+                opt.repeatable = true;
+            } else if word.starts_with("--") {
+                opt.long = Some(word.to_string());
+            } else if word.starts_with("-") {
+                opt.short = Some(word.to_string());
+            } else {
+                opt.value = Some(word.to_string());
+            }
+        }
+        opt
+    }
+
+    /// Returns the long form of the option, or the short one if it has none.
+    pub fn name(&self) -> &str {
+        self.long.as_ref().or(self.short.as_ref()).map(|s| &s[]).unwrap()
+    }
+}
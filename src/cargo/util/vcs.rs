@@ -19,8 +19,7 @@ impl GitRepo {
 
 impl HgRepo {
     pub fn init(path: &Path) -> CargoResult<HgRepo> {
-        let path_str = path.as_str().unwrap();
-        try!(try!(process("hg")).arg("init").arg(path_str).exec());
+        try!(try!(process("hg")).arg("init").arg(path).exec());
         return Ok(HgRepo)
     }
     pub fn discover(path: &Path) -> CargoResult<HgRepo> {
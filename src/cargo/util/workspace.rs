@@ -0,0 +1,345 @@
+//! Discovery of workspaces.
+//!
+//! A workspace is a set of packages sharing one output directory and one
+//! lockfile. The root of a workspace is a package whose manifest has a
+//! `[workspace]` section listing the paths of its members relative to the
+//! root:
+//!
+//! ```toml
+//! [workspace]
+//! members = ["crates/foo", "crates/bar"]
+//! ```
+//!
+//! Members may also be glob patterns like `crates/*`, and `workspace.exclude`
+//! lists patterns of directories which aren't members even if they match.
+
+use std::old_io::fs::{self, PathExtensions};
+use std::old_io::File;
+
+use glob::Pattern;
+use toml;
+
+use core::{Package, SourceId};
+use core::dependency::Kind;
+use ops;
+use util::{CargoResult, ChainError, Config, Graph, human};
+use util::toml as cargo_toml;
+
+/// Returns the directories of the members of the workspace rooted at the
+/// manifest `root_manifest`, or `None` if it doesn't define a workspace.
+///
+/// Members containing glob patterns, like `crates/*`, expand to the matching
+/// directories which contain a `Cargo.toml`, in sorted order. Directories
+/// matching a pattern of `workspace.exclude` are never members.
+pub fn members(root_manifest: &Path) -> CargoResult<Option<Vec<Path>>> {
+    let table = try!(parse(root_manifest));
+    let workspace = match table.get("workspace") {
+        Some(&toml::Value::Table(ref t)) => t,
+        Some(..) => {
+            return Err(human(format!("`workspace` in {} must be a table",
+                                     root_manifest.display())))
+        }
+        None => return Ok(None),
+    };
+    let root = root_manifest.dir_path();
+    let members = try!(paths(workspace, "members", root_manifest));
+    let exclude = try!(paths(workspace, "exclude", root_manifest));
+    let exclude = try!(exclude.iter().map(|p| {
+        Pattern::new(p).map_err(|e| {
+            human(format!("could not parse pattern `{}`: {}", p, e))
+        })
+    }).collect::<CargoResult<Vec<_>>>());
+
+    let mut ret = Vec::new();
+    for member in members.iter() {
+        let dirs = if is_glob(member) {
+            try!(expand(&root, member))
+        } else {
+            vec![root.join(member)]
+        };
+        for dir in dirs.into_iter() {
+            let excluded = dir.path_relative_from(&root).and_then(|rel| {
+                rel.as_str().map(|rel| {
+                    exclude.iter().any(|p| p.matches(rel))
+                })
+            }).unwrap_or(false);
+            if !excluded && !ret.contains(&dir) {
+                ret.push(dir);
+            }
+        }
+    }
+    Ok(Some(ret))
+}
+
+/// Whether an entry of `workspace.members` is a glob pattern rather than the
+/// path of a single member.
+pub fn is_glob(member: &str) -> bool {
+    member.chars().any(|c| c == '*' || c == '?' || c == '[')
+}
+
+/// Returns the directories below `root` matching the glob pattern `member`
+/// which contain a `Cargo.toml`. Each component of the pattern is matched
+/// against a single level of directories, in sorted order.
+fn expand(root: &Path, member: &str) -> CargoResult<Vec<Path>> {
+    let mut dirs = vec![root.clone()];
+    for component in member.split('/') {
+        if !is_glob(component) {
+            for dir in dirs.iter_mut() {
+                dir.push(component);
+            }
+            continue
+        }
+        let pattern = try!(Pattern::new(component).map_err(|e| {
+            human(format!("could not parse pattern `{}`: {}", member, e))
+        }));
+        let mut next = Vec::new();
+        for dir in dirs.iter().filter(|d| d.is_dir()) {
+            let mut entries = try!(fs::readdir(dir));
+            entries.sort();
+            next.extend(entries.into_iter().filter(|e| {
+                e.filename_str().map_or(false, |f| pattern.matches(f))
+            }));
+        }
+        dirs = next;
+    }
+    Ok(dirs.into_iter().filter(|d| d.join("Cargo.toml").exists()).collect())
+}
+
+/// Reads the array of paths `key` of the `[workspace]` section of `manifest`,
+/// which is empty if it isn't present.
+fn paths(workspace: &toml::Table, key: &str,
+         manifest: &Path) -> CargoResult<Vec<String>> {
+    let invalid = || {
+        human(format!("`workspace.{}` in {} must be an array of paths",
+                      key, manifest.display()))
+    };
+    let array = match workspace.get(key) {
+        Some(&toml::Value::Array(ref a)) => a,
+        Some(..) => return Err(invalid()),
+        None => return Ok(Vec::new()),
+    };
+    array.iter().map(|p| {
+        p.as_str().map(|s| s.to_string()).ok_or_else(|| invalid())
+    }).collect()
+}
+
+/// Returns the directory of the workspace root which the package in `dir`
+/// belongs to, if any. A workspace root is a member of its own workspace.
+pub fn find_root(dir: &Path) -> CargoResult<Option<Path>> {
+    let mut current = dir.clone();
+    loop {
+        let manifest = current.join("Cargo.toml");
+        if manifest.exists() {
+            if let Some(members) = try!(members(&manifest)) {
+                if current == *dir || members.iter().any(|m| m == dir) {
+                    return Ok(Some(current))
+                }
+            }
+        }
+        if !current.pop() { return Ok(None) }
+    }
+}
+
+/// Returns the root manifest of the workspace the package at `manifest_path`
+/// is a member of, failing if it isn't part of one.
+pub fn root_manifest(manifest_path: &Path) -> CargoResult<Path> {
+    let dir = manifest_path.dir_path();
+    match try!(find_root(&dir)) {
+        Some(root) => Ok(root.join("Cargo.toml")),
+        None => Err(human(format!("`{}` is not a member of a workspace",
+                                  manifest_path.display()))),
+    }
+}
+
+/// Loads all packages of the workspace rooted at `root_manifest`, starting
+/// with the root package itself.
+pub fn packages(root_manifest: &Path, config: &Config)
+                -> CargoResult<Vec<Package>> {
+    let root = root_manifest.dir_path();
+    let members = try!(members(root_manifest)).unwrap_or(Vec::new());
+    let mut dirs = vec![root.clone()];
+    dirs.extend(members.into_iter().filter(|m| *m != root));
+    dirs.iter().map(|dir| {
+        let manifest = dir.join("Cargo.toml");
+        let source_id = try!(SourceId::for_path(dir));
+        let (pkg, _) = try!(ops::read_package(&manifest, &source_id, config)
+                                .chain_error(|| {
+            human(format!("failed to load workspace member at `{}`",
+                          dir.display()))
+        }));
+        Ok(pkg)
+    }).collect()
+}
+
+/// Orders the workspace members `packages` so that each comes after the
+/// members it depends on by path.
+///
+/// Development dependencies aren't needed to package or publish a member, and
+/// leaving them out means they may form cycles between members.
+pub fn dependency_order(packages: &[Package]) -> Vec<&Package> {
+    let mut graph = Graph::new();
+    for pkg in packages.iter() {
+        graph.add(pkg.name().to_string(), &[]);
+    }
+    for pkg in packages.iter() {
+        for dep in pkg.dependencies().iter() {
+            if dep.kind() == Kind::Development || !dep.source_id().is_path() {
+                continue
+            }
+            if packages.iter().any(|p| p.name() == dep.name()) {
+                graph.link(pkg.name().to_string(), dep.name().to_string());
+            }
+        }
+    }
+    graph.sort().unwrap().iter().map(|name| {
+        packages.iter().find(|p| p.name() == *name).unwrap()
+    }).collect()
+}
+
+/// Returns the manifest of the closest workspace root strictly above `dir`,
+/// regardless of whether `dir` is a member of it.
+pub fn find_enclosing(dir: &Path) -> CargoResult<Option<Path>> {
+    let mut current = dir.clone();
+    while current.pop() {
+        let manifest = current.join("Cargo.toml");
+        if manifest.exists() && try!(members(&manifest)).is_some() {
+            return Ok(Some(manifest))
+        }
+    }
+    Ok(None)
+}
+
+/// Adds `member` to the `workspace.members` list of `root_manifest`.
+///
+/// The manifest is edited textually so the formatting and comments of the
+/// rest of the file are preserved. The edited manifest is parsed again before
+/// it is written, and if anything but the list of members changed, the
+/// manifest is left alone and an error asks to add the member by hand.
+pub fn add_member(root_manifest: &Path, member: &Path) -> CargoResult<()> {
+    if let Some(members) = try!(members(root_manifest)) {
+        if members.iter().any(|m| m == member) { return Ok(()) }
+    }
+    let root = root_manifest.dir_path();
+    let relative = try!(member.path_relative_from(&root).chain_error(|| {
+        human(format!("`{}` is not inside of the workspace at `{}`",
+                      member.display(), root.display()))
+    }));
+    // Manifests always use forward slashes, even on windows
+    let relative = relative.display().to_string().replace("\\", "/");
+
+    let contents = try!(File::open(root_manifest).read_to_string());
+    let edited = try!(insert_member(&contents, &relative).chain_error(|| {
+        human(format!("could not find where to add `{}` to \
+                       `workspace.members` in {}, add it by hand",
+                      relative, root_manifest.display()))
+    }));
+
+    let mut expected = try!(parse(root_manifest));
+    let actual = try!(cargo_toml::parse(&edited, root_manifest));
+    if let Some(&mut toml::Value::Table(ref mut workspace)) =
+            expected.get_mut("workspace") {
+        let mut members = match workspace.remove("members") {
+            Some(toml::Value::Array(members)) => members,
+            _ => Vec::new(),
+        };
+        members.push(toml::Value::String(relative.clone()));
+        workspace.insert("members".to_string(),
+                         toml::Value::Array(members));
+    }
+    if actual != expected {
+        return Err(human(format!("could not add `{}` to `workspace.members` \
+                                  in {} without changing the rest of the \
+                                  manifest, add it by hand",
+                                 relative, root_manifest.display())))
+    }
+    try!(File::create(root_manifest).write_str(&edited));
+    Ok(())
+}
+
+/// Returns `contents` with `member` added to the `members` key of its
+/// `[workspace]` section, or `None` if the section or the end of the key
+/// can't be found.
+fn insert_member(contents: &str, member: &str) -> Option<String> {
+    let entry = format!("\"{}\"", member);
+    let mut lines: Vec<String> = contents.lines().map(|s| s.to_string())
+                                         .collect();
+    let header = match lines.iter().position(|l| l.trim() == "[workspace]") {
+        Some(header) => header,
+        None => return None,
+    };
+    let end = lines.iter().skip(header + 1)
+                   .position(|l| l.trim().starts_with("["))
+                   .map(|i| i + header + 1)
+                   .unwrap_or(lines.len());
+    let start = lines[header + 1..end].iter().position(|l| {
+        is_key(l.trim(), "members")
+    }).map(|i| i + header + 1);
+    let start = match start {
+        Some(start) => start,
+        None => {
+            lines.insert(header + 1, format!("members = [{}]", entry));
+            return Some(finish(lines))
+        }
+    };
+
+    // Insert before the closing bracket of the array, which may be on a later
+    // line, ignoring comments
+    let close = match (start..end).find(|&i| code(&lines[i]).contains("]")) {
+        Some(close) => close,
+        None => return None,
+    };
+    let line = lines[close].clone();
+    let idx = code(&line).rfind(']').unwrap();
+    let before = line[..idx].trim_right().to_string();
+    if before.is_empty() {
+        // The closing bracket is on a line of its own, so the entry goes on a
+        // line of its own after the last one which isn't a comment
+        let last = match (start..close).rev().find(|&i| {
+            !code(&lines[i]).trim().is_empty()
+        }) {
+            Some(last) => last,
+            None => return None,
+        };
+        let prev = lines[last].clone();
+        let prev_code = code(&prev).trim_right();
+        if !prev_code.ends_with("[") && !prev_code.ends_with(",") {
+            lines[last] = format!("{},{}", prev_code,
+                                  &prev[prev_code.len()..]);
+        }
+        lines.insert(close, format!("    {},", entry));
+    } else {
+        let sep = if before.ends_with("[") || before.ends_with(",") {
+            ""
+        } else {
+            ", "
+        };
+        lines[close] = format!("{}{}{}{}", before, sep, entry, &line[idx..]);
+    }
+    Some(finish(lines))
+}
+
+/// Whether `line` assigns to `key`.
+fn is_key(line: &str, key: &str) -> bool {
+    line.starts_with(key) && line[key.len()..].trim_left().starts_with("=")
+}
+
+/// The part of `line` before a comment.
+fn code(line: &str) -> &str {
+    match line.find('#') {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+fn finish(lines: Vec<String>) -> String {
+    let mut out = lines.connect("\n");
+    out.push_str("\n");
+    out
+}
+
+fn parse(manifest: &Path) -> CargoResult<toml::Table> {
+    let contents = try!(File::open(manifest).read_to_string().chain_error(|| {
+        human(format!("failed to read `{}`", manifest.display()))
+    }));
+    cargo_toml::parse(&contents, manifest)
+}
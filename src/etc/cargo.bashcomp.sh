@@ -9,7 +9,7 @@ _cargo() {
 
     _expand || return 0
 
-    commands=$(cargo --list|grep -v 'Installed Commands:')
+    commands=$(cargo --list|grep -v 'Installed Commands:'|awk '{print $1}')
 
     # these options require an argument
     if [[ "${cmd}" == -@(A|B|C|G|g|m) ]] ; then
@@ -37,6 +37,7 @@ pub enum Error {
     Unauthorized,
     TokenMissing,
     Io(old_io::IoError),
+    Json(json::DecoderError),
 }
 
 #[derive(RustcDecodable)]
@@ -74,6 +75,12 @@ pub struct NewCrateDependency {
     pub kind: String,
 }
 
+#[derive(RustcDecodable)]
+pub struct Version {
+    pub num: String,
+    pub yanked: bool,
+}
+
 #[derive(RustcDecodable)]
 pub struct User {
     pub id: u32,
@@ -88,7 +95,10 @@ pub struct User {
 #[derive(RustcDecodable)] struct ApiError { detail: String }
 #[derive(RustcEncodable)] struct OwnersReq<'a> { users: &'a [&'a str] }
 #[derive(RustcDecodable)] struct Users { users: Vec<User> }
-#[derive(RustcDecodable)] struct Crates { crates: Vec<Crate> }
+#[derive(RustcDecodable)]
+struct Crates { crates: Vec<Crate>, meta: Option<Meta> }
+#[derive(RustcDecodable)] struct Meta { total: u32 }
+#[derive(RustcDecodable)] struct Versions { versions: Vec<Version> }
 
 impl Registry {
     pub fn new(host: String, token: Option<String>) -> Registry {
@@ -158,11 +168,24 @@ impl Registry {
         Ok(())
     }
 
-    pub fn search(&mut self, query: &str) -> Result<Vec<Crate>> {
-        let body = try!(self.req(format!("/crates?q={}", query), None, Get,
+    /// Returns at most `limit` crates matching `query`, along with the total
+    /// number of matching crates if the registry reports it.
+    pub fn search(&mut self, query: &str,
+                  limit: u32) -> Result<(Vec<Crate>, Option<u32>)> {
+        let body = try!(self.req(format!("/crates?q={}&per_page={}", query,
+                                         limit), None, Get,
                                  Auth::Unauthorized));
 
-        Ok(json::decode::<Crates>(&body).unwrap().crates)
+        let crates = try!(json::decode::<Crates>(&body).map_err(Error::Json));
+        Ok((crates.crates, crates.meta.map(|m| m.total)))
+    }
+
+    pub fn list_versions(&mut self, krate: &str) -> Result<Vec<Version>> {
+        let body = try!(self.req(format!("/crates/{}/versions", krate), None,
+                                 Get, Auth::Unauthorized));
+        let versions = try!(json::decode::<Versions>(&body)
+                                .map_err(Error::Json));
+        Ok(versions.versions)
     }
 
     pub fn yank(&mut self, krate: &str, version: &str) -> Result<()> {
@@ -179,6 +202,13 @@ impl Registry {
         Ok(())
     }
 
+    /// Revokes the token of this registry handle on the server, after which
+    /// it can't be used anymore.
+    pub fn revoke_token(&mut self) -> Result<()> {
+        try!(self.delete("/tokens/current".to_string(), None));
+        Ok(())
+    }
+
     fn put(&mut self, path: String, b: &[u8]) -> Result<String> {
         self.req(path, Some(b), Put, Auth::Authorized)
     }
@@ -215,7 +245,7 @@ fn handle(response: result::Result<http::Response, curl::ErrCode>)
     let response = try!(response.map_err(Error::Curl));
     match response.get_code() {
         0 => {} // file upload url sometimes
-        200 => {}
+        200 | 204 => {}
         403 => return Err(Error::Unauthorized),
         _ => return Err(Error::NotOkResponse(response))
     }
@@ -248,6 +278,7 @@ impl fmt::Display for Error {
             Error::Unauthorized => write!(f, "unauthorized API access"),
             Error::TokenMissing => write!(f, "no upload token found, please run `cargo login`"),
             Error::Io(ref e) => write!(f, "io error: {}", e),
+            Error::Json(ref e) => write!(f, "invalid response: {}", e),
         }
     }
 }
@@ -80,14 +80,25 @@ pub fn mock_pkg_yank(name: &str, version: &str, deps: &[(&str, &str, &str)],
     mock_archive(name, version, deps);
     let c = File::open(&mock_archive_dst(name, version)).read_to_end().unwrap();
     let line = pkg(name, version, deps, cksum(c.as_slice()).as_slice(), yanked);
+    publish(index_file(name).as_slice(), line.as_slice());
+}
 
-    let file = match name.len() {
+pub fn mock_pkg_license(name: &str, version: &str, license: &str) {
+    mock_archive(name, version, &[]);
+    let c = File::open(&mock_archive_dst(name, version)).read_to_end().unwrap();
+    let line = pkg(name, version, &[], cksum(c.as_slice()).as_slice(), false);
+    let line = format!("{},\"license\":\"{}\"}}",
+                       line.slice_to(line.len() - 1), license);
+    publish(index_file(name).as_slice(), line.as_slice());
+}
+
+fn index_file(name: &str) -> String {
+    match name.len() {
         1 => format!("1/{}", name),
         2 => format!("2/{}", name),
         3 => format!("3/{}/{}", name.slice_to(1), name),
         _ => format!("{}/{}/{}", name.slice(0, 2), name.slice(2, 4), name),
-    };
-    publish(file.as_slice(), line.as_slice());
+    }
 }
 
 pub fn publish(file: &str, line: &str) {
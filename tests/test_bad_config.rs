@@ -220,3 +220,211 @@ Caused by:
   expected a section for the key `root`
 "));
 });
+
+test!(bad_proxy_scheme {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [http]
+              proxy = "ftp://localhost:21"
+        "#);
+    assert_that(foo.cargo_process("publish").arg("-v"),
+                execs().with_status(101).with_stderr("\
+unsupported proxy scheme `ftp` in `ftp://localhost:21`; expected one of http, \
+https, socks4, socks4a, socks5, socks5h
+"));
+});
+
+test!(bad_ssl_version {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [http]
+              ssl-version = "tlsv9"
+        "#);
+    assert_that(foo.cargo_process("publish").arg("-v"),
+                execs().with_status(101).with_stderr("\
+unsupported `http.ssl-version` `tlsv9` in [..]config; expected one of tlsv1, \
+tlsv1.0, tlsv1.1, tlsv1.2 or sslv3
+"));
+});
+
+test!(negative_http_timeout {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [http]
+              low-speed-time = -1
+        "#);
+    assert_that(foo.cargo_process("publish").arg("-v"),
+                execs().with_status(101).with_stderr("\
+`http.low-speed-time` in [..]config must not be negative, found -1
+"));
+});
+
+test!(missing_cainfo {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [http]
+              cainfo = "missing.pem"
+        "#);
+    assert_that(foo.cargo_process("publish").arg("-v"),
+                execs().with_status(101).with_stderr("\
+the file `[..]missing.pem` from the `[http]` configuration does not exist
+"));
+});
+
+test!(bad_term_color {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [term]
+              color = "sometimes"
+        "#);
+    assert_that(foo.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+`term.color` in [..]config must be one of auto, always or never, but found \
+`sometimes`
+"));
+    assert_that(foo.process(cargo_dir().join("cargo")).arg("build")
+                   .arg("--color").arg("never"),
+                execs().with_status(0));
+});
+
+test!(bad_term_color_in_environment {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [term]
+              color = "never"
+        "#);
+    assert_that(foo.cargo_process("build").env("CARGO_TERM_COLOR",
+                                               Some("sometimes")),
+                execs().with_status(101).with_stderr("\
+`term.color` in environment variable `CARGO_TERM_COLOR` must be one of auto, \
+always or never, but found `sometimes`
+"));
+});
+
+test!(bad_term_color_in_cli_config {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    assert_that(foo.cargo_process("--config")
+                   .arg("term.color=\"sometimes\"").arg("build")
+                   .env("CARGO_TERM_COLOR", Some("never")),
+                execs().with_status(101).with_stderr("\
+`term.color` in --config must be one of auto, always or never, but found \
+`sometimes`
+"));
+});
+
+test!(cli_config_not_toml {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    assert_that(foo.cargo_process("--config").arg("term.color=never")
+                   .arg("build"),
+                execs().with_status(101).with_stderr("\
+failed to parse the value of --config argument `term.color=never` as TOML, \
+strings need to be quoted
+"));
+    assert_that(foo.process(cargo_dir().join("cargo")).arg("--config")
+                   .arg("term.color").arg("build"),
+                execs().with_status(101).with_stderr("\
+--config argument `term.color` must be of the form KEY=VALUE
+"));
+});
+
+test!(include_cycle {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            include = "other"
+        "#)
+        .file(".cargo/other", r#"
+            include = ["config"]
+        "#);
+    assert_that(foo.cargo_process("build").arg("-v"),
+                execs().with_status(101).with_stderr("\
+Couldn't load Cargo configuration
+
+Caused by:
+  configuration files include each other: [..]config -> [..]other -> \
+[..]config
+"));
+});
+
+test!(include_missing_file {
+    let foo = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            include = "shared"
+        "#);
+    assert_that(foo.cargo_process("build").arg("-v"),
+                execs().with_status(101).with_stderr("\
+Couldn't load Cargo configuration
+
+Caused by:
+  configuration file `[..]shared` included by `[..]config` does not exist
+"));
+});
@@ -8,6 +8,7 @@ use cargo::util::process;
 
 use support::paths;
 use support::{execs, project, cargo_dir, mkdir_recursive, ProjectBuilder};
+use support::COMPILING;
 use hamcrest::{assert_that};
 
 fn setup() {
@@ -43,7 +44,101 @@ test!(list_commands_looks_at_path {
                    .env("PATH", Some(path.to_str().unwrap()));
     let output = output.exec_with_output().unwrap();
     let output = str::from_utf8(output.output.as_slice()).unwrap();
-    assert!(output.contains("\n    1\n"), "missing 1: {}", output);
+    assert!(output.contains("\n    1                    "), "missing 1: {}",
+            output);
+    let expected = format!("{}\n", proj.root().join("path-test")
+                                       .join("cargo-1").display());
+    assert!(output.contains(&expected[]), "missing path of 1: {}", output);
+});
+
+test!(list_commands_with_descriptions {
+    let pr = process(cargo_dir().join("cargo")).unwrap()
+                    .arg("--list").cwd(paths::root())
+                    .env("HOME", Some(paths::home()));
+    let output = pr.exec_with_output().unwrap();
+    let output = str::from_utf8(output.output.as_slice()).unwrap();
+    assert!(output.contains("\n    build                Compile a local \
+                             package and all of its dependencies\n"),
+            "missing build: {}", output);
+    assert!(output.contains("\n    version              Show version \
+                             information\n"),
+            "missing version: {}", output);
+});
+
+#[cfg(unix)]
+test!(list_commands_describes_external_commands {
+    let p = project("foo")
+        .file("bin/cargo-hello", "#!/bin/sh\necho 'Say hello'\n");
+    p.build();
+    let script = p.root().join("bin/cargo-hello");
+    fs::chmod(&script, USER_RWX).unwrap();
+
+    let mut path = path();
+    path.push(p.root().join("bin"));
+    let path = env::join_paths(path.iter()).unwrap();
+    let pr = process(cargo_dir().join("cargo")).unwrap()
+                    .arg("--list").arg("-v").cwd(p.root())
+                    .env("HOME", Some(paths::home()))
+                    .env("PATH", Some(path.to_str().unwrap()));
+    let output = pr.exec_with_output().unwrap();
+    let output = str::from_utf8(output.output.as_slice()).unwrap();
+    let expected = format!("\n    hello                Say hello ({})\n",
+                           script.display());
+    assert!(output.contains(&expected[]), "missing hello: {}", output);
+});
+
+#[cfg(windows)]
+test!(script_subcommands {
+    let p = project("foo")
+        .file("bin/cargo-hello.bat", "@echo hello %1")
+        .file("bin/cargo-greet.cmd", "@echo greetings %1");
+    p.build();
+
+    let mut path = path();
+    path.push(p.root().join("bin"));
+    let path = env::join_paths(path.iter()).unwrap();
+    let pr = process(cargo_dir().join("cargo")).unwrap()
+                    .cwd(p.root())
+                    .env("HOME", Some(paths::home()))
+                    .env("PATH", Some(path.to_str().unwrap()));
+
+    let output = pr.clone().arg("--list").exec_with_output().unwrap();
+    let output = str::from_utf8(output.output.as_slice()).unwrap();
+    let hello = format!("\n    hello                {}\n",
+                        p.root().join("bin/cargo-hello.bat").display());
+    let greet = format!("\n    greet                {}\n",
+                        p.root().join("bin/cargo-greet.cmd").display());
+    assert!(output.contains(&hello[]), "missing hello: {}", output);
+    assert!(output.contains(&greet[]), "missing greet: {}", output);
+
+    assert_that(pr.clone().arg("hello").arg("world"),
+                execs().with_status(0).with_stdout("hello world\n"));
+    assert_that(pr.arg("greet").arg("world"),
+                execs().with_status(0).with_stdout("greetings world\n"));
+});
+
+#[cfg(windows)]
+test!(batch_subcommand_arguments_are_escaped {
+    let p = project("foo")
+        .file("bin/cargo-hello.bat", "@echo \"%~1\"");
+    p.build();
+
+    let mut path = path();
+    path.push(p.root().join("bin"));
+    let path = env::join_paths(path.iter()).unwrap();
+    let pr = process(cargo_dir().join("cargo")).unwrap()
+                    .cwd(p.root())
+                    .env("HOME", Some(paths::home()))
+                    .env("PATH", Some(path.to_str().unwrap()));
+
+    assert_that(pr.clone().arg("hello").arg("a&echo injected"),
+                execs().with_status(0).with_stdout("\"a&echo injected\"\n"));
+    assert_that(pr.clone().arg("hello").arg("a&echo"),
+                execs().with_status(0).with_stdout("\"a&echo\"\n"));
+    assert_that(pr.arg("hello").arg("%PATH%"),
+                execs().with_status(101).with_stderr("\
+the argument `%PATH%` can't be passed to a batch file safely
+"));
 });
 
 test!(find_closest_biuld_to_build {
@@ -60,6 +155,47 @@ Did you mean `build`?
 "));
 });
 
+test!(find_closest_alias {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            [alias]
+            deploy = "build --release"
+        "#);
+    p.build();
+
+    let pr = process(cargo_dir().join("cargo")).unwrap()
+                    .arg("depoly").cwd(p.root())
+                    .env("HOME", Some(paths::home()));
+    assert_that(pr,
+                execs().with_status(127)
+                       .with_stderr("No such subcommand
+
+Did you mean `deploy`?
+
+"));
+});
+
+test!(find_closest_several_candidates {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            [alias]
+            tess = "test --release"
+        "#);
+    p.build();
+
+    // Built-in commands are suggested before aliases which are as close
+    let pr = process(cargo_dir().join("cargo")).unwrap()
+                    .arg("tes").cwd(p.root())
+                    .env("HOME", Some(paths::home()));
+    assert_that(pr,
+                execs().with_status(127)
+                       .with_stderr("No such subcommand
+
+Did you mean one of `test`, `tess`?
+
+"));
+});
+
 // if a subcommand is more than 3 edit distance away, we don't make a suggestion
 test!(find_closest_dont_correct_nonsense {
     let pr = process(cargo_dir().join("cargo")).unwrap()
@@ -72,6 +208,46 @@ test!(find_closest_dont_correct_nonsense {
 "));
 });
 
+#[cfg(not(windows))]
+test!(external_subcommand_environment {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file("bin/cargo-envtest", r#"#!/bin/sh
+            echo "$CARGO_MANIFEST_PATH"
+            echo "$CARGO_TERM_VERBOSITY"
+            echo "$CARGO_TERM_COLOR"
+            echo "$CARGO_BUILD_JOBS"
+            echo "$CARGO_ALIAS_LS"
+        "#);
+    p.build();
+    let script = p.root().join("bin/cargo-envtest");
+    let old_io::FileStat{perm, ..} = fs::stat(&script).unwrap();
+    fs::chmod(&script, old_io::USER_EXECUTE | old_io::OTHER_EXECUTE | perm)
+       .unwrap();
+
+    let mut path = path();
+    path.push(p.root().join("bin"));
+    let path = env::join_paths(path.iter()).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo"))
+                 .arg("--color").arg("never").arg("--config")
+                 .arg("build.jobs=2").arg("--config")
+                 .arg("alias.ls=[\"a b\", \"c\"]").arg("-v").arg("envtest")
+                 .env("PATH", Some(path.to_str().unwrap())),
+                execs().with_status(0).with_stdout(format!("\
+{}
+verbose
+never
+2
+[\"a b\", \"c\"]
+", p.root().join("Cargo.toml").display())));
+});
+
 test!(override_cargo_home {
     let root = paths::root();
     let my_home = root.join("my_home");
@@ -95,3 +271,146 @@ test!(override_cargo_home {
     let toml = File::open(&toml).read_to_string().unwrap();
     assert!(toml.as_slice().contains(r#"authors = ["foo <bar>"]"#));
 });
+
+test!(alias_expands_to_builtin_command {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [alias]
+            b-quiet = "build --quiet"
+            bq = ["b-quiet"]
+        "#);
+
+    assert_that(p.cargo_process("bq"),
+                execs().with_status(0).with_stdout(""));
+});
+
+test!(recursive_alias {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [alias]
+            a = "b --quiet"
+            b = "a"
+        "#);
+
+    assert_that(p.cargo_process("a"),
+                execs().with_status(101).with_stderr("\
+alias `a` is recursive: a -> b -> a
+"));
+});
+
+test!(alias_shadowed_by_builtin_command {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [alias]
+            build = "test"
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-q"),
+                execs().with_status(0).with_stdout("").with_stderr("\
+warning: the alias `build` is ignored, as it is shadowed by a built-in command
+"));
+});
+
+test!(builtin_alias {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("b").arg("-q"),
+                execs().with_status(0).with_stdout(""));
+});
+
+test!(builtin_alias_overridden_by_user_alias {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [alias]
+            b = "build --quiet"
+        "#);
+
+    assert_that(p.cargo_process("b"),
+                execs().with_status(0).with_stdout(""));
+});
+
+test!(alias_from_included_config {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            include = ["../shared/config"]
+
+            [alias]
+            b2 = "build --quiet"
+        "#)
+        .file("shared/config", r#"
+            [alias]
+            b1 = "build"
+            b2 = "build"
+        "#);
+
+    assert_that(p.cargo_process("b1"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({dir})
+", compiling = COMPILING, dir = p.url())));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("b2"),
+                execs().with_status(0).with_stdout(""));
+});
+
+test!(help_shows_manual_page {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("help").arg("help"),
+                execs().with_status(0).with_stdout("\
+NAME
+    cargo-help - Get some help with a cargo command.
+
+SYNOPSIS
+    cargo help <command>
+    cargo help -h | --help
+
+DESCRIPTION
+    The manual page of built-in commands is shown with `man` when printing to a
+    terminal, and as plain text otherwise.
+
+OPTIONS
+    -h, --help
+        Print this message
+"));
+});
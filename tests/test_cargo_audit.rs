@@ -0,0 +1,173 @@
+use std::old_io::{self, fs, File};
+
+use support::{project, execs, cargo_dir};
+use support::{UPDATING, DOWNLOADING, COMPILING};
+use support::{git, paths};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+const ADVISORY: &'static str = r#"
+    [advisory]
+    id = "EXAMPLE-2015-0001"
+    package = "bar"
+    title = "Buffer overflow when parsing headers"
+    url = "https://example.com/EXAMPLE-2015-0001"
+    patched_versions = [">= 0.1.2"]
+    unaffected_versions = ["< 0.1.0"]
+"#;
+
+fn advisory_db() -> Path {
+    let db = paths::root().join("advisory-db");
+    let path = db.join("bar").join("EXAMPLE-2015-0001.toml");
+    fs::mkdir_recursive(&path.dir_path(), old_io::USER_DIR).unwrap();
+    File::create(&path).write_str(ADVISORY).unwrap();
+    db
+}
+
+test!(audit_affected_package {
+    let db = advisory_db();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.1"
+            baz = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.1.1", &[]);
+    r::mock_pkg("baz", "0.1.0", &[]);
+
+    assert_that(p.cargo_process("audit").arg("-q")
+                 .arg("--db").arg(db.clone()),
+                execs().with_status(1).with_stdout("\
+EXAMPLE-2015-0001: Buffer overflow when parsing headers
+    package: bar v0.1.1 (registry file://[..])
+    url:     https://example.com/EXAMPLE-2015-0001
+").with_stderr("\
+1 package is affected by an advisory
+"));
+
+    r::mock_pkg("bar", "0.1.2", &[]);
+    assert_that(p.process(cargo_dir().join("cargo")).arg("update"),
+                execs().with_status(0));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("audit")
+                 .arg("--db").arg(db),
+                execs().with_status(0).with_stdout(""));
+});
+
+test!(audit_without_database {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("audit"),
+                execs().with_status(101).with_stderr("\
+no advisory database is configured, pass one with `--db` or set \
+`audit.database` or `audit.url`
+"));
+});
+
+test!(audit_on_build {
+    advisory_db();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [audit]
+            database = "../advisory-db"
+            on-build = true
+        "#);
+
+    r::mock_pkg("bar", "0.1.1", &[]);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+{downloading} bar v0.1.1 (registry file://[..])
+{compiling} bar v0.1.1 (registry file://[..])
+{compiling} foo v0.0.1 ({dir})
+",
+        updating = UPDATING,
+        downloading = DOWNLOADING,
+        compiling = COMPILING,
+        dir = p.url(),
+        reg = r::registry())).with_stderr("\
+warning: bar v0.1.1 (registry file://[..]) is affected by EXAMPLE-2015-0001: \
+Buffer overflow when parsing headers
+"));
+});
+
+test!(audit_on_build_fetches_head_once {
+    // The advisory is only on `main`, which HEAD points to
+    let db = paths::root().join("advisory-db");
+    git::repo(&db).file("README", "").build();
+    File::create(&db.join(".git/HEAD")).write_str("ref: refs/heads/main\n")
+                                       .unwrap();
+    let db = git::repo(&db).file("bar/EXAMPLE-2015-0001.toml", ADVISORY);
+    db.build();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &format!(r#"
+            [audit]
+            url = "{}"
+            on-build = true
+        "#, db.url()));
+
+    r::mock_pkg("bar", "0.1.1", &[]);
+
+    let warning = "\
+warning: bar v0.1.1 (registry file://[..]) is affected by EXAMPLE-2015-0001: \
+Buffer overflow when parsing headers
+";
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+{updating} advisory database `{db}`
+{downloading} bar v0.1.1 (registry file://[..])
+{compiling} bar v0.1.1 (registry file://[..])
+{compiling} foo v0.0.1 ({dir})
+",
+        updating = UPDATING,
+        downloading = DOWNLOADING,
+        compiling = COMPILING,
+        dir = p.url(),
+        db = db.url(),
+        reg = r::registry())).with_stderr(warning));
+
+    // The database was fetched just now, so it isn't fetched again
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stdout("").with_stderr(warning));
+});
@@ -1,11 +1,17 @@
 use std::collections::HashSet;
+use std::env;
+use std::ffi::OsString;
 use std::old_io::net::tcp::TcpAcceptor;
+use std::old_io::{self, fs, File};
 use std::old_io::{TcpListener, Listener, Acceptor, BufferedStream};
 use std::thread::Thread;
 use git2;
+use url::Url;
 
-use support::{project, execs, UPDATING};
+use support::{project, execs, cargo_dir, UPDATING, RUNNING};
 use support::paths;
+use support::git;
+use support::registry as r;
 use hamcrest::assert_that;
 
 fn setup() {
@@ -123,6 +129,60 @@ Caused by:
     t.join().ok().unwrap();
 });
 
+// Test that `credential.helper` is only consulted once, even though the server
+// rejects what it returned
+test!(credential_helper_consulted_once {
+    if cfg!(windows) { return }
+
+    let mut listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.socket_name().unwrap();
+    let mut a = listener.listen().unwrap();
+    let _c = Closer { a: a.clone() };
+
+    let t = Thread::scoped(move|| {
+        for _ in range(0, 2u) {
+            let mut s = BufferedStream::new(a.accept().unwrap());
+            read_headers(&mut s);
+            s.write_all(b"\
+                HTTP/1.1 401 Unauthorized\r\n\
+                WWW-Authenticate: Basic realm=\"wheee\"\r\n\
+                Content-Length: 0\r\n\
+                \r\n\
+            ").unwrap();
+        }
+    });
+
+    let helper = paths::root().join("helper");
+    File::create(&helper).write_str(format!(r#"#!/bin/sh
+        echo "$1" >> {}
+        echo username=foo
+        echo password=bar
+    "#, paths::root().join("calls").display()).as_slice()).unwrap();
+    fs::chmod(&helper, old_io::USER_RWX).unwrap();
+    let config = paths::home().join(".gitconfig");
+    let mut config = git2::Config::open(&config).unwrap();
+    config.set_str("credential.helper",
+                   helper.display().to_string().as_slice()).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            git = "http://127.0.0.1:{}/foo/bar"
+        "#, addr.port).as_slice())
+        .file("src/main.rs", "");
+
+    assert_that(p.cargo_process("build"), execs().with_status(101));
+    t.join().ok().unwrap();
+
+    let calls = File::open(&paths::root().join("calls")).read_to_string();
+    assert_eq!(calls.unwrap(), "get\n");
+});
+
 // Boy, sure would be nice to have a TLS implementation in rust!
 test!(https_something_happens {
     let mut listener = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -173,8 +233,9 @@ Caused by:
     t.join().ok().unwrap();
 });
 
-// Boy, sure would be nice to have an SSH implementation in rust!
-test!(ssh_something_happens {
+// Test that ssh repositories are fetched with the git executable, where
+// OpenSSH verifies the host key of the server
+test!(ssh_uses_git_executable {
     let mut listener = TcpListener::bind("127.0.0.1:0").unwrap();
     let addr = listener.socket_name().unwrap();
     let mut a = listener.listen().unwrap();
@@ -199,19 +260,138 @@ test!(ssh_something_happens {
     assert_that(p.cargo_process("build").arg("-v"),
                 execs().with_status(101).with_stdout(format!("\
 {updating} git repository `ssh://{addr}/foo/bar`
+{running} `git fetch [..] ssh://{addr}/foo/bar [..]`
 ",
         updating = UPDATING,
+        running = RUNNING,
         addr = addr,
-        ).as_slice())
-                      .with_stderr(format!("\
-Unable to update ssh://{addr}/foo/bar
+        ).as_slice()));
+    t.join().ok().unwrap();
+});
 
-Caused by:
-  failed to clone into: [..]
+fn read_headers<R: Buffer>(rdr: &mut R) -> Vec<String> {
+    rdr.lines().map(|s| s.unwrap().trim().to_string())
+       .take_while(|s| !s.is_empty())
+       .collect()
+}
+
+// Test that the registry token isn't sent along when a download is redirected
+// to another server
+test!(token_not_sent_on_cross_origin_redirect {
+    let mut listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.socket_name().unwrap();
+    let mut a = listener.listen().unwrap();
+    let _c = Closer { a: a.clone() };
+    let mut cdn = TcpListener::bind("127.0.0.1:0").unwrap();
+    let cdn_addr = cdn.socket_name().unwrap();
+    let mut b = cdn.listen().unwrap();
+    let _c2 = Closer { a: b.clone() };
+
+    r::mock_archive("bar", "0.0.1", &[]);
+    let krate = File::open(&r::mock_archive_dst("bar", "0.0.1"))
+                     .read_to_end().unwrap();
+    let index = paths::root().join("auth-registry");
+    git::repo(&index)
+        .file("config.json", format!(r#"{{"dl":"http://{}/dl","api":""}}"#,
+                                     addr).as_slice())
+        .file("3/b/bar", r::pkg("bar", "0.0.1", &[],
+                                &r::cksum(&krate[]), false).as_slice())
+        .build();
+    let index = Url::from_file_path(&index).ok().unwrap();
+    let config = paths::home().join(".cargo/config");
+    fs::mkdir_recursive(&config.dir_path(), old_io::USER_DIR).unwrap();
+    File::create(&config).write_str(format!(r#"
+        [registry]
+        index = "{}"
+        token = "api-token"
+        auth-required = true
+    "#, index).as_slice()).unwrap();
+
+    let t = Thread::scoped(move|| {
+        let mut s = BufferedStream::new(a.accept().unwrap());
+        let req = read_headers(&mut s);
+        assert!(req.contains(&"Authorization: api-token".to_string()),
+                "{:?}", req);
+        s.write_all(format!("\
+            HTTP/1.1 302 Found\r\n\
+            Location: http://{}/bar.crate\r\n\
+            Content-Length: 0\r\n\
+            \r\n", cdn_addr).as_bytes()).unwrap();
+        drop(s);
+
+        let mut s = BufferedStream::new(b.accept().unwrap());
+        let req = read_headers(&mut s);
+        assert!(!req.iter().any(|h| h.starts_with("Authorization")),
+                "{:?}", req);
+        s.write_all(format!("\
+            HTTP/1.1 200 OK\r\n\
+            Content-Length: {}\r\n\
+            \r\n", krate.len()).as_bytes()).unwrap();
+        s.write_all(&krate[]).unwrap();
+    });
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    assert_that(p.cargo_process("fetch"), execs().with_status(0));
 
-Caused by:
-  [[..]] Failed to start SSH session: Failed getting banner
-",
-        addr = addr)));
     t.join().ok().unwrap();
 });
+
+// Test that the git executable is offered the registry token too, without it
+// showing up in its arguments
+test!(token_offered_to_git_executable {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [registry]
+            index = "http://127.0.0.1:1/index"
+            token = "api-token"
+            auth-required = true
+
+            [net]
+            git-fetch-with-cli = true
+        "#)
+        .file("bin/git", r#"#!/bin/sh
+            dir=$(dirname $0)
+            echo "$@" > $dir/args
+            echo "$CARGO_REGISTRY_GIT_TOKEN" > $dir/token
+            exit 1
+        "#);
+    p.build();
+    if cfg!(windows) { return }
+    let git = p.root().join("bin/git");
+    fs::chmod(&git, old_io::USER_RWX).unwrap();
+
+    let mut path = vec![p.root().join("bin")];
+    path.extend(env::split_paths(&env::var("PATH")
+                                     .unwrap_or(OsString::new())));
+    let path = env::join_paths(path.iter()).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("fetch")
+                 .env("PATH", Some(path.to_str().unwrap())),
+                execs().with_status(101));
+
+    let args = File::open(&p.root().join("bin/args")).read_to_string();
+    let args = args.unwrap();
+    assert!(args.contains("credential.helper"), "{}", args);
+    assert!(!args.contains("api-token"), "{}", args);
+    let token = File::open(&p.root().join("bin/token")).read_to_string();
+    assert_eq!(token.unwrap(), "api-token\n");
+});
@@ -0,0 +1,68 @@
+use std::old_io::{fs, File, USER_RWX};
+
+use support::{project, execs, paths};
+use hamcrest::{assert_that, existing_file, is_not};
+
+fn setup() {
+}
+
+fn cached_crate(name: &str) -> Path {
+    let dir = paths::home().join(".cargo/registry/cache/example.com-0123");
+    fs::mkdir_recursive(&dir, USER_RWX).unwrap();
+    let krate = dir.join(format!("{}.crate", name));
+    File::create(&krate).write_str("crate").unwrap();
+    krate
+}
+
+test!(removes_old_entries {
+    let old = cached_crate("old-0.1.0");
+    let new = cached_crate("new-0.1.0");
+    fs::change_file_times(&old, 0, 0).unwrap();
+
+    let p = project("foo");
+    assert_that(p.cargo_process("cache").arg("--dry-run"),
+                execs().with_status(0).with_stdout("\
+[..]Summary 1 unused entry would be removed, 5B total
+"));
+    assert_that(&old, existing_file());
+
+    assert_that(p.cargo_process("cache"),
+                execs().with_status(0).with_stdout("\
+[..]Summary 1 unused entry removed, 5B total
+"));
+    assert_that(&old, is_not(existing_file()));
+    assert_that(&new, existing_file());
+});
+
+test!(max_age_flag {
+    let krate = cached_crate("foo-0.1.0");
+
+    let p = project("foo");
+    assert_that(p.cargo_process("cache").arg("--max-age").arg("0"),
+                execs().with_status(0));
+    assert_that(&krate, is_not(existing_file()));
+});
+
+test!(last_use_is_recorded_outside_of_the_cache {
+    let krate = cached_crate("used-0.1.0");
+    fs::change_file_times(&krate, 0, 0).unwrap();
+    let marker = paths::home().join(".cargo/.last-use/registry/cache/\
+                                     example.com-0123/used-0.1.0.crate");
+    fs::mkdir_recursive(&marker.dir_path(), USER_RWX).unwrap();
+    File::create(&marker).unwrap();
+
+    let p = project("foo");
+    assert_that(p.cargo_process("cache"),
+                execs().with_status(0).with_stdout("\
+[..]Summary 0 unused entries removed, 0B total
+"));
+    assert_that(&krate, existing_file());
+
+    fs::change_file_times(&marker, 0, 0).unwrap();
+    assert_that(p.cargo_process("cache"),
+                execs().with_status(0).with_stdout("\
+[..]Summary 1 unused entry removed, 5B total
+"));
+    assert_that(&krate, is_not(existing_file()));
+    assert_that(&marker, is_not(existing_file()));
+});
@@ -31,3 +31,36 @@ test!(different_dir {
                 execs().with_status(0).with_stdout(""));
     assert_that(&p.build_dir(), is_not(existing_dir()));
 });
+
+test!(dry_run {
+    let p = project("foo")
+              .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+              .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(&p.build_dir(), existing_dir());
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("clean")
+                 .arg("--dry-run"),
+                execs().with_status(0).with_stdout("\
+[..]Summary [..] files would be removed, [..] total
+"));
+    assert_that(&p.build_dir(), existing_dir());
+});
+
+test!(release_only {
+    let p = project("foo")
+              .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+              .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    assert_that(p.cargo_process("build").arg("--release"),
+                execs().with_status(0));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("clean")
+                 .arg("--release"),
+                execs().with_status(0));
+    assert_that(&p.build_dir().join("release"), is_not(existing_dir()));
+    assert_that(&p.build_dir(), existing_dir());
+});
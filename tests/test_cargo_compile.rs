@@ -1,12 +1,15 @@
-use std::old_io::{self, fs, TempDir, File};
+use std::old_io::{self, fs, TempDir, File, ChanWriter};
 use std::env;
 use std::old_path;
+use std::sync::mpsc::channel;
 
 use support::{project, execs, main_file, basic_bin_manifest};
 use support::{COMPILING, RUNNING, cargo_dir, ProjectBuilder};
-use hamcrest::{assert_that, existing_file};
+use hamcrest::{assert_that, existing_file, is_not};
 use support::paths::PathExt;
-use cargo::util::process;
+use cargo::core::{MultiShell, Verbosity};
+use cargo::ops::{self, CompileOptions};
+use cargo::util::{process, Config};
 
 fn setup() {
 }
@@ -93,6 +96,27 @@ Caused by:
 src[..]Cargo.toml:1:5-1:6 expected a value\n\n"))
 });
 
+test!(cargo_compile_manifest_path_must_be_a_manifest {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("build").arg("--manifest-path").arg("src"),
+                execs().with_status(101).with_stderr("\
+the manifest-path must be a path to a Cargo.toml file
+"));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--manifest-path").arg("bar/Cargo.toml"),
+                execs().with_status(101).with_stderr("\
+manifest path `[..]bar[..]Cargo.toml` does not exist
+"));
+});
+
 test!(cargo_compile_with_invalid_version {
     let p = project("foo")
         .file("Cargo.toml", r#"
@@ -181,9 +205,50 @@ test!(cargo_compile_with_warnings_in_the_root_package {
 on by default
 {filename}:1 fn main() {{}} fn dead() {{}}
                           ^~~~~~~~~~~~
+warning: `foo` (bin \"foo\") generated 1 warning
+note: warnings of crates which are not rebuilt are replayed, so they are \
+printed by every build until fixed
 ", filename = format!("src{}foo.rs", old_path::SEP).as_slice())));
 });
 
+test!(warnings_are_replayed_for_fresh_crates {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", "fn main() {} fn dead() {}");
+    let stderr = format!("\
+{filename}:1:14: 1:26 warning: function is never used: `dead`, #[warn(dead_code)] \
+on by default
+{filename}:1 fn main() {{}} fn dead() {{}}
+                          ^~~~~~~~~~~~
+warning: `foo` (bin \"foo\") generated 1 warning
+note: warnings of crates which are not rebuilt are replayed, so they are \
+printed by every build until fixed
+", filename = format!("src{}foo.rs", old_path::SEP).as_slice());
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stderr(stderr.as_slice()));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stdout("")
+                       .with_stderr(stderr.as_slice()));
+});
+
+test!(only_diagnostic_headers_are_counted_as_warnings {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", r#"fn main() {} fn dead() { "a warning: b"; }"#);
+
+    assert_that(p.cargo_process("build"),
+        execs().with_status(0)
+        .with_stderr("\
+[..]warning: function is never used: `dead`[..]
+[..]fn dead() { \"a warning: b\"; }
+[..]^~~~[..]
+warning: `foo` (bin \"foo\") generated 1 warning
+note: warnings of crates which are not rebuilt are replayed, so they are \
+printed by every build until fixed
+"));
+});
+
 test!(cargo_compile_with_warnings_in_a_dep_package {
     let mut p = project("foo");
 
@@ -233,6 +298,9 @@ test!(cargo_compile_with_warnings_in_a_dep_package {
 [..]warning: function is never used: `dead`[..]
 [..]fn dead() {}
 [..]^~~~~~~~~~~~
+warning: `bar` (lib) generated 1 warning
+note: warnings of crates which are not rebuilt are replayed, so they are \
+printed by every build until fixed
 "));
 
     assert_that(&p.bin("foo"), existing_file());
@@ -710,7 +778,8 @@ test!(unused_keys {
         "#);
     assert_that(p.cargo_process("build"),
                 execs().with_status(0)
-                       .with_stderr("unused manifest key: project.bulid\n"));
+                       .with_stderr("unused manifest key: project.bulid \
+                                     (did you mean `build`?)\n"));
 
     let mut p = project("bar");
     p = p
@@ -734,6 +803,32 @@ test!(unused_keys {
                        .with_stderr("unused manifest key: lib.build\n"));
 });
 
+test!(misspelled_keys {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            decription = "foo"
+
+            [dependencis]
+            bar = "0.1"
+            baz = "0.1"
+
+            [profile.dev]
+            opt_levle = 1
+        "#)
+        .file("src/lib.rs", "");
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0)
+                       .with_stderr("\
+unused manifest key: dependencis (did you mean `dependencies`?)
+unused manifest key: package.decription (did you mean `description`?)
+unused manifest key: profile.dev.opt_levle (did you mean `opt-level`?)
+"));
+});
+
 test!(self_dependency {
     let mut p = project("foo");
     p = p
@@ -760,7 +855,7 @@ cyclic package dependency: package `test v0.0.0 ([..])` depends on itself
 "));
 });
 
-test!(ignore_broken_symlinks {
+test!(broken_symlinks_are_errors {
     // windows and symlinks don't currently agree that well
     if cfg!(windows) { return }
 
@@ -769,12 +864,10 @@ test!(ignore_broken_symlinks {
         .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice())
         .symlink("Notafile", "bar");
 
-    assert_that(p.cargo_process("build"), execs());
-    assert_that(&p.bin("foo"), existing_file());
-
-    assert_that(
-      process(p.bin("foo")).unwrap(),
-      execs().with_stdout("i am foo\n"));
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+`[..]bar` is a broken symlink
+"));
 });
 
 test!(missing_lib_and_bin {
@@ -1650,3 +1743,229 @@ test!(cyclic_deps_rejected {
 cyclic package dependency: package `foo v0.0.1 ([..])` depends on itself
 "));
 });
+
+test!(quiet_build_has_no_status_lines {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    assert_that(p.cargo_process("build").arg("-q"),
+                execs().with_status(0).with_stdout(""));
+    assert_that(&p.bin("foo"), existing_file());
+});
+
+test!(verbose_and_quiet_conflict {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    assert_that(p.cargo_process("build").arg("-v").arg("-q"),
+                execs().with_status(101).with_stderr("\
+cannot pass both --verbose and --quiet
+"));
+});
+
+#[cfg(not(windows))]
+test!(build_hooks {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice())
+        .file(".cargo/config", r#"
+            [hooks]
+            pre-build = "echo before"
+            post-build = ["sh", "-c", "test -f target/foo && echo after"]
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stdout(format!("\
+{running} pre-build hook `echo before`
+before
+{compiling} foo v0.5.0 ({url})
+{running} post-build hook `sh -c test -f target/foo && echo after`
+after
+", running = RUNNING, compiling = COMPILING, url = p.url())));
+});
+
+#[cfg(not(windows))]
+test!(failing_build_hook {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice())
+        .file(".cargo/config", r#"
+            [hooks]
+            pre-build = ["sh", "-c", "echo oops >&2; exit 3"]
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stdout(format!("\
+{running} pre-build hook `sh -c echo oops >&2; exit 3`
+", running = RUNNING)).with_stderr("\
+oops
+the pre-build hook failed
+
+To learn more, run the command again with --verbose.
+"));
+    assert_that(&p.bin("foo"), is_not(existing_file()));
+});
+
+test!(compile_through_library_with_custom_shell {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+    p.build();
+
+    let (tx, rx) = channel();
+    let out = Box::new(ChanWriter::new(tx.clone()));
+    let err = Box::new(ChanWriter::new(tx));
+    {
+        let mut shell = MultiShell::from_writers(out, err, Verbosity::Normal);
+        let config = Config::with_cwd(&mut shell, p.root()).unwrap();
+        let options = CompileOptions::new("compile", &config);
+        ops::compile(&p.root().join("Cargo.toml"), &options).unwrap();
+    }
+    let output = rx.iter().flat_map(|v| v.into_iter()).collect::<Vec<u8>>();
+    assert_eq!(String::from_utf8(output).unwrap(),
+               format!("{} foo v0.5.0 ({})\n", COMPILING, p.url()));
+    assert_that(&p.bin("foo"), existing_file());
+});
+
+test!(out_dir_gets_final_artifacts {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [lib]
+
+            name = "foo"
+            crate_type = ["rlib", "dylib", "staticlib"]
+
+            [[bin]]
+
+            name = "bar"
+        "#)
+        .file("src/lib.rs", r#"
+            pub fn foo() {}
+        "#)
+        .file("src/bin/bar.rs", r#"
+            extern crate foo;
+            fn main() { foo::foo() }
+        "#);
+    assert_that(p.cargo_process("build").arg("--out-dir").arg("out"),
+                execs().with_status(0));
+
+    let mut files = fs::readdir(&p.root().join("out")).unwrap().iter().map(|f| {
+        f.filename_str().unwrap().to_string()
+    }).collect::<Vec<_>>();
+    files.sort();
+    let mut expected = vec![
+        format!("bar{}", env::consts::EXE_SUFFIX),
+        format!("{}foo{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX),
+        "libfoo.a".to_string(),
+    ];
+    expected.sort();
+    assert_eq!(files, expected);
+});
+
+test!(dep_info_of_top_level_artifacts {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+
+            [[bin]]
+            name = "foo"
+        "#)
+        .file("src/foo.rs", r#"
+            extern crate bar;
+            fn main() { bar::bar() }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", r#"
+            mod baz;
+            pub fn bar() { baz::baz() }
+        "#)
+        .file("bar/src/baz.rs", r#"
+            pub fn baz() {}
+        "#);
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    let contents = File::open(&p.root().join("target/foo.d")).read_to_string()
+                                                             .unwrap();
+    let root = p.root();
+    assert_eq!(contents, format!("{}: {} {} {}\n",
+                                 p.bin("foo").display(),
+                                 root.join("src/foo.rs").display(),
+                                 root.join("bar/src/lib.rs").display(),
+                                 root.join("bar/src/baz.rs").display()));
+});
+
+test!(size_report_invalid_format {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    assert_that(p.cargo_process("build").arg("--size-report").arg("xml"),
+                execs().with_status(1).with_stderr("\
+--size-report must be human or json, but found `xml`
+"));
+});
+
+test!(size_report_json {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    let output = p.cargo_process("build").arg("--size-report").arg("json")
+                  .exec_with_output().unwrap();
+    let stdout = String::from_utf8(output.output).unwrap();
+    let stderr = String::from_utf8(output.error).unwrap();
+    assert!(stderr.contains("Compiling foo v0.5.0"), "{}", stderr);
+    assert_eq!(stdout.lines().count(), 1);
+    let json = stdout.lines().next().unwrap();
+    assert!(json.starts_with(r#"{"artifacts":[{"path":"#), "{}", json);
+    assert!(json.contains(r#""file_size":"#), "{}", json);
+    assert!(json.contains(r#""sections":["#), "{}", json);
+});
+
+#[cfg(not(windows))]
+test!(build_hooks_from_manifest {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [hooks]
+            pre-build = "echo manifest"
+            post-build = "echo manifest"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [hooks]
+            post-build = "echo config"
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(0).with_stdout(format!("\
+{running} pre-build hook `echo manifest`
+manifest
+{compiling} foo v0.5.0 ({url})
+{running} post-build hook `echo config`
+config
+", running = RUNNING, compiling = COMPILING, url = p.url())));
+});
@@ -1,8 +1,8 @@
 use std::env;
-use std::old_io::{File, fs};
+use std::old_io::{File, fs, USER_DIR};
 
-use support::{project, execs, cargo_dir};
-use support::{COMPILING, RUNNING, DOCTEST};
+use support::{project, execs, cargo_dir, path2url};
+use support::{COMPILING, RUNNING, DOCTEST, FRESH};
 use support::paths::PathExt;
 use hamcrest::{assert_that};
 
@@ -1077,3 +1077,413 @@ test!(build_script_with_dynamic_native_dependency {
     assert_that(foo.cargo_process("build").env("SRC", Some(lib.as_vec())),
                 execs().with_status(0));
 });
+
+test!(env_config {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/main.rs", r#"
+            use std::env;
+            fn main() {
+                println!("{}", env::var_string("FOO").unwrap());
+            }
+        "#)
+        .file("build.rs", r#"
+            use std::env;
+            fn main() {
+                assert_eq!(env::var_string("FOO").unwrap().as_slice(), "bar");
+                let data = Path::new(env::var_string("DATA").unwrap());
+                assert!(data.is_absolute());
+                assert!(data.ends_with_path(&Path::new("foo/data")));
+                assert_eq!(env::var_string("KEPT").unwrap().as_slice(),
+                           "outer");
+                assert_eq!(env::var_string("FORCED").unwrap().as_slice(),
+                           "inner");
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            FOO = "bar"
+            KEPT = "inner"
+
+            [env.DATA]
+            value = "data"
+            relative = true
+
+            [env.FORCED]
+            value = "inner"
+            force = true
+        "#);
+
+    assert_that(p.cargo_process("run").env("KEPT", Some("outer"))
+                 .env("FORCED", Some("outer")),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+{running} `target[..]foo[..]`
+bar
+", compiling = COMPILING, running = RUNNING, url = p.url())));
+
+    // Changing a variable reruns the build script
+    File::create(&p.root().join(".cargo/config")).write_str(r#"
+        [env]
+        FOO = "baz"
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(101));
+});
+
+test!(env_config_merges_cli_and_file_tables {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("build.rs", r#"
+            use std::env;
+            fn main() {
+                assert_eq!(env::var_string("FILE").unwrap().as_slice(),
+                           "file");
+                assert_eq!(env::var_string("CLI").unwrap().as_slice(), "cli");
+                assert_eq!(env::var_string("BOTH").unwrap().as_slice(),
+                           "cli");
+            }
+        "#)
+        .file(".cargo/config", r#"
+            [env]
+            FILE = "file"
+            BOTH = "file"
+        "#);
+
+    assert_that(p.cargo_process("--config").arg("env.CLI=\"cli\"")
+                 .arg("--config").arg("env.BOTH=\"cli\"").arg("build"),
+                execs().with_status(0));
+});
+
+test!(build_deps_artifact_bin {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+            [build-dependencies.gen]
+            path = "gen"
+            artifact = "bin"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            use std::old_io::Command;
+            fn main() {
+                let gen = env::var_string("CARGO_BIN_FILE_GEN_GEN").unwrap();
+                let out = Command::new(gen).output().unwrap();
+                assert_eq!(out.output.as_slice(), b"generated\n");
+            }
+        "#)
+        .file("gen/Cargo.toml", r#"
+            [project]
+            name = "gen"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("gen/src/main.rs", r#"
+            fn main() { println!("generated"); }
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{compiling} gen v0.5.0 (file://[..])
+{running} `rustc [..] --crate-name gen [..]`
+{compiling} foo v0.5.0 (file://[..])
+{running} `rustc build.rs [..]`
+{running} `[..]foo-[..]build-script-build[..]`
+{running} `rustc [..] --crate-name foo [..]`
+", compiling = COMPILING, running = RUNNING).as_slice()));
+});
+
+test!(build_deps_artifact_not_for_normal {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            [dependencies.gen]
+            path = "gen"
+            artifact = "bin"
+        "#)
+        .file("src/lib.rs", "")
+        .file("gen/Cargo.toml", r#"
+            [project]
+            name = "gen"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("gen/src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  dependency `gen` requests an artifact, which is only supported for \
+build-dependencies
+"));
+});
+
+test!(build_script_allowlist {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+
+            [dependencies.bar]
+            path = "bar"
+
+            [workspace]
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("bar/build.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [build]
+            script-allowlist = []
+        "#);
+
+    let denied = "\
+these packages run code on the host, but don't match `build.script-allowlist`:
+  bar v0.5.0 ([..])
+review their build scripts and plugins and add them to the list to allow them
+";
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr(denied));
+
+    // Packages of the same name from other sources aren't allowed
+    File::create(&p.root().join(".cargo/config")).write_str(r#"
+        [build]
+        script-allowlist = ["https://example.com/bar#bar"]
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(101).with_stderr(denied));
+
+    // The build script of the package itself may always run
+    File::create(&p.root().join(".cargo/config")).write_str(format!(r#"
+        [build]
+        script-allowlist = ["{}#bar"]
+    "#, path2url(p.root().join("bar"))).as_slice()).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+});
+
+test!(script_allowlist_covers_plugins {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+
+            [dependencies.bar]
+            path = "bar"
+            [dependencies.baz]
+            path = "baz"
+
+            [workspace]
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [project]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("bar/build.rs", "fn main() {}")
+        .file("baz/Cargo.toml", r#"
+            [project]
+            name = "baz"
+            version = "0.5.0"
+            authors = []
+
+            [lib]
+            name = "baz"
+            plugin = true
+        "#)
+        .file("baz/src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [build]
+            script-allowlist = []
+        "#);
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+these packages run code on the host, but don't match `build.script-allowlist`:
+  bar v0.5.0 ([..])
+  baz v0.5.0 ([..])
+review their build scripts and plugins and add them to the list to allow them
+"));
+
+    File::create(&p.root().join(".cargo/config")).write_str(r#"
+        [build]
+        script-allowlist = ["bar"]
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(101)
+                       .with_stderr("\
+these packages run code on the host, but don't match `build.script-allowlist`:
+  baz v0.5.0 ([..])
+review their build scripts and plugins and add them to the list to allow them
+"));
+
+    File::create(&p.root().join(".cargo/config")).write_str(r#"
+        [build]
+        script-allowlist = ["bar", "baz"]
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+});
+
+test!(new_file_in_new_directory_reruns_build_script {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            fn main() {}
+        "#);
+
+    assert_that(p.cargo_process("build").arg("-v"),
+                execs().with_status(0));
+    p.root().move_into_the_past().unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build").arg("-v"),
+                execs().with_status(0));
+
+    fs::mkdir(&p.root().join("data"), USER_DIR).unwrap();
+    File::create(&p.root().join("data/some-new-file")).unwrap();
+    p.root().move_into_the_past().unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{compiling} foo v0.5.0 (file://[..])
+{running} `[..]build-script-build[..]`
+{running} `rustc [..] --crate-name foo [..]`
+", compiling = COMPILING, running = RUNNING).as_slice()));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build").arg("-v"),
+                execs().with_status(0)
+                       .with_stdout(format!("\
+{fresh} foo v0.5.0 (file://[..])
+", fresh = FRESH).as_slice()));
+});
+
+test!(build_script_jobserver {
+    if cfg!(windows) { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            build = "build.rs"
+        "#)
+        .file("src/lib.rs", "")
+        .file("build.rs", r#"
+            use std::env;
+            use std::old_io::{File, Open, ReadWrite};
+            use std::old_io::timer;
+            use std::sync::mpsc::channel;
+            use std::thread::Thread;
+            use std::time::Duration;
+
+            fn main() {
+                let flags = match env::var_string("CARGO_MAKEFLAGS") {
+                    Ok(flags) => flags,
+                    Err(..) => return,
+                };
+                assert_eq!(env::var_string("MAKEFLAGS").unwrap(),
+                           format!("-s {}", flags));
+                let prefix = "-j --jobserver-auth=fifo:";
+                assert!(flags.starts_with(prefix), "{}", flags);
+                let fifo = Path::new(&flags[prefix.len()..]);
+
+                // With two jobs, one token is left while this script runs
+                let mut pipe = File::open_mode(&fifo, Open, ReadWrite).unwrap();
+                let first = pipe.read_byte().unwrap();
+                let (tx, rx) = channel();
+                Thread::spawn(move || {
+                    let mut pipe = File::open_mode(&fifo, Open,
+                                                   ReadWrite).unwrap();
+                    tx.send(pipe.read_byte().unwrap()).unwrap();
+                });
+                timer::sleep(Duration::milliseconds(500));
+                assert!(rx.try_recv().is_err(), "more tokens than jobs");
+                pipe.write_u8(first).unwrap();
+                let second = rx.recv().unwrap();
+                pipe.write_u8(second).unwrap();
+                File::create(&Path::new(env::var_string("OUT_DIR").unwrap())
+                                 .join("announced")).unwrap();
+            }
+        "#);
+
+    // Only announced when asked to, as old versions of make don't understand
+    // it, and never when a `make` runs cargo
+    assert_that(p.cargo_process("build").arg("-j2")
+                 .env("MAKEFLAGS", None::<&str>)
+                 .env("MFLAGS", None::<&str>)
+                 .env("CARGO_MAKEFLAGS", None::<&str>),
+                execs().with_status(0));
+    let root = p.root();
+    let announced = || {
+        fs::walk_dir(&root.join("target/build")).unwrap().any(|f| {
+            f.filename_str() == Some("announced")
+        })
+    };
+    assert!(!announced());
+
+    fs::rmdir_recursive(&root.join("target")).unwrap();
+    fs::mkdir_recursive(&root.join(".cargo"), USER_DIR).unwrap();
+    File::create(&root.join(".cargo/config")).write_str(r#"
+        [build]
+        jobserver = true
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build").arg("-j2")
+                 .env("MAKEFLAGS", Some("-s"))
+                 .env("MFLAGS", None::<&str>)
+                 .env("CARGO_MAKEFLAGS", None::<&str>),
+                execs().with_status(0));
+    assert!(announced());
+});
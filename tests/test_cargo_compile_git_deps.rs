@@ -1,5 +1,7 @@
-use std::old_io::fs;
-use std::old_io::{timer, File};
+use std::old_io::fs::{self, PathExtensions};
+use std::old_io::process::Command;
+use std::old_io::{self, timer, File};
+use std::os;
 use std::time::Duration;
 use git2;
 
@@ -7,6 +9,7 @@ use support::{ProjectBuilder, project, execs, main_file};
 use support::{cargo_dir, path2url};
 use support::{COMPILING, UPDATING, RUNNING};
 use support::paths::{self, PathExt};
+use support::registry as r;
 use hamcrest::{assert_that,existing_file};
 use cargo;
 use cargo::util::{ProcessError, process};
@@ -602,9 +605,11 @@ test!(recompilation {
 
     // Update the dependency and carry on!
     assert_that(p.process(cargo_dir().join("cargo")).arg("update"),
-                execs().with_stdout(format!("{} git repository `{}`",
+                execs().with_stdout(format!("{} git repository `{}`\n\
+                                             {} bar v0.5.0 ([..] -> [..])\n",
                                             UPDATING,
-                                            git_project.url())));
+                                            git_project.url(),
+                                            UPDATING)));
     println!("going for the last compile");
     assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
                 execs().with_stdout(format!("{} bar v0.5.0 ({}#[..])\n\
@@ -720,9 +725,11 @@ test!(update_with_shared_deps {
     assert_that(p.process(cargo_dir().join("cargo")).arg("update")
                  .arg("-p").arg("dep1")
                  .arg("--aggressive"),
-                execs().with_stdout(format!("{} git repository `{}`",
+                execs().with_stdout(format!("{} git repository `{}`\n\
+                                             {} bar v0.5.0 ([..] -> [..])\n",
                                             UPDATING,
-                                            git_project.url())));
+                                            git_project.url(),
+                                            UPDATING)));
 
     // Make sure we still only compile one version of the git repo
     println!("build");
@@ -847,8 +854,9 @@ test!(two_deps_only_update_one {
     assert_that(project.process(cargo_dir().join("cargo")).arg("update")
                        .arg("-p").arg("dep1"),
         execs()
-        .with_stdout(format!("{} git repository `{}`\n",
-                             UPDATING, git1.url()))
+        .with_stdout(format!("{} git repository `{}`\n\
+                              {} dep1 v0.5.0 ([..] -> [..])\n",
+                             UPDATING, git1.url(), UPDATING))
         .with_stderr(""));
 });
 
@@ -1005,9 +1013,11 @@ test!(dep_with_changed_submodule {
     assert_that(project.process(cargo_dir().join("cargo")).arg("update").arg("-v"),
                 execs()
                 .with_stderr("")
-                .with_stdout(format!("{} git repository `{}`",
+                .with_stdout(format!("{} git repository `{}`\n\
+                                      {} dep1 v0.5.0 ([..] -> [..])\n",
                                      UPDATING,
-                                     git_project.url())));
+                                     git_project.url(),
+                                     UPDATING)));
 
     println!("last run");
     assert_that(project.process(cargo_dir().join("cargo")).arg("run"), execs()
@@ -1352,6 +1362,85 @@ test!(warnings_in_git_dep {
         .with_stderr(""));
 });
 
+test!(warnings_in_git_dep_without_lint_cap {
+    let bar = git_repo("bar", |project| {
+        project.file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+        "#)
+        .file("src/lib.rs", "fn unused() {}")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()).as_slice())
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [build]
+            cap-lints = "none"
+        "#);
+
+    assert_that(p.cargo_process("build"),
+        execs()
+        .with_status(0)
+        .with_stderr("\
+[..]warning: function is never used: `unused`[..]
+[..]fn unused() {}
+[..]^~~~~~~~~~~~~~
+warning: `bar` (lib) generated 1 warning
+note: warnings of crates which are not rebuilt are replayed, so they are \
+printed by every build until fixed
+"));
+});
+
+test!(cap_lints_flag_overrides_config {
+    let bar = git_repo("bar", |project| {
+        project.file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+        "#)
+        .file("src/lib.rs", "fn unused() {}")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            [dependencies.bar]
+            git = '{}'
+        "#, bar.url()).as_slice())
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [build]
+            cap-lints = "none"
+        "#);
+
+    assert_that(p.cargo_process("build").arg("--cap-lints").arg("allow"),
+        execs()
+        .with_status(0)
+        .with_stderr(""));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--cap-lints").arg("maybe"),
+        execs()
+        .with_status(101)
+        .with_stderr("\
+--cap-lints must be one of allow, warn, deny, forbid or none, but found `maybe`
+"));
+});
+
 test!(update_ambiguous {
     let foo1 = git_repo("foo1", |project| {
         project.file("Cargo.toml", r#"
@@ -1681,3 +1770,278 @@ test!(dont_require_submodules_are_checked_out {
                     .cwd(dst),
                 execs().with_status(0));
 });
+
+test!(fetch_with_cli {
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", r#"
+                pub fn hello() -> &'static str { "hello world" }
+            "#)
+    }).unwrap();
+
+    let project = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.dep1]
+            git = '{}'
+        "#, git_project.url()))
+        .file("src/main.rs", main_file(r#""{}", dep1::hello()"#, &["dep1"]))
+        .file(".cargo/config", r#"
+            [net]
+            git-fetch-with-cli = true
+        "#);
+
+    assert_that(project.cargo_process("build"), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process(project.bin("foo")).unwrap(),
+      execs().with_stdout("hello world\n"));
+});
+
+test!(shallow_fetch {
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", r#"
+                pub fn hello() -> &'static str { "hello world" }
+            "#)
+    }).unwrap();
+
+    let project = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.dep1]
+            git = '{}'
+        "#, git_project.url()))
+        .file("src/main.rs", main_file(r#""{}", dep1::hello()"#, &["dep1"]))
+        .file(".cargo/config", r#"
+            [net]
+            git-shallow = true
+        "#);
+
+    assert_that(project.cargo_process("build"), execs().with_status(0));
+    assert_that(
+      cargo::util::process(project.bin("foo")).unwrap(),
+      execs().with_stdout("hello world\n"));
+
+    let db = paths::home().join(".cargo/git/db");
+    let shallow = fs::readdir(&db).unwrap().into_iter().any(|dir| {
+        dir.join("shallow").exists()
+    });
+    assert!(shallow);
+});
+
+test!(shallow_fetch_disabled_fetches_full_history {
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let project = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.dep1]
+            git = '{}'
+        "#, git_project.url()))
+        .file("src/lib.rs", "extern crate dep1;")
+        .file(".cargo/config", r#"
+            [net]
+            git-shallow = true
+        "#);
+    assert_that(project.cargo_process("build"), execs().with_status(0));
+
+    let git = paths::home().join(".cargo/git");
+    let shallow = || {
+        fs::walk_dir(&git).unwrap().any(|p| p.filename_str() == Some("shallow"))
+    };
+    assert!(shallow());
+
+    File::create(&project.root().join(".cargo/config")).unwrap();
+    assert_that(project.process(cargo_dir().join("cargo")).arg("update"),
+                execs().with_status(0));
+    assert_that(project.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+    assert!(!shallow());
+});
+
+test!(shallow_fetch_only_applies_to_git_dependencies {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/lib.rs", "")
+        .file(".cargo/config", r#"
+            [net]
+            git-shallow = true
+        "#);
+    p.build();
+    r::init();
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+    let index = paths::home().join(".cargo/registry/index");
+    assert!(!fs::walk_dir(&index).unwrap().any(|p| {
+        p.filename_str() == Some("shallow")
+    }));
+});
+
+test!(submodules_disabled {
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [package]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+    let git_project2 = git_repo("dep2", |project| {
+        project.file("huge.txt", "")
+    }).unwrap();
+
+    let repo = git2::Repository::open(&git_project.root()).unwrap();
+    let url = path2url(git_project2.root()).to_string();
+    add_submodule(&repo, url.as_slice(), &Path::new("vendor"));
+    commit(&repo);
+
+    let project = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.dep1]
+            git = '{}'
+        "#, git_project.url()))
+        .file("src/lib.rs", "extern crate dep1;")
+        .file(".cargo/config", r#"
+            [net]
+            git-submodules = "none"
+        "#);
+
+    assert_that(project.cargo_process("build"),
+                execs().with_stderr("").with_status(0));
+
+    let checkouts = paths::home().join(".cargo/git/checkouts");
+    let vendored = fs::walk_dir(&checkouts).unwrap().any(|p| {
+        p.filename_str() == Some("huge.txt")
+    });
+    assert!(!vendored);
+});
+
+/// Builds a project depending on a git repository, returning the project and
+/// the path of the lock of the repository's database.
+fn locked_git_project() -> (ProjectBuilder, Path) {
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+                name = "dep1"
+                version = "0.5.0"
+                authors = []
+            "#)
+            .file("src/lib.rs", "")
+    }).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.dep1]
+            git = '{}'
+        "#, git_project.url()))
+        .file("src/lib.rs", "");
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    let db = paths::home().join(".cargo/git/db");
+    let name = fs::readdir(&db).unwrap().into_iter().find(|p| {
+        p.extension_str().is_none()
+    }).unwrap();
+    let lock = db.join(format!("{}.lock", name.filename_str().unwrap()));
+    fs::mkdir(&lock, old_io::USER_DIR).unwrap();
+    (p, lock)
+}
+
+test!(lock_of_exited_process_is_taken_over {
+    if cfg!(windows) { return }
+    let (p, lock) = locked_git_project();
+    let mut child = Command::new("true").spawn().unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    File::create(&lock.join("pid")).write_str(&pid.to_string()).unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("update"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} git repository `[..]`
+", updating = UPDATING)));
+    assert!(!lock.exists());
+});
+
+test!(lock_of_running_process_blocks {
+    let (p, lock) = locked_git_project();
+    File::create(&lock.join("pid")).write_str(&os::getpid().to_string())
+                                    .unwrap();
+
+    let mut child = p.process(cargo_dir().join("cargo")).arg("update")
+                     .build_command().spawn().unwrap();
+    timer::sleep(Duration::milliseconds(2000));
+    fs::rmdir_recursive(&lock).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.output).unwrap();
+    assert!(stdout.contains("Blocking waiting for another cargo process"),
+            "{}", stdout);
+});
+
+test!(lock_untouched_for_long_is_taken_over {
+    let (p, lock) = locked_git_project();
+    File::create(&lock.join("pid")).write_str(&os::getpid().to_string())
+                                    .unwrap();
+    // A lock which is still held would have been touched more recently
+    lock.join("pid").move_into_the_past().unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("update"),
+                execs().with_status(0));
+    assert!(!lock.exists());
+});
@@ -4,7 +4,7 @@ use std::time::Duration;
 use support::{project, execs, main_file, cargo_dir};
 use support::{COMPILING, RUNNING};
 use support::paths::{self, PathExt};
-use hamcrest::{assert_that, existing_file};
+use hamcrest::{assert_that, existing_file, is_not};
 use cargo;
 use cargo::util::{process};
 
@@ -755,3 +755,53 @@ test result: ok. 0 passed; 0 failed; 0 ignored; 0 measured
 
 ", COMPILING, p.url(), COMPILING, p.url())));
 });
+
+test!(workspace_members_share_one_lockfile {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [workspace]
+            members = ["bar", "baz"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.qux]
+            path = "../qux"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "")
+        .file("qux/Cargo.toml", r#"
+            [package]
+            name = "qux"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("qux/src/lib.rs", "");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .cwd(p.root().join("bar")),
+                execs().with_status(0));
+    assert_that(&p.root().join("bar/Cargo.lock"), is_not(existing_file()));
+    assert_that(&p.root().join("baz/Cargo.lock"), is_not(existing_file()));
+    let lock = File::open(&p.root().join("Cargo.lock")).read_to_string()
+                    .unwrap();
+    for name in ["foo", "bar", "baz", "qux"].iter() {
+        assert!(lock.contains(&format!("name = \"{}\"", name)), "{}", lock);
+    }
+});
@@ -0,0 +1,53 @@
+use support::{project, cargo_dir, execs};
+use hamcrest::assert_that;
+
+fn setup() {}
+
+test!(lists_target_names {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/bin/bar.rs", "fn main() {}")
+        .file("examples/ex.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("completions").arg("--targets").arg("bin"),
+                execs().with_status(0).with_stdout("\
+bar
+foo
+"));
+    assert_that(p.process(cargo_dir().join("cargo"))
+                 .arg("completions").arg("--targets").arg("example"),
+                execs().with_status(0).with_stdout("\
+ex
+"));
+});
+
+test!(bad_target_kind {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("completions").arg("--targets").arg("lib"),
+                execs().with_status(101).with_stderr("\
+--targets must be one of bin, example, test or bench, but found `lib`
+"));
+});
+
+test!(unsupported_shell {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("completions").arg("tcsh"),
+                execs().with_status(1).with_stderr("\
+unsupported shell `tcsh`, expected bash, zsh or fish
+"));
+});
@@ -0,0 +1,110 @@
+use support::{project, cargo_dir, execs};
+use hamcrest::assert_that;
+
+fn setup() {}
+
+test!(get_value {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            [build]
+            jobs = 4
+        "#);
+
+    assert_that(p.cargo_process("config").arg("get").arg("build.jobs"),
+                execs().with_status(0).with_stdout(format!("\
+build.jobs = 4  # {}
+", p.root().join(".cargo/config").display())));
+});
+
+test!(get_table {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            [alias]
+            bq = "build --quiet"
+            rr = ["run", "--release"]
+        "#);
+
+    assert_that(p.cargo_process("config").arg("get").arg("alias"),
+                execs().with_status(0).with_stdout(format!("\
+alias.bq = \"build --quiet\"  # {config}
+alias.rr = [\"run\", \"--release\"]  # {config}
+", config = p.root().join(".cargo/config").display())));
+});
+
+test!(get_overridden_value {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            [build]
+            jobs = 4
+        "#);
+
+    assert_that(p.cargo_process("config").arg("get").arg("build.jobs")
+                 .env("CARGO_BUILD_JOBS", Some("2")),
+                execs().with_status(0).with_stdout("\
+build.jobs = 2  # environment variable `CARGO_BUILD_JOBS`
+"));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("--config")
+                 .arg("build.jobs=3").arg("config").arg("get")
+                 .arg("build.jobs")
+                 .env("CARGO_BUILD_JOBS", Some("2")),
+                execs().with_status(0).with_stdout("\
+build.jobs = 3  # --config
+"));
+});
+
+test!(get_missing_value {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("config").arg("get").arg("build.nothing"),
+                execs().with_status(1).with_stderr("\
+configuration key `build.nothing` is not set
+"));
+});
+
+test!(get_value_included_twice {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            include = ["../shared/config", "../shared/common"]
+        "#)
+        .file("shared/config", r#"
+            include = "common"
+        "#)
+        .file("shared/common", r#"
+            [alias]
+            rr = ["run", "--release"]
+        "#);
+
+    assert_that(p.cargo_process("config").arg("get").arg("alias.rr"),
+                execs().with_status(0).with_stdout("\
+alias.rr = [\"run\", \"--release\"]  # [..]shared[..]common
+"));
+});
+
+test!(credentials_are_redacted {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            [registry]
+            token = "secret"
+        "#);
+
+    assert_that(p.cargo_process("config").arg("get").arg("registry"),
+                execs().with_status(0).with_stdout(format!("\
+registry.token = \"<redacted>\"  # {}
+", p.root().join(".cargo/config").display())));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("--config")
+                 .arg("registry.token=secret").arg("config").arg("list"),
+                execs().with_status(101).with_stderr("\
+failed to parse the value of --config argument `registry.token=<redacted>` as \
+TOML, strings need to be quoted
+"));
+});
+
+test!(get_list_from_environment {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("config").arg("get").arg("alias.ls")
+                 .env("CARGO_ALIAS_LS", Some("[\"a b\", \"c\"]")),
+                execs().with_status(0).with_stdout("\
+alias.ls = [\"a b\", \"c\"]  # environment variable `CARGO_ALIAS_LS`
+"));
+});
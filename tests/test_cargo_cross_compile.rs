@@ -1,4 +1,6 @@
 use std::env;
+use std::ffi::OsString;
+use std::old_io::{self, fs, File};
 use std::old_path;
 
 use support::{project, execs, basic_bin_manifest};
@@ -64,6 +66,56 @@ test!(simple_cross {
       execs().with_status(0));
 });
 
+test!(default_target {
+    if disabled() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            default-target = "{}"
+        "#, alternate()).as_slice())
+        .file("src/main.rs", r#"
+            use std::env;
+            fn main() {
+                assert_eq!(env::consts::ARCH, "x86");
+            }
+        "#);
+
+    assert_that(p.cargo_process("run"), execs().with_status(0));
+    assert_that(&p.target_bin(alternate(), "foo"), existing_file());
+
+    // `--target` overrides the default
+    let (_, host) = rustc_version().unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--target").arg(&host),
+                execs().with_status(0));
+    assert_that(&p.target_bin(&host, "foo"), existing_file());
+});
+
+test!(forced_target {
+    if disabled() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", format!(r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            forced-target = "{}"
+        "#, alternate()).as_slice())
+        .file("src/main.rs", "fn main() {}");
+
+    let (_, host) = rustc_version().unwrap();
+    assert_that(p.cargo_process("build").arg("--target").arg(&host),
+                execs().with_status(0).with_stderr(format!("\
+warning: `foo` is always built for `{}`, ignoring `--target {}`
+", alternate(), host)));
+    assert_that(&p.target_bin(alternate(), "foo"), existing_file());
+});
+
 test!(simple_deps {
     if disabled() { return }
 
@@ -449,6 +501,40 @@ test!(simple_cargo_run {
                 execs().with_status(0));
 });
 
+test!(cargo_run_with_runner {
+    if disabled() { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", format!(r#"
+            [target.{}]
+            runner = ["echo", "emulating"]
+        "#, alternate()).as_slice());
+
+    let target = alternate();
+    assert_that(p.cargo_process("run").arg("--target").arg(target)
+                 .arg("--").arg("arg"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.0 ({url})
+{running} `echo emulating target[..]{target}[..]foo arg`
+emulating target[..]{target}[..]foo arg
+", compiling = COMPILING, running = RUNNING, url = p.url(),
+   target = target)));
+
+    // Binaries built for the host are run directly
+    assert_that(p.process(cargo_dir().join("cargo")).arg("run"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.0 ({url})
+{running} `target[..]foo`
+", compiling = COMPILING, running = RUNNING, url = p.url())));
+});
+
 test!(cross_with_a_build_script {
     if disabled() { return }
 
@@ -572,6 +658,44 @@ test!(build_script_needed_for_host_and_target {
    dir = p.root().display(), sep = old_path::SEP).as_slice()));
 });
 
+test!(switching_to_host_target_keeps_host_units_fresh {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+            build = "build.rs"
+
+            [dependencies.d1]
+            path = "d1"
+            [build-dependencies.d1]
+            path = "d1"
+        "#)
+        .file("build.rs", "extern crate d1; fn main() {}")
+        .file("src/main.rs", "extern crate d1; fn main() {}")
+        .file("d1/Cargo.toml", r#"
+            [package]
+            name = "d1"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("d1/src/lib.rs", "");
+
+    let (_, host) = rustc_version().unwrap();
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--target").arg(&host),
+                execs().with_status(0));
+
+    // Neither build invalidates what the other compiled
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stdout(""));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--target").arg(&host),
+                execs().with_status(0).with_stdout(""));
+});
+
 test!(build_deps_for_the_right_arch {
     if disabled() { return }
 
@@ -680,3 +804,82 @@ test!(plugin_build_script_right_arch {
 {running} `rustc src[..]lib.rs [..]`
 ", compiling = COMPILING, running = RUNNING)));
 });
+
+test!(wasm_artifacts_need_a_runner {
+    if cfg!(windows) { return }
+    // Stands in for a rustc targeting emscripten, which writes a WebAssembly
+    // module next to the JavaScript of a binary
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.0"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bin/rustc", "\
+#!/bin/sh
+case \"$*\" in
+    *\"--target wasm32-unknown-emscripten\"*) ;;
+    *) PATH=\"${PATH#*:}\" exec rustc \"$@\" ;;
+esac
+case \"$*\" in
+    *--print=file-names*)
+        echo \"unsupported crate type \\`dylib\\`\" >&2
+        echo \"-.js\"
+        exit 0
+        ;;
+esac
+while [ $# -gt 0 ]; do
+    if [ \"$1\" = \"--out-dir\" ]; then out=\"$2\"; fi
+    shift
+done
+touch \"$out/foo.js\" \"$out/foo.wasm\"
+echo \"$out/foo.js: src/main.rs\" > \"$out/foo.d\"
+");
+    p.build();
+    fs::chmod(&p.root().join("bin/rustc"), old_io::USER_RWX).unwrap();
+
+    let mut path = vec![p.root().join("bin")];
+    path.extend(env::split_paths(&env::var("PATH")
+                                     .unwrap_or(OsString::new())));
+    let path = env::join_paths(path.iter()).unwrap();
+    let path = path.to_str().unwrap();
+    let target = "wasm32-unknown-emscripten";
+    let dst = p.root().join("target").join(target);
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--target").arg(target).env("PATH", Some(path)),
+                execs().with_status(0));
+    assert_that(&dst.join("foo.js"), existing_file());
+    assert_that(&dst.join("foo.wasm"), existing_file());
+
+    // The module is an output of the binary like the JavaScript
+    fs::unlink(&dst.join("foo.wasm")).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--target").arg(target).env("PATH", Some(path)),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.0 ({url})
+", compiling = COMPILING, url = p.url())));
+    assert_that(&dst.join("foo.wasm"), existing_file());
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("run")
+                 .arg("--target").arg(target).env("PATH", Some(path)),
+                execs().with_status(101).with_stderr("\
+`target[..]foo.js` was built for `wasm32-unknown-emscripten` and can't be run \
+natively; configure a program to run it with, like a JavaScript or \
+WebAssembly shell, as `target.wasm32-unknown-emscripten.runner`
+"));
+
+    fs::mkdir(&p.root().join(".cargo"), old_io::USER_DIR).unwrap();
+    File::create(&p.root().join(".cargo/config")).write_str(r#"
+        [target.wasm32-unknown-emscripten]
+        runner = ["echo", "emulating"]
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("run")
+                 .arg("--target").arg(target).env("PATH", Some(path)),
+                execs().with_status(0).with_stdout(format!("\
+{running} `echo emulating target[..]foo.js`
+emulating target[..]foo.js
+", running = RUNNING)));
+});
@@ -0,0 +1,112 @@
+use support::{project, execs, cargo_dir};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+test!(manifest_parse_error {
+    let p = project("foo")
+        .file("Cargo.toml", r"
+            [project]
+            foo = bar
+        ");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("--error-format")
+                 .arg("json").arg("build"),
+                execs().with_status(101).with_stderr("\
+{\"reason\":\"cargo-error\",\"code\":\"toml-parse\",\
+\"message\":\"failed to parse manifest at `[..]Cargo.toml`\",\
+\"causes\":[\"could not parse input as TOML\\nCargo.toml:3:19-3:20 expected a \
+value\\n\"],\
+\"spans\":[{\"file\":\"Cargo.toml\",\"line_start\":3,\"column_start\":19,\
+\"line_end\":3,\"column_end\":20,\"label\":\"expected a value\"}]}
+"));
+});
+
+test!(manifest_decode_error {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = 1
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("--error-format")
+                 .arg("json").arg("build"),
+                execs().with_status(101).with_stderr("\
+{\"reason\":\"cargo-error\",\"code\":\"toml\",\
+\"message\":\"failed to parse manifest at `[..]Cargo.toml`\",\
+\"causes\":[\"[..]project.name[..]\"],\
+\"spans\":[{\"file\":\"Cargo.toml\",\"line_start\":3,\"column_start\":13,\
+\"line_end\":3,\"column_end\":21,\"label\":\"[..]project.name[..]\"}]}
+"));
+});
+
+test!(resolution_error {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("--error-format")
+                 .arg("json").arg("build"),
+                execs().with_status(101).with_stderr("\
+{\"reason\":\"cargo-error\",\"code\":\"resolve-not-found\",\
+\"message\":\"no matching package named `bar` found (required by `foo`)[..]\",\
+\"causes\":[],\"spans\":[]}
+"));
+});
+
+test!(error_format_from_config {
+    let p = project("foo")
+        .file("Cargo.toml", r"
+            [project]
+            foo = bar
+        ");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .env("CARGO_TERM_ERROR_FORMAT", Some("json")),
+                execs().with_status(101).with_stderr("\
+{\"reason\":\"cargo-error\",\"code\":\"toml-parse\",[..]}
+"));
+});
+
+test!(invalid_error_format {
+    let p = project("foo");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("--error-format")
+                 .arg("xml").arg("build"),
+                execs().with_status(101).with_stderr("\
+--error-format must be one of human or json, but found `xml`
+"));
+});
+
+test!(config_argument_error {
+    let p = project("foo");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("--error-format=json")
+                 .arg("--config").arg("build.jobs").arg("build"),
+                execs().with_status(101).with_stderr("\
+{\"reason\":\"cargo-error\",\"code\":null,\
+\"message\":\"--config argument `build.jobs` must be of the form \
+KEY=VALUE\",\"causes\":[],\"spans\":[]}
+"));
+});
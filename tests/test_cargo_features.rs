@@ -1,6 +1,6 @@
 use std::old_io::File;
 
-use support::{project, execs, cargo_dir};
+use support::{project, execs, cargo_dir, ProjectBuilder};
 use support::{COMPILING, FRESH};
 use support::paths::PathExt;
 use hamcrest::assert_that;
@@ -653,3 +653,378 @@ test!(everything_in_the_lockfile {
     assert!(lockfile.contains(r#"name = "d2""#), "d2 not found\n{}", lockfile);
     assert!(lockfile.contains(r#"name = "d3""#), "d3 not found\n{}", lockfile);
 });
+
+test!(dep_syntax_hides_implicit_feature {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            fancy = ["dep:bar"]
+
+            [dependencies.bar]
+            path = "bar"
+            optional = true
+        "#)
+        .file("src/main.rs", r#"
+            #[cfg(feature = "fancy")]
+            extern crate bar;
+            #[cfg(all(feature = "fancy", not(feature = "bar")))]
+            fn main() { bar::bar(); println!("fancy") }
+            #[cfg(not(feature = "fancy"))]
+            fn main() {}
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build").arg("--features").arg("fancy"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.0.1 ({dir})
+{compiling} foo v0.0.1 ({dir})
+", compiling = COMPILING, dir = p.url()).as_slice()));
+    assert_that(p.process(p.bin("foo")),
+                execs().with_status(0).with_stdout("fancy\n"));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--features").arg("bar"),
+                execs().with_status(101).with_stderr(format!("\
+Package `foo v0.0.1 ({dir})` does not have the feature `bar`; it has an \
+optional dependency of that name, but it's activated with `dep:bar` instead
+", dir = p.url()).as_slice()));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--features").arg("dep:bar"),
+                execs().with_status(101).with_stderr(format!("\
+Package `foo v0.0.1 ({dir})` was requested with `dep:bar`, but `dep:` can \
+only be used in the `[features]` section of a manifest
+", dir = p.url()).as_slice()));
+});
+
+test!(dep_syntax_feature_named_like_dep {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            bar = ["dep:bar"]
+
+            [dependencies.bar]
+            path = "bar"
+            optional = true
+        "#)
+        .file("src/main.rs", r#"
+            #[cfg(feature = "bar")]
+            extern crate bar;
+            #[cfg(feature = "bar")]
+            fn main() { bar::bar(); println!("bar") }
+            #[cfg(not(feature = "bar"))]
+            fn main() {}
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}");
+
+    assert_that(p.cargo_process("build").arg("--features").arg("bar"),
+                execs().with_status(0));
+    assert_that(p.process(p.bin("foo")),
+                execs().with_status(0).with_stdout("bar\n"));
+});
+
+test!(dep_syntax_invalid {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            fancy = ["dep:bar"]
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr(format!("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  Feature `fancy` includes `dep:bar`, but `bar` is not an optional dependency
+").as_slice()));
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            fancy = ["dep:bar"]
+            plain = ["bar"]
+
+            [dependencies.bar]
+            path = "bar"
+            optional = true
+        "#)
+        .file("src/main.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr(format!("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  Feature `plain` includes `bar`, but `bar` has no implicit feature as it is \
+activated with `dep:bar` elsewhere; use `dep:bar` to activate it here as well
+").as_slice()));
+});
+
+test!(weak_dep_features {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            serde = ["bar?/serde"]
+
+            [dependencies.bar]
+            path = "bar"
+            optional = true
+        "#)
+        .file("src/main.rs", r#"
+            #[cfg(feature = "bar")]
+            extern crate bar;
+            #[cfg(feature = "bar")]
+            fn main() { bar::serde(); println!("bar") }
+            #[cfg(not(feature = "bar"))]
+            fn main() {}
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            serde = []
+        "#)
+        .file("bar/src/lib.rs", r#"
+            #[cfg(feature = "serde")]
+            pub fn serde() {}
+        "#);
+
+    // The weak feature doesn't activate `bar` on its own
+    assert_that(p.cargo_process("build").arg("--features").arg("serde"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({dir})
+", compiling = COMPILING, dir = p.url()).as_slice()));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--features").arg("serde bar"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.0.1 ({dir})
+{compiling} foo v0.0.1 ({dir})
+", compiling = COMPILING, dir = p.url()).as_slice()));
+    assert_that(p.process(p.bin("foo")),
+                execs().with_status(0).with_stdout("bar\n"));
+});
+
+test!(weak_dep_features_unknown_dep {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            serde = ["bar?/serde"]
+        "#)
+        .file("src/main.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr(format!("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  Feature `serde` requires `bar` which is not an optional dependency
+").as_slice()));
+});
+
+test!(unknown_feature_suggestion {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            serde = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("build").arg("--features").arg("sered"),
+                execs().with_status(101).with_stderr("\
+Package `foo v0.0.1 ([..])` does not have these features: `sered`
+Did you mean `serde`?
+"));
+});
+
+test!(unknown_feature_of_dependency {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+            features = ["jsno", "xz"]
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            json = []
+            yaml = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+Package `bar v0.0.1 ([..])` does not have these features: `jsno, xz`
+Did you mean `json` instead of `jsno`?
+"));
+});
+
+test!(non_optional_dependency_is_not_a_feature {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("build").arg("--features").arg("bar"),
+                execs().with_status(101).with_stderr("\
+Package `foo v0.0.1 ([..])` does not have these features: `bar`
+"));
+});
+
+fn workspace_with_features() -> ProjectBuilder {
+    let main = r#"
+        #[cfg(feature = "fancy")]
+        fn main() { println!("fancy") }
+        #[cfg(not(feature = "fancy"))]
+        fn main() { println!("plain") }
+    "#;
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            extra = []
+
+            [workspace]
+            members = ["bar", "baz"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            fancy = []
+        "#)
+        .file("bar/src/main.rs", main)
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            fancy = []
+        "#)
+        .file("baz/src/main.rs", main)
+}
+
+test!(workspace_member_qualified_features {
+    let p = workspace_with_features();
+
+    assert_that(p.cargo_process("build").arg("--workspace")
+                 .arg("--features").arg("bar/fancy extra"),
+                execs().with_status(0));
+    assert_that(p.process(p.bin("bar")),
+                execs().with_status(0).with_stdout("fancy\n"));
+    assert_that(p.process(p.bin("baz")),
+                execs().with_status(0).with_stdout("plain\n"));
+});
+
+test!(workspace_ambiguous_feature {
+    let p = workspace_with_features();
+
+    assert_that(p.cargo_process("build").arg("--workspace")
+                 .arg("--features").arg("fancy"),
+                execs().with_status(101).with_stderr("\
+the feature `fancy` is ambiguous, as several members of the workspace have \
+it; qualify it with the member to activate it for, like `bar/fancy` or \
+`baz/fancy`
+"));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--workspace").arg("--features").arg("qux/fancy"),
+                execs().with_status(101).with_stderr("\
+`qux` is not a member of the workspace, but features of the form \
+`member/feature` must name a member when compiling a workspace
+"));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .arg("--workspace").arg("--features").arg("shiny"),
+                execs().with_status(101).with_stderr("\
+none of the members of the workspace have the feature `shiny`
+"));
+});
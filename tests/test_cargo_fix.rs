@@ -0,0 +1,140 @@
+use std::old_io::File;
+
+use cargo::util::process;
+
+use support::{project, execs, cargo_dir, paths, git};
+use support::COMPILING;
+use hamcrest::{assert_that, existing_file, is_not};
+
+fn setup() {
+}
+
+test!(fix_unused_mut {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            pub fn foo() -> i32 { let mut x = 1; x }
+        "#);
+
+    assert_that(p.cargo_process("fix").arg("--allow-no-vcs"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+      Fixing src[..]lib.rs (1 fix)
+{compiling} foo v0.0.1 ({url})
+", compiling = COMPILING, url = p.url())));
+
+    let contents = File::open(&p.root().join("src/lib.rs")).read_to_string()
+                        .unwrap();
+    assert!(contents.contains("let x = 1;"), "{}", contents);
+    assert_that(&p.root().join("src/lib.rs.orig"), existing_file());
+});
+
+test!(fix_errors {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", r#"
+            pub fn foo() -> i32 { let x = 1; x = 2; x }
+        "#);
+
+    assert_that(p.cargo_process("fix").arg("--allow-no-vcs"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+      Fixing src[..]lib.rs (1 fix)
+{compiling} foo v0.0.1 ({url})
+", compiling = COMPILING, url = p.url())));
+
+    let contents = File::open(&p.root().join("src/lib.rs")).read_to_string()
+                        .unwrap();
+    assert!(contents.contains("let mut x = 1;"), "{}", contents);
+});
+
+test!(unfixable_errors {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() -> i32 { bar() }");
+
+    assert_that(p.cargo_process("fix").arg("--allow-no-vcs"),
+                execs().with_status(101));
+    assert_that(&p.root().join("src/lib.rs.orig"),
+                is_not(existing_file()));
+});
+
+test!(nothing_to_fix {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}");
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("fix")
+                 .arg("--allow-no-vcs"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({url})
+", compiling = COMPILING, url = p.url())));
+});
+
+test!(no_vcs {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}");
+
+    assert_that(p.cargo_process("fix"),
+                execs().with_status(101).with_stderr("\
+no VCS found for this package and `cargo fix` can potentially perform \
+destructive changes; to proceed despite this, pass the `--allow-no-vcs` flag
+"));
+});
+
+test!(dirty_worktree {
+    let root = paths::root().join("foo");
+    let p = git::repo(&root)
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn foo() {}");
+    p.build();
+    File::create(&root.join("src/lib.rs")).write_str("pub fn foo() {  }")
+                                          .unwrap();
+
+    let cargo = process(cargo_dir().join("cargo")).unwrap()
+                    .cwd(root.clone())
+                    .env("HOME", Some(paths::home()));
+    assert_that(cargo.clone().arg("fix"),
+                execs().with_status(101).with_stderr("\
+1 file in the working directory contain changes that were not yet \
+committed into git:
+
+src[..]lib.rs
+
+to proceed despite this, pass the `--allow-dirty` flag
+"));
+    assert_that(cargo.arg("fix").arg("--allow-dirty"),
+                execs().with_status(0));
+});
@@ -0,0 +1,57 @@
+use support::{project, execs, cargo_dir};
+use support::{UPDATING, DOWNLOADING};
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+test!(info_of_crate {
+    let p = project("foo");
+    p.build();
+
+    r::mock_pkg("bar", "0.1.0", &[]);
+    r::mock_pkg("bar", "0.2.0", &[("baz", "0.1", "normal"),
+                                   ("qux", "*", "dev")]);
+    r::mock_pkg_yank("bar", "0.2.1", &[], true);
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("info").arg("bar"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+{downloading} bar v0.2.0 (registry file://[..])
+bar 0.2.0
+versions: 0.2.0, 0.1.0
+dependencies:
+    baz = \"[..]0.1\"
+dev-dependencies:
+    qux = \"*\"
+",
+        updating = UPDATING,
+        downloading = DOWNLOADING,
+        reg = r::registry())));
+
+    // The index and the crate are reused offline
+    assert_that(p.process(cargo_dir().join("cargo")).arg("info").arg("bar")
+                 .arg("--offline").arg("--message-format").arg("json"),
+                execs().with_status(0).with_stdout("\
+{\"name\":\"bar\",\"version\":\"0.2.0\",\"versions\":[\"0.2.0\",\"0.1.0\"],\
+\"description\":null,\"license\":null,\"homepage\":null,\"repository\":null,\
+\"documentation\":null,\"features\":{},\"dependencies\":[\
+{\"name\":\"baz\",\"req\":\"[..]0.1\",\"kind\":\"normal\",\"optional\":false},\
+{\"name\":\"qux\",\"req\":\"*\",\"kind\":\"dev\",\"optional\":false}]}
+"));
+});
+
+test!(info_of_missing_crate {
+    let p = project("foo");
+    p.build();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("info").arg("bar")
+                 .arg("-q"),
+                execs().with_status(101).with_stderr(format!("\
+crate `bar` could not be found in registry `{reg}`
+",
+        reg = r::registry())));
+});
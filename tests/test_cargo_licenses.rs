@@ -0,0 +1,106 @@
+use support::{project, execs, paths};
+use support::UPDATING;
+use support::registry as r;
+use hamcrest::{assert_that, existing_dir, is_not};
+
+fn setup() {}
+
+test!(licenses_grouped {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            license = "MIT"
+
+            [dependencies.bar]
+            path = "bar"
+            [dependencies.baz]
+            path = "baz"
+            [dependencies.qux]
+            path = "qux"
+            [dependencies.quux]
+            path = "quux"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+            license = "MIT"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.2.0"
+            authors = []
+            license = "MIT/Apache-2.0"
+        "#)
+        .file("baz/src/lib.rs", "")
+        .file("qux/Cargo.toml", r#"
+            [package]
+            name = "qux"
+            version = "0.3.0"
+            authors = []
+            license-file = "LICENSE"
+        "#)
+        .file("qux/src/lib.rs", "")
+        .file("quux/Cargo.toml", r#"
+            [package]
+            name = "quux"
+            version = "0.4.0"
+            authors = []
+        "#)
+        .file("quux/src/lib.rs", "");
+
+    assert_that(p.cargo_process("licenses"),
+                execs().with_status(0).with_stdout("\
+MIT (2)
+    bar 0.1.0
+    foo 0.5.0
+MIT/Apache-2.0 (1)
+    baz 0.2.0
+license-file (1)
+    qux 0.3.0
+").with_stderr("\
+warning: package `quux v0.4.0 ([..])` has no license metadata
+"));
+});
+
+test!(registry_licenses_from_index {
+    r::init();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            license = "MIT"
+
+            [dependencies]
+            bar = "0.1"
+            baz = "0.1"
+        "#)
+        .file("src/lib.rs", "");
+
+    r::mock_pkg_license("bar", "0.1.0", "Apache-2.0");
+    r::mock_pkg("baz", "0.1.0", &[]);
+
+    assert_that(p.cargo_process("licenses"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+Apache-2.0 (1)
+    bar 0.1.0
+MIT (1)
+    foo 0.5.0
+",
+        updating = UPDATING,
+        reg = r::registry())).with_stderr("\
+warning: package `baz v0.1.0 (registry [..])` has no license metadata
+"));
+    assert_that(&paths::home().join(".cargo/registry/cache"),
+                is_not(existing_dir()));
+});
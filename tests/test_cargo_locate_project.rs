@@ -0,0 +1,53 @@
+use support::{project, cargo_dir, execs};
+use hamcrest::assert_that;
+
+fn setup() {}
+
+test!(simple {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("locate-project"),
+                execs().with_status(0).with_stdout(format!("\
+{{\"root\":\"{}\"}}
+", p.root().join("Cargo.toml").display())));
+});
+
+test!(plain_workspace_root {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("locate-project")
+                 .arg("--message-format").arg("plain")
+                 .cwd(p.root().join("bar")),
+                execs().with_status(0).with_stdout(format!("{}\n",
+                    p.root().join("bar/Cargo.toml").display())));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("locate-project")
+                 .arg("--workspace")
+                 .arg("--message-format").arg("plain")
+                 .cwd(p.root().join("bar")),
+                execs().with_status(0).with_stdout(format!("{}\n",
+                    p.root().join("Cargo.toml").display())));
+});
@@ -90,7 +90,34 @@ test!(existing {
 test!(invalid_characters {
     assert_that(cargo_process("new").arg("foo.rs"),
                 execs().with_status(101)
-                       .with_stderr("Invalid character `.` in crate name: `foo.rs`"));
+                       .with_stderr("\
+Invalid character `.` in crate name: `foo.rs`
+use --name to override the crate name"));
+});
+
+test!(reserved_name {
+    assert_that(cargo_process("new").arg("match"),
+                execs().with_status(101)
+                       .with_stderr("\
+The name `match` cannot be used as a crate name because it is a Rust keyword
+use --name to override the crate name"));
+});
+
+test!(explicit_name {
+    assert_that(cargo_process("new").arg("foo.rs").arg("--name").arg("bar")
+                                    .arg("--vcs").arg("none")
+                                    .env("USER", Some("foo")),
+                execs().with_status(0));
+
+    let toml = File::open(&paths::root().join("foo.rs/Cargo.toml"))
+                    .read_to_string().unwrap();
+    assert!(toml.contains(r#"name = "bar""#));
+});
+
+test!(lib_and_bin {
+    assert_that(cargo_process("new").arg("foo").arg("--lib").arg("--bin"),
+                execs().with_status(101)
+                       .with_stderr("can't specify both --lib and --bin"));
 });
 
 test!(finds_author_user {
@@ -189,3 +216,104 @@ test!(subpackage_no_git {
     assert_that(&paths::root().join("foo/components/subcomponent/.gitignore"),
                  is_not(existing_file()));
 });
+
+test!(subpackage_explicit_git {
+    assert_that(cargo_process("new").arg("foo").env("USER", Some("foo")),
+                execs().with_status(0));
+
+    assert_that(cargo_process("new").arg("foo/bar").arg("--vcs").arg("git")
+                                    .env("USER", Some("foo")),
+                execs().with_status(0));
+
+    assert_that(&paths::root().join("foo/bar/.git"), existing_dir());
+    assert_that(&paths::root().join("foo/bar/.gitignore"), existing_file());
+});
+
+test!(adds_workspace_member {
+    let root = paths::root().join("ws");
+    fs::mkdir_recursive(&root.join("src"), USER_RWX).unwrap();
+    File::create(&root.join("Cargo.toml")).write_str(r#"
+[package]
+name = "ws"
+version = "0.0.1"
+authors = []
+
+[workspace]
+members = ["a"]
+"#).unwrap();
+    File::create(&root.join("src/lib.rs")).write_str("").unwrap();
+
+    assert_that(cargo_process("new").arg("a").cwd(root.clone())
+                                    .env("USER", Some("foo")),
+                execs().with_status(0));
+    assert_that(cargo_process("new").arg("crates/b").cwd(root.clone())
+                                    .env("USER", Some("foo")),
+                execs().with_status(0));
+
+    let toml = File::open(&root.join("Cargo.toml")).read_to_string().unwrap();
+    assert!(toml.contains(r#"members = ["a", "crates/b"]"#), "{}", toml);
+    assert_that(&root.join("crates/b/.gitignore"), is_not(existing_file()));
+
+    assert_that(cargo_process("build").cwd(root.join("crates/b")),
+                execs().with_status(0));
+    assert_that(&root.join("target"), existing_dir());
+    assert_that(&root.join("crates/b/target"), is_not(existing_dir()));
+});
+
+test!(adds_workspace_member_to_multiline_array {
+    let root = paths::root().join("ws");
+    fs::mkdir_recursive(&root.join("src"), USER_RWX).unwrap();
+    File::create(&root.join("Cargo.toml")).write_str(r#"
+[package]
+name = "ws"
+version = "0.0.1"
+authors = []
+
+[package.metadata]
+members = "not the workspace"
+
+[workspace]
+members = [
+    "a",  # the first one ]
+    # more to come
+]
+"#).unwrap();
+    File::create(&root.join("src/lib.rs")).write_str("").unwrap();
+
+    assert_that(cargo_process("new").arg("b").cwd(root.clone())
+                                    .env("USER", Some("foo")),
+                execs().with_status(0));
+
+    let toml = File::open(&root.join("Cargo.toml")).read_to_string().unwrap();
+    assert!(toml.contains(r#"members = "not the workspace""#), "{}", toml);
+    assert!(toml.contains("    \"a\",  # the first one ]\n    \
+                           # more to come\n    \"b\",\n]"), "{}", toml);
+});
+
+test!(adds_workspace_member_fails_without_editing {
+    let root = paths::root().join("ws");
+    fs::mkdir_recursive(&root.join("src"), USER_RWX).unwrap();
+    let manifest = r#"
+[package]
+name = "ws"
+version = "0.0.1"
+authors = []
+
+[workspace]
+members = ["a#1"]
+"#;
+    File::create(&root.join("Cargo.toml")).write_str(manifest).unwrap();
+    File::create(&root.join("src/lib.rs")).write_str("").unwrap();
+
+    assert_that(cargo_process("new").arg("b").cwd(root.clone())
+                                    .env("USER", Some("foo")),
+                execs().with_status(101).with_stderr("\
+Failed to add `b` to the workspace at `[..]Cargo.toml`
+
+Caused by:
+  could not find where to add `b` to `workspace.members` in [..]Cargo.toml, \
+add it by hand
+"));
+    let toml = File::open(&root.join("Cargo.toml")).read_to_string().unwrap();
+    assert_eq!(toml, manifest);
+});
@@ -0,0 +1,161 @@
+use support::{project, execs, cargo_dir};
+use support::UPDATING;
+use support::registry as r;
+
+use hamcrest::assert_that;
+
+fn setup() {
+    r::init();
+}
+
+test!(outdated_dependencies {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            baz = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.1.0", &[]);
+    r::mock_pkg("baz", "0.1.0", &[]);
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    r::mock_pkg("bar", "0.1.1", &[]);
+    r::mock_pkg("bar", "0.2.0", &[]);
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("outdated"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+Name  Kind    Locked  Compatible  Latest
+bar   normal  0.1.0   0.1.1       0.2.0
+",
+        updating = UPDATING,
+        reg = r::registry())));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("outdated")
+                 .arg("--message-format").arg("json"),
+                execs().with_status(0).with_stdout("\
+[{\"package\":\"foo\",\"name\":\"bar\",\"kind\":\"normal\",\
+\"locked\":\"0.1.0\",\"compatible\":\"0.1.1\",\"latest\":\"0.2.0\"}]
+").with_stderr(format!("\
+{updating} registry `{reg}`
+",
+        updating = UPDATING,
+        reg = r::registry())));
+});
+
+test!(prereleases_are_not_latest {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.1.0", &[]);
+    assert_that(p.cargo_process("generate-lockfile"),
+                execs().with_status(0));
+
+    r::mock_pkg("bar", "0.2.0-alpha.1", &[]);
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("outdated")
+                 .arg("-q"),
+                execs().with_status(0).with_stdout("\
+All dependencies are up to date
+"));
+});
+
+test!(up_to_date {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.1.0", &[]);
+
+    assert_that(p.cargo_process("outdated").arg("-q"),
+                execs().with_status(0).with_stdout("\
+All dependencies are up to date
+"));
+});
+
+test!(outdated_workspace {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            members = ["qux"]
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("qux/Cargo.toml", r#"
+            [project]
+            name = "qux"
+            version = "0.0.1"
+            authors = []
+
+            [dev-dependencies]
+            bar = "*"
+        "#)
+        .file("qux/src/lib.rs", "");
+
+    r::mock_pkg("bar", "0.1.0", &[]);
+    p.build();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("generate-lockfile"),
+                execs().with_status(0));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("generate-lockfile")
+                 .cwd(p.root().join("qux")),
+                execs().with_status(0));
+
+    r::mock_pkg("bar", "0.2.0", &[]);
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("outdated")
+                 .arg("-q").arg("--workspace").cwd(p.root().join("qux")),
+                execs().with_status(0).with_stdout("\
+Package  Name  Kind    Locked  Compatible  Latest
+foo      bar   normal  0.1.0   0.1.0       0.2.0
+qux      bar   dev     0.1.0   0.2.0       0.2.0
+"));
+});
+
+test!(bad_message_format {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("outdated").arg("--message-format")
+                 .arg("xml"),
+                execs().with_status(1).with_stderr("\
+--message-format must be plain or json, but found `xml`
+"));
+});
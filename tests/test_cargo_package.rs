@@ -1,4 +1,4 @@
-use std::old_io::{File, MemReader};
+use std::old_io::{fs, File, MemReader};
 
 use tar::Archive;
 use flate2::reader::GzDecoder;
@@ -6,7 +6,7 @@ use cargo::util::process;
 
 use support::{project, execs, cargo_dir, paths, git};
 use support::{PACKAGING, VERIFYING, COMPILING, ARCHIVING};
-use hamcrest::{assert_that, existing_file};
+use hamcrest::{assert_that, existing_file, existing_dir, is_not};
 
 fn setup() {
 }
@@ -242,3 +242,480 @@ test!(include {
 {archiving} [..]
 ", packaging = PACKAGING, archiving = ARCHIVING).as_slice()));
 });
+
+test!(list_matches_include {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            include = ["foo.txt", "**/*.rs", "Cargo.toml"]
+        "#)
+        .file("foo.txt", "")
+        .file("src/main.rs", r#"
+            fn main() { println!("hello"); }
+        "#)
+        .file("src/bar.txt", "");
+
+    assert_that(p.cargo_process("package").arg("--list"),
+                execs().with_status(0).with_stdout("\
+Cargo.toml
+foo.txt
+src[..]main.rs
+"));
+    assert_that(&p.root().join("target/package"), is_not(existing_dir()));
+});
+
+test!(reproducible {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/a.rs", "")
+        .file("src/b.rs", "");
+
+    let krate = p.root().join("target/package/foo-0.0.1.crate");
+    assert_that(p.cargo_process("package").arg("--no-verify"),
+                execs().with_status(0));
+    let first = File::open(&krate).read_to_end().unwrap();
+
+    fs::unlink(&krate).unwrap();
+    fs::change_file_times(&p.root().join("src/a.rs"), 0, 0).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("package")
+                 .arg("--no-verify"),
+                execs().with_status(0));
+    let second = File::open(&krate).read_to_end().unwrap();
+    assert!(first == second);
+
+    let mut rdr = GzDecoder::new(MemReader::new(second));
+    let ar = Archive::new(MemReader::new(rdr.read_to_end().unwrap()));
+    let names = ar.files().unwrap().map(|f| {
+        f.unwrap().filename().unwrap().to_string()
+    }).collect::<Vec<_>>();
+    assert_eq!(names, vec!["foo-0.0.1/Cargo.toml", "foo-0.0.1/src/a.rs",
+                           "foo-0.0.1/src/b.rs", "foo-0.0.1/src/main.rs"]);
+});
+
+test!(long_paths {
+    let dir = "src/a-directory-with-a-rather-long-name/\
+               and-another-one-below-it-which-is-also-long";
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(&format!("{}/some-file-with-a-long-name.rs", dir)[], "");
+
+    assert_that(p.cargo_process("package").arg("--no-verify"),
+                execs().with_status(0));
+
+    // The path doesn't fit into the name field, so it's split into the
+    // prefix and name fields of the ustar header
+    let krate = p.root().join("target/package/foo-0.0.1.crate");
+    let mut rdr = GzDecoder::new(File::open(&krate).unwrap());
+    let tar = rdr.read_to_end().unwrap();
+    let field = |header: &[u8], start: usize, end: usize| {
+        let field = &header[start..end];
+        let len = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+        String::from_utf8(field[..len].to_vec()).unwrap()
+    };
+    let found = tar.chunks(512).any(|header| {
+        field(header, 345, 500) == format!("foo-0.0.1/{}", dir) &&
+            field(header, 0, 100) == "some-file-with-a-long-name.rs"
+    });
+    assert!(found);
+});
+
+test!(vcs_info {
+    let root = paths::root().join("foo");
+    let p = git::repo(&root)
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    let cargo = process(cargo_dir().join("cargo")).unwrap()
+                                                  .cwd(root.clone())
+                                                  .env("HOME", Some(paths::home()));
+    assert_that(cargo.clone().arg("package").arg("--list"),
+                execs().with_status(0).with_stdout("\
+.cargo_vcs_info.json
+Cargo.toml
+src[..]main.rs
+"));
+    assert_that(cargo.arg("package").arg("--no-verify"),
+                execs().with_status(0));
+
+    let f = File::open(&root.join("target/package/foo-0.0.1.crate")).unwrap();
+    let mut rdr = GzDecoder::new(f);
+    let ar = Archive::new(MemReader::new(rdr.read_to_end().unwrap()));
+    let mut found = false;
+    for f in ar.files().unwrap() {
+        let mut f = f.unwrap();
+        if f.filename() != Some("foo-0.0.1/.cargo_vcs_info.json") { continue }
+        let contents = f.read_to_string().unwrap();
+        assert!(contents.contains(r#""dirty":false"#), "{}", contents);
+        assert!(contents.contains(r#""path_in_vcs":"""#), "{}", contents);
+        found = true;
+    }
+    assert!(found);
+});
+
+test!(dirty_worktree {
+    let root = paths::root().join("foo");
+    let p = git::repo(&root)
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+    File::create(&root.join("src/main.rs")).write_str("fn main() {  }").unwrap();
+    File::create(&root.join("src/new.rs")).write_str("").unwrap();
+
+    let cargo = process(cargo_dir().join("cargo")).unwrap()
+                                                  .cwd(root.clone())
+                                                  .env("HOME", Some(paths::home()));
+    assert_that(cargo.clone().arg("package").arg("--no-verify"),
+                execs().with_status(101).with_stderr("\
+2 files in the working directory contain changes that were not yet \
+committed into git:
+
+src[..]main.rs
+src[..]new.rs
+
+to proceed despite this, pass the `--allow-dirty` flag
+"));
+    assert_that(cargo.arg("package").arg("--no-verify").arg("--allow-dirty"),
+                execs().with_status(0));
+});
+
+test!(normalizes_path_dependencies {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+            version = "0.0.1"
+
+            [dev-dependencies.baz]
+            path = "baz"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    assert_that(p.cargo_process("package").arg("--no-verify"),
+                execs().with_status(0));
+
+    let f = File::open(&p.root().join("target/package/foo-0.0.1.crate"));
+    let mut rdr = GzDecoder::new(f.unwrap());
+    let ar = Archive::new(MemReader::new(rdr.read_to_end().unwrap()));
+    let mut manifest = ar.files().unwrap().map(|f| f.unwrap()).find(|f| {
+        f.filename() == Some("foo-0.0.1/Cargo.toml")
+    }).unwrap();
+    let contents = manifest.read_to_string().unwrap();
+    assert!(contents.contains("version = \"0.0.1\""), "{}", contents);
+    assert!(!contents.contains("path"), "{}", contents);
+    assert!(!contents.contains("baz"), "{}", contents);
+});
+
+test!(inherits_workspace_fields {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+
+            [workspace.package]
+            version = "1.2.3"
+            authors = ["ws@example.com"]
+            license = "MIT"
+            edition = "2015"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+
+            [package.version]
+            workspace = true
+
+            [package.authors]
+            workspace = true
+
+            [package.license]
+            workspace = true
+
+            [package.edition]
+            workspace = true
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("package").cwd(p.root().join("bar")),
+                execs().with_status(0));
+
+    // The archived manifest doesn't depend on the workspace anymore
+    let f = File::open(&p.root().join("bar/target/package/bar-1.2.3.crate"));
+    let mut rdr = GzDecoder::new(f.unwrap());
+    let ar = Archive::new(MemReader::new(rdr.read_to_end().unwrap()));
+    let mut manifest = ar.files().unwrap().map(|f| f.unwrap()).find(|f| {
+        f.filename() == Some("bar-1.2.3/Cargo.toml")
+    }).unwrap();
+    let contents = manifest.read_to_string().unwrap();
+    assert!(contents.contains("version = \"1.2.3\""), "{}", contents);
+    assert!(contents.contains("authors = [\"ws@example.com\"]"), "{}",
+            contents);
+    assert!(contents.contains("license = \"MIT\""), "{}", contents);
+    assert!(contents.contains("edition = \"2015\""), "{}", contents);
+    assert!(!contents.contains("workspace"), "{}", contents);
+});
+
+test!(inherits_outside_of_workspace {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            authors = []
+
+            [package.version]
+            workspace = true
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("package"),
+                execs().with_status(101).with_stderr("\
+failed to parse manifest at `[..]`
+
+Caused by:
+  `package` inherits fields from the workspace, but isn't a member of one
+"));
+});
+
+test!(path_dependency_no_version {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("package"),
+                execs().with_status(101).with_stderr("\
+all path and git dependencies must have a version specified when packaging.
+dependency `bar` does not specify a version
+"));
+});
+
+test!(package_workspace {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+            version = "0.0.1"
+
+            [workspace]
+            members = ["bar", "tool"]
+        "#)
+        .file("src/lib.rs", "extern crate bar; pub use bar::bar;")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .file("tool/Cargo.toml", r#"
+            [package]
+            name = "tool"
+            version = "0.0.1"
+            authors = []
+            publish = false
+        "#)
+        .file("tool/src/main.rs", "fn main() {}");
+
+    // `bar` isn't in any registry, so `foo` can only be verified against the
+    // freshly packaged `bar`
+    assert_that(p.cargo_process("package").arg("--workspace")
+                 .arg("--no-metadata"),
+                execs().with_status(0));
+    assert_that(&p.root().join("target/package/foo-0.0.1.crate"),
+                existing_file());
+    assert_that(&p.root().join("bar/target/package/bar-0.0.1.crate"),
+                existing_file());
+    assert_that(&p.root().join("tool/target/package"), is_not(existing_dir()));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("package")
+                 .arg("--workspace").arg("--list"),
+                execs().with_status(101).with_stderr("\
+`--list` can't be used when packaging a workspace
+"));
+});
+
+test!(symlinks {
+    // windows and symlinks don't currently agree that well
+    if cfg!(windows) { return }
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "mod linked; fn main() {}")
+        .file("shared/linked.rs", "")
+        .symlink("shared/linked.rs", "src/linked.rs");
+
+    // Symlinked files are archived with the contents they link to
+    assert_that(p.cargo_process("package").arg("--no-verify")
+                 .arg("--no-metadata").arg("-l"),
+                execs().with_status(0).with_stdout("\
+Cargo.toml
+shared[..]linked.rs
+src[..]linked.rs
+src[..]main.rs
+"));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("package")
+                 .arg("--no-metadata"),
+                execs().with_status(0));
+
+    // Symlinks must not lead out of the package
+    File::create(&paths::root().join("outside.rs")).unwrap();
+    fs::unlink(&p.root().join("src/linked.rs")).unwrap();
+    fs::symlink(&paths::root().join("outside.rs"),
+                &p.root().join("src/linked.rs")).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("package")
+                 .arg("--no-metadata").arg("-l"),
+                execs().with_status(101).with_stderr("\
+`[..]linked.rs` is a symlink to `[..]outside.rs`, which is outside of the \
+package root `[..]`
+"));
+});
+
+test!(list_respects_vcs {
+    let root = paths::root().join("foo");
+    let p = git::repo(&root)
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file(".gitignore", "data/\n")
+        .file("src/main.rs", "fn main() {}")
+        .nocommit_file("src/new.rs", "")
+        .nocommit_file("data/big.bin", "");
+    p.build();
+    let cargo = process(cargo_dir().join("cargo")).unwrap()
+                                                  .cwd(root.clone())
+                                                  .env("HOME", Some(paths::home()));
+
+    // New files are a part of the package, ignored ones aren't
+    assert_that(cargo.arg("package").arg("--list"),
+                execs().with_status(0).with_stdout("\
+.cargo_vcs_info.json
+.gitignore
+Cargo.toml
+src[..]main.rs
+src[..]new.rs
+"));
+});
+
+test!(list_skips_temporary_files {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("src/main.rs~", "")
+        .file("src/.main.rs.swp", "")
+        .file(".hg/store", "");
+
+    assert_that(p.cargo_process("package").arg("--list"),
+                execs().with_status(0).with_stdout("\
+Cargo.toml
+src[..]main.rs
+"));
+});
+
+#[cfg(not(windows))]
+test!(verify_build_skips_hooks {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+
+            [hooks]
+            pre-build = ["sh", "-c", "exit 1"]
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("package"),
+                execs().with_status(0).with_stdout(format!("\
+{packaging} foo v0.0.1 ({dir})
+{verifying} foo v0.0.1 ({dir})
+{compiling} foo v0.0.1 ({dir}[..])
+",
+        packaging = PACKAGING,
+        verifying = VERIFYING,
+        compiling = COMPILING,
+        dir = p.url()).as_slice()));
+});
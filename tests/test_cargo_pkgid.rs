@@ -0,0 +1,61 @@
+use support::{project, execs, cargo_dir};
+use hamcrest::{assert_that, existing_file, is_not};
+
+fn setup() {}
+
+test!(pkgid_without_lockfile {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("pkgid"),
+                execs().with_status(0).with_stdout("\
+file://[..]foo#0.5.0
+"));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("pkgid").arg("bar"),
+                execs().with_status(0).with_stdout("\
+file://[..]bar#0.5.0
+"));
+    assert_that(&p.root().join("Cargo.lock"), is_not(existing_file()));
+});
+
+test!(pkgid_of_workspace_member {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("pkgid").arg("bar"),
+                execs().with_status(0).with_stdout("\
+file://[..]bar#0.5.0
+"));
+});
@@ -23,6 +23,9 @@ fn setup() {
         [registry]
             index = "{reg}"
             token = "api-token"
+
+        [publish]
+            timeout = 0
     "#, reg = registry()).as_slice()).unwrap();
     fs::mkdir_recursive(&upload_path().join("api/v1/crates"), old_io::USER_DIR).unwrap();
 
@@ -127,3 +130,107 @@ all path dependencies must have a version specified when publishing.
 dependency `bar` does not specify a version
 "));
 });
+
+test!(wait_for_index_timeout {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [publish]
+            timeout = 1
+        "#);
+
+    assert_that(p.cargo_process("publish").arg("--no-verify"),
+                execs().with_status(0).with_stderr("\
+warning: timed out waiting for `foo v0.0.1 ([..])` to appear in the registry \
+index; it was uploaded but may not be usable for a while yet
+"));
+});
+
+test!(workspace_dependency_order {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "a"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "a"
+
+            [dependencies.b]
+            path = "b"
+            version = "0.0.1"
+
+            [workspace]
+            members = ["b"]
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("b/Cargo.toml", r#"
+            [package]
+            name = "b"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "b"
+        "#)
+        .file("b/src/lib.rs", "");
+
+    assert_that(p.cargo_process("publish").arg("--workspace")
+                 .arg("--no-verify"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{reg}`
+{updating} registry `{reg}`
+{updating} registry `{reg}`
+{packaging} b v0.0.1 ({dir}/b)
+{uploading} b v0.0.1 ({dir}/b)
+{updating} registry `{reg}`
+{updating} registry `{reg}`
+{packaging} a v0.0.1 ({dir})
+{uploading} a v0.0.1 ({dir})
+",
+        updating = UPDATING,
+        uploading = UPLOADING,
+        packaging = PACKAGING,
+        dir = p.url(),
+        reg = registry()).as_slice()));
+});
+
+test!(workspace_not_a_member {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish").arg("--workspace"),
+                execs().with_status(101).with_stderr(format!("\
+`{}` is not a member of a workspace
+", p.root().join("Cargo.toml").display())));
+});
+
+test!(publish_false {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            publish = false
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("publish"),
+                execs().with_status(101).with_stderr("\
+`foo` cannot be published, as its manifest sets `publish = false`
+"));
+});
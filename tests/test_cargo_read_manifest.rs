@@ -0,0 +1,64 @@
+use support::{project, execs, main_file, basic_bin_manifest};
+use hamcrest::assert_that;
+
+fn setup() {}
+
+test!(cargo_read_manifest_path_to_cargo_toml {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    assert_that(p.cargo_process("read-manifest")
+                 .arg("--manifest-path").arg("foo/Cargo.toml")
+                 .cwd(p.root().dir_path()),
+                execs().with_status(0).with_stdout("\
+{\"format_version\":1,\"name\":\"foo\",\"version\":\"0.5.0\",\
+\"id\":\"foo 0.5.0 ([..])\",\"source\":null,\
+\"manifest_path\":\"[..]Cargo.toml\",\"dependencies\":[],\
+\"targets\":[{\"kind\":[\"bin\"],\"name\":\"foo\",\"src_path\":\"[..]foo.rs\"}],\
+\"features\":{},\"authors\":[\"wycats@example.com\"],\"description\":null,\
+\"license\":null,\"license_file\":null,\"homepage\":null,\"repository\":null,\
+\"documentation\":null,\"readme\":null,\"keywords\":[]}
+"));
+});
+
+test!(cargo_read_manifest_dependencies_and_tests {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+            optional = true
+
+            [features]
+            default = ["bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("tests/it.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.5.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("read-manifest"),
+                execs().with_status(0).with_stdout("\
+{\"format_version\":1,\"name\":\"foo\",\"version\":\"0.5.0\",\
+\"id\":\"foo 0.5.0 ([..])\",\"source\":null,\
+\"manifest_path\":\"[..]Cargo.toml\",\"dependencies\":[\
+{\"name\":\"bar\",\"req\":\"*\",\"source\":null,\"kind\":\"normal\",\
+\"target\":null,\"optional\":true,\"uses_default_features\":true,\
+\"features\":[]}],\
+\"targets\":[{\"kind\":[\"lib\"],\"name\":\"foo\",\"src_path\":\"[..]lib.rs\"},\
+{\"kind\":[\"test\"],\"name\":\"it\",\"src_path\":\"[..]it.rs\"}],\
+\"features\":{\"default\":[\"bar\"]},\"authors\":[],\"description\":null,\
+\"license\":null,\"license_file\":null,\"homepage\":null,\"repository\":null,\
+\"documentation\":null,\"readme\":null,\"keywords\":[]}
+"));
+});
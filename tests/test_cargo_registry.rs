@@ -1,8 +1,9 @@
 use std::old_io::{self, fs, File};
 use cargo::util::process;
+use url::Url;
 
 use support::{project, execs, cargo_dir};
-use support::{UPDATING, DOWNLOADING, COMPILING, PACKAGING, VERIFYING};
+use support::{UPDATING, DOWNLOADING, COMPILING, PACKAGING, VERIFYING, FRESH};
 use support::paths::{self, PathExt};
 use support::registry as r;
 use support::git;
@@ -329,6 +330,44 @@ test!(lockfile_locks_transitively {
                 execs().with_status(0).with_stdout(""));
 });
 
+test!(new_dep_satisfied_by_lockfile_does_not_update {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("baz", "0.0.1", &[]);
+    r::mock_pkg("bar", "0.0.1", &[("baz", "*", "normal")]);
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+
+    r::mock_pkg("baz", "0.0.2", &[]);
+    File::create(&p.root().join("Cargo.toml")).write_str(r#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "*"
+        baz = "*"
+    "#).unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} foo v0.0.1 ({dir})
+", compiling = COMPILING, dir = p.url()).as_slice()));
+});
+
 test!(yanks_are_not_used {
     let p = project("foo")
         .file("Cargo.toml", r#"
@@ -475,6 +514,7 @@ test!(update_lockfile {
                  .arg("-p").arg("bar").arg("--precise").arg("0.0.2"),
                 execs().with_status(0).with_stdout(format!("\
 {updating} registry `[..]`
+{updating} bar v0.0.1 -> v0.0.2
 ", updating = UPDATING).as_slice()));
 
     println!("0.0.2 build");
@@ -491,6 +531,7 @@ test!(update_lockfile {
                  .arg("-p").arg("bar"),
                 execs().with_status(0).with_stdout(format!("\
 {updating} registry `[..]`
+{updating} bar v0.0.2 -> v0.0.3
 ", updating = UPDATING).as_slice()));
 
     println!("0.0.3 build");
@@ -503,6 +544,184 @@ test!(update_lockfile {
    dir = p.url()).as_slice()));
 });
 
+test!(update_explain {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.1.0", &[]);
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+
+    r::mock_pkg("bar", "0.1.1", &[]);
+    r::mock_pkg("bar", "0.2.0", &[]);
+    fs::rmdir_recursive(&paths::home().join(".cargo/registry")).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("update")
+                 .arg("-p").arg("bar").arg("--explain"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `[..]`
+  foo v0.0.1 ({dir}) requires `bar [..]0.1`
+    rejected bar 0.2.0: not matched by `[..]0.1`
+  trying bar v0.1.1 (registry file://[..])
+  selected bar v0.1.1 (registry file://[..])
+{updating} bar v0.1.0 -> v0.1.1
+", updating = UPDATING, dir = p.url()).as_slice()));
+});
+
+test!(lockfile_changes {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    r::mock_pkg("baz", "0.0.1", &[]);
+    assert_that(p.process(cargo_dir().join("cargo")).arg("generate-lockfile"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `[..]`
+", updating = UPDATING).as_slice()));
+
+    File::create(&p.root().join("Cargo.toml")).write_str(r#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        baz = "*"
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("generate-lockfile"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `[..]`
+    Removing bar v0.0.1
+      Adding baz v0.0.1
+", updating = UPDATING).as_slice()));
+});
+
+test!(lockfile_with_merge_conflicts {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.process(cargo_dir().join("cargo")).arg("generate-lockfile"),
+                execs().with_status(0));
+
+    // Both sides of a rebase locked a different version of `bar`
+    r::mock_pkg("bar", "0.0.2", &[]);
+    let lockfile = p.root().join("Cargo.lock");
+    let ours = File::open(&lockfile).read_to_string().unwrap();
+    let theirs = ours.replace("0.0.1 (registry", "0.0.2 (registry")
+                     .replace("version = \"0.0.1\"\nsource",
+                              "version = \"0.0.2\"\nsource");
+    File::create(&lockfile).write_str(format!("\
+<<<<<<< HEAD
+{}=======
+{}>>>>>>> feature
+", ours, theirs).as_slice()).unwrap();
+
+    // Only a fresh resolution may be guided by both sides
+    assert_that(p.process(cargo_dir().join("cargo")).arg("pkgid").arg("bar"),
+                execs().with_status(101).with_stderr("\
+failed to parse lock file at: [..]Cargo.lock
+
+Caused by:
+  the lock file has merge conflicts, which building the project or running \
+`cargo generate-lockfile` resolves
+"));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+    let lock = File::open(&lockfile).read_to_string().unwrap();
+    assert!(!lock.as_slice().contains("<<<<<<<"), "{}", lock);
+    assert_eq!(lock.lines().filter(|l| *l == "name = \"bar\"").count(), 1);
+});
+
+test!(adding_dependency_keeps_unrelated_pins {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.process(cargo_dir().join("cargo")).arg("generate-lockfile"),
+                execs().with_status(0));
+
+    // A new dependency on `baz`, which also depends on `bar`, shouldn't move
+    // `bar` to its newest version
+    r::mock_pkg("bar", "0.0.2", &[]);
+    r::mock_pkg("baz", "0.0.1", &[("bar", "*", "normal")]);
+    File::create(&p.root().join("Cargo.toml")).write_str(r#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "*"
+        baz = "*"
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+    let lock = File::open(&p.root().join("Cargo.lock")).read_to_string()
+                                                       .unwrap();
+    assert!(lock.as_slice().contains("\"bar 0.0.1 "), "{}", lock);
+    assert!(lock.as_slice().contains("\"baz 0.0.1 "), "{}", lock);
+
+    // Editing the requirement of `baz` only changes `baz`
+    r::mock_pkg("baz", "0.0.2", &[("bar", "*", "normal")]);
+    File::create(&p.root().join("Cargo.toml")).write_str(r#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "*"
+        baz = "0.0.2"
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+    let lock = File::open(&p.root().join("Cargo.lock")).read_to_string()
+                                                       .unwrap();
+    assert!(lock.as_slice().contains("\"bar 0.0.1 "), "{}", lock);
+    assert!(lock.as_slice().contains("\"baz 0.0.2 "), "{}", lock);
+});
+
 test!(dev_dependency_not_used {
     let p = project("foo")
         .file("Cargo.toml", r#"
@@ -540,6 +759,177 @@ test!(login_with_no_cargo_dir {
                 execs().with_status(0));
 });
 
+test!(login_stdin_empty {
+    let home = paths::home().join("new-home");
+    fs::mkdir(&home, old_io::USER_DIR).unwrap();
+    assert_that(process(cargo_dir().join("cargo")).unwrap()
+                       .arg("login").arg("--stdin")
+                       .cwd(paths::root())
+                       .env("HOME", Some(home)),
+                execs().with_status(101).with_stderr("\
+no token was provided on stdin
+"));
+});
+
+test!(login_token_and_stdin {
+    assert_that(process(cargo_dir().join("cargo")).unwrap()
+                       .arg("login").arg("foo").arg("--stdin")
+                       .cwd(paths::root()),
+                execs().with_status(1).with_stderr("\
+cannot specify both a token and --stdin
+"));
+});
+
+/// Creates a registry whose API is served from files, where revoking a token
+/// succeeds if `revocable` is set.
+fn api_registry(revocable: bool) -> Url {
+    let api = paths::root().join("api");
+    fs::mkdir_recursive(&api.join("api/v1/tokens"), old_io::USER_DIR).unwrap();
+    if revocable {
+        File::create(&api.join("api/v1/tokens/current")).write_str("{}")
+             .unwrap();
+    }
+    let path = paths::root().join("api-registry");
+    let api = Url::from_file_path(&api).ok().unwrap();
+    git::repo(&path)
+        .file("config.json", format!(r#"{{"dl":"{0}","api":"{0}"}}"#, api))
+        .build();
+    Url::from_file_path(&path).ok().unwrap()
+}
+
+test!(logout {
+    let home = paths::home().join("new-home");
+    fs::mkdir_recursive(&home.join(".cargo"), old_io::USER_DIR).unwrap();
+    File::create(&home.join(".cargo/config")).write_str(format!(r#"
+        [registry]
+        index = "{}"
+    "#, api_registry(true)).as_slice()).unwrap();
+    let cargo = || {
+        process(cargo_dir().join("cargo")).unwrap()
+               .cwd(paths::root())
+               .env("HOME", Some(home.clone()))
+    };
+    assert_that(cargo().arg("login").arg("foo"), execs().with_status(0));
+    let config = home.join(".cargo/credentials");
+    assert!(File::open(&config).read_to_string().unwrap().contains("foo"));
+
+    assert_that(cargo().arg("logout"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `[..]`
+[..]Revoked token for `[..]` on the registry
+[..]Logout token for `[..]` has been removed from local storage
+", updating = UPDATING).as_slice()));
+    assert!(!File::open(&config).read_to_string().unwrap().contains("foo"));
+
+    assert_that(cargo().arg("logout"),
+                execs().with_status(0).with_stderr("\
+warning: no token is saved for `[..]`
+"));
+});
+
+test!(logout_reports_unsaved_token {
+    let home = paths::home().join("new-home");
+    fs::mkdir(&home, old_io::USER_DIR).unwrap();
+    assert_that(process(cargo_dir().join("cargo")).unwrap()
+                       .arg("logout").cwd(paths::root())
+                       .env("HOME", Some(home))
+                       .env("CARGO_REGISTRY_TOKEN", Some("foo")),
+                execs().with_status(0).with_stderr("\
+warning: no token is saved for `[..]`
+warning: a token is still set by environment variable `CARGO_REGISTRY_TOKEN`, \
+which `cargo logout` doesn't change
+"));
+});
+
+test!(login_saves_only_the_token {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [build]
+            jobs = 3
+        "#);
+    p.build();
+    let home = paths::home().join(".cargo");
+    let config = "[build]\njobs = 2\n";
+    File::create(&home.join("config")).write_str(config).unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo"))
+                 .arg("--config").arg("http.timeout=10")
+                 .arg("login").arg("foo")
+                 .env("CARGO_REGISTRY_INDEX", Some("http://example.com")),
+                execs().with_status(0));
+    let credentials = File::open(&home.join("credentials")).read_to_string();
+    let credentials = credentials.unwrap();
+    assert!(credentials.contains("token = \"foo\""));
+    assert!(!credentials.contains("jobs"));
+    assert!(!credentials.contains("timeout"));
+    assert!(!credentials.contains("example.com"));
+    let global = File::open(&home.join("config")).read_to_string();
+    assert_eq!(global.unwrap(), config);
+});
+
+test!(logout_removes_legacy_token {
+    let home = paths::home().join(".cargo");
+    File::create(&home.join("config")).write_str(format!(r#"
+        [build]
+        jobs = 2
+
+        [registry]
+        index = "{}"
+        token = "foo"
+    "#, api_registry(false)).as_slice()).unwrap();
+
+    // The token is removed even though it can't be revoked
+    assert_that(process(cargo_dir().join("cargo")).unwrap()
+                       .arg("logout").cwd(paths::root()),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `[..]`
+[..]Logout token for `[..]` has been removed from local storage
+", updating = UPDATING).as_slice())
+                       .with_stderr("\
+warning: failed to revoke the token on the registry, revoke it on the \
+registry's website instead: [..]
+"));
+    let global = File::open(&home.join("config")).read_to_string().unwrap();
+    assert!(global.contains("jobs = 2"));
+    assert!(!global.contains("token"));
+});
+
+test!(login_credential_provider {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("provider.sh", r#"
+            dir=$(dirname $0)
+            echo "$1 $CARGO_REGISTRY_INDEX_URL" > $dir/action
+            cat > $dir/token
+        "#);
+    p.build();
+    fs::mkdir(&p.root().join(".cargo"), old_io::USER_DIR).unwrap();
+    File::create(&p.root().join(".cargo/config")).write_str(format!(r#"
+        [registry]
+        credential-provider = ["sh", "{}"]
+    "#, p.root().join("provider.sh").display()).as_slice()).unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("login").arg("foo"),
+                execs().with_status(0));
+    let action = File::open(&p.root().join("action")).read_to_string();
+    assert_eq!(action.unwrap(), format!("store {}\n", r::registry()));
+    let token = File::open(&p.root().join("token")).read_to_string();
+    assert_eq!(token.unwrap(), "foo");
+});
+
 test!(bad_license_file {
     let p = project("all")
         .file("Cargo.toml", r#"
@@ -707,3 +1097,316 @@ test!(update_publish_then_update {
    dir = p.url()).as_slice()));
 
 });
+
+test!(index_mirror_fallback {
+    let bogus = Url::from_file_path(&paths::root().join("bogus")).ok().unwrap();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", format!(r#"
+            [registry]
+            index = "{bogus}"
+            mirrors = ["{reg}"]
+        "#, bogus = bogus, reg = r::registry()));
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    assert_that(p.cargo_process("fetch").arg("-v"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `{bogus}`
+{using} mirror `{reg}` of registry `{bogus}`
+{downloading} bar v0.0.1 (registry {bogus})
+",
+        updating = UPDATING,
+        using = "       Using",
+        downloading = DOWNLOADING,
+        bogus = bogus,
+        reg = r::registry())).with_stderr(format!("\
+warning: failed to fetch `{bogus}`, trying mirror `{reg}`
+",
+        bogus = bogus,
+        reg = r::registry())));
+});
+
+#[cfg(unix)]
+test!(sources_shared_between_registries {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    p.build();
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.process(cargo_dir().join("cargo")).arg("fetch"),
+                execs().with_status(0));
+
+    // A copy of the registry under another URL serves the same crate
+    let copy = paths::root().join("registry-copy");
+    assert_that(process("cp").unwrap().arg("-r").arg(r::registry_path())
+                             .arg(&copy),
+                execs().with_status(0));
+    let copy = Url::from_file_path(&copy).ok().unwrap();
+    fs::mkdir(&p.root().join(".cargo"), old_io::USER_DIR).unwrap();
+    File::create(&p.root().join(".cargo/config")).write_str(format!(r#"
+        [registry]
+        index = "{}"
+    "#, copy).as_slice()).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("fetch"),
+                execs().with_status(0));
+
+    // Both registries unpacked the crate, but it's only stored once
+    let src = paths::home().join(".cargo/registry/src");
+    let dirs = fs::readdir(&src).unwrap().into_iter().filter(|p| {
+        p.join("bar-0.0.1").exists()
+    }).collect::<Vec<_>>();
+    assert_eq!(dirs.len(), 2);
+    let stat = fs::stat(&dirs[0].join("bar-0.0.1/src/lib.rs")).unwrap();
+    assert_eq!(stat.unstable.nlink, 2);
+});
+
+test!(modified_sources_are_rebuilt {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    let src = fs::readdir(&paths::home().join(".cargo/registry/src")).unwrap()
+                 .into_iter().find(|p| p.is_dir()).unwrap()
+                 .join("bar-0.0.1/src/lib.rs");
+    let perm = fs::stat(&src).unwrap().perm;
+    assert!(!perm.intersects(old_io::USER_WRITE | old_io::GROUP_WRITE |
+                             old_io::OTHER_WRITE));
+
+    // Nothing changed, so nothing is rebuilt
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stdout(format!("\
+{fresh} bar v0.0.1 (registry file://[..])
+{fresh} foo v0.0.1 ({dir})
+",
+        fresh = FRESH,
+        dir = p.url())));
+
+    fs::chmod(&src, old_io::USER_FILE).unwrap();
+    File::create(&src).write_str("pub fn edited() {}").unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.0.1 (registry file://[..])
+{compiling} foo v0.0.1 ({dir})
+",
+        compiling = COMPILING,
+        dir = p.url())).with_stderr("\
+warning: the sources of `bar v0.0.1 (registry file://[..])` in `[..]bar-0.0.1` \
+were modified after they were unpacked, run `cargo verify-cache --fix` to \
+unpack them again
+"));
+});
+
+test!(modified_sources_keeping_their_mtime_are_rebuilt {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+
+    let src = fs::readdir(&paths::home().join(".cargo/registry/src")).unwrap()
+                 .into_iter().find(|p| p.is_dir()).unwrap()
+                 .join("bar-0.0.1/src/lib.rs");
+    let stat = fs::stat(&src).unwrap();
+    fs::chmod(&src, old_io::USER_FILE).unwrap();
+    File::create(&src).write_str("pub fn edited() {}").unwrap();
+    fs::change_file_times(&src, stat.accessed, stat.modified).unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.0.1 (registry file://[..])
+{compiling} foo v0.0.1 ({dir})
+",
+        compiling = COMPILING,
+        dir = p.url())).with_stderr("\
+warning: the sources of `bar v0.0.1 (registry file://[..])` in `[..]bar-0.0.1` \
+were modified after they were unpacked, run `cargo verify-cache --fix` to \
+unpack them again
+"));
+});
+
+test!(unsigned_crates {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [registry]
+            trusted-keys = ["keys.gpg"]
+            unsigned = "deny"
+        "#);
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+`bar v0.0.1 (registry file://[..])` isn't signed, and unsigned crates are \
+denied by `registry.unsigned`
+"));
+
+    File::create(&p.root().join(".cargo/config")).write_str(r#"
+        [registry]
+        trusted-keys = ["keys.gpg"]
+        unsigned = "warn"
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0).with_stderr("\
+warning: `bar v0.0.1 (registry file://[..])` isn't signed
+"));
+});
+
+test!(bad_unsigned_policy {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = ">= 0.0.0"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [registry]
+            trusted-keys = ["keys.gpg"]
+            unsigned = "allow"
+        "#);
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.cargo_process("build"),
+                execs().with_status(101).with_stderr("\
+registry.unsigned must be either warn or deny, but found `allow` in \
+[..]config
+"));
+
+    // Denying unsigned crates needs keys to verify the signed ones with
+    File::create(&p.root().join(".cargo/config")).write_str(r#"
+        [registry]
+        unsigned = "deny"
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(101).with_stderr("\
+registry.unsigned is deny, but there are no registry.trusted-keys to verify \
+signatures with
+"));
+});
+
+test!(signed_crates {
+    if cfg!(windows) { return }
+    let home = paths::root().join("gnupg");
+    let gpg = |args: &[&str]| {
+        let mut cmd = process("gpg").unwrap().arg("--batch")
+                                            .arg("--homedir").arg(&home);
+        for arg in args.iter() {
+            cmd = cmd.arg(*arg);
+        }
+        cmd.exec_with_output()
+    };
+    // The signatures are made with a throwaway key, if gpg is installed
+    if gpg(&["--version"]).is_err() { return }
+    fs::mkdir(&home, old_io::USER_RWX).unwrap();
+    gpg(&["--passphrase", "", "--quick-gen-key", "Cargo <cargo@example.com>",
+          "default", "default", "never"]).unwrap();
+
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", r#"
+            [registry]
+            trusted-keys = ["keys.gpg"]
+            unsigned = "deny"
+        "#);
+    p.build();
+    let keys = p.root().join("keys.gpg");
+    gpg(&["--export", "-o", keys.as_str().unwrap()]).unwrap();
+    let sign = |name: &str, version: &str| {
+        let krate = r::mock_archive_dst(name, version);
+        let sig = krate.with_extension("sig");
+        gpg(&["--detach-sign", "-o", sig.as_str().unwrap(),
+              krate.as_str().unwrap()]).unwrap();
+    };
+
+    r::mock_pkg("bar", "0.0.1", &[]);
+    sign("bar", "0.0.1");
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+
+    // A crate changed after it was signed is rejected, even if the index
+    // lists its checksum, and it's never unpacked
+    r::mock_archive("bar", "0.0.2", &[]);
+    sign("bar", "0.0.2");
+    r::mock_archive("bar", "0.0.2", &[("baz", "*", "normal")]);
+    let krate = File::open(&r::mock_archive_dst("bar", "0.0.2"))
+                     .read_to_end().unwrap();
+    r::publish("3/b/bar", &r::pkg("bar", "0.0.2", &[], &r::cksum(&krate[]),
+                                  false)[]);
+    File::create(&p.root().join("Cargo.toml")).write_str(r#"
+        [project]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "0.0.2"
+    "#).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(101));
+    let registry = paths::home().join(".cargo/registry");
+    assert!(!fs::walk_dir(&registry).unwrap().any(|f| {
+        f.filename_str().map_or(false, |f| f.starts_with("bar-0.0.2"))
+    }));
+});
@@ -0,0 +1,132 @@
+use std::env;
+use std::ffi::OsString;
+use std::old_io::{self, fs};
+
+use support::{project, execs, cargo_dir};
+use support::registry as r;
+use hamcrest::{assert_that, existing_file};
+
+fn setup() {
+    r::init();
+}
+
+test!(no_report_before_build {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("report").arg("future-incompat"),
+                execs().with_status(101).with_stderr("\
+no future-incompat report was found, the report is written by builds like \
+`cargo build`
+"));
+});
+
+test!(nothing_to_report {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    assert_that(&p.root().join("target/.fingerprint")
+                          .join("future-incompat-report.json"),
+                existing_file());
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("report")
+                 .arg("future-incompat"),
+                execs().with_status(0).with_stdout("\
+The last build found no code which future versions of Rust will reject
+"));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("report")
+                 .arg("future-incompat").arg("-p").arg("bar"),
+                execs().with_status(0).with_stdout("\
+The last build found no code which future versions of Rust will reject
+"));
+});
+
+test!(warnings_of_capped_dependencies {
+    if cfg!(windows) { return }
+    // Stands in for a rustc which warns about code in `bar` that it will
+    // reject in the future, unless lints are capped at `allow`
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "*"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bin/rustc", "\
+#!/bin/sh
+PATH=\"${PATH#*:}\" rustc \"$@\" || exit $?
+case \"$*\" in
+    *\"--cap-lints allow\"*) ;;
+    *\"--crate-name bar \"*)
+        echo \"warning: this is accepted for now\" >&2
+        echo \"note: this was previously accepted by the compiler but is \\
+being phased out\" >&2
+        ;;
+esac
+");
+    p.build();
+    fs::chmod(&p.root().join("bin/rustc"), old_io::USER_RWX).unwrap();
+    r::mock_pkg("bar", "0.0.1", &[]);
+
+    let mut path = vec![p.root().join("bin")];
+    path.extend(env::split_paths(&env::var("PATH")
+                                     .unwrap_or(OsString::new())));
+    let path = env::join_paths(path.iter()).unwrap();
+    let path = path.to_str().unwrap();
+    let stderr = "\
+warning: the following packages contain code that will be rejected by a \
+future version of Rust: bar v0.0.1
+note: to see what the problems were, use `cargo report future-incompat`
+";
+
+    // The warnings themselves are hidden, as lints of dependencies are capped
+    // at `allow`, but they're collected for the report of each build
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .env("PATH", Some(path)),
+                execs().with_status(0).with_stderr(stderr));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build")
+                 .env("PATH", Some(path)),
+                execs().with_status(0).with_stderr(stderr));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("report")
+                 .arg("future-incompat").arg("-p").arg("bar"),
+                execs().with_status(0).with_stdout("\
+The package `bar v0.0.1` currently triggers the following future \
+incompatibility lints:
+> warning: this is accepted for now
+> note: this was previously accepted by the compiler but is being phased out
+
+"));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("report")
+                 .arg("future-incompat").arg("-p").arg("foo"),
+                execs().with_status(0).with_stdout("\
+The last build found no code which future versions of Rust will reject
+"));
+});
@@ -0,0 +1,155 @@
+use support::{project, execs, paths, git, ProjectBuilder};
+use hamcrest::assert_that;
+
+fn setup() {}
+
+fn project_with_dep() -> ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+            license = "MIT"
+
+            [dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+            license = "MIT/Apache-2.0"
+        "#)
+        .file("bar/src/lib.rs", "")
+}
+
+test!(sbom_spdx {
+    let p = project_with_dep();
+
+    assert_that(p.cargo_process("sbom"),
+                execs().with_status(0).with_stdout("\
+{\"SPDXID\":\"SPDXRef-DOCUMENT\",\
+\"creationInfo\":{\"created\":\"[..]\",\"creators\":[\"Tool: cargo-[..]\"]},\
+\"dataLicense\":\"CC0-1.0\",\
+\"documentNamespace\":\"https://spdx.org/spdxdocs/foo-0.5.0-[..]\",\
+\"name\":\"foo-0.5.0\",\
+\"packages\":[\
+{\"SPDXID\":\"SPDXRef-Package-bar-0.1.0\",\"copyrightText\":\"NOASSERTION\",\
+\"downloadLocation\":\"NOASSERTION\",\"filesAnalyzed\":false,\
+\"licenseConcluded\":\"NOASSERTION\",\"licenseDeclared\":\"MIT/Apache-2.0\",\
+\"name\":\"bar\",\"versionInfo\":\"0.1.0\"},\
+{\"SPDXID\":\"SPDXRef-Package-foo-0.5.0\",\"copyrightText\":\"NOASSERTION\",\
+\"downloadLocation\":\"NOASSERTION\",\"filesAnalyzed\":false,\
+\"licenseConcluded\":\"NOASSERTION\",\"licenseDeclared\":\"MIT\",\
+\"name\":\"foo\",\"versionInfo\":\"0.5.0\"}],\
+\"relationships\":[\
+{\"relatedSpdxElement\":\"SPDXRef-Package-foo-0.5.0\",\
+\"relationshipType\":\"DESCRIBES\",\"spdxElementId\":\"SPDXRef-DOCUMENT\"},\
+{\"relatedSpdxElement\":\"SPDXRef-Package-bar-0.1.0\",\
+\"relationshipType\":\"DEPENDS_ON\",\
+\"spdxElementId\":\"SPDXRef-Package-foo-0.5.0\"}],\
+\"spdxVersion\":\"SPDX-2.2\"}
+"));
+});
+
+test!(sbom_cyclonedx {
+    let p = project_with_dep();
+
+    assert_that(p.cargo_process("sbom").arg("--format").arg("cyclonedx"),
+                execs().with_status(0).with_stdout("\
+{\"bomFormat\":\"CycloneDX\",\
+\"components\":[\
+{\"bom-ref\":\"bar v0.1.0 ([..])\",\
+\"licenses\":[{\"expression\":\"MIT/Apache-2.0\"}],\
+\"name\":\"bar\",\"purl\":\"pkg:cargo/bar@0.1.0?download_url=file://[..]/bar\",\"type\":\"library\",\
+\"version\":\"0.1.0\"}],\
+\"dependencies\":[\
+{\"dependsOn\":[],\"ref\":\"bar v0.1.0 ([..])\"},\
+{\"dependsOn\":[\"bar v0.1.0 ([..])\"],\"ref\":\"foo v0.5.0 ([..])\"}],\
+\"metadata\":{\"component\":\
+{\"bom-ref\":\"foo v0.5.0 ([..])\",\
+\"licenses\":[{\"expression\":\"MIT\"}],\
+\"name\":\"foo\",\"purl\":\"pkg:cargo/foo@0.5.0?download_url=file://[..]\",\"type\":\"application\",\
+\"version\":\"0.5.0\"},\
+\"timestamp\":\"[..]\",\
+\"tools\":[{\"name\":\"cargo\",\"version\":\"[..]\"}]},\
+\"specVersion\":\"1.4\",\"version\":1}
+"));
+});
+
+test!(sbom_bad_format {
+    let p = project_with_dep();
+
+    assert_that(p.cargo_process("sbom").arg("--format").arg("xml"),
+                execs().with_status(1).with_stderr("\
+--format must be spdx or cyclonedx, but found `xml`
+"));
+});
+
+test!(sbom_excludes_dev_dependencies {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dev-dependencies.bar]
+            path = "bar"
+        "#)
+        .file("src/main.rs", "fn main() {}")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("sbom").arg("--format").arg("cyclonedx"),
+                execs().with_status(0).with_stdout("\
+{\"bomFormat\":\"CycloneDX\",\
+\"components\":[],\
+\"dependencies\":[\
+{\"dependsOn\":[],\"ref\":\"foo v0.5.0 ([..])\"}],\
+\"metadata\":{\"component\":\
+{\"bom-ref\":\"foo v0.5.0 ([..])\",\
+\"name\":\"foo\",\"purl\":\"pkg:cargo/foo@0.5.0?download_url=file://[..]\",\
+\"type\":\"application\",\"version\":\"0.5.0\"},\
+\"timestamp\":\"[..]\",\
+\"tools\":[{\"name\":\"cargo\",\"version\":\"[..]\"}]},\
+\"specVersion\":\"1.4\",\"version\":1}
+"));
+});
+
+test!(sbom_git_purl {
+    let bar = git::repo(&paths::root().join("bar"))
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+    bar.build();
+
+    let p = project("foo")
+        .file("Cargo.toml", &format!(r#"
+            [package]
+            name = "foo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies.bar]
+            git = "{}"
+        "#, bar.url()))
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("sbom").arg("--format").arg("cyclonedx"),
+                execs().with_status(0).with_stdout("\
+[..]\"purl\":\"pkg:cargo/bar@0.1.0?vcs_url=git%2Bfile://[..]/bar@[..]\"[..]
+"));
+});
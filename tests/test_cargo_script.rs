@@ -0,0 +1,96 @@
+use support::{project, execs, cargo_dir, path2url};
+use support::{COMPILING, RUNNING};
+use hamcrest::assert_that;
+
+fn setup() {
+}
+
+test!(simple {
+    let p = project("foo")
+        .file("hello.rs", r#"
+            fn main() { println!("hello {}", std::os::args()[1]); }
+        "#);
+
+    assert_that(p.cargo_process("script").arg("hello.rs").arg("world"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} hello v0.0.0 ([..])
+{running} `[..]hello[..] world`
+hello world
+", compiling = COMPILING, running = RUNNING)));
+
+    // The build of an unchanged script is reused
+    assert_that(p.process(cargo_dir().join("cargo")).arg("script")
+                 .arg("hello.rs").arg("again"),
+                execs().with_status(0).with_stdout(format!("\
+{running} `[..]hello[..] again`
+hello again
+", running = RUNNING)));
+});
+
+test!(embedded_manifest {
+    let p = project("foo")
+        .file("script.rs", r#"#!/usr/bin/env cargo-script
+            //! Prints a greeting.
+            //!
+            //! ```cargo
+            //! [dependencies.bar]
+            //! path = "bar"
+            //! ```
+            extern crate bar;
+            fn main() { println!("{}", bar::greeting()); }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", r#"
+            pub fn greeting() -> &'static str { "hello" }
+        "#);
+
+    assert_that(p.cargo_process("script").arg("script.rs"),
+                execs().with_status(0).with_stdout(format!("\
+{compiling} bar v0.0.1 ({url})
+{compiling} script v0.0.0 ([..])
+{running} `[..]script[..]`
+hello
+", compiling = COMPILING, running = RUNNING, url = path2url(p.root().join("bar")))));
+});
+
+test!(unclosed_manifest {
+    let p = project("foo")
+        .file("script.rs", r#"
+            //! ```cargo
+            //! [dependencies]
+            fn main() {}
+        "#);
+
+    assert_that(p.cargo_process("script").arg("script.rs"),
+                execs().with_status(101).with_stderr("\
+failed to prepare the script `script.rs`
+
+Caused by:
+  the `cargo` code block in the doc comment of the script is not closed
+"));
+});
+
+test!(generated_sections {
+    let p = project("foo")
+        .file("script.rs", r#"
+            //! ```cargo
+            //! [package]
+            //! name = "foo"
+            //! ```
+            fn main() {}
+        "#);
+
+    assert_that(p.cargo_process("script").arg("script.rs"),
+                execs().with_status(101).with_stderr("\
+failed to prepare the script `script.rs`
+
+Caused by:
+  the manifest of a script can't have a `package` section, it is generated \
+by cargo
+"));
+});
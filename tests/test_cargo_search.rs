@@ -76,6 +76,8 @@ test!(simple {
     if !cfg!(windows) {
         File::create(&base.with_filename("crates?q=postgres"))
              .write_str(contents).unwrap();
+        File::create(&base.with_filename("crates?q=postgres&per_page=10"))
+             .write_str(contents).unwrap();
     }
 
     assert_that(cargo_process("search").arg("postgres"),
@@ -83,3 +85,87 @@ test!(simple {
 {updating} registry `[..]`
 hoare (0.1.1)    Design by contract style assertions for Rust", updating = UPDATING)));
 });
+
+test!(exact_match_first_and_limit {
+    let contents = r#"{
+        "crates": [{
+            "description": "Bindings to libpq",
+            "max_version": "0.2.0",
+            "name": "postgres-sys"
+        }, {
+            "description": "A native PostgreSQL driver",
+            "max_version": "0.7.1",
+            "name": "postgres"
+        }],
+        "meta": {
+            "total": 5
+        }
+    }"#;
+    let base = api_path().join("api/v1/crates");
+    File::create(&base).write_str(contents).unwrap();
+    if !cfg!(windows) {
+        File::create(&base.with_filename("crates?q=postgres&per_page=2"))
+             .write_str(contents).unwrap();
+    }
+
+    assert_that(cargo_process("search").arg("postgres").arg("--limit").arg("2"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `[..]`
+postgres (0.7.1)        A native PostgreSQL driver
+postgres-sys (0.2.0)    Bindings to libpq
+... and 3 crates more (use --limit to show more)
+", updating = UPDATING)));
+});
+
+test!(missing_total {
+    let contents = r#"{
+        "crates": [{
+            "description": "A native PostgreSQL driver",
+            "max_version": "0.7.1",
+            "name": "postgres"
+        }]
+    }"#;
+    let base = api_path().join("api/v1/crates");
+    File::create(&base).write_str(contents).unwrap();
+    if !cfg!(windows) {
+        File::create(&base.with_filename("crates?q=postgres&per_page=10"))
+             .write_str(contents).unwrap();
+    }
+
+    assert_that(cargo_process("search").arg("postgres"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} registry `[..]`
+postgres (0.7.1)    A native PostgreSQL driver
+", updating = UPDATING)));
+});
+
+test!(invalid_response {
+    let contents = r#"{"crates": {}}"#;
+    let base = api_path().join("api/v1/crates");
+    File::create(&base).write_str(contents).unwrap();
+    if !cfg!(windows) {
+        File::create(&base.with_filename("crates?q=postgres&per_page=10"))
+             .write_str(contents).unwrap();
+    }
+
+    assert_that(cargo_process("search").arg("postgres"),
+                execs().with_status(101).with_stderr("\
+failed to retrieve search results from the registry: invalid response: [..]
+"));
+});
+
+test!(bad_limit {
+    assert_that(cargo_process("search").arg("postgres").arg("--limit").arg("0"),
+                execs().with_status(101).with_stderr("\
+the limit must be between 1 and 100
+"));
+});
+
+test!(unknown_named_registry {
+    assert_that(cargo_process("search").arg("postgres")
+                                       .arg("--registry").arg("foo"),
+                execs().with_status(101).with_stderr("\
+no index is configured for the registry `foo`, set `registries.foo.index` in \
+the configuration
+"));
+});
@@ -0,0 +1,201 @@
+use std::old_io::File;
+
+use support::{project, execs, cargo_dir, UPDATING};
+use hamcrest::assert_that;
+
+fn setup() {}
+
+fn read(path: &Path) -> String {
+    File::open(path).read_to_string().unwrap()
+}
+
+test!(set_and_bump {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0-pre"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("set-version").arg("0.2.0"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} foo v0.1.0-pre -> v0.2.0
+", updating = UPDATING)));
+    assert!(read(&p.root().join("Cargo.toml")).contains("version = \"0.2.0\""));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("set-version")
+                 .arg("--bump").arg("minor"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} foo v0.2.0 -> v0.3.0
+", updating = UPDATING)));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("set-version")
+                 .arg("--bump").arg("patch"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} foo v0.3.0 -> v0.3.1
+", updating = UPDATING)));
+    assert!(read(&p.root().join("Cargo.toml")).contains("version = \"0.3.1\""));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("set-version")
+                 .arg("--bump").arg("huge"),
+                execs().with_status(101).with_stderr("\
+`--bump` must be `major`, `minor` or `patch`, not `huge`
+"));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("set-version")
+                 .arg("--workspace").arg("--bump").arg("major"),
+                execs().with_status(101).with_stderr("\
+`[..]Cargo.toml` is not a member of a workspace
+"));
+});
+
+test!(workspace {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+            version = "0.1.0"
+
+            [workspace]
+            members = ["bar", "baz"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "1.0.0"
+            authors = []
+
+            [dev-dependencies.bar]
+            path = "../bar"
+            version = "~0.1.0"
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    assert_that(p.cargo_process("generate-lockfile"), execs().with_status(0));
+
+    // Dependents of the changed member are updated along with it
+    assert_that(p.process(cargo_dir().join("cargo")).arg("set-version")
+                 .arg("--bump").arg("minor").arg("-p").arg("bar"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} bar v0.1.0 -> v0.2.0
+", updating = UPDATING)));
+    let foo = read(&p.root().join("Cargo.toml"));
+    assert!(foo.contains("version = \"0.1.0\""), "{}", foo);
+    assert!(foo.contains("version = \"0.2.0\""), "{}", foo);
+    let baz = read(&p.root().join("baz/Cargo.toml"));
+    assert!(baz.contains("version = \"~0.2.0\""), "{}", baz);
+    let lock = read(&p.root().join("Cargo.lock"));
+    assert!(lock.contains("bar 0.2.0"), "{}", lock);
+
+    // Each member is bumped from its own version
+    assert_that(p.process(cargo_dir().join("cargo")).arg("set-version")
+                 .arg("--workspace").arg("--bump").arg("major"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} foo v0.1.0 -> v1.0.0
+{updating} bar v0.2.0 -> v1.0.0
+{updating} baz v1.0.0 -> v2.0.0
+", updating = UPDATING)));
+    let baz = read(&p.root().join("baz/Cargo.toml"));
+    assert!(baz.contains("version = \"~1.0.0\""), "{}", baz);
+    let lock = read(&p.root().join("Cargo.lock"));
+    assert!(lock.contains("version = \"1.0.0\""), "{}", lock);
+    assert!(lock.contains("bar 1.0.0"), "{}", lock);
+});
+
+test!(inherited_version {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            members = ["bar", "baz"]
+
+            [workspace.package]
+            version = "0.1.0"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            authors = []
+
+            [package.version]
+            workspace = true
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            authors = []
+
+            [package.version]
+            workspace = true
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    // The version is changed where it's defined, for all members sharing it
+    assert_that(p.cargo_process("set-version").arg("0.5.0")
+                 .arg("-p").arg("bar"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} bar v0.1.0 -> v0.5.0
+{updating} baz v0.1.0 -> v0.5.0
+", updating = UPDATING)));
+    let root = read(&p.root().join("Cargo.toml"));
+    assert!(root.contains("version = \"0.0.1\""), "{}", root);
+    assert!(root.contains("version = \"0.5.0\""), "{}", root);
+    let bar = read(&p.root().join("bar/Cargo.toml"));
+    assert!(bar.contains("workspace = true"), "{}", bar);
+});
+
+test!(inline_table_requirement {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.1.0" # released
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar", version = "=0.1.0" } # pinned
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+        "#)
+        .file("bar/src/lib.rs", "");
+
+    assert_that(p.cargo_process("set-version").arg("--workspace")
+                 .arg("0.3.0"),
+                execs().with_status(0).with_stdout(format!("\
+{updating} foo v0.1.0 -> v0.3.0
+{updating} bar v0.1.0 -> v0.3.0
+", updating = UPDATING)));
+    let foo = read(&p.root().join("Cargo.toml"));
+    assert!(foo.contains("version = \"0.3.0\" # released"), "{}", foo);
+    assert!(foo.contains("bar = { path = \"bar\", version = \"=0.3.0\" } \
+                          # pinned"), "{}", foo);
+    let bar = read(&p.root().join("bar/Cargo.toml"));
+    assert!(bar.contains("version = \"0.3.0\""), "{}", bar);
+});
@@ -0,0 +1,138 @@
+use support::{project, execs, cargo_dir};
+use hamcrest::assert_that;
+
+fn setup() {
+}
+
+test!(simple {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "bar"
+
+            [dependencies.baz]
+            path = "baz"
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.baz]
+            path = "../baz"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    assert_that(p.cargo_process("tree"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+|-- bar v0.0.1 ([..])
+|   `-- baz v0.0.1 ([..])
+`-- baz v0.0.1 ([..])
+"));
+});
+
+test!(features_edges {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            default = ["fancy"]
+            fancy = ["bar/y", "baz"]
+
+            [dependencies.bar]
+            path = "bar"
+            features = ["x"]
+
+            [dependencies.baz]
+            path = "baz"
+            optional = true
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+
+            [features]
+            default = ["z"]
+            x = []
+            y = []
+            z = []
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    assert_that(p.cargo_process("tree").arg("-e").arg("features"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+|-- foo feature \"default\"
+|   |-- foo v0.0.1 ([..]) (*)
+|   `-- foo feature \"fancy\"
+|       |-- foo v0.0.1 ([..]) (*)
+|       |-- bar feature \"y\"
+|       |   `-- bar v0.0.1 ([..])
+|       `-- foo feature \"baz\"
+|           |-- foo v0.0.1 ([..]) (*)
+|           `-- baz v0.0.1 ([..])
+|-- bar feature \"x\"
+|   `-- bar v0.0.1 ([..])
+`-- bar feature \"default\"
+    |-- bar v0.0.1 ([..])
+    `-- bar feature \"z\"
+        `-- bar v0.0.1 ([..])
+"));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("tree")
+                 .arg("-e").arg("features").arg("--no-default-features"),
+                execs().with_status(0).with_stdout("\
+foo v0.0.1 ([..])
+|-- bar feature \"x\"
+|   `-- bar v0.0.1 ([..])
+`-- bar feature \"default\"
+    |-- bar v0.0.1 ([..])
+    `-- bar feature \"z\"
+        `-- bar v0.0.1 ([..])
+"));
+});
+
+test!(invalid_edges {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("tree").arg("-e").arg("build"),
+                execs().with_status(1).with_stderr("\
+--edges must be normal or features, but found `build`
+"));
+});
@@ -0,0 +1,79 @@
+use std::old_io::{self, fs, File};
+
+use support::{project, execs, cargo_dir, paths, ProjectBuilder};
+use support::registry as r;
+use hamcrest::{assert_that, existing_file, existing_dir, is_not};
+
+fn setup() {
+    r::init();
+}
+
+fn project_with_bar() -> ProjectBuilder {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+    r::mock_pkg("bar", "0.0.1", &[]);
+    assert_that(p.cargo_process("build"), execs().with_status(0));
+    p
+}
+
+/// Returns the only registry directory within the cache directory `dir`.
+fn registry_dir(dir: &str) -> Path {
+    let dir = paths::home().join(".cargo/registry").join(dir);
+    fs::readdir(&dir).unwrap().into_iter().next().unwrap()
+}
+
+test!(intact_cache {
+    let p = project_with_bar();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("verify-cache"),
+                execs().with_status(0).with_stdout("").with_stderr(""));
+});
+
+test!(corrupted_crate_file {
+    let p = project_with_bar();
+    let krate = registry_dir("cache").join("bar-0.0.1.crate");
+    let src = registry_dir("src").join("bar-0.0.1");
+    File::create(&krate).write_str("garbage").unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("verify-cache"),
+                execs().with_status(1).with_stderr("\
+warning: `[..]bar-0.0.1.crate` is corrupted
+warning: `[..]bar-0.0.1` is corrupted
+found 2 corrupted entries in the cache, run the command again with --fix to \
+remove them
+"));
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("verify-cache")
+                 .arg("--fix"),
+                execs().with_status(0));
+    assert_that(&krate, is_not(existing_file()));
+    assert_that(&src, is_not(existing_dir()));
+
+    // The next build downloads the crate again
+    fs::rmdir_recursive(&p.root().join("target")).unwrap();
+    assert_that(p.process(cargo_dir().join("cargo")).arg("build"),
+                execs().with_status(0));
+    assert_that(&krate, existing_file());
+});
+
+test!(modified_unpacked_source {
+    let p = project_with_bar();
+    let src = registry_dir("src").join("bar-0.0.1");
+    fs::chmod(&src.join("src/lib.rs"), old_io::USER_FILE).unwrap();
+    File::create(&src.join("src/lib.rs")).write_str("oops").unwrap();
+
+    assert_that(p.process(cargo_dir().join("cargo")).arg("verify-cache"),
+                execs().with_status(1).with_stderr("\
+warning: `[..]bar-0.0.1` is corrupted
+found 1 corrupted entry in the cache, run the command again with --fix to \
+remove it
+"));
+});
@@ -0,0 +1,178 @@
+use support::{project, execs, main_file, basic_bin_manifest, cargo_dir};
+use hamcrest::assert_that;
+
+fn setup() {}
+
+test!(simple {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, &[]).as_slice());
+
+    assert_that(p.cargo_process("verify-project"),
+                execs().with_status(0).with_stdout("\
+{\"success\":\"true\",\"errors\":[],\"warnings\":[]}
+"));
+});
+
+test!(missing_manifest {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("verify-project")
+                 .arg("--manifest-path").arg("bar/Cargo.toml"),
+                execs().with_status(1).with_stdout("\
+{\"invalid\":\"manifest path `[..]Cargo.toml` does not exist\",\
+\"errors\":[{\"manifest\":null,\
+\"message\":\"manifest path `[..]Cargo.toml` does not exist\"}],\
+\"warnings\":[]}
+"));
+});
+
+test!(targets_and_unused_keys {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            bulid = "build.rs"
+
+            [[bin]]
+            name = "foo"
+            path = "src/foo.rs"
+
+            [[bin]]
+            name = "foo"
+            path = "src/main.rs"
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("verify-project"),
+                execs().with_status(1).with_stdout(format!("\
+{{\"invalid\":\"target `foo` has no source file at `{root}/src/foo.rs`\",\
+\"errors\":[\
+{{\"manifest\":\"{manifest}\",\
+\"message\":\"target `foo` has no source file at `{root}/src/foo.rs`\"}},\
+{{\"manifest\":\"{manifest}\",\
+\"message\":\"more than one binary is named `foo`\"}}],\
+\"warnings\":[{{\"manifest\":\"{manifest}\",\
+\"message\":\"unused manifest key: package.bulid \
+(did you mean `build`?)\"}}]}}
+", manifest = p.root().join("Cargo.toml").display(),
+   root = p.root().display())));
+});
+
+test!(invalid_version_requirement {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "not a version"
+        "#)
+        .file("src/lib.rs", "");
+
+    assert_that(p.cargo_process("verify-project"),
+                execs().with_status(1).with_stdout(format!("\
+{{\"invalid\":\"the version requirement `not a version` of dependency \
+`bar` is invalid\",\"errors\":[{{\"manifest\":\"{}\",\
+\"message\":\"the version requirement `not a version` of dependency `bar` \
+is invalid\"}}],\"warnings\":[]}}
+", p.root().join("Cargo.toml").display())));
+});
+
+test!(workspace {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            members = ["bar"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+            bulid = "build.rs"
+        "#)
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", r#"
+            [package]
+            name = "baz"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("baz/src/lib.rs", "");
+
+    assert_that(p.cargo_process("verify-project"),
+                execs().with_status(0).with_stdout(format!("\
+{{\"success\":\"true\",\"errors\":[],\"warnings\":[{{\"manifest\":\"{}\",\
+\"message\":\"unused manifest key: package.bulid \
+(did you mean `build`?)\"}}]}}
+", p.root().join("bar/Cargo.toml").display())));
+    assert_that(p.cargo_process("verify-project").cwd(p.root().join("baz")),
+                execs().with_status(0).with_stdout(format!("\
+{{\"success\":\"true\",\"errors\":[],\"warnings\":[{{\"manifest\":\"{}\",\
+\"message\":\"the package is inside of the workspace at `{}` but isn't one \
+of its members\"}}]}}
+", p.root().join("baz/Cargo.toml").display(),
+   p.root().join("Cargo.toml").display())));
+});
+
+test!(workspace_glob_members {
+    let member = |name: &str| format!(r#"
+        [package]
+        name = "{}"
+        version = "0.0.1"
+        authors = []
+        bulid = "build.rs"
+    "#, name);
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            members = ["crates/*", "crates/a"]
+            exclude = ["crates/skip"]
+        "#)
+        .file("src/lib.rs", "")
+        .file("crates/b/Cargo.toml", member("b"))
+        .file("crates/b/src/lib.rs", "")
+        .file("crates/a/Cargo.toml", member("a"))
+        .file("crates/a/src/lib.rs", "")
+        .file("crates/skip/Cargo.toml", member("skip"))
+        .file("crates/skip/src/lib.rs", "")
+        .file("crates/README", "");
+
+    // Members matching a pattern are checked in sorted order, only once, and
+    // excluded ones not at all
+    assert_that(p.cargo_process("verify-project"),
+                execs().with_status(0).with_stdout(format!("\
+{{\"success\":\"true\",\"errors\":[],\"warnings\":[\
+{{\"manifest\":\"{}\",\"message\":\"unused manifest key: package.bulid \
+(did you mean `build`?)\"}},\
+{{\"manifest\":\"{}\",\"message\":\"unused manifest key: package.bulid \
+(did you mean `build`?)\"}}]}}
+", p.root().join("crates/a/Cargo.toml").display(),
+   p.root().join("crates/b/Cargo.toml").display())));
+    assert_that(p.process(cargo_dir().join("cargo")).arg("verify-project")
+                 .cwd(p.root().join("crates/skip")),
+                execs().with_status(0).with_stdout(format!("\
+{{\"success\":\"true\",\"errors\":[],\"warnings\":[\
+{{\"manifest\":\"{manifest}\",\"message\":\"unused manifest key: \
+package.bulid (did you mean `build`?)\"}},\
+{{\"manifest\":\"{manifest}\",\"message\":\"the package is inside of the \
+workspace at `{}` but isn't one of its members\"}}]}}
+", p.root().join("Cargo.toml").display(),
+   manifest = p.root().join("crates/skip/Cargo.toml").display())));
+});
@@ -16,3 +16,18 @@ test!(simple {
         cargo::version()).as_slice()));
 
 });
+
+test!(verbose {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("version").arg("-v"),
+                execs().with_status(0).with_stdout(format!("\
+{}
+release: [..]
+commit-hash: [..]
+commit-date: [..]
+build-date: [..]
+release-channel: [..]
+features: [..]
+", cargo::version()).as_slice()));
+});
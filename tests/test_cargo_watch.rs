@@ -0,0 +1,51 @@
+use std::old_io::{timer, File};
+use std::time::Duration;
+
+use support::{project, cargo_dir};
+
+fn setup() {
+}
+
+test!(rebuilds_on_changes_to_path_deps_outside_the_root {
+    if cfg!(windows) { return }
+    let bar = project("bar")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+        "#)
+        .file("src/lib.rs", "pub fn bar() {}");
+    bar.build();
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies.bar]
+            path = "../bar"
+        "#)
+        .file("src/main.rs", "extern crate bar; fn main() { bar::bar() }");
+    p.build();
+
+    let mut child = p.process(cargo_dir().join("cargo")).arg("build")
+                     .arg("--watch").arg("--color").arg("always")
+                     .build_command().spawn().unwrap();
+    timer::sleep(Duration::milliseconds(5000));
+    File::create(&bar.root().join("src/lib.rs"))
+         .write_str("pub fn bar() { }").unwrap();
+    timer::sleep(Duration::milliseconds(5000));
+    child.signal_kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.output).unwrap();
+
+    let rebuilds = stdout.lines().filter(|l| {
+        l.contains("Compiling") && l.contains("bar v0.0.1")
+    }).count();
+    assert_eq!(rebuilds, 2);
+    assert_eq!(stdout.lines().filter(|l| l.contains("Watching")).count(), 2);
+    // The screen is only cleared on terminals
+    assert!(!stdout.contains("\x1b[2J"), "{}", stdout);
+});
@@ -0,0 +1,96 @@
+use std::old_io::{self, fs, File};
+
+use url::Url;
+
+use support::{project, execs};
+use support::paths;
+use support::git::repo;
+
+use hamcrest::assert_that;
+
+fn registry_path() -> Path { paths::root().join("registry") }
+fn registry() -> Url { Url::from_file_path(&registry_path()).ok().unwrap() }
+fn api_path() -> Path { paths::root().join("api") }
+fn api() -> Url { Url::from_file_path(&api_path()).ok().unwrap() }
+
+fn setup() {
+    let config = paths::root().join(".cargo/config");
+    fs::mkdir_recursive(&config.dir_path(), old_io::USER_DIR).unwrap();
+    File::create(&config).write_str(format!(r#"
+        [registry]
+            index = "{reg}"
+            token = "api-token"
+    "#, reg = registry()).as_slice()).unwrap();
+
+    let versions = api_path().join("api/v1/crates/foo/versions");
+    fs::mkdir_recursive(&versions.dir_path(), old_io::USER_DIR).unwrap();
+    File::create(&versions).write_str(r#"{"versions": [
+        {"num": "0.0.2", "yanked": false},
+        {"num": "0.0.1", "yanked": true}
+    ]}"#).unwrap();
+
+    repo(&registry_path())
+        .file("config.json", format!(r#"{{
+            "dl": "{0}",
+            "api": "{0}"
+        }}"#, api()))
+        .build();
+}
+
+test!(list {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.2"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("yank").arg("--list"),
+                execs().with_status(0).with_stdout("\
+[..]
+0.0.2
+0.0.1 (yanked)
+"));
+});
+
+test!(missing_version {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.2"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("yank"),
+                execs().with_status(101).with_stderr("\
+a version must be specified to yank; use `--list` to see the published versions
+"));
+    assert_that(p.cargo_process("yank").arg("--vers").arg("0.1.0"),
+                execs().with_status(101).with_stderr("\
+crate `foo` has no published version `0.1.0`
+"));
+});
+
+test!(already_yanked {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+            name = "foo"
+            version = "0.0.2"
+            authors = []
+        "#)
+        .file("src/main.rs", "fn main() {}");
+
+    assert_that(p.cargo_process("yank").arg("--vers").arg("0.0.1"),
+                execs().with_status(0).with_stderr("\
+warning: `foo:0.0.1` is already yanked
+"));
+    assert_that(p.cargo_process("yank").arg("--undo").arg("--vers").arg("0.0.2"),
+                execs().with_status(0).with_stderr("\
+warning: `foo:0.0.2` is not yanked
+"));
+});
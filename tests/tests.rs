@@ -27,28 +27,49 @@ macro_rules! test {
 
 mod test_bad_config;
 mod test_cargo;
+mod test_cargo_audit;
 mod test_cargo_bench;
 mod test_cargo_build_auth;
 mod test_cargo_build_lib;
+mod test_cargo_cache;
 mod test_cargo_clean;
 mod test_cargo_compile;
 mod test_cargo_compile_custom_build;
 mod test_cargo_compile_git_deps;
 mod test_cargo_compile_path_deps;
 mod test_cargo_compile_plugins;
+mod test_cargo_completions;
+mod test_cargo_config;
 mod test_cargo_cross_compile;
 mod test_cargo_doc;
+mod test_cargo_error_format;
 mod test_cargo_features;
 mod test_cargo_fetch;
+mod test_cargo_fix;
 mod test_cargo_freshness;
 mod test_cargo_generate_lockfile;
+mod test_cargo_info;
+mod test_cargo_licenses;
+mod test_cargo_locate_project;
 mod test_cargo_new;
+mod test_cargo_outdated;
 mod test_cargo_package;
+mod test_cargo_pkgid;
 mod test_cargo_profiles;
 mod test_cargo_publish;
+mod test_cargo_read_manifest;
 mod test_cargo_registry;
+mod test_cargo_report;
 mod test_cargo_run;
+mod test_cargo_sbom;
+mod test_cargo_script;
 mod test_cargo_search;
+mod test_cargo_set_version;
 mod test_cargo_test;
+mod test_cargo_tree;
+mod test_cargo_verify_cache;
+mod test_cargo_verify_project;
 mod test_cargo_version;
+mod test_cargo_watch;
+mod test_cargo_yank;
 mod test_shell;